@@ -0,0 +1,57 @@
+// Sorts the dependency tables of the status page by the column whose header is clicked and
+// filters their rows by name. Cells may carry a `data-sort-value` to sort by instead of their
+// text, and rows of dependencies carry their name in `data-name`.
+(function () {
+  "use strict";
+
+  function filterRows(query) {
+    query = query.trim().toLowerCase();
+    document.querySelectorAll("tr.dependency").forEach(function (row) {
+      var name = row.getAttribute("data-name").toLowerCase();
+      row.hidden = query !== "" && name.indexOf(query) === -1;
+    });
+  }
+
+  function sortValue(row, index) {
+    var cell = row.cells[index];
+    return cell.getAttribute("data-sort-value") || cell.textContent.trim();
+  }
+
+  function sortTable(table, header) {
+    var index = header.cellIndex;
+    var ascending = header.getAttribute("aria-sort") !== "ascending";
+    var body = table.tBodies[0];
+    var rows = Array.prototype.slice.call(body.rows);
+
+    rows.sort(function (a, b) {
+      var order = sortValue(a, index).localeCompare(sortValue(b, index), undefined, {
+        numeric: true,
+      });
+      return ascending ? order : -order;
+    });
+    rows.forEach(function (row) {
+      body.appendChild(row);
+    });
+
+    Array.prototype.forEach.call(table.tHead.rows[0].cells, function (cell) {
+      cell.removeAttribute("aria-sort");
+    });
+    header.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+  }
+
+  document.querySelectorAll("table.is-sortable").forEach(function (table) {
+    table.querySelectorAll("th[data-sortable]").forEach(function (header) {
+      header.addEventListener("click", function () {
+        sortTable(table, header);
+      });
+    });
+  });
+
+  var filter = document.getElementById("dependency-filter");
+  if (filter) {
+    filter.addEventListener("input", function () {
+      filterRows(filter.value);
+    });
+    filterRows(filter.value);
+  }
+})();
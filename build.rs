@@ -15,6 +15,13 @@ fn build_style() -> String {
     sass::compile_file("./assets/styles/main.sass", options).expect("failed to compile style sheet")
 }
 
+/// Writes the SHA-1 of an asset next to it, for cache-busting paths and ETags
+fn write_hash(out_dir: &str, name: &str, contents: &[u8]) {
+    let hash_path = Path::new(out_dir).join(format!("{}.sha1", name));
+    let digest = Sha1::digest(contents);
+    fs::write(hash_path, format!("{:x}", digest)).unwrap();
+}
+
 fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
 
@@ -22,8 +29,8 @@ fn main() {
 
     let css_path = Path::new(&out_dir).join("style.css");
     fs::write(css_path, style.as_bytes()).unwrap();
+    write_hash(&out_dir, "style.css", style.as_bytes());
 
-    let hash_path = Path::new(&out_dir).join("style.css.sha1");
-    let digest = Sha1::digest(style.as_bytes());
-    fs::write(hash_path, format!("{:x}", digest)).unwrap();
+    let script = fs::read("./assets/scripts/status.js").expect("failed to read status script");
+    write_hash(&out_dir, "status.js", &script);
 }
@@ -1,23 +1,65 @@
 //! Simple badge generator
 
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
 use base64::display::Base64Display;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{self, fontdb, TreeParsing, TreeTextToPath};
 use rusttype::{point, Font, Point, PositionedGlyph, Scale};
+use serde::{Deserialize, Serialize};
 
 const FONT_DATA: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/DejaVuSans.ttf"));
+const FONT_FAMILY: &str = "DejaVu Sans,Verdana,Geneva,sans-serif";
 const FONT_SIZE: f32 = 11.;
 const SCALE: Scale = Scale {
     x: FONT_SIZE,
     y: FONT_SIZE,
 };
 
+/// Rendering variant of a badge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeVariant {
+    /// Colored background behind the status
+    #[default]
+    Color,
+    /// Black-and-white outline, suited for print and high-contrast themes
+    Mono,
+}
+
+/// Shape of a badge, named like the shields.io styles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BadgeStyle {
+    /// Rounded corners with a subtle gradient
+    #[default]
+    Flat,
+    /// Square corners without gradient or text shadow
+    FlatSquare,
+    /// Slightly shorter, with a glossy gradient
+    Plastic,
+    /// Light subject followed by the status in a speech bubble, like social media counters
+    Social,
+    /// Taller, with uppercase spaced-out text
+    ForTheBadge,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BadgeOptions {
     /// Subject will be displayed on the left side of badge
     pub subject: String,
     /// Status will be displayed on the right side of badge
     pub status: String,
-    /// HTML color of badge
+    /// HTML color of badge, ignored by the mono variant
     pub color: String,
+    /// Rendering variant of badge
+    pub variant: BadgeVariant,
+    /// Shape of badge, ignored by the mono variant
+    pub style: BadgeStyle,
 }
 
 impl Default for BadgeOptions {
@@ -26,28 +68,141 @@ impl Default for BadgeOptions {
             subject: "build".to_owned(),
             status: "passing".to_owned(),
             color: "#4c1".to_owned(),
+            variant: BadgeVariant::default(),
+            style: BadgeStyle::default(),
         }
     }
 }
 
 struct BadgeStaticData {
     font: Font<'static>,
+    /// The font for rasterizing, loaded on first use
+    fontdb: OnceCell<fontdb::Database>,
+    font_data: Vec<u8>,
+    font_family: String,
     scale: Scale,
     offset: Point<f32>,
 }
 
+impl BadgeStaticData {
+    fn new(font: Font<'static>, font_data: Vec<u8>, font_family: String) -> BadgeStaticData {
+        let v_metrics = font.v_metrics(SCALE);
+        let offset = point(0.0, v_metrics.ascent);
+
+        BadgeStaticData {
+            font,
+            fontdb: OnceCell::new(),
+            font_data,
+            font_family,
+            scale: SCALE,
+            offset,
+        }
+    }
+
+    fn fontdb(&self) -> &fontdb::Database {
+        self.fontdb.get_or_init(|| {
+            let mut fontdb = fontdb::Database::new();
+            fontdb.load_font_data(self.font_data.clone());
+            fontdb
+        })
+    }
+}
+
 static DATA: Lazy<BadgeStaticData> = Lazy::new(|| {
     let font = Font::try_from_bytes(FONT_DATA).expect("failed to parse font collection");
+    BadgeStaticData::new(font, FONT_DATA.to_vec(), FONT_FAMILY.to_owned())
+});
+
+static CUSTOM_DATA: OnceCell<BadgeStaticData> = OnceCell::new();
 
-    let v_metrics = font.v_metrics(SCALE);
-    let offset = point(0.0, v_metrics.ascent);
+fn data() -> &'static BadgeStaticData {
+    CUSTOM_DATA.get().unwrap_or(&DATA)
+}
 
-    BadgeStaticData {
-        font,
-        scale: SCALE,
-        offset,
+#[derive(Debug)]
+pub enum FontError {
+    /// The font data could not be parsed
+    Invalid,
+    /// A custom font has already been configured
+    AlreadySet,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Invalid => f.write_str("failed to parse font data"),
+            FontError::AlreadySet => f.write_str("a custom font has already been set"),
+        }
     }
-});
+}
+
+impl Error for FontError {}
+
+/// The SVG of a badge could not be rasterized
+#[derive(Debug)]
+pub struct RasterError(String);
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to rasterize badge: {}", self.0)
+    }
+}
+
+impl Error for RasterError {}
+
+/// Rasterizes the SVG of a badge into a PNG of the same size, with the text rendered in the
+/// badge font.
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>, RasterError> {
+    let mut tree = usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map_err(|e| RasterError(e.to_string()))?;
+    tree.convert_text(data().fontdb());
+
+    let tree = resvg::Tree::from_usvg(&tree);
+    let size = tree.size.to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| RasterError("badge has no size".to_owned()))?;
+    tree.render(Transform::default(), &mut pixmap.as_mut());
+
+    pixmap.encode_png().map_err(|e| RasterError(e.to_string()))
+}
+
+/// Replaces the compiled-in DejaVu Sans with a custom TrueType font.
+///
+/// The font is used to measure text, while `font_family` is what the rendered SVG asks
+/// the viewer to display it with. This can be called once and should happen before
+/// the first badge is rendered.
+pub fn set_font(data: Vec<u8>, font_family: String) -> Result<(), FontError> {
+    let font = Font::try_from_vec(data.clone()).ok_or(FontError::Invalid)?;
+    CUSTOM_DATA
+        .set(BadgeStaticData::new(font, data, font_family))
+        .map_err(|_| FontError::AlreadySet)
+}
+
+/// Rounded-corner mask shared by all badges, referenced as `#r`.
+fn svg_mask(width: u32) -> String {
+    svg_mask_with(width, 20, 3)
+}
+
+fn svg_mask_with(width: u32, height: u32, radius: u32) -> String {
+    format!(
+        r##"<mask id="r"><rect width="{}" height="{}" rx="{}" fill="#fff"/></mask>"##,
+        width, height, radius
+    )
+}
+
+/// Gradient and rounded-corner mask of colored badges, referenced as `#s` and `#r`.
+fn svg_defs(width: u32) -> String {
+    format!(
+        concat!(
+            r##"<linearGradient id="s" x2="0" y2="100%">"##,
+            r##"<stop offset="0" stop-color="#bbb" stop-opacity=".1"/>"##,
+            r##"<stop offset="1" stop-opacity=".1"/>"##,
+            "</linearGradient>",
+            "{}",
+        ),
+        svg_mask(width)
+    )
+}
 
 pub struct Badge {
     options: BadgeOptions,
@@ -65,57 +220,259 @@ impl Badge {
         )
     }
 
+    pub fn to_png(&self) -> Result<Vec<u8>, RasterError> {
+        svg_to_png(&self.to_svg())
+    }
+
     pub fn to_svg(&self) -> String {
         let left_width = self.calculate_width(&self.options.subject) + 6;
         let right_width = self.calculate_width(&self.options.status) + 6;
 
-        let svg = format!(
-            r###"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="20">
-  <linearGradient id="smooth" x2="0" y2="100%">
-    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
-    <stop offset="1" stop-opacity=".1"/>
-  </linearGradient>
-
-  <mask id="round">
-    <rect width="{}" height="20" rx="3" fill="#fff"/>
-  </mask>
-
-  <g mask="url(#round)">
-    <rect width="{}" height="20" fill="#555"/>
-    <rect x="{}" width="{}" height="20" fill="{}"/>
-    <rect width="{}" height="20" fill="url(#smooth)"/>
-  </g>
-
-  <g fill="#fff" text-anchor="middle" font-family="DejaVu Sans,Verdana,Geneva,sans-serif" font-size="11">
-    <text x="{}" y="15" fill="#010101" fill-opacity=".3">{}</text>
-    <text x="{}" y="14">{}</text>
-    <text x="{}" y="15" fill="#010101" fill-opacity=".3">{}</text>
-    <text x="{}" y="14">{}</text>
-  </g>
-</svg>"###,
-            left_width + right_width,
-            left_width + right_width,
-            left_width,
-            left_width,
-            right_width,
-            self.options.color,
-            left_width + right_width,
-            (left_width) / 2,
-            self.options.subject,
-            (left_width) / 2,
-            self.options.subject,
-            left_width + (right_width / 2),
-            self.options.status,
-            left_width + (right_width / 2),
-            self.options.status
-        );
+        match (self.options.variant, self.options.style) {
+            (BadgeVariant::Mono, _) => self.to_mono_svg(left_width, right_width),
+            (BadgeVariant::Color, BadgeStyle::Flat) => self.to_color_svg(left_width, right_width),
+            (BadgeVariant::Color, BadgeStyle::FlatSquare) => {
+                self.to_flat_square_svg(left_width, right_width)
+            }
+            (BadgeVariant::Color, BadgeStyle::Plastic) => {
+                self.to_plastic_svg(left_width, right_width)
+            }
+            (BadgeVariant::Color, BadgeStyle::Social) => self.to_social_svg(),
+            (BadgeVariant::Color, BadgeStyle::ForTheBadge) => self.to_for_the_badge_svg(),
+        }
+    }
 
-        svg
+    fn to_flat_square_svg(&self, left_width: u32, right_width: u32) -> String {
+        let width = left_width + right_width;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20">"##,
+                r##"<rect width="{left}" height="20" fill="#555"/>"##,
+                r##"<rect x="{left}" width="{right}" height="20" fill="{color}"/>"##,
+                r##"<g fill="#fff" text-anchor="middle" font-family="{font_family}" font-size="11">"##,
+                r##"<text x="{subject_x}" y="14">{subject}</text>"##,
+                r##"<text x="{status_x}" y="14">{status}</text>"##,
+                "</g>",
+                "</svg>",
+            ),
+            width = width,
+            left = left_width,
+            right = right_width,
+            color = self.options.color,
+            font_family = data().font_family,
+            subject_x = left_width / 2,
+            subject = self.options.subject,
+            status_x = left_width + right_width / 2,
+            status = self.options.status,
+        )
+    }
+
+    /// Renders an 18 pixel high badge with a glossy gradient and more rounded corners.
+    fn to_plastic_svg(&self, left_width: u32, right_width: u32) -> String {
+        let width = left_width + right_width;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="18">"##,
+                r##"<linearGradient id="s" x2="0" y2="100%">"##,
+                r##"<stop offset="0" stop-color="#fff" stop-opacity=".7"/>"##,
+                r##"<stop offset=".1" stop-color="#aaa" stop-opacity=".1"/>"##,
+                r##"<stop offset=".9" stop-opacity=".3"/>"##,
+                r##"<stop offset="1" stop-opacity=".5"/>"##,
+                "</linearGradient>",
+                "{mask}",
+                r##"<g mask="url(#r)">"##,
+                r##"<rect width="{left}" height="18" fill="#555"/>"##,
+                r##"<rect x="{left}" width="{right}" height="18" fill="{color}"/>"##,
+                r##"<rect width="{width}" height="18" fill="url(#s)"/>"##,
+                "</g>",
+                r##"<g fill="#fff" text-anchor="middle" font-family="{font_family}" font-size="11">"##,
+                r##"<g fill="#010101" fill-opacity=".3">"##,
+                r##"<text x="{subject_x}" y="14">{subject}</text>"##,
+                r##"<text x="{status_x}" y="14">{status}</text>"##,
+                "</g>",
+                r##"<text x="{subject_x}" y="13">{subject}</text>"##,
+                r##"<text x="{status_x}" y="13">{status}</text>"##,
+                "</g>",
+                "</svg>",
+            ),
+            width = width,
+            mask = svg_mask_with(width, 18, 4),
+            left = left_width,
+            right = right_width,
+            color = self.options.color,
+            font_family = data().font_family,
+            subject_x = left_width / 2,
+            subject = self.options.subject,
+            status_x = left_width + right_width / 2,
+            status = self.options.status,
+        )
+    }
+
+    /// Renders a light subject button followed by the status in a bubble pointing at it.
+    /// The color is not used, like on shields.io.
+    fn to_social_svg(&self) -> String {
+        let left_width = self.calculate_width(&self.options.subject) + 10;
+        let right_width = self.calculate_width(&self.options.status) + 10;
+        // gap between the button and the bubble, taken up by the bubble's arrow
+        let gap = 6;
+        let width = left_width + gap + right_width + 1;
+        let bubble_x = left_width + gap;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20">"##,
+                r##"<linearGradient id="s" x2="0" y2="100%">"##,
+                r##"<stop offset="0" stop-color="#fcfcfc" stop-opacity="0"/>"##,
+                r##"<stop offset="1" stop-opacity=".1"/>"##,
+                "</linearGradient>",
+                r##"<g stroke="#d5d5d5">"##,
+                r##"<rect x=".5" y=".5" width="{left}" height="19" rx="2" fill="#fcfcfc"/>"##,
+                r##"<rect x=".5" y=".5" width="{left}" height="19" rx="2" fill="url(#s)"/>"##,
+                r##"<rect x="{bubble_x}.5" y=".5" width="{right}" height="19" rx="2" fill="#fafafa"/>"##,
+                r##"<path d="M{arrow_x} 7.5l-3 2.5 3 2.5" fill="#fafafa"/>"##,
+                "</g>",
+                r##"<g fill="#333" text-anchor="middle" font-family="{font_family}" font-size="11">"##,
+                r##"<text x="{subject_x}" y="14" font-weight="700">{subject}</text>"##,
+                r##"<text x="{status_x}" y="14">{status}</text>"##,
+                "</g>",
+                "</svg>",
+            ),
+            width = width,
+            left = left_width,
+            right = right_width,
+            bubble_x = bubble_x,
+            arrow_x = bubble_x + 1,
+            font_family = data().font_family,
+            subject_x = left_width / 2 + 1,
+            subject = self.options.subject,
+            status_x = bubble_x + right_width / 2 + 1,
+            status = self.options.status,
+        )
+    }
+
+    /// Renders a 28 pixel high badge with square corners and uppercase, spaced-out text.
+    fn to_for_the_badge_svg(&self) -> String {
+        let subject = self.options.subject.to_uppercase();
+        let status = self.options.status.to_uppercase();
+        let text_width = |text: &str| {
+            // glyphs are measured at 11px, the text is rendered at 10px with 1px spacing
+            self.calculate_width(text) * 10 / 11 + text.chars().count() as u32
+        };
+        let left_width = text_width(&subject) + 20;
+        let right_width = text_width(&status) + 20;
+        let width = left_width + right_width;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="28">"##,
+                r##"<rect width="{left}" height="28" fill="#555"/>"##,
+                r##"<rect x="{left}" width="{right}" height="28" fill="{color}"/>"##,
+                r##"<g fill="#fff" text-anchor="middle" font-family="{font_family}" font-size="10" letter-spacing="1">"##,
+                r##"<text x="{subject_x}" y="18">{subject}</text>"##,
+                r##"<text x="{status_x}" y="18" font-weight="700">{status}</text>"##,
+                "</g>",
+                "</svg>",
+            ),
+            width = width,
+            left = left_width,
+            right = right_width,
+            color = self.options.color,
+            font_family = data().font_family,
+            subject_x = left_width / 2,
+            subject = subject,
+            status_x = left_width + right_width / 2,
+            status = status,
+        )
+    }
+
+    fn to_color_svg(&self, left_width: u32, right_width: u32) -> String {
+        let width = left_width + right_width;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20">"##,
+                "{defs}",
+                r##"<g mask="url(#r)">"##,
+                r##"<rect width="{left}" height="20" fill="#555"/>"##,
+                r##"<rect x="{left}" width="{right}" height="20" fill="{color}"/>"##,
+                r##"<rect width="{width}" height="20" fill="url(#s)"/>"##,
+                "</g>",
+                "{text}",
+                "</svg>",
+            ),
+            width = width,
+            defs = svg_defs(width),
+            left = left_width,
+            right = right_width,
+            color = self.options.color,
+            text = self.svg_text(left_width, right_width),
+        )
+    }
+
+    /// Renders an outlined badge with black subject text on white and white status
+    /// text on black, without shadows or gradients.
+    fn to_mono_svg(&self, left_width: u32, right_width: u32) -> String {
+        let width = left_width + right_width;
+
+        format!(
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20">"##,
+                "{mask}",
+                r##"<g mask="url(#r)">"##,
+                r##"<rect width="{left}" height="20" fill="#fff"/>"##,
+                r##"<rect x="{left}" width="{right}" height="20" fill="#000"/>"##,
+                "</g>",
+                r##"<rect x=".5" y=".5" width="{outline}" height="19" rx="2.5" fill="none" stroke="#000"/>"##,
+                r##"<g text-anchor="middle" font-family="{font_family}" font-size="11">"##,
+                r##"<text x="{subject_x}" y="14" fill="#000">{subject}</text>"##,
+                r##"<text x="{status_x}" y="14" fill="#fff">{status}</text>"##,
+                "</g>",
+                "</svg>",
+            ),
+            width = width,
+            mask = svg_mask(width),
+            left = left_width,
+            right = right_width,
+            outline = width - 1,
+            font_family = data().font_family,
+            subject_x = left_width / 2,
+            subject = self.options.subject,
+            status_x = left_width + right_width / 2,
+            status = self.options.status,
+        )
+    }
+
+    /// Renders subject and status, each with a drop shadow one pixel below.
+    fn svg_text(&self, left_width: u32, right_width: u32) -> String {
+        let subject_x = left_width / 2;
+        let status_x = left_width + right_width / 2;
+
+        format!(
+            concat!(
+                r##"<g fill="#fff" text-anchor="middle" font-family="{font_family}" font-size="11">"##,
+                r##"<g fill="#010101" fill-opacity=".3">"##,
+                r##"<text x="{subject_x}" y="15">{subject}</text>"##,
+                r##"<text x="{status_x}" y="15">{status}</text>"##,
+                "</g>",
+                r##"<text x="{subject_x}" y="14">{subject}</text>"##,
+                r##"<text x="{status_x}" y="14">{status}</text>"##,
+                "</g>",
+            ),
+            font_family = data().font_family,
+            subject_x = subject_x,
+            status_x = status_x,
+            subject = self.options.subject,
+            status = self.options.status,
+        )
     }
 
     fn calculate_width(&self, text: &str) -> u32 {
-        let glyphs: Vec<PositionedGlyph> =
-            DATA.font.layout(text, DATA.scale, DATA.offset).collect();
+        let glyphs: Vec<PositionedGlyph> = data()
+            .font
+            .layout(text, data().scale, data().offset)
+            .collect();
         let width = glyphs
             .iter()
             .rev()
@@ -125,7 +482,44 @@ impl Badge {
             })
             .next()
             .unwrap_or(0.0);
-        (width + ((text.len() as f32 - 1f32) * 1.3)).ceil() as u32
+        (width + ((text.chars().count() as f32 - 1f32) * 1.3)).ceil() as u32
+    }
+}
+
+/// Cache of rendered badges, keyed by the options they were rendered with.
+///
+/// The set of badges a service hands out is usually small, so hot badges can skip
+/// glyph layout and formatting entirely.
+pub struct BadgeCache {
+    rendered: Mutex<HashMap<BadgeOptions, String>>,
+    capacity: usize,
+}
+
+impl BadgeCache {
+    /// Creates a cache holding at most `capacity` rendered badges.
+    pub fn new(capacity: usize) -> BadgeCache {
+        BadgeCache {
+            rendered: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Returns the SVG for `options`, rendering it on a cache miss.
+    pub fn to_svg(&self, options: BadgeOptions) -> String {
+        if let Some(svg) = self.rendered.lock().unwrap().get(&options) {
+            return svg.clone();
+        }
+
+        let svg = Badge::new(options.clone()).to_svg();
+
+        let mut rendered = self.rendered.lock().unwrap();
+        // the key space is expected to be small, so simply start over when it isn't
+        if rendered.len() >= self.capacity {
+            rendered.clear();
+        }
+        rendered.insert(options, svg.clone());
+
+        svg
     }
 }
 
@@ -144,6 +538,79 @@ mod tests {
         assert_eq!(badge.calculate_width("passing"), 44);
     }
 
+    #[test]
+    fn test_svg_is_minified() {
+        let svg = Badge::new(options()).to_svg();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        assert!(!svg.contains('\n'));
+        assert!(!svg.contains("  "));
+        assert_eq!(svg.matches(">passing<").count(), 2);
+    }
+
+    #[test]
+    fn test_mono_variant() {
+        let svg = Badge::new(BadgeOptions {
+            variant: BadgeVariant::Mono,
+            ..options()
+        })
+        .to_svg();
+        assert!(!svg.contains("#4c1"));
+        assert!(!svg.contains("url(#s)"));
+        assert_eq!(svg.matches(">passing<").count(), 1);
+    }
+
+    #[test]
+    fn test_styles() {
+        let render = |style| Badge::new(BadgeOptions { style, ..options() }).to_svg();
+
+        let plastic = render(BadgeStyle::Plastic);
+        assert!(plastic.contains(r#"height="18""#));
+        assert_eq!(plastic.matches(">passing<").count(), 2);
+
+        let social = render(BadgeStyle::Social);
+        assert!(!social.contains("#4c1"));
+        assert_eq!(social.matches(">passing<").count(), 1);
+
+        let for_the_badge = render(BadgeStyle::ForTheBadge);
+        assert!(for_the_badge.contains(">PASSING<"));
+
+        for svg in &[
+            plastic,
+            social,
+            for_the_badge,
+            render(BadgeStyle::FlatSquare),
+        ] {
+            assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>"));
+        }
+    }
+
+    #[test]
+    fn test_to_png() {
+        let png = Badge::new(options()).to_png().unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_cache_matches_uncached() {
+        let cache = BadgeCache::new(2);
+        let expected = Badge::new(options()).to_svg();
+        assert_eq!(cache.to_svg(options()), expected);
+        assert_eq!(cache.to_svg(options()), expected);
+    }
+
+    #[test]
+    fn test_cache_capacity() {
+        let cache = BadgeCache::new(2);
+        for status in &["a", "b", "c"] {
+            cache.to_svg(BadgeOptions {
+                status: status.to_string(),
+                ..options()
+            });
+        }
+        assert_eq!(cache.rendered.lock().unwrap().len(), 1);
+    }
+
     #[test]
     #[ignore]
     fn test_to_svg() {
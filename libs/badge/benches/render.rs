@@ -0,0 +1,21 @@
+use badge::{Badge, BadgeCache, BadgeOptions};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn options() -> BadgeOptions {
+    BadgeOptions {
+        subject: "dependencies".to_owned(),
+        status: "3 of 12 outdated".to_owned(),
+        color: "#dfb317".to_owned(),
+        ..BadgeOptions::default()
+    }
+}
+
+fn render(c: &mut Criterion) {
+    c.bench_function("uncached", |b| b.iter(|| Badge::new(options()).to_svg()));
+
+    let cache = BadgeCache::new(16);
+    c.bench_function("cached", |b| b.iter(|| cache.to_svg(options())));
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
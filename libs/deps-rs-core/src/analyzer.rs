@@ -0,0 +1,657 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use rustsec::{
+    advisory::Advisory,
+    cargo_lock::{self, Lockfile},
+    database::{self, Database},
+};
+use semver::{Version, VersionReq};
+
+use crate::crates::{
+    compatibility_key, AnalyzedDependencies, AnalyzedDependency, CrateDeps, CrateName,
+    CrateRelease, DuplicateDependency,
+};
+
+/// Finds the security advisories affecting a release
+fn vulnerabilities(advisory_db: &Database, name: &CrateName, ver: &Version) -> Vec<Advisory> {
+    advisories(advisory_db, name, ver, false)
+}
+
+/// Finds the informational advisories, e.g. about being unmaintained or unsound, that
+/// affect a release
+fn informational_advisories(
+    advisory_db: &Database,
+    name: &CrateName,
+    ver: &Version,
+) -> Vec<Advisory> {
+    advisories(advisory_db, name, ver, true)
+}
+
+fn advisories(
+    advisory_db: &Database,
+    name: &CrateName,
+    ver: &Version,
+    informational: bool,
+) -> Vec<Advisory> {
+    let name: cargo_lock::Name = name.as_ref().parse().unwrap();
+    let version: cargo_lock::Version = ver.to_string().parse().unwrap();
+    let query = database::Query::new()
+        .package_version(name, version)
+        .informational(informational);
+
+    advisory_db
+        .query(&query)
+        .into_iter()
+        .filter(|vuln| !vuln.metadata.yanked)
+        .map(|v| v.to_owned())
+        .collect()
+}
+
+/// Records the versions a lockfile resolves the dependencies to. Vulnerabilities are then
+/// only reported for the locked versions.
+pub fn apply_lockfile(
+    deps: &mut AnalyzedDependencies,
+    lockfile: &Lockfile,
+    advisory_db: Option<&Database>,
+) {
+    for (name, dep) in deps
+        .main
+        .iter_mut()
+        .chain(deps.dev.iter_mut())
+        .chain(deps.build.iter_mut())
+    {
+        if dep.is_from_external_registry() {
+            continue;
+        }
+
+        // a lockfile can contain several semver-incompatible versions of a crate
+        let locked = lockfile
+            .packages
+            .iter()
+            .filter(|package| package.source.is_some() && package.name.as_str() == name.as_ref())
+            .filter_map(|package| Version::parse(&package.version.to_string()).ok())
+            .filter(|version| dep.required.matches(version))
+            .max();
+
+        if let Some(locked) = locked {
+            if let Some(db) = advisory_db {
+                dep.vulnerabilities = vulnerabilities(db, name, &locked);
+                dep.informational = informational_advisories(db, name, &locked);
+            }
+            dep.locked = Some(locked);
+        }
+    }
+}
+
+/// Finds the crates from registries that a lockfile contains in several semver-incompatible
+/// versions
+pub fn find_duplicates(lockfile: &Lockfile) -> Vec<DuplicateDependency> {
+    let mut versions: BTreeMap<&str, BTreeMap<(u64, u64, u64), Version>> = BTreeMap::new();
+    for package in lockfile.packages.iter().filter(|p| p.source.is_some()) {
+        let version = match Version::parse(&package.version.to_string()) {
+            Ok(version) => version,
+            Err(_) => continue,
+        };
+
+        let lines = versions.entry(package.name.as_str()).or_default();
+        let newest = lines
+            .entry(compatibility_key(&version))
+            .or_insert_with(|| version.clone());
+        if *newest < version {
+            *newest = version;
+        }
+    }
+
+    versions
+        .into_iter()
+        .filter(|(_, lines)| lines.len() > 1)
+        .filter_map(|(name, lines)| {
+            Some(DuplicateDependency {
+                name: name.parse().ok()?,
+                versions: lines.into_values().collect(),
+            })
+        })
+        .collect()
+}
+
+/// Returns `true` if the requirement opts into pre-releases, e.g. `0.8.0-rc.1`
+fn requires_prerelease(req: &VersionReq) -> bool {
+    req.comparators
+        .iter()
+        .any(|comparator| !comparator.pre.is_empty())
+}
+
+pub struct DependencyAnalyzer {
+    deps: AnalyzedDependencies,
+    advisory_db: Option<Arc<Database>>,
+    track_prereleases: bool,
+}
+
+impl DependencyAnalyzer {
+    pub fn new(deps: &CrateDeps, advisory_db: Option<Arc<Database>>) -> DependencyAnalyzer {
+        DependencyAnalyzer {
+            deps: AnalyzedDependencies::new(deps),
+            advisory_db,
+            track_prereleases: false,
+        }
+    }
+
+    /// Lets pre-releases count as the latest version of dependencies whose requirement opts
+    /// into pre-releases, so they are outdated when a newer pre-release is out
+    pub fn track_prereleases(&mut self, track: bool) {
+        self.track_prereleases = track;
+    }
+
+    fn process_single(
+        dep: &mut AnalyzedDependency,
+        release: &CrateRelease,
+        advisory_db: Option<&Database>,
+        track_prereleases: bool,
+    ) {
+        let ver = &release.version;
+        dep.available = true;
+
+        if dep.required.matches(ver) {
+            let is_latest_that_matches = match dep.latest_that_matches {
+                Some(ref current_latest_that_matches) => current_latest_that_matches < ver,
+                None => true,
+            };
+            if is_latest_that_matches {
+                dep.latest_that_matches = Some(ver.clone());
+                dep.rust_version = release.rust_version.clone();
+            }
+
+            if let Some(db) = advisory_db {
+                let vulnerabilities = vulnerabilities(db, &release.name, ver);
+                if !vulnerabilities.is_empty() {
+                    dep.vulnerabilities = vulnerabilities;
+                }
+                let informational = informational_advisories(db, &release.name, ver);
+                if !informational.is_empty() {
+                    dep.informational = informational;
+                }
+            }
+        }
+        if ver.pre.is_empty() || (track_prereleases && requires_prerelease(&dep.required)) {
+            let is_latest = match dep.latest {
+                Some(ref current_latest) => current_latest < ver,
+                None => true,
+            };
+            if is_latest {
+                dep.latest = Some(ver.clone());
+                dep.latest_rust_version = release.rust_version.clone();
+            }
+        }
+    }
+
+    pub fn process<I: IntoIterator<Item = CrateRelease>>(&mut self, releases: I) {
+        let advisory_db = self.advisory_db.clone();
+        self.process_with(releases, advisory_db.as_deref());
+    }
+
+    /// Processes releases from an alternative registry. The advisory database only covers
+    /// crates.io, so these aren't checked against it.
+    pub fn process_from_registry<I: IntoIterator<Item = CrateRelease>>(&mut self, releases: I) {
+        self.process_with(releases, None);
+    }
+
+    fn process_with<I: IntoIterator<Item = CrateRelease>>(
+        &mut self,
+        releases: I,
+        advisory_db: Option<&Database>,
+    ) {
+        let track_prereleases = self.track_prereleases;
+        for release in releases {
+            if release.yanked {
+                self.process_yanked(&release);
+                continue;
+            }
+
+            if let Some(main_dep) = self.deps.main.get_mut(&release.name) {
+                DependencyAnalyzer::process_single(
+                    main_dep,
+                    &release,
+                    advisory_db,
+                    track_prereleases,
+                )
+            }
+            if let Some(dev_dep) = self.deps.dev.get_mut(&release.name) {
+                DependencyAnalyzer::process_single(
+                    dev_dep,
+                    &release,
+                    advisory_db,
+                    track_prereleases,
+                )
+            }
+            if let Some(build_dep) = self.deps.build.get_mut(&release.name) {
+                DependencyAnalyzer::process_single(
+                    build_dep,
+                    &release,
+                    advisory_db,
+                    track_prereleases,
+                )
+            }
+        }
+    }
+
+    /// Remembers which requirements a yanked release would satisfy
+    fn process_yanked(&mut self, release: &CrateRelease) {
+        for deps in [
+            &mut self.deps.main,
+            &mut self.deps.dev,
+            &mut self.deps.build,
+        ] {
+            if let Some(dep) = deps.get_mut(&release.name) {
+                if dep.required.matches(&release.version) {
+                    dep.yanked = true;
+                }
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> AnalyzedDependencies {
+        for dep in self
+            .deps
+            .main
+            .values_mut()
+            .chain(self.deps.dev.values_mut())
+            .chain(self.deps.build.values_mut())
+        {
+            // only yanked if no other release satisfies the requirement
+            if dep.latest_that_matches.is_some() {
+                dep.yanked = false;
+            }
+        }
+
+        self.deps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crates::{CrateDep, CrateDeps, CrateRelease};
+
+    use super::*;
+
+    #[test]
+    fn finds_duplicates_in_lockfile() {
+        let lockfile: Lockfile = r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "rand"
+version = "0.7.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "rand"
+version = "0.8.4"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "1.0.80"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "1.0.86"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            find_duplicates(&lockfile),
+            vec![DuplicateDependency {
+                name: "rand".parse().unwrap(),
+                versions: vec!["0.7.3".parse().unwrap(), "0.8.4".parse().unwrap()],
+            }]
+        );
+    }
+
+    #[test]
+    fn tracks_latest_without_matching() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.11.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest_that_matches,
+            None
+        );
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest,
+            Some("0.10.1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn tracks_latest_that_matches() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.10.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.11.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest_that_matches,
+            Some("0.10.1".parse().unwrap())
+        );
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest,
+            Some("0.11.0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn tracks_prereleases_when_required() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "rand".parse().unwrap(),
+            CrateDep::External("^0.9.0-alpha.1".parse().unwrap()),
+        );
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.14.0".parse().unwrap()),
+        );
+
+        let releases: Vec<_> = [
+            ("rand", "0.8.5"),
+            ("rand", "0.9.0-alpha.1"),
+            ("rand", "0.9.0-alpha.2"),
+            ("rand", "0.10.0-alpha.1"),
+            ("hyper", "0.14.0"),
+            ("hyper", "1.0.0-rc.1"),
+        ]
+        .iter()
+        .map(|(name, version)| CrateRelease {
+            name: name.parse().unwrap(),
+            version: version.parse().unwrap(),
+            deps: Default::default(),
+            yanked: false,
+            rust_version: None,
+        })
+        .collect();
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(releases.clone());
+        let analyzed = analyzer.finalize();
+        assert_eq!(
+            analyzed.main.get("rand").unwrap().latest,
+            Some("0.8.5".parse().unwrap())
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.track_prereleases(true);
+        analyzer.process(releases);
+        let analyzed = analyzer.finalize();
+        assert_eq!(
+            analyzed.main.get("rand").unwrap().latest,
+            Some("0.10.0-alpha.1".parse().unwrap())
+        );
+        assert!(analyzed.main.get("rand").unwrap().is_outdated());
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest,
+            Some("0.14.0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn skips_yanked_releases() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.10.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: true,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest_that_matches,
+            Some("0.10.0".parse().unwrap())
+        );
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest,
+            Some("0.10.0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn skips_prereleases() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.10.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1-alpha".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest_that_matches,
+            Some("0.10.0".parse().unwrap())
+        );
+        assert_eq!(
+            analyzed.main.get("hyper").unwrap().latest,
+            Some("0.10.0".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn tracks_availability() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.10.0".parse().unwrap()),
+        );
+        deps.main.insert(
+            "gone".parse().unwrap(),
+            CrateDep::External("^1.0.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "gone".parse().unwrap(),
+                version: "1.0.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: true,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert!(!analyzed.main.get("hyper").unwrap().is_unavailable());
+        assert!(analyzed.main.get("gone").unwrap().is_unavailable());
+    }
+
+    #[test]
+    fn tracks_rust_version_of_latest_that_matches() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("^0.10.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: Some("1.60".parse().unwrap()),
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: Some("1.56".parse().unwrap()),
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.11.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: Some("1.70".parse().unwrap()),
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+        let hyper = analyzed.main.get("hyper").unwrap();
+
+        assert_eq!(hyper.rust_version, Some("1.60".parse().unwrap()));
+        assert!(hyper.exceeds_msrv(&"1.56".parse().unwrap()));
+        assert!(!hyper.exceeds_msrv(&"1.60".parse().unwrap()));
+
+        assert_eq!(hyper.latest_rust_version, Some("1.70".parse().unwrap()));
+        assert!(hyper.latest_exceeds_msrv(&"1.60".parse().unwrap()));
+        assert!(!hyper.latest_exceeds_msrv(&"1.70".parse().unwrap()));
+    }
+
+    #[test]
+    fn tracks_requirements_only_satisfied_by_yanked_releases() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "hyper".parse().unwrap(),
+            CrateDep::External("=0.10.1".parse().unwrap()),
+        );
+        deps.main.insert(
+            "serde".parse().unwrap(),
+            CrateDep::External("^1.0.0".parse().unwrap()),
+        );
+
+        let mut analyzer = DependencyAnalyzer::new(&deps, None);
+        analyzer.process(vec![
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "hyper".parse().unwrap(),
+                version: "0.10.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: true,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "serde".parse().unwrap(),
+                version: "1.0.1".parse().unwrap(),
+                deps: Default::default(),
+                yanked: true,
+                rust_version: None,
+            },
+            CrateRelease {
+                name: "serde".parse().unwrap(),
+                version: "1.0.0".parse().unwrap(),
+                deps: Default::default(),
+                yanked: false,
+                rust_version: None,
+            },
+        ]);
+
+        let analyzed = analyzer.finalize();
+
+        assert!(analyzed.main.get("hyper").unwrap().is_yanked());
+        assert!(!analyzed.main.get("serde").unwrap().is_yanked());
+    }
+}
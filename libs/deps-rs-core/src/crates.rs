@@ -0,0 +1,1006 @@
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use relative_path::RelativePathBuf;
+use rustsec::Advisory;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct CratePath {
+    pub name: CrateName,
+    pub version: Version,
+}
+
+impl CratePath {
+    /// Parses a crate path, tolerating surrounding (percent-encoded) whitespace and a `v` prefix
+    /// on the version
+    pub fn from_parts(name: &str, version: &str) -> Result<CratePath, Error> {
+        let version = clean_path_segment(version);
+        let version = version.strip_prefix(&['v', 'V'][..]).unwrap_or(&version);
+
+        Ok(CratePath {
+            name: clean_path_segment(name).parse()?,
+            version: version.parse()?,
+        })
+    }
+}
+
+/// Decodes percent-encoded characters and trims whitespace
+fn clean_path_segment(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[idx], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded)
+        .map(|decoded| decoded.trim().to_owned())
+        .unwrap_or_else(|_| input.trim().to_owned())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct CrateName(String);
+
+impl From<CrateName> for String {
+    fn from(crate_name: CrateName) -> String {
+        crate_name.0
+    }
+}
+
+impl TryFrom<String> for CrateName {
+    type Error = Error;
+
+    fn try_from(input: String) -> Result<CrateName, Error> {
+        input.parse()
+    }
+}
+
+impl Borrow<str> for CrateName {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CrateName {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl FromStr for CrateName {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<CrateName, Error> {
+        let is_valid = input
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if !is_valid {
+            Err(anyhow!("failed to validate crate name: {}", input))
+        } else {
+            Ok(CrateName(input.to_string()))
+        }
+    }
+}
+
+/// Minimum supported Rust version, as declared by `package.rust-version`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RustVersion(Version);
+
+impl FromStr for RustVersion {
+    type Err = Error;
+
+    /// Parses a bare version like `1.56` or `1.56.1`, without a requirement operator
+    fn from_str(input: &str) -> Result<RustVersion, Error> {
+        let components = input
+            .trim()
+            .split('.')
+            .map(|c| c.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("failed to parse rust version: {}", input))?;
+
+        match components[..] {
+            [major, minor] => Ok(RustVersion(Version::new(major, minor, 0))),
+            [major, minor, patch] => Ok(RustVersion(Version::new(major, minor, patch))),
+            _ => Err(anyhow!("failed to parse rust version: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Version {
+                major,
+                minor,
+                patch: 0,
+                ..
+            } => write!(f, "{}.{}", major, minor),
+            ref version => write!(f, "{}", version),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrateRelease {
+    pub name: CrateName,
+    pub version: Version,
+    pub deps: CrateDeps,
+    pub yanked: bool,
+    /// Only known for releases looked up in a sparse index
+    pub rust_version: Option<RustVersion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrateDep {
+    External(VersionReq),
+    /// Path dependency, optionally with the version requirement used once published
+    Internal(RelativePathBuf, Option<VersionReq>),
+    /// Dependency from a registry other than crates.io, identified by its name or index URL
+    Registry(String, VersionReq),
+    /// Dependency fetched from a git repository, which can't be version-checked
+    Git(GitDependency),
+}
+
+/// Branch, tag or commit a git dependency is pinned to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GitReference {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl fmt::Display for GitReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitReference::Branch(branch) => write!(f, "branch {}", branch),
+            GitReference::Tag(tag) => write!(f, "tag {}", tag),
+            GitReference::Rev(rev) => write!(f, "rev {}", rev),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitDependency {
+    pub url: String,
+    /// Unset if the dependency follows the repository's default branch
+    pub reference: Option<GitReference>,
+}
+
+impl GitDependency {
+    /// Returns the tag or commit the dependency is pinned to. Branches move along with the
+    /// repository, so they aren't pins.
+    pub fn pinned_ref(&self) -> Option<&str> {
+        match self.reference {
+            Some(GitReference::Tag(ref tag)) => Some(tag),
+            Some(GitReference::Rev(ref rev)) => Some(rev),
+            Some(GitReference::Branch(_)) | None => None,
+        }
+    }
+}
+
+/// Commits behind the default branch from which a pinned git dependency is considered stale
+const STALE_PIN_COMMITS: usize = 100;
+/// Days behind the default branch from which a pinned git dependency is considered stale
+const STALE_PIN_DAYS: u64 = 180;
+
+/// How far the commit a git dependency is pinned to is behind its repository's default branch
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitPinLag {
+    /// Commits on the default branch the pinned commit doesn't have
+    pub commits: usize,
+    /// Days between the pinned commit and the newest one on the default branch
+    pub days: u64,
+}
+
+impl GitPinLag {
+    pub fn is_stale(&self) -> bool {
+        self.commits > 0 && (self.commits >= STALE_PIN_COMMITS || self.days >= STALE_PIN_DAYS)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalyzedGitDependency {
+    pub source: GitDependency,
+    /// Only known for pinned dependencies from repositories whose host can be asked
+    pub lag: Option<GitPinLag>,
+}
+
+impl AnalyzedGitDependency {
+    pub fn is_stale(&self) -> bool {
+        self.lag.map_or(false, |lag| lag.is_stale())
+    }
+}
+
+impl CrateDep {
+    pub fn is_external(&self) -> bool {
+        matches!(self, CrateDep::External(_))
+    }
+}
+
+/// How a dependency is pulled in, beyond its version requirement
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepUsage {
+    pub optional: bool,
+    pub default_features: bool,
+    /// Platform the dependency is restricted to, like `cfg(unix)`
+    pub target: Option<String>,
+    /// Features that enable an optional dependency, including the implicit one named after it
+    pub features: Vec<String>,
+}
+
+impl DepUsage {
+    /// Returns `true` for an unconditional dependency with default features
+    pub fn is_plain(&self) -> bool {
+        !self.optional && self.default_features && self.target.is_none()
+    }
+
+    /// Whether the dependency is pulled in with `features` enabled
+    pub fn is_enabled_by(&self, features: &[String]) -> bool {
+        !self.optional
+            || self
+                .features
+                .iter()
+                .any(|feature| features.contains(feature))
+    }
+}
+
+/// Usage of the dependencies which aren't plain, separately for each kind of dependency as a
+/// crate can be pulled in differently as e.g. a normal and a dev dependency
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DepUsages {
+    pub main: HashMap<CrateName, DepUsage>,
+    pub dev: HashMap<CrateName, DepUsage>,
+    pub build: HashMap<CrateName, DepUsage>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CrateDeps {
+    pub main: IndexMap<CrateName, CrateDep>,
+    pub dev: IndexMap<CrateName, CrateDep>,
+    pub build: IndexMap<CrateName, CrateDep>,
+    pub usage: DepUsages,
+    /// Crates.io dependencies the project overrides in `[patch.crates-io]`
+    pub patched: HashSet<CrateName>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AnalyzedDependency {
+    pub required: VersionReq,
+    pub latest_that_matches: Option<Version>,
+    pub latest: Option<Version>,
+    pub vulnerabilities: Vec<Advisory>,
+    /// Informational advisories, e.g. about the crate being unmaintained or unsound
+    pub informational: Vec<Advisory>,
+    /// Whether any release of the crate exists that hasn't been yanked
+    pub available: bool,
+    /// Whether the requirement is only satisfied by yanked releases, e.g. when it pins one
+    pub yanked: bool,
+    pub deprecation: Option<Deprecation>,
+    /// Alternative registry the crate comes from. It is only analyzed if the server has
+    /// access to the registry's index.
+    pub registry: Option<String>,
+    /// Line of the manifest the dependency is declared on, if known
+    pub manifest_line: Option<usize>,
+    pub usage: Option<DepUsage>,
+    /// Version the dependency is resolved to in `Cargo.lock`, when analyzing with it
+    pub locked: Option<Version>,
+    /// `rust-version` of the newest release the requirement allows, if it declares one
+    pub rust_version: Option<RustVersion>,
+    /// `rust-version` of the newest release, if it declares one
+    pub latest_rust_version: Option<RustVersion>,
+    /// SPDX license expression of the newest release the requirement allows, if known
+    pub license: Option<String>,
+    /// URL of the crate's source repository from crates.io, if it links one
+    pub repository: Option<String>,
+    /// Short description of the crate from crates.io
+    pub description: Option<String>,
+    /// Whether the project overrides the crate in `[patch.crates-io]`, in which case it isn't
+    /// counted as outdated unless asked for
+    pub patched: bool,
+}
+
+impl AnalyzedDependency {
+    pub fn new(required: VersionReq) -> AnalyzedDependency {
+        AnalyzedDependency {
+            required,
+            latest_that_matches: None,
+            latest: None,
+            vulnerabilities: Vec::new(),
+            informational: Vec::new(),
+            available: false,
+            yanked: false,
+            deprecation: None,
+            registry: None,
+            manifest_line: None,
+            usage: None,
+            locked: None,
+            rust_version: None,
+            latest_rust_version: None,
+            license: None,
+            repository: None,
+            description: None,
+            patched: false,
+        }
+    }
+
+    pub fn from_registry(required: VersionReq, registry: String) -> AnalyzedDependency {
+        AnalyzedDependency {
+            registry: Some(registry),
+            ..AnalyzedDependency::new(required)
+        }
+    }
+
+    /// Returns `true` if the crate comes from a registry other than crates.io
+    pub fn is_from_external_registry(&self) -> bool {
+        self.registry.is_some()
+    }
+
+    /// Returns `true` for crates from an alternative registry that couldn't be looked up
+    pub fn is_unanalyzed(&self) -> bool {
+        self.is_from_external_registry() && !self.available
+    }
+
+    /// Returns `true` if the crate has been removed or all of its releases are yanked
+    pub fn is_unavailable(&self) -> bool {
+        !self.available && !self.is_from_external_registry()
+    }
+
+    pub fn is_insecure(&self) -> bool {
+        !self.vulnerabilities.is_empty()
+    }
+
+    /// Returns `true` if both have the same requirement and status
+    fn same_state(&self, other: &AnalyzedDependency) -> bool {
+        self.required == other.required
+            && self.is_unanalyzed() == other.is_unanalyzed()
+            && self.is_unavailable() == other.is_unavailable()
+            && self.is_insecure() == other.is_insecure()
+            && self.is_yanked() == other.is_yanked()
+            && self.is_outdated() == other.is_outdated()
+    }
+
+    /// Returns `true` if the crate has releases, but all that the requirement allows are yanked
+    pub fn is_yanked(&self) -> bool {
+        self.yanked && self.available
+    }
+
+    /// Returns `true` if there is a newer release than the locked version, or else the newest
+    /// one the requirement allows
+    pub fn is_outdated(&self) -> bool {
+        let resolved = self.locked.as_ref().or(self.latest_that_matches.as_ref());
+        self.latest.as_ref() > resolved
+    }
+
+    /// Classifies how far an outdated dependency is behind the latest release
+    pub fn lag(&self) -> Option<VersionLag> {
+        if !self.is_outdated() {
+            return None;
+        }
+        let latest = self.latest.as_ref()?;
+        let resolved = match self.locked.as_ref().or(self.latest_that_matches.as_ref()) {
+            Some(resolved) => resolved,
+            None => return Some(VersionLag::Major),
+        };
+
+        if compatibility_key(latest) != compatibility_key(resolved) {
+            Some(VersionLag::Major)
+        } else if latest.minor != resolved.minor {
+            Some(VersionLag::Minor)
+        } else {
+            Some(VersionLag::Patch)
+        }
+    }
+
+    /// Returns `true` if the dependency needs a newer Rust than `msrv`
+    pub fn exceeds_msrv(&self, msrv: &RustVersion) -> bool {
+        self.rust_version
+            .as_ref()
+            .map_or(false, |rust_version| rust_version > msrv)
+    }
+
+    /// Returns `true` if upgrading to the newest release would need a newer Rust than `msrv`
+    pub fn latest_exceeds_msrv(&self, msrv: &RustVersion) -> bool {
+        self.latest_rust_version
+            .as_ref()
+            .map_or(false, |rust_version| rust_version > msrv)
+    }
+
+    /// Returns the diff.rs URL comparing the resolved version with the latest one, if the
+    /// dependency is outdated
+    pub fn diff_url(&self, name: &str) -> Option<String> {
+        if !self.is_outdated() {
+            return None;
+        }
+        let resolved = self.locked.as_ref().or(self.latest_that_matches.as_ref())?;
+        let latest = self.latest.as_ref()?;
+
+        Some(format!("https://diff.rs/{}/{}/{}", name, resolved, latest))
+    }
+
+    pub fn deps_rs_path(&self, name: &str) -> String {
+        match &self.latest_that_matches {
+            Some(version) => ["/crate/", name, "/", version.to_string().as_str()].concat(),
+            None => ["/crate/", name].concat(),
+        }
+    }
+}
+
+/// How far a dependency is behind its latest release, by semver compatibility
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionLag {
+    /// Only newer patch releases, e.g. 1.2.3 to 1.2.4
+    Patch,
+    /// Newer compatible releases with new features, e.g. 1.2.3 to 1.3.0
+    Minor,
+    /// A semver-incompatible release, e.g. 1.2.3 to 2.0.0 or 0.2.3 to 0.3.0
+    Major,
+}
+
+impl VersionLag {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VersionLag::Patch => "patch",
+            VersionLag::Minor => "minor",
+            VersionLag::Major => "major",
+        }
+    }
+}
+
+/// Notice that a crate is deprecated or has been superseded by another one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// Crate that should be used instead, if known
+    pub replacement: Option<String>,
+}
+
+/// Kinds of dependencies a count covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepScope {
+    pub main: bool,
+    pub dev: bool,
+    pub build: bool,
+    /// Whether dependencies overridden in `[patch.crates-io]` count as outdated
+    pub patched: bool,
+}
+
+impl DepScope {
+    /// Main and build dependencies, which the status covers unless asked otherwise
+    pub const STATUS: DepScope = DepScope {
+        main: true,
+        dev: false,
+        build: true,
+        patched: false,
+    };
+
+    /// Only `dev-dependencies`
+    pub const DEV: DepScope = DepScope {
+        main: false,
+        dev: true,
+        build: false,
+        patched: false,
+    };
+}
+
+impl Default for DepScope {
+    fn default() -> DepScope {
+        DepScope::STATUS
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnalyzedDependencies {
+    pub main: IndexMap<CrateName, AnalyzedDependency>,
+    pub dev: IndexMap<CrateName, AnalyzedDependency>,
+    pub build: IndexMap<CrateName, AnalyzedDependency>,
+    /// Git dependencies of all kinds, which are only checked for stale pins
+    pub git: IndexMap<CrateName, AnalyzedGitDependency>,
+}
+
+impl AnalyzedDependencies {
+    pub fn new(deps: &CrateDeps) -> AnalyzedDependencies {
+        AnalyzedDependencies {
+            main: analyzable(&deps.main, &deps.usage.main, &deps.patched),
+            dev: analyzable(&deps.dev, &deps.usage.dev, &deps.patched),
+            build: analyzable(&deps.build, &deps.usage.build, &deps.patched),
+            git: git_dependencies(deps),
+        }
+    }
+
+    /// Records the manifest lines the dependencies are declared on
+    pub fn set_manifest_lines(&mut self, lines: &DependencyLines) {
+        for (deps, lines) in [
+            (&mut self.main, &lines.main),
+            (&mut self.dev, &lines.dev),
+            (&mut self.build, &lines.build),
+        ] {
+            for (name, dep) in deps.iter_mut() {
+                dep.manifest_line = lines.get(name.as_ref()).copied();
+            }
+        }
+    }
+
+    /// Iterates over the dependencies of the kinds in `scope`
+    fn scoped(&self, scope: DepScope) -> impl Iterator<Item = &AnalyzedDependency> {
+        IntoIterator::into_iter([
+            (scope.main, &self.main),
+            (scope.dev, &self.dev),
+            (scope.build, &self.build),
+        ])
+        .filter(|&(included, _)| included)
+        .flat_map(|(_, deps)| deps.values())
+    }
+
+    /// Counts the total number of analyzed dependencies in `scope`
+    pub fn count_total(&self, scope: DepScope) -> usize {
+        self.scoped(scope)
+            .filter(|dep| !dep.is_unanalyzed())
+            .count()
+    }
+
+    /// Returns the number of outdated dependencies in `scope`
+    pub fn count_outdated(&self, scope: DepScope) -> usize {
+        self.scoped(scope)
+            .filter(|dep| scope.patched || !dep.patched)
+            .filter(|dep| dep.is_outdated())
+            .count()
+    }
+
+    /// Returns the largest lag of the outdated dependencies in `scope`
+    pub fn max_lag(&self, scope: DepScope) -> Option<VersionLag> {
+        self.scoped(scope)
+            .filter(|dep| scope.patched || !dep.patched)
+            .filter_map(|dep| dep.lag())
+            .max()
+    }
+
+    /// Returns the number of main and build dependencies that need a newer Rust than `msrv`
+    pub fn count_exceeding_msrv(&self, msrv: &RustVersion) -> usize {
+        self.main
+            .values()
+            .chain(self.build.values())
+            .filter(|dep| dep.exceeds_msrv(msrv))
+            .count()
+    }
+
+    /// Returns the number of insecure dependencies in `scope`
+    pub fn count_insecure(&self, scope: DepScope) -> usize {
+        self.scoped(scope).filter(|dep| dep.is_insecure()).count()
+    }
+
+    /// Returns the number of git dependencies pinned to a commit far behind their repository
+    pub fn count_stale_git(&self) -> usize {
+        self.git.values().filter(|dep| dep.is_stale()).count()
+    }
+
+    /// Checks if any outdated main or build dependencies exist, leaving out patched ones
+    pub fn any_outdated(&self) -> bool {
+        self.count_outdated(DepScope::STATUS) > 0
+    }
+
+    /// Checks if any dependencies have been removed or had all of their releases yanked
+    pub fn any_unavailable(&self) -> bool {
+        self.main
+            .iter()
+            .chain(&self.dev)
+            .chain(&self.build)
+            .any(|(_, dep)| dep.is_unavailable())
+    }
+
+    /// Checks if any dependencies can only be resolved to yanked releases
+    pub fn any_yanked(&self) -> bool {
+        self.main
+            .iter()
+            .chain(&self.dev)
+            .chain(&self.build)
+            .any(|(_, dep)| dep.is_yanked())
+    }
+
+    /// Returns `true` if any dev-dependencies are either insecure or outdated.
+    pub fn any_dev_issues(&self) -> bool {
+        self.dev
+            .iter()
+            .any(|(_, dep)| (dep.is_outdated() && !dep.patched) || dep.is_insecure())
+    }
+
+    /// Lists the dependencies that were added, removed or whose requirement or status changed
+    /// from `before` to these, main dependencies first
+    pub fn changes_since(&self, before: &AnalyzedDependencies) -> Vec<DependencyChange> {
+        let mut changes = Vec::new();
+
+        for (kind, old_deps, new_deps) in [
+            ("main", &before.main, &self.main),
+            ("build", &before.build, &self.build),
+            ("dev", &before.dev, &self.dev),
+        ] {
+            for (name, old) in old_deps {
+                let new = new_deps.get(name);
+                if new.map_or(true, |new| !new.same_state(old)) {
+                    changes.push(DependencyChange {
+                        name: name.clone(),
+                        kind,
+                        before: Some(old.clone()),
+                        after: new.cloned(),
+                    });
+                }
+            }
+
+            for (name, new) in new_deps {
+                if !old_deps.contains_key(name) {
+                    changes.push(DependencyChange {
+                        name: name.clone(),
+                        kind,
+                        before: None,
+                        after: Some(new.clone()),
+                    });
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// A dependency that differs between two analyses
+#[derive(Clone, Debug)]
+pub struct DependencyChange {
+    pub name: CrateName,
+    /// `main`, `dev` or `build`
+    pub kind: &'static str,
+    /// Unset if the dependency was added
+    pub before: Option<AnalyzedDependency>,
+    /// Unset if the dependency was removed
+    pub after: Option<AnalyzedDependency>,
+}
+
+/// Selects the dependencies which aren't part of the analyzed project itself
+fn analyzable(
+    deps: &IndexMap<CrateName, CrateDep>,
+    usage: &HashMap<CrateName, DepUsage>,
+    patched: &HashSet<CrateName>,
+) -> IndexMap<CrateName, AnalyzedDependency> {
+    deps.iter()
+        .filter_map(|(name, dep)| {
+            let mut analyzed = match dep {
+                CrateDep::External(req) => AnalyzedDependency::new(req.clone()),
+                CrateDep::Registry(registry, req) => {
+                    AnalyzedDependency::from_registry(req.clone(), registry.clone())
+                }
+                CrateDep::Internal(..) | CrateDep::Git(_) => return None,
+            };
+            analyzed.usage = usage.get(name).cloned();
+            analyzed.patched = patched.contains(name);
+            Some((name.clone(), analyzed))
+        })
+        .collect()
+}
+
+/// Collects the git dependencies, the first declaration of a crate wins
+fn git_dependencies(deps: &CrateDeps) -> IndexMap<CrateName, AnalyzedGitDependency> {
+    let mut git = IndexMap::new();
+    for (name, dep) in deps.main.iter().chain(&deps.dev).chain(&deps.build) {
+        if let CrateDep::Git(dep) = dep {
+            git.entry(name.clone())
+                .or_insert_with(|| AnalyzedGitDependency {
+                    source: dep.clone(),
+                    lag: None,
+                });
+        }
+    }
+    git
+}
+
+/// Requirements of the same external crate by different workspace members which resolve to
+/// semver-incompatible versions, so that the crate ends up being built more than once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequirementConflict {
+    pub name: CrateName,
+    /// Members and their requirements of the crate
+    pub requirements: Vec<(CrateName, VersionReq)>,
+}
+
+/// Returns the part of a version that has to match for two versions to be semver-compatible,
+/// i.e. the left-most non-zero component
+pub fn compatibility_key(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor, version.patch) {
+        (0, 0, patch) => (0, 0, patch),
+        (0, minor, _) => (0, minor, 0),
+        (major, _, _) => (major, 0, 0),
+    }
+}
+
+/// A path dependency whose version requirement doesn't match the current version of the
+/// crate it points to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InternalVersionMismatch {
+    pub dependent: CrateName,
+    pub dependency: CrateName,
+    pub required: VersionReq,
+    pub actual: Version,
+}
+
+/// A manifest that couldn't be analyzed, the crates of the other manifests are analyzed
+/// without it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestError {
+    /// Path of the `Cargo.toml`
+    pub path: RelativePathBuf,
+    pub message: String,
+}
+
+/// A crate that `Cargo.lock` contains in several semver-incompatible versions
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateDependency {
+    pub name: CrateName,
+    /// Newest locked version of each incompatible release line, oldest first
+    pub versions: Vec<Version>,
+}
+
+/// 1-based line numbers on which dependencies are declared in a manifest, by crate name
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DependencyLines {
+    pub main: HashMap<String, usize>,
+    pub dev: HashMap<String, usize>,
+    pub build: HashMap<String, usize>,
+}
+
+/// Metadata from the `[package]` section of a manifest
+#[derive(Clone, Debug)]
+pub struct CratePackage {
+    pub name: CrateName,
+    /// Unset if not declared or inherited from the workspace
+    pub version: Option<Version>,
+    /// Unset if not declared or inherited from the workspace
+    pub rust_version: Option<RustVersion>,
+    pub metadata: PackageMetadata,
+}
+
+/// Descriptive fields of a `[package]` section, each unset if not declared or inherited from
+/// the workspace
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageMetadata {
+    pub edition: Option<String>,
+    pub description: Option<String>,
+    pub repository: Option<String>,
+    /// Whether `publish = false` keeps the crate off crates.io
+    pub unpublished: bool,
+}
+
+/// What crates.io lists about a release of a crate
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CratesIoInfo {
+    pub description: Option<String>,
+    /// SPDX license expression of the release, if it declares one
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    /// Downloads of all releases of the crate
+    pub downloads: Option<u64>,
+}
+
+impl PackageMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.edition.is_none()
+            && self.description.is_none()
+            && self.repository.is_none()
+            && !self.unpublished
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CrateManifest {
+    Package(CratePackage, CrateDeps),
+    Workspace {
+        members: Vec<RelativePathBuf>,
+        /// Crates.io dependencies of the members the workspace overrides
+        patched: HashSet<CrateName>,
+    },
+    Mixed {
+        package: CratePackage,
+        deps: CrateDeps,
+        members: Vec<RelativePathBuf>,
+    },
+}
+
+impl CrateManifest {
+    pub fn package(&self) -> Option<&CratePackage> {
+        match self {
+            CrateManifest::Package(package, _) | CrateManifest::Mixed { package, .. } => {
+                Some(package)
+            }
+            CrateManifest::Workspace { .. } => None,
+        }
+    }
+
+    /// Returns the crates the manifest overrides in `[patch.crates-io]`
+    pub fn patched(&self) -> &HashSet<CrateName> {
+        match self {
+            CrateManifest::Package(_, deps) | CrateManifest::Mixed { deps, .. } => &deps.patched,
+            CrateManifest::Workspace { patched, .. } => patched,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerant_crate_path_parsing() {
+        let expected = CratePath::from_parts("serde", "1.0.130").unwrap();
+
+        for (name, version) in &[
+            ("serde", "v1.0.130"),
+            ("serde", " 1.0.130 "),
+            ("serde%20", "%201.0.130"),
+            ("serde", "V1.0.130%0A"),
+        ] {
+            assert_eq!(CratePath::from_parts(name, version).unwrap(), expected);
+        }
+
+        assert!(CratePath::from_parts("serde", "vv1.0.130").is_err());
+        assert!(CratePath::from_parts("serde", "1.0").is_err());
+    }
+
+    #[test]
+    fn rust_version_parsing() {
+        let short: RustVersion = "1.56".parse().unwrap();
+        let full: RustVersion = "1.56.1".parse().unwrap();
+
+        assert_eq!(short.to_string(), "1.56");
+        assert_eq!(full.to_string(), "1.56.1");
+        assert!(short < full);
+        assert!(full < "1.60".parse().unwrap());
+
+        for invalid in &["1", "^1.56", "1.56.0-beta", "1.56.1.2", ""] {
+            assert!(invalid.parse::<RustVersion>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn counts_dependencies_in_scope() {
+        let mut deps = CrateDeps::default();
+        for (kind, name) in [
+            (&mut deps.main, "serde"),
+            (&mut deps.dev, "criterion"),
+            (&mut deps.build, "cc"),
+        ] {
+            kind.insert(
+                name.parse().unwrap(),
+                CrateDep::External("1".parse().unwrap()),
+            );
+        }
+
+        let mut analyzed = AnalyzedDependencies::new(&deps);
+        for dep in analyzed
+            .main
+            .values_mut()
+            .chain(analyzed.dev.values_mut())
+            .chain(analyzed.build.values_mut())
+        {
+            dep.available = true;
+            dep.latest_that_matches = Some("1.0.0".parse().unwrap());
+            dep.latest = Some("2.0.0".parse().unwrap());
+        }
+
+        assert_eq!(analyzed.count_outdated(DepScope::STATUS), 2);
+        assert_eq!(analyzed.count_outdated(DepScope::DEV), 1);
+        let main_only = DepScope {
+            build: false,
+            ..DepScope::STATUS
+        };
+        assert_eq!(analyzed.count_total(main_only), 1);
+
+        analyzed
+            .build
+            .values_mut()
+            .for_each(|dep| dep.patched = true);
+        assert_eq!(analyzed.count_outdated(DepScope::STATUS), 1);
+        let with_patched = DepScope {
+            patched: true,
+            ..DepScope::STATUS
+        };
+        assert_eq!(analyzed.count_outdated(with_patched), 2);
+    }
+
+    #[test]
+    fn lists_changed_dependencies() {
+        let dep = |req: &str| AnalyzedDependency::new(req.parse().unwrap());
+        let mut before = AnalyzedDependencies::default();
+        before.main.insert("serde".parse().unwrap(), dep("1.0"));
+        before.main.insert("rand".parse().unwrap(), dep("0.7"));
+        before.main.insert("log".parse().unwrap(), dep("0.4"));
+
+        let mut after = before.clone();
+        after.main.shift_remove("log");
+        after.main.insert("rand".parse().unwrap(), dep("0.8"));
+        after.dev.insert("criterion".parse().unwrap(), dep("0.3"));
+
+        let changes: Vec<_> = after
+            .changes_since(&before)
+            .into_iter()
+            .map(|change| {
+                (
+                    change.name.as_ref().to_owned(),
+                    change.kind,
+                    change.before.is_some(),
+                    change.after.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("rand".to_owned(), "main", true, true),
+                ("log".to_owned(), "main", true, false),
+                ("criterion".to_owned(), "dev", false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_lag_by_semver_compatibility() {
+        let lag = |resolved: &str, latest: &str| {
+            let mut dep = AnalyzedDependency::new("*".parse().unwrap());
+            dep.latest_that_matches = Some(resolved.parse().unwrap());
+            dep.latest = Some(latest.parse().unwrap());
+            dep.lag()
+        };
+
+        assert_eq!(lag("1.2.3", "1.2.3"), None);
+        assert_eq!(lag("1.2.3", "1.2.4"), Some(VersionLag::Patch));
+        assert_eq!(lag("1.2.3", "1.3.0"), Some(VersionLag::Minor));
+        assert_eq!(lag("1.2.3", "2.0.0"), Some(VersionLag::Major));
+        assert_eq!(lag("0.2.3", "0.2.4"), Some(VersionLag::Patch));
+        assert_eq!(lag("0.2.3", "0.3.0"), Some(VersionLag::Major));
+    }
+
+    #[test]
+    fn crate_release_roundtrips_through_json() {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            "serde".parse().unwrap(),
+            CrateDep::External("1".parse().unwrap()),
+        );
+        let release = CrateRelease {
+            name: "serde_json".parse().unwrap(),
+            version: "1.0.64".parse().unwrap(),
+            deps,
+            yanked: false,
+            rust_version: Some("1.36".parse().unwrap()),
+        };
+
+        let json = serde_json::to_string(&release).unwrap();
+        let parsed: CrateRelease = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.name, release.name);
+        assert_eq!(parsed.deps.main, release.deps.main);
+        assert_eq!(parsed.rust_version, release.rust_version);
+
+        assert!(serde_json::from_str::<CrateName>(r#""not a crate""#).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! The dependency analysis of deps.rs, without the web server.
+//!
+//! Manifests are parsed with [`manifest::parse_manifest_toml`], and their dependencies are
+//! compared against the releases of the dependencies by an [`analyzer::DependencyAnalyzer`].
+//! [`analyze_manifest`] does both, given a [`ReleaseSource`] to look up releases in.
+
+use std::sync::Arc;
+
+use anyhow::Error;
+use rustsec::database::Database;
+
+use crate::analyzer::DependencyAnalyzer;
+use crate::crates::{AnalyzedDependencies, CrateDeps, CrateManifest, CrateName, CrateRelease};
+
+pub mod analyzer;
+pub mod crates;
+pub mod manifest;
+
+/// Looks up all releases of a crate, e.g. in a crates.io index
+pub trait ReleaseSource {
+    /// Returns no releases for a crate that doesn't exist
+    fn releases(&self, name: &CrateName) -> Result<Vec<CrateRelease>, Error>;
+}
+
+/// Analyzes dependencies against their releases. Only dependencies on crates.io crates are
+/// looked up, path and alternative registry dependencies are left unanalyzed. Without an
+/// `advisory_db`, no vulnerabilities are reported.
+pub fn analyze_dependencies(
+    deps: &CrateDeps,
+    source: &impl ReleaseSource,
+    advisory_db: Option<Arc<Database>>,
+) -> Result<AnalyzedDependencies, Error> {
+    let mut analyzer = DependencyAnalyzer::new(deps, advisory_db);
+
+    let external = deps
+        .main
+        .iter()
+        .chain(&deps.dev)
+        .chain(&deps.build)
+        .filter(|(_, dep)| dep.is_external())
+        .map(|(name, _)| name);
+    for name in external {
+        analyzer.process(source.releases(name)?);
+    }
+
+    Ok(analyzer.finalize())
+}
+
+/// Parses a `Cargo.toml` and analyzes the dependencies of the package it declares. A virtual
+/// workspace manifest has no package, and `None` is returned for it.
+pub fn analyze_manifest(
+    input: &str,
+    source: &impl ReleaseSource,
+    advisory_db: Option<Arc<Database>>,
+) -> Result<Option<(CrateName, AnalyzedDependencies)>, Error> {
+    let (package, deps) = match manifest::parse_manifest_toml(input)? {
+        CrateManifest::Package(package, deps) | CrateManifest::Mixed { package, deps, .. } => {
+            (package, deps)
+        }
+        CrateManifest::Workspace { .. } => return Ok(None),
+    };
+
+    let analyzed = analyze_dependencies(&deps, source, advisory_db)?;
+    Ok(Some((package.name, analyzed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    struct Releases(HashMap<&'static str, Vec<&'static str>>);
+
+    impl ReleaseSource for Releases {
+        fn releases(&self, name: &CrateName) -> Result<Vec<CrateRelease>, Error> {
+            let versions = self.0.get(name.as_ref()).cloned().unwrap_or_default();
+
+            Ok(versions
+                .into_iter()
+                .map(|version| CrateRelease {
+                    name: name.clone(),
+                    version: version.parse().unwrap(),
+                    deps: CrateDeps::default(),
+                    yanked: false,
+                    rust_version: None,
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn analyzes_manifest() {
+        let source = Releases(
+            vec![
+                ("serde", vec!["1.0.0", "1.0.100"]),
+                ("rand", vec!["0.7.3", "0.8.5"]),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let manifest = r#"
+            [package]
+            name = "demo"
+
+            [dependencies]
+            serde = "1.0"
+            rand = "0.7"
+            local = { path = "../local" }
+        "#;
+
+        let (name, analyzed) = analyze_manifest(manifest, &source, None).unwrap().unwrap();
+        assert_eq!(name.as_ref(), "demo");
+        assert!(!analyzed.main["serde"].is_outdated());
+        assert!(analyzed.main["rand"].is_outdated());
+        assert_eq!(analyzed.main.len(), 2);
+
+        let workspace = "[workspace]\nmembers = [\"a\"]";
+        assert!(analyze_manifest(workspace, &source, None)
+            .unwrap()
+            .is_none());
+    }
+}
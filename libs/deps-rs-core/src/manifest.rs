@@ -0,0 +1,741 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Error};
+use indexmap::IndexMap;
+use relative_path::RelativePathBuf;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::crates::{
+    CrateDep, CrateDeps, CrateManifest, CrateName, CratePackage, DepUsage, DepUsages,
+    DependencyLines, GitDependency, GitReference, PackageMetadata, RustVersion,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CargoTomlComplexDependency {
+    git: Option<String>,
+    branch: Option<String>,
+    tag: Option<String>,
+    rev: Option<String>,
+    path: Option<RelativePathBuf>,
+    version: Option<String>,
+    package: Option<String>,
+    registry: Option<String>,
+    #[serde(rename = "registry-index")]
+    registry_index: Option<String>,
+    #[serde(default)]
+    optional: bool,
+    #[serde(rename = "default-features", alias = "default_features")]
+    default_features: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum CargoTomlDependency {
+    Simple(String),
+    Complex(CargoTomlComplexDependency),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CargoTomlPackage {
+    name: String,
+    // either a version string or a `{ workspace = true }` table
+    #[serde(default)]
+    version: Option<toml::Value>,
+    // same as `version`
+    #[serde(default, rename = "rust-version")]
+    rust_version: Option<toml::Value>,
+    // same as `version`
+    #[serde(default)]
+    edition: Option<toml::Value>,
+    // same as `version`
+    #[serde(default)]
+    description: Option<toml::Value>,
+    // same as `version`
+    #[serde(default)]
+    repository: Option<toml::Value>,
+    // a boolean, a list of registries or `{ workspace = true }`
+    #[serde(default)]
+    publish: Option<toml::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CargoTomlWorkspace {
+    #[serde(default)]
+    members: Vec<RelativePathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CargoToml {
+    #[serde(default)]
+    package: Option<CargoTomlPackage>,
+    #[serde(default)]
+    workspace: Option<CargoTomlWorkspace>,
+    #[serde(default)]
+    dependencies: IndexMap<String, CargoTomlDependency>,
+    #[serde(rename = "dev-dependencies")]
+    #[serde(default)]
+    dev_dependencies: IndexMap<String, CargoTomlDependency>,
+    #[serde(rename = "build-dependencies")]
+    #[serde(default)]
+    build_dependencies: IndexMap<String, CargoTomlDependency>,
+    /// Overridden dependencies, by the registry or URL of the source they come from
+    #[serde(default)]
+    patch: IndexMap<String, IndexMap<String, CargoTomlDependency>>,
+    #[serde(default)]
+    features: IndexMap<String, Vec<String>>,
+}
+
+/// Returns the features that enable the optional dependency declared as `key`, directly or
+/// through other features, in the order they are declared. The implicit feature named after
+/// the dependency comes first, unless the dependency is only referred to as `dep:key`.
+pub fn enabling_features(key: &str, features: &IndexMap<String, Vec<String>>) -> Vec<String> {
+    // `key?/feature` only enables a feature of the dependency if something else enables it
+    let enables = |item: &str| {
+        let item = item.strip_prefix("dep:").unwrap_or(item);
+        item == key || item.split_once('/').map_or(false, |(dep, _)| dep == key)
+    };
+
+    let mut enabling = Vec::new();
+    for name in features.keys() {
+        let mut pending = vec![name.as_str()];
+        let mut seen = HashSet::new();
+        while let Some(feature) = pending.pop() {
+            if !seen.insert(feature) {
+                continue;
+            }
+            let items = match features.get(feature) {
+                Some(items) => items,
+                None => continue,
+            };
+            if items.iter().any(|item| enables(item.as_str())) {
+                enabling.push(name.clone());
+                break;
+            }
+            pending.extend(items.iter().map(String::as_str));
+        }
+    }
+
+    let dep_syntax = format!("dep:{}", key);
+    let has_implicit_feature =
+        !features.contains_key(key) && !features.values().flatten().any(|item| *item == dep_syntax);
+    if has_implicit_feature {
+        enabling.insert(0, key.to_owned());
+    }
+
+    enabling
+}
+
+/// Describes how a dependency is pulled in if it isn't plain, under the name of the crate it
+/// refers to
+fn dependency_usage(
+    key: &str,
+    dep: &CargoTomlDependency,
+    features: &IndexMap<String, Vec<String>>,
+) -> Option<(String, DepUsage)> {
+    let cplx = match dep {
+        CargoTomlDependency::Complex(cplx) => cplx,
+        CargoTomlDependency::Simple(_) => return None,
+    };
+
+    let usage = DepUsage {
+        optional: cplx.optional,
+        default_features: cplx.default_features.unwrap_or(true),
+        target: None,
+        features: if cplx.optional {
+            enabling_features(key, features)
+        } else {
+            Vec::new()
+        },
+    };
+    if usage.is_plain() {
+        return None;
+    }
+
+    let name = cplx.package.clone().unwrap_or_else(|| key.to_owned());
+    Some((name, usage))
+}
+
+/// Collects the usage of one kind of dependencies, the first declaration of a crate wins
+fn dependencies_usage(
+    deps: &IndexMap<String, CargoTomlDependency>,
+    features: &IndexMap<String, Vec<String>>,
+) -> HashMap<CrateName, DepUsage> {
+    let mut usage = HashMap::new();
+    for (key, dep) in deps {
+        if let Some((name, dep_usage)) = dependency_usage(key, dep, features) {
+            if let Ok(name) = name.parse::<CrateName>() {
+                usage.entry(name).or_insert(dep_usage);
+            }
+        }
+    }
+    usage
+}
+
+/// Returns the names of the crates.io crates a manifest patches, which are the packages the
+/// patches replace rather than what they are called in the manifest
+fn patched_crates(
+    patch: &mut IndexMap<String, IndexMap<String, CargoTomlDependency>>,
+) -> Result<HashSet<CrateName>, Error> {
+    patch
+        .shift_remove("crates-io")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, dep)| match dep {
+            CargoTomlDependency::Complex(CargoTomlComplexDependency {
+                package: Some(package),
+                ..
+            }) => package.parse(),
+            _ => name.parse(),
+        })
+        .collect()
+}
+
+/// Returns a string field of `[package]`, unless it is inherited from the workspace
+fn package_string(value: &Option<toml::Value>) -> Option<String> {
+    value.as_ref()?.as_str().map(ToOwned::to_owned)
+}
+
+fn convert_dependency(
+    cargo_dep: (String, CargoTomlDependency),
+) -> Option<Result<(CrateName, CrateDep), Error>> {
+    match cargo_dep {
+        (name, CargoTomlDependency::Simple(string)) => {
+            Some(name.parse::<CrateName>().and_then(|parsed_name| {
+                string
+                    .parse::<VersionReq>()
+                    .map_err(|err| err.into())
+                    .map(|version| (parsed_name, CrateDep::External(version)))
+            }))
+        }
+        (name, CargoTomlDependency::Complex(cplx)) => {
+            if let Some(url) = cplx.git {
+                let reference = match (cplx.branch, cplx.tag, cplx.rev) {
+                    (Some(branch), _, _) => Some(GitReference::Branch(branch)),
+                    (_, Some(tag), _) => Some(GitReference::Tag(tag)),
+                    (_, _, Some(rev)) => Some(GitReference::Rev(rev)),
+                    _ => None,
+                };
+                let name = cplx.package.as_deref().unwrap_or(&name);
+                Some(name.parse::<CrateName>().map(|parsed_name| {
+                    (parsed_name, CrateDep::Git(GitDependency { url, reference }))
+                }))
+            } else if let Some(path) = cplx.path {
+                let version = cplx.version;
+                Some(name.parse::<CrateName>().and_then(|parsed_name| {
+                    let required = version.as_deref().map(VersionReq::parse).transpose()?;
+                    Ok((parsed_name, CrateDep::Internal(path, required)))
+                }))
+            } else {
+                let registry = cplx.registry.or(cplx.registry_index);
+                cplx.version.as_deref().map(|version| {
+                    let name = cplx.package.as_deref().unwrap_or(&name);
+                    name.parse::<CrateName>().and_then(|parsed_name| {
+                        let version = version.parse::<VersionReq>()?;
+                        let dep = match registry {
+                            Some(registry) => CrateDep::Registry(registry, version),
+                            None => CrateDep::External(version),
+                        };
+                        Ok((parsed_name, dep))
+                    })
+                })
+            }
+        }
+    }
+}
+
+pub fn parse_manifest_toml(input: &str) -> Result<CrateManifest, Error> {
+    let mut cargo_toml = toml::de::from_str::<CargoToml>(input)?;
+    let patched = patched_crates(&mut cargo_toml.patch)?;
+
+    let mut package_part = None;
+    let mut workspace_part = None;
+
+    if let Some(package) = cargo_toml.package {
+        let crate_name = package.name.parse::<CrateName>()?;
+        let version = package
+            .version
+            .as_ref()
+            .and_then(|version| version.as_str())
+            .and_then(|version| version.parse::<Version>().ok());
+        let rust_version = package
+            .rust_version
+            .as_ref()
+            .and_then(|rust_version| rust_version.as_str())
+            .and_then(|rust_version| rust_version.parse::<RustVersion>().ok());
+        let metadata = PackageMetadata {
+            edition: package_string(&package.edition),
+            description: package_string(&package.description),
+            repository: package_string(&package.repository),
+            unpublished: match package.publish {
+                Some(toml::Value::Boolean(publish)) => !publish,
+                Some(toml::Value::Array(ref registries)) => registries.is_empty(),
+                _ => false,
+            },
+        };
+
+        let usage = DepUsages {
+            main: dependencies_usage(&cargo_toml.dependencies, &cargo_toml.features),
+            dev: dependencies_usage(&cargo_toml.dev_dependencies, &cargo_toml.features),
+            build: dependencies_usage(&cargo_toml.build_dependencies, &cargo_toml.features),
+        };
+
+        let dependencies = cargo_toml
+            .dependencies
+            .into_iter()
+            .filter_map(convert_dependency)
+            .collect::<Result<IndexMap<_, _>, _>>()?;
+        let dev_dependencies = cargo_toml
+            .dev_dependencies
+            .into_iter()
+            .filter_map(convert_dependency)
+            .collect::<Result<IndexMap<_, _>, _>>()?;
+        let build_dependencies = cargo_toml
+            .build_dependencies
+            .into_iter()
+            .filter_map(convert_dependency)
+            .collect::<Result<IndexMap<_, _>, _>>()?;
+
+        let deps = CrateDeps {
+            main: dependencies,
+            dev: dev_dependencies,
+            build: build_dependencies,
+            usage,
+            patched: patched.clone(),
+        };
+
+        let package = CratePackage {
+            name: crate_name,
+            version,
+            rust_version,
+            metadata,
+        };
+
+        package_part = Some((package, deps));
+    }
+
+    if let Some(workspace) = cargo_toml.workspace {
+        workspace_part = Some(workspace.members);
+    }
+
+    match (package_part, workspace_part) {
+        (Some((package, deps)), None) => Ok(CrateManifest::Package(package, deps)),
+        (None, Some(members)) => Ok(CrateManifest::Workspace { members, patched }),
+        (Some((package, deps)), Some(members)) => Ok(CrateManifest::Mixed {
+            package,
+            deps,
+            members,
+        }),
+        (None, None) => Err(anyhow!("neither workspace nor package found in manifest")),
+    }
+}
+
+/// Finds the lines on which dependencies are declared by scanning the raw manifest, since the
+/// TOML parser doesn't keep track of positions. Platform-specific dependencies are skipped,
+/// as they aren't analyzed either.
+pub fn find_dependency_lines(input: &str) -> DependencyLines {
+    let mut lines = DependencyLines::default();
+    let mut section = None;
+
+    for (idx, line) in input.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            section = dependency_section(header);
+
+            if let Some((kind, Some(name))) = section {
+                kind_lines(&mut lines, kind)
+                    .entry(unquote(name).to_owned())
+                    .or_insert(line_no);
+            }
+        } else if let Some((kind, None)) = section {
+            let (key, value) = match line.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let key = unquote(key.split('.').next().unwrap_or(key).trim());
+            let name = renamed_package(value).unwrap_or(key);
+
+            if !name.is_empty() && !name.starts_with('#') {
+                kind_lines(&mut lines, kind)
+                    .entry(name.to_owned())
+                    .or_insert(line_no);
+            }
+        }
+    }
+
+    lines
+}
+
+/// Splits a table header into the kind of dependencies it declares and, for tables like
+/// `[dependencies.foo]`, the name of the dependency
+fn dependency_section(header: &str) -> Option<(&'static str, Option<&str>)> {
+    ["dependencies", "dev-dependencies", "build-dependencies"]
+        .iter()
+        .find_map(|&kind| {
+            if header == kind {
+                Some((kind, None))
+            } else {
+                let name = header.strip_prefix(kind)?.strip_prefix('.')?;
+                Some((kind, Some(name.trim())))
+            }
+        })
+}
+
+fn kind_lines<'a>(lines: &'a mut DependencyLines, kind: &str) -> &'a mut HashMap<String, usize> {
+    match kind {
+        "dev-dependencies" => &mut lines.dev,
+        "build-dependencies" => &mut lines.build,
+        _ => &mut lines.main,
+    }
+}
+
+/// Extracts `package` from an inline table like `{ version = "1", package = "foo" }`
+fn renamed_package(value: &str) -> Option<&str> {
+    let rest = value
+        .split("package")
+        .nth(1)?
+        .trim_start()
+        .strip_prefix('=')?;
+    rest.trim_start().strip_prefix('"')?.split('"').next()
+}
+
+fn unquote(key: &str) -> &str {
+    key.trim_matches(|c| c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crates::{AnalyzedDependencies, CrateManifest};
+
+    use super::*;
+
+    #[test]
+    fn parse_workspace_without_members_declaration() {
+        let toml = r#"[package]
+name = "symbolic"
+
+[workspace]
+
+[dependencies]
+symbolic-common = { version = "2.0.6", path = "common" }
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Mixed {
+                package,
+                deps,
+                members,
+            } => {
+                assert_eq!(package.name.as_ref(), "symbolic");
+                assert_eq!(deps.main.len(), 1);
+                assert_eq!(deps.dev.len(), 0);
+                assert_eq!(deps.build.len(), 0);
+                assert_eq!(members.len(), 0);
+            }
+            _ => panic!("expected mixed manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_manifest_with_renamed_deps() {
+        let toml = r#"[package]
+name = "symbolic"
+
+[dependencies]
+symbolic-common_crate = { version = "2.0.6", package = "symbolic-common" }
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(package, deps) => {
+                assert_eq!(package.name.as_ref(), "symbolic");
+                assert_eq!(deps.main.len(), 1);
+                assert_eq!(deps.dev.len(), 0);
+                assert_eq!(deps.build.len(), 0);
+
+                let name: CrateName = "symbolic-common".parse().unwrap();
+                assert!(deps.main.get(&name).is_some());
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_package_version_and_path_dependency_requirements() {
+        let toml = r#"[package]
+name = "symbolic"
+version = "2.0.6"
+
+[dependencies]
+symbolic-common = { version = "2.0.6", path = "common" }
+symbolic-debuginfo = { path = "debuginfo" }
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(package, deps) => {
+                assert_eq!(package.version, Some("2.0.6".parse().unwrap()));
+                assert_eq!(
+                    deps.main.get("symbolic-common").unwrap(),
+                    &CrateDep::Internal("common".into(), Some("2.0.6".parse().unwrap()))
+                );
+                assert_eq!(
+                    deps.main.get("symbolic-debuginfo").unwrap(),
+                    &CrateDep::Internal("debuginfo".into(), None)
+                );
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_alternative_registry_dependencies() {
+        let toml = r#"[package]
+name = "foo"
+
+[dependencies]
+bar = { version = "1.0", registry = "my-registry" }
+baz = { version = "0.3", registry-index = "sparse+https://example.com/index/" }
+qux = "2.0"
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(_, deps) => {
+                assert_eq!(
+                    deps.main.get("bar").unwrap(),
+                    &CrateDep::Registry("my-registry".into(), "1.0".parse().unwrap())
+                );
+                assert_eq!(
+                    deps.main.get("baz").unwrap(),
+                    &CrateDep::Registry(
+                        "sparse+https://example.com/index/".into(),
+                        "0.3".parse().unwrap()
+                    )
+                );
+                assert_eq!(
+                    deps.main.get("qux").unwrap(),
+                    &CrateDep::External("2.0".parse().unwrap())
+                );
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_git_dependencies() {
+        let toml = r#"[package]
+name = "foo"
+
+[dependencies]
+bar = { git = "https://github.com/foo/bar", branch = "next" }
+baz = { git = "https://github.com/foo/baz" }
+
+[dev-dependencies]
+qux = { git = "https://github.com/foo/qux", rev = "4c59b70" }
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(_, deps) => {
+                assert_eq!(
+                    deps.main.get("bar").unwrap(),
+                    &CrateDep::Git(GitDependency {
+                        url: "https://github.com/foo/bar".into(),
+                        reference: Some(GitReference::Branch("next".into())),
+                    })
+                );
+                assert_eq!(
+                    deps.main.get("baz").unwrap(),
+                    &CrateDep::Git(GitDependency {
+                        url: "https://github.com/foo/baz".into(),
+                        reference: None,
+                    })
+                );
+
+                let analyzed = AnalyzedDependencies::new(&deps);
+                assert!(analyzed.main.is_empty() && analyzed.dev.is_empty());
+                assert_eq!(
+                    analyzed
+                        .git
+                        .keys()
+                        .map(|name| name.as_ref())
+                        .collect::<Vec<&str>>(),
+                    vec!["bar", "baz", "qux"]
+                );
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_optional_dependencies_and_features() {
+        let toml = r#"[package]
+name = "foo"
+
+[dependencies]
+serde = { version = "1.0", optional = true }
+native-tls = { version = "0.2", optional = true }
+tokio-rustls = { version = "0.24", optional = true }
+log = { version = "0.4", default-features = false }
+rand = "0.8"
+
+[features]
+default = ["tls"]
+tls = ["dep:native-tls"]
+rustls = ["tokio-rustls/early-data"]
+derive = ["serde?/derive"]
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(_, deps) => {
+                let features = |name: &str| deps.usage.main[name].features.clone();
+                assert_eq!(features("serde"), vec!["serde"]);
+                assert_eq!(features("native-tls"), vec!["default", "tls"]);
+                assert_eq!(features("tokio-rustls"), vec!["tokio-rustls", "rustls"]);
+                assert!(!deps.usage.main["log"].default_features);
+                assert!(!deps.usage.main.contains_key("rand"));
+
+                let usage = &deps.usage.main["native-tls"];
+                assert!(usage.is_enabled_by(&["default".to_owned()]));
+                assert!(!usage.is_enabled_by(&["rustls".to_owned()]));
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn dependency_usage_per_kind() {
+        let toml = r#"[package]
+name = "foo"
+
+[dependencies]
+tokio = "1"
+
+[dev-dependencies]
+tokio = { version = "1", default-features = false }
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+
+        match manifest {
+            CrateManifest::Package(_, deps) => {
+                assert!(!deps.usage.main.contains_key("tokio"));
+                assert!(!deps.usage.dev["tokio"].default_features);
+            }
+            _ => panic!("expected package manifest"),
+        }
+    }
+
+    #[test]
+    fn parse_inherited_package_version() {
+        let toml = r#"[package]
+name = "symbolic"
+version.workspace = true
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        assert!(manifest.package().unwrap().version.is_none());
+    }
+
+    #[test]
+    fn parse_rust_version() {
+        let toml = r#"[package]
+name = "symbolic"
+rust-version = "1.56"
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        let rust_version = manifest.package().unwrap().rust_version.as_ref();
+        assert_eq!(rust_version, Some(&"1.56".parse().unwrap()));
+
+        let toml = r#"[package]
+name = "symbolic"
+rust-version.workspace = true
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        assert!(manifest.package().unwrap().rust_version.is_none());
+    }
+
+    #[test]
+    fn parse_package_metadata() {
+        let toml = r#"[package]
+name = "symbolic"
+edition = "2021"
+description = "A library to symbolicate and process stack traces"
+repository.workspace = true
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        let metadata = &manifest.package().unwrap().metadata;
+        assert_eq!(metadata.edition.as_deref(), Some("2021"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("A library to symbolicate and process stack traces")
+        );
+        assert!(metadata.repository.is_none());
+        assert!(!metadata.unpublished);
+
+        let toml = r#"[package]
+name = "symbolic"
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        assert!(manifest.package().unwrap().metadata.is_empty());
+
+        let toml = r#"[package]
+name = "symbolic-testutils"
+publish = false
+"#;
+
+        let manifest = parse_manifest_toml(toml).unwrap();
+        assert!(manifest.package().unwrap().metadata.unpublished);
+    }
+
+    #[test]
+    fn find_lines_of_dependencies() {
+        let toml = r#"[package]
+name = "foo"
+
+[dependencies]
+serde = "1.0"
+tokio = { version = "1", features = ["full"] }
+futures01 = { version = "0.1", package = "futures" }
+
+[dependencies.hyper]
+version = "0.14"
+
+[dev-dependencies]
+"quickcheck" = "1"
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[build-dependencies]
+cc.version = "1"
+"#;
+
+        let lines = find_dependency_lines(toml);
+
+        assert_eq!(lines.main.get("serde"), Some(&5));
+        assert_eq!(lines.main.get("tokio"), Some(&6));
+        assert_eq!(lines.main.get("futures"), Some(&7));
+        assert_eq!(lines.main.get("hyper"), Some(&9));
+        assert_eq!(lines.main.get("version"), None);
+        assert_eq!(lines.main.get("libc"), None);
+        assert_eq!(lines.dev.get("quickcheck"), Some(&13));
+        assert_eq!(lines.build.get("cc"), Some(&19));
+    }
+}
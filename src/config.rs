@@ -0,0 +1,202 @@
+//! Server configuration, read from the TOML file given by `CONFIG_FILE`.
+//!
+//! Every setting has a default, and environment variables still take precedence over the
+//! file, so a deployment can override single settings without editing it.
+
+use std::{fs, net::IpAddr, net::Ipv4Addr, path::Path};
+
+use anyhow::Context as _;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub metrics: MetricsConfig,
+    pub timeouts: TimeoutConfig,
+    pub cache: CacheConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address to listen on, overridden by `BIND_ADDRESS`
+    pub address: IpAddr,
+    /// Overridden by `PORT`
+    pub port: u16,
+    /// URL the server is reachable at, used in links and redirects. Overridden by `BASE_URL`.
+    pub base_url: String,
+    /// Hosts that repositories may be retrieved from through a `raw_base` URL template, e.g.
+    /// Gitea instances. Overridden by the comma-separated `CUSTOM_GIT_HOSTS`.
+    pub custom_git_hosts: Vec<String>,
+    /// Gitea instances whose popular Rust repositories are listed on the front page. They are
+    /// allowed as custom git hosts too. Overridden by the comma-separated `POPULAR_GITEA_HOSTS`.
+    pub popular_gitea_hosts: Vec<String>,
+    /// Lower bound for the `cacheSeconds` query parameter on badges, overridden by
+    /// `BADGE_MIN_CACHE_SECONDS`
+    pub badge_min_cache_seconds: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 8080,
+            base_url: "http://localhost:8080".to_owned(),
+            custom_git_hosts: Vec::new(),
+            popular_gitea_hosts: vec!["codeberg.org".to_owned()],
+            badge_min_cache_seconds: 300,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Host and port of the statsd server, overridden by `STATSD_HOST`
+    pub statsd_host: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> MetricsConfig {
+        MetricsConfig {
+            statsd_host: "127.0.0.1:8125".to_owned(),
+        }
+    }
+}
+
+/// Timeouts of outbound requests in seconds, overridden by the `<NAME>_TIMEOUT_SECS` variables
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    /// Overridden by `HTTP_TIMEOUT_SECS`
+    pub default: u64,
+    pub github: u64,
+    pub gitlab: u64,
+    pub bitbucket: u64,
+    pub sourceforge: u64,
+    pub popular_repos: u64,
+    pub popular_crates: u64,
+    pub crawl: u64,
+    pub analysis: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> TimeoutConfig {
+        TimeoutConfig {
+            default: 5,
+            github: 5,
+            gitlab: 5,
+            bitbucket: 5,
+            sourceforge: 5,
+            popular_repos: 5,
+            popular_crates: 5,
+            crawl: 30,
+            analysis: 30,
+        }
+    }
+}
+
+/// Lifetimes in seconds and capacities of the engine's caches, overridden by the
+/// `<NAME>_CACHE_TTL_SECS` and `<NAME>_CACHE_SIZE` variables
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub query_crate_ttl: u64,
+    pub query_crate_size: usize,
+    pub crate_metadata_ttl: u64,
+    pub crate_metadata_size: usize,
+    pub popular_crates_ttl: u64,
+    pub popular_repos_ttl: u64,
+    pub manifest_dirs_ttl: u64,
+    pub manifest_dirs_size: usize,
+    pub analysis_ttl: u64,
+    pub analysis_size: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig {
+            query_crate_ttl: 10,
+            query_crate_size: 500,
+            crate_metadata_ttl: 86400,
+            crate_metadata_size: 5000,
+            popular_crates_ttl: 120,
+            popular_repos_ttl: 120,
+            manifest_dirs_ttl: 3600,
+            manifest_dirs_size: 500,
+            analysis_ttl: 300,
+            analysis_size: 1000,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration file at `path`
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        toml::from_str(&raw).with_context(|| format!("could not parse {}", path.display()))
+    }
+}
+
+/// Makes `config` the configuration returned by `get`. Can only be done once, at startup.
+pub fn init(config: Config) {
+    CONFIG
+        .set(config)
+        .expect("configuration has already been initialized");
+}
+
+/// Reads the comma-separated list of hosts in the environment variable `name`, or else
+/// returns `default`
+pub fn hosts_from_env(name: &str, default: &[String]) -> Vec<String> {
+    match std::env::var(name) {
+        Ok(hosts) => hosts
+            .split(',')
+            .map(|host| host.trim().to_owned())
+            .filter(|host| !host.is_empty())
+            .collect(),
+        Err(_) => default.to_vec(),
+    }
+}
+
+/// Returns the configuration, or the defaults if none has been loaded
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            base_url = "https://deps.example.com"
+            custom_git_hosts = ["gitea.example.com"]
+
+            [timeouts]
+            github = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.server.base_url, "https://deps.example.com");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.server.custom_git_hosts, vec!["gitea.example.com"]);
+        assert_eq!(config.server.popular_gitea_hosts, vec!["codeberg.org"]);
+        assert_eq!(config.timeouts.github, 10);
+        assert_eq!(config.timeouts.gitlab, 5);
+        assert_eq!(config.cache.analysis_ttl, 300);
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(toml::from_str::<Config>("[server]\nbase_uri = \"x\"").is_err());
+    }
+}
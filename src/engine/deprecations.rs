@@ -0,0 +1,96 @@
+use crate::models::crates::{CrateName, Deprecation};
+
+/// Crates known to be deprecated or unmaintained, along with their suggested replacements
+static DEPRECATED_CRATES: &[(&str, Option<&str>)] = &[
+    ("ansi_term", Some("nu-ansi-term")),
+    ("dotenv", Some("dotenvy")),
+    ("failure", Some("anyhow")),
+    ("net2", Some("socket2")),
+    ("rustc-serialize", Some("serde")),
+    ("structopt", Some("clap")),
+    ("tempdir", Some("tempfile")),
+    ("term_size", Some("terminal_size")),
+];
+
+/// Words a description starting with them marks the crate as deprecated, e.g. "DEPRECATED:
+/// use foo instead"
+static DESCRIPTION_PREFIXES: &[&str] = &["deprecated", "unmaintained", "no longer maintained"];
+
+/// Phrases which mark a crate as deprecated anywhere in its description. They are anchored to
+/// the crate itself, so that e.g. "replacement for the deprecated foo" doesn't count.
+static DESCRIPTION_MARKERS: &[&str] = &[
+    "this crate is deprecated",
+    "this crate has been deprecated",
+    "this crate is unmaintained",
+    "this crate is no longer maintained",
+    "this crate has been superseded by",
+    "this crate is superseded by",
+    "this project is deprecated",
+    "this project is unmaintained",
+    "this project is no longer maintained",
+    "this library is deprecated",
+    "this library is no longer maintained",
+    "deprecated in favor of",
+    "deprecated in favour of",
+];
+
+/// Looks up a crate in the curated table of deprecated crates
+pub fn curated_deprecation(name: &CrateName) -> Option<Deprecation> {
+    DEPRECATED_CRATES
+        .iter()
+        .find(|(deprecated, _)| *deprecated == name.as_ref())
+        .map(|(_, replacement)| Deprecation {
+            replacement: replacement.map(str::to_owned),
+        })
+}
+
+/// Checks whether a crate's crates.io description announces its deprecation
+pub fn description_deprecation(description: &str) -> Option<Deprecation> {
+    let description = description.to_lowercase();
+    // e.g. "[DEPRECATED]" or "**Deprecated**"
+    let start = description.trim_start_matches(|c: char| !c.is_alphanumeric());
+
+    if DESCRIPTION_PREFIXES
+        .iter()
+        .any(|prefix| start.starts_with(prefix))
+        || DESCRIPTION_MARKERS
+            .iter()
+            .any(|marker| description.contains(marker))
+    {
+        Some(Deprecation { replacement: None })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curated_crates_have_replacements() {
+        let deprecation = curated_deprecation(&"failure".parse().unwrap()).unwrap();
+        assert_eq!(deprecation.replacement.as_deref(), Some("anyhow"));
+
+        assert!(curated_deprecation(&"anyhow".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn deprecation_from_description() {
+        assert!(description_deprecation("DEPRECATED: use something else").is_some());
+        assert!(description_deprecation("[Unmaintained] A terminal library").is_some());
+        assert!(description_deprecation("This crate is no longer maintained.").is_some());
+        assert!(description_deprecation("Old bindings, deprecated in favor of foo").is_some());
+        assert!(description_deprecation("A fast HTTP implementation").is_none());
+    }
+
+    #[test]
+    fn descriptions_mentioning_deprecation() {
+        assert!(
+            description_deprecation("A replacement for the deprecated tempdir crate").is_none()
+        );
+        assert!(description_deprecation("Lint for deprecated APIs").is_none());
+        assert!(description_deprecation("A maintained fork of the unmaintained net2").is_none());
+        assert!(description_deprecation("Finds crates that are no longer maintained").is_none());
+    }
+}
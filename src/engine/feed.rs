@@ -0,0 +1,98 @@
+//! History of the issues found in a repository, served as its Atom feed.
+
+use std::collections::{BTreeMap, HashSet};
+use std::time::SystemTime;
+
+use semver::Version;
+
+use crate::engine::AnalyzeDependenciesOutcome;
+use crate::models::crates::CrateName;
+
+/// Entries kept per repository, older ones are dropped
+const MAX_FEED_ENTRIES: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FeedEvent {
+    /// A dependency has a newer release than its requirement allows
+    Outdated { name: CrateName, latest: Version },
+    /// A security advisory affects a dependency
+    Advisory {
+        name: CrateName,
+        id: String,
+        title: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct FeedEntry {
+    /// Identifies the event, stable across analyses
+    pub key: String,
+    pub time: SystemTime,
+    pub event: FeedEvent,
+}
+
+/// The feed of a repository, with the issues of its last analysis to tell new ones apart
+#[derive(Clone, Debug, Default)]
+pub struct RepoFeed {
+    /// Newest first
+    pub entries: Vec<FeedEntry>,
+    known: HashSet<String>,
+}
+
+impl RepoFeed {
+    /// Adds entries for the issues in `outcome` that the previous analysis didn't have
+    pub fn update(&mut self, outcome: &AnalyzeDependenciesOutcome, time: SystemTime) {
+        let current = events(outcome);
+
+        let mut new_entries: Vec<FeedEntry> = current
+            .iter()
+            .filter(|(key, _)| !self.known.contains(*key))
+            .map(|(key, event)| FeedEntry {
+                key: key.clone(),
+                time,
+                event: event.clone(),
+            })
+            .collect();
+
+        new_entries.append(&mut self.entries);
+        new_entries.truncate(MAX_FEED_ENTRIES);
+        self.entries = new_entries;
+        self.known = current.into_keys().collect();
+    }
+}
+
+/// Collects the outdated and insecure main and build dependencies, keyed by what identifies
+/// them across analyses
+pub(super) fn events(outcome: &AnalyzeDependenciesOutcome) -> BTreeMap<String, FeedEvent> {
+    let mut events = BTreeMap::new();
+
+    let deps = outcome
+        .crates
+        .iter()
+        .flat_map(|(_, deps)| deps.main.iter().chain(&deps.build));
+
+    for (name, dep) in deps {
+        if let (true, Some(latest)) = (dep.is_outdated(), &dep.latest) {
+            events.insert(
+                format!("outdated/{}/{}", name.as_ref(), latest),
+                FeedEvent::Outdated {
+                    name: name.clone(),
+                    latest: latest.clone(),
+                },
+            );
+        }
+
+        for advisory in &dep.vulnerabilities {
+            events.insert(
+                format!("advisory/{}/{}", name.as_ref(), advisory.id()),
+                FeedEvent::Advisory {
+                    name: name.clone(),
+                    id: advisory.id().to_string(),
+                    title: advisory.title().to_owned(),
+                },
+            );
+        }
+    }
+
+    events
+}
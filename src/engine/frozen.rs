@@ -0,0 +1,175 @@
+//! Analyses frozen when they are recorded in the history, so that permalinks keep showing the
+//! state that was analyzed. Each is saved to a JSON file of its own, named after a hash of
+//! what it found.
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use sha2::{Digest, Sha256};
+use tokio::{fs, sync::Mutex};
+
+use crate::engine::AnalyzeDependenciesOutcome;
+use crate::models::repo::RepoPath;
+use crate::utils::json_file::JsonFileStore;
+
+/// Frozen analyses younger than this are kept even if no snapshot points to them, as their
+/// snapshot may not be recorded yet
+const PRUNE_GRACE: Duration = Duration::from_secs(3600);
+
+/// Formats the ID of a frozen analysis for URLs, as 16 hex digits
+pub fn format_id(id: u64) -> String {
+    format!("{:016x}", id)
+}
+
+/// Parses the ID of a frozen analysis from a URL, rejecting anything but 16 hex digits
+pub fn parse_id(id: &str) -> Option<u64> {
+    if id.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(id, 16).ok()
+}
+
+/// Hashes what an analysis found, leaving out when it ran and how long it took so that
+/// re-analyses finding the same get the same ID
+fn content_id(outcome: &AnalyzeDependenciesOutcome) -> Result<u64, Error> {
+    let mut value = serde_json::to_value(outcome)?;
+    if let Some(fields) = value.as_object_mut() {
+        for field in &["duration", "analyzed_at", "stale", "snapshot", "frozen"] {
+            fields.remove(*field);
+        }
+    }
+
+    // objects are converted to maps with sorted keys, so hash maps are hashed in a stable order
+    let hash = Sha256::digest(&serde_json::to_vec(&value)?);
+    Ok(u64::from_be_bytes(hash[..8].try_into()?))
+}
+
+/// Directory of a repository's frozen analyses, named after a hash of its path so that any
+/// qualifier and name make a valid file name
+fn repo_dir(dir: &Path, repo_path: &RepoPath) -> PathBuf {
+    let key = format!(
+        "{}/{}/{}",
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref()
+    );
+    dir.join(hex::encode(&Sha256::digest(key.as_bytes())[..16]))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FrozenAnalyses {
+    /// Directory the analyses are saved in, nothing is frozen if unset
+    dir: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl FrozenAnalyses {
+    /// Saves frozen analyses in `dir` from now on, creating it if needed
+    pub async fn open(&self, dir: PathBuf) -> Result<(), Error> {
+        fs::create_dir_all(&dir).await?;
+        *self.dir.lock().await = Some(dir);
+        Ok(())
+    }
+
+    /// Freezes an analysis of a repository, keeping the earlier one if it found the same.
+    /// Returns the ID of the frozen analysis, or `None` if no directory is set.
+    pub async fn freeze(
+        &self,
+        repo_path: &RepoPath,
+        outcome: &AnalyzeDependenciesOutcome,
+    ) -> Result<Option<u64>, Error> {
+        let repo_dir = match self.dir.lock().await.clone() {
+            Some(dir) => repo_dir(&dir, repo_path),
+            None => return Ok(None),
+        };
+
+        let id = content_id(outcome)?;
+        let store = JsonFileStore::new(repo_dir.join(format!("{}.json", format_id(id))));
+        match store.load_if_exists::<serde_json::Value>().await? {
+            // saved again as it was, so it isn't pruned before its snapshot is recorded
+            Some(frozen) => store.save(&frozen).await?,
+            None => {
+                fs::create_dir_all(&repo_dir).await?;
+                store.save(outcome).await?;
+            }
+        }
+        Ok(Some(id))
+    }
+
+    /// Returns a frozen analysis of a repository, if there is one with that ID
+    pub async fn get(
+        &self,
+        repo_path: &RepoPath,
+        id: u64,
+    ) -> Result<Option<AnalyzeDependenciesOutcome>, Error> {
+        let repo_dir = match self.dir.lock().await.clone() {
+            Some(dir) => repo_dir(&dir, repo_path),
+            None => return Ok(None),
+        };
+
+        let path = repo_dir.join(format!("{}.json", format_id(id)));
+        JsonFileStore::new(path).load_if_exists().await
+    }
+
+    /// Drops the frozen analyses that no snapshot points to anymore, along with the
+    /// directories of repositories the history doesn't keep
+    pub async fn prune(&self, referenced: &HashMap<RepoPath, HashSet<u64>>) -> Result<(), Error> {
+        let dir = match self.dir.lock().await.clone() {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        let referenced: HashMap<_, _> = referenced
+            .iter()
+            .map(|(repo_path, ids)| (repo_dir(&dir, repo_path), ids))
+            .collect();
+        let now = SystemTime::now();
+
+        let mut repo_dirs = fs::read_dir(&dir).await?;
+        while let Some(repo_dir) = repo_dirs.next_entry().await? {
+            if !repo_dir.file_type().await?.is_dir() {
+                continue;
+            }
+            let ids = referenced.get(&repo_dir.path());
+
+            let mut left = 0;
+            let mut entries = fs::read_dir(repo_dir.path()).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let id = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(parse_id);
+                let is_referenced = matches!((ids, id), (Some(ids), Some(id)) if ids.contains(&id));
+                let modified = entry.metadata().await?.modified()?;
+                let age = now.duration_since(modified).unwrap_or_default();
+
+                if is_referenced || age < PRUNE_GRACE {
+                    left += 1;
+                } else {
+                    fs::remove_file(path).await?;
+                }
+            }
+
+            if left == 0 {
+                fs::remove_dir(repo_dir.path()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ids() {
+        assert_eq!(format_id(0xab), "00000000000000ab");
+        assert_eq!(parse_id("00000000000000ab"), Some(0xab));
+        assert_eq!(parse_id("ab"), None);
+        assert_eq!(parse_id("../../../etc/pwd"), None);
+    }
+}
@@ -1,9 +1,10 @@
 use anyhow::Error;
-use futures::StreamExt;
+use futures::{stream, StreamExt};
 
 use crate::{
     engine::machines::analyzer::DependencyAnalyzer,
     models::crates::{AnalyzedDependencies, CrateDep, CrateDeps, CrateName},
+    utils::index::Registry,
     Engine,
 };
 
@@ -15,12 +16,34 @@ fn filter_external((name, dep): (CrateName, CrateDep)) -> Option<CrateName> {
     }
 }
 
+/// Selects the dependencies from alternative registries the engine has an index for
+fn deps_from_registries(engine: &Engine, deps: &CrateDeps) -> Vec<(Registry, CrateName)> {
+    deps.main
+        .iter()
+        .chain(&deps.dev)
+        .chain(&deps.build)
+        .filter_map(|(name, dep)| match dep {
+            CrateDep::Registry(registry, _) => engine
+                .registries
+                .find(registry)
+                .map(|registry| (registry.clone(), name.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Analyzes dependencies against their releases. With `track_prereleases`, dependencies that
+/// require a pre-release are compared against the newest pre-release too.
 pub async fn analyze_dependencies(
     engine: Engine,
     deps: CrateDeps,
+    track_prereleases: bool,
 ) -> Result<AnalyzedDependencies, Error> {
     let advisory_db = engine.fetch_advisory_db().await?;
     let mut analyzer = DependencyAnalyzer::new(&deps, Some(advisory_db));
+    analyzer.track_prereleases(track_prereleases);
+
+    let registry_deps = deps_from_registries(&engine, &deps);
 
     let main_deps = deps.main.into_iter().filter_map(filter_external);
     let dev_deps = deps.dev.into_iter().filter_map(filter_external);
@@ -34,5 +57,17 @@ pub async fn analyze_dependencies(
         analyzer.process(release)
     }
 
-    Ok(analyzer.finalize())
+    let mut registry_releases = stream::iter(registry_deps)
+        .map(|(registry, name)| engine.fetch_registry_releases(registry, name))
+        .buffer_unordered(engine.concurrency.fetches_per_analysis);
+
+    while let Some(releases) = registry_releases.next().await {
+        analyzer.process_from_registry(releases?);
+    }
+
+    let mut analyzed = analyzer.finalize();
+    engine.attach_crate_metadata(&mut analyzed).await;
+    engine.attach_git_pin_lags(&mut analyzed).await;
+
+    Ok(analyzed)
 }
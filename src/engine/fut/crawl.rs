@@ -1,56 +1,137 @@
-use anyhow::Error;
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{anyhow, Error};
 use futures::{future::BoxFuture, stream::FuturesOrdered, FutureExt as _, StreamExt as _};
 use relative_path::RelativePathBuf;
 
-use crate::models::repo::RepoPath;
+use crate::models::repo::{GitRef, RepoPath};
+use crate::utils::middleware::is_transient;
 
 use crate::engine::{
     machines::crawler::{ManifestCrawler, ManifestCrawlerOutput},
     Engine,
 };
 
+/// Manifests waiting to be retrieved, each queued once
+struct CrawlQueue {
+    queue: VecDeque<RelativePathBuf>,
+    seen: HashSet<RelativePathBuf>,
+    limit: usize,
+    skipped: usize,
+}
+
+impl CrawlQueue {
+    fn new(limit: usize) -> CrawlQueue {
+        CrawlQueue {
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+            limit,
+            skipped: 0,
+        }
+    }
+
+    /// Queues a manifest, unless it was queued before or the limit has been reached. Several
+    /// crates can point to the same manifest before it has been retrieved.
+    fn push(&mut self, path: RelativePathBuf) {
+        if !self.seen.insert(path.clone()) {
+            return;
+        }
+
+        if self.seen.len() > self.limit {
+            self.skipped += 1;
+        } else {
+            self.queue.push_back(path);
+        }
+    }
+}
+
+/// Retrieves the manifests at the entry points and the ones they lead to, up to
+/// `manifests_per_crawl` at once and `max_manifests` in total. They are processed in the
+/// order they were found, so that the crates come out in the same order on every analysis.
 pub async fn crawl_manifest(
     engine: Engine,
     repo_path: RepoPath,
-    entry_point: RelativePathBuf,
+    git_ref: Option<GitRef>,
+    entry_points: Vec<RelativePathBuf>,
 ) -> anyhow::Result<ManifestCrawlerOutput> {
     let mut crawler = ManifestCrawler::new();
-    let mut futures: FuturesOrdered<BoxFuture<'static, Result<(RelativePathBuf, String), Error>>> =
+    let mut queue = CrawlQueue::new(engine.concurrency.max_manifests);
+    let mut futures: FuturesOrdered<BoxFuture<'static, (RelativePathBuf, Result<String, Error>)>> =
         FuturesOrdered::new();
+    let concurrency = engine.concurrency.manifests_per_crawl.max(1);
 
-    let engine2 = engine.clone();
-    let repo_path2 = repo_path.clone();
-
-    let fut = async move {
-        let contents = engine2
-            .retrieve_manifest_at_path(&repo_path2, &entry_point)
-            .await?;
-        Ok((entry_point, contents))
+    for entry_point in &entry_points {
+        queue.push(entry_point.clone());
     }
-    .boxed();
-
-    futures.push(fut);
-
-    while let Some(item) = futures.next().await {
-        let (path, raw_manifest) = item?;
-        let output = crawler.step(path, raw_manifest)?;
 
-        let engine = engine.clone();
-        let repo_path = repo_path.clone();
+    loop {
+        while futures.len() < concurrency {
+            let path = match queue.queue.pop_front() {
+                Some(path) => path,
+                None => break,
+            };
 
-        for path in output.paths_of_interest {
             let engine = engine.clone();
             let repo_path = repo_path.clone();
+            let git_ref = git_ref.clone();
 
             let fut = async move {
-                let contents = engine.retrieve_manifest_at_path(&repo_path, &path).await?;
-                Ok((path, contents))
+                let contents = engine
+                    .retrieve_manifest_at_path(&repo_path, git_ref.as_ref(), &path)
+                    .await;
+                (path, contents)
             }
             .boxed();
 
             futures.push(fut);
         }
+
+        let (path, raw_manifest) = match futures.next().await {
+            Some((path, Ok(raw_manifest))) => (path, raw_manifest),
+            // a stale member entry shouldn't fail the analysis, unlike a missing entry point
+            // or a host that is in trouble
+            Some((path, Err(err))) if !entry_points.contains(&path) && !is_transient(&err) => {
+                crawler.skip(&path, &err);
+                continue;
+            }
+            Some((_, Err(err))) => return Err(err),
+            None => break,
+        };
+        let output = crawler.step(path, raw_manifest);
+        for path in output.paths_of_interest {
+            queue.push(path);
+        }
+    }
+
+    let output = crawler.finalize();
+    // failing members are left out, but there is nothing to analyze without any crate
+    if output.crates.is_empty() {
+        if let Some(err) = output.manifest_errors.first() {
+            return Err(anyhow!("could not analyze {}: {}", err.path, err.message));
+        }
     }
 
-    Ok(crawler.finalize())
+    Ok(ManifestCrawlerOutput {
+        skipped_manifests: queue.skipped,
+        ..output
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use relative_path::RelativePath;
+
+    use super::*;
+
+    #[test]
+    fn queues_manifests_once_up_to_the_limit() {
+        let mut queue = CrawlQueue::new(2);
+        for path in &["", "a", "", "b", "c"] {
+            queue.push(RelativePath::new(path).to_relative_path_buf());
+        }
+
+        let queued: Vec<&str> = queue.queue.iter().map(|path| path.as_str()).collect();
+        assert_eq!(queued, vec!["", "a"]);
+        assert_eq!(queue.skipped, 2);
+    }
 }
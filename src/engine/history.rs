@@ -0,0 +1,252 @@
+//! Daily snapshots of how many dependencies of a repository were outdated or insecure,
+//! persisted to a JSON file.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::engine::AnalyzeDependenciesOutcome;
+use crate::models::crates::DepScope;
+use crate::models::repo::{RepoPath, RepoSite};
+use crate::utils::json_file::JsonFileStore;
+use crate::utils::time::unix_secs;
+
+/// Snapshots kept per repository, a year of daily ones
+const MAX_SNAPSHOTS: usize = 365;
+
+/// Repositories whose history is kept, the ones that went without a snapshot for the longest
+/// are dropped first
+const MAX_REPOS: usize = 1000;
+
+const SECS_PER_DAY: u64 = 86400;
+
+/// The counts of the main and build dependencies of a repository at one point in time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub total: usize,
+    pub outdated: usize,
+    pub insecure: usize,
+    /// ID of the analysis frozen along with the snapshot, for its permalink
+    #[serde(default)]
+    pub id: Option<u64>,
+}
+
+impl Snapshot {
+    pub fn new(
+        outcome: &AnalyzeDependenciesOutcome,
+        time: SystemTime,
+        id: Option<u64>,
+    ) -> Snapshot {
+        let (outdated, total) = outcome.outdated_ratio(DepScope::STATUS);
+
+        Snapshot {
+            time: unix_secs(time),
+            total,
+            outdated,
+            insecure: outcome.count_insecure(DepScope::STATUS),
+            id,
+        }
+    }
+
+    fn day(&self) -> u64 {
+        self.time / SECS_PER_DAY
+    }
+}
+
+/// The history of a repository as it is saved
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedHistory {
+    site: String,
+    qual: String,
+    name: String,
+    snapshots: Vec<Snapshot>,
+}
+
+#[derive(Debug, Default)]
+struct HistoryState {
+    /// File the snapshots are saved to, if any
+    file: Option<JsonFileStore>,
+    /// Oldest snapshot first
+    repos: BTreeMap<RepoPath, Vec<Snapshot>>,
+    /// Whether there are snapshots that haven't been saved yet
+    dirty: bool,
+}
+
+impl HistoryState {
+    /// Adds a snapshot, replacing the one from the same day if there is one
+    fn record(&mut self, repo_path: RepoPath, snapshot: Snapshot) {
+        let snapshots = self.repos.entry(repo_path).or_default();
+        if snapshots.last().map(Snapshot::day) == Some(snapshot.day()) {
+            snapshots.pop();
+        }
+        snapshots.push(snapshot);
+
+        let excess = snapshots.len().saturating_sub(MAX_SNAPSHOTS);
+        snapshots.drain(..excess);
+
+        if self.repos.len() > MAX_REPOS {
+            let stalest = self
+                .repos
+                .iter()
+                .min_by_key(|(_, snapshots)| snapshots.last().map_or(0, |snapshot| snapshot.time))
+                .map(|(repo_path, _)| repo_path.clone());
+            if let Some(repo_path) = stalest {
+                self.repos.remove(&repo_path);
+            }
+        }
+
+        self.dirty = true;
+    }
+}
+
+/// Snapshots of the repositories analyzed with the default options
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    state: Arc<Mutex<HistoryState>>,
+}
+
+impl History {
+    /// Loads the snapshots saved at `path`, where they are saved to from now on
+    pub async fn load(&self, path: PathBuf) -> Result<(), Error> {
+        let file = JsonFileStore::new(path);
+        let saved: Vec<SavedHistory> = file.load().await?;
+
+        let repos = saved
+            .into_iter()
+            .filter_map(|history| {
+                let repo_path =
+                    RepoPath::from_parts(&history.site, &history.qual, &history.name).ok()?;
+                Some((repo_path, history.snapshots))
+            })
+            .collect();
+
+        let mut state = self.state.lock().await;
+        state.file = Some(file);
+        state.repos = repos;
+        state.dirty = false;
+        Ok(())
+    }
+
+    /// Records the state of a repository, along with the ID of its frozen analysis if there is
+    /// one. Repositories on custom git hosts are left out, as they can't be re-analyzed without
+    /// their URL template.
+    pub async fn record(
+        &self,
+        repo_path: &RepoPath,
+        outcome: &AnalyzeDependenciesOutcome,
+        time: SystemTime,
+        id: Option<u64>,
+    ) {
+        if matches!(repo_path.site, RepoSite::Custom(_)) {
+            return;
+        }
+
+        let mut state = self.state.lock().await;
+        state.record(repo_path.clone(), Snapshot::new(outcome, time, id));
+    }
+
+    /// Returns the snapshots of a repository, oldest first
+    pub async fn snapshots(&self, repo_path: &RepoPath) -> Vec<Snapshot> {
+        let state = self.state.lock().await;
+        state.repos.get(repo_path).cloned().unwrap_or_default()
+    }
+
+    /// Returns the repositories that don't have a snapshot from the day of `time` yet
+    pub async fn repos_due(&self, time: SystemTime) -> Vec<RepoPath> {
+        let today = unix_secs(time) / SECS_PER_DAY;
+
+        let state = self.state.lock().await;
+        state
+            .repos
+            .iter()
+            .filter(|(_, snapshots)| snapshots.last().map(Snapshot::day) != Some(today))
+            .map(|(repo_path, _)| repo_path.clone())
+            .collect()
+    }
+
+    /// Returns the IDs of the frozen analyses the snapshots of each repository point to
+    pub async fn frozen_ids(&self) -> HashMap<RepoPath, HashSet<u64>> {
+        let state = self.state.lock().await;
+        state
+            .repos
+            .iter()
+            .map(|(repo_path, snapshots)| {
+                let ids = snapshots
+                    .iter()
+                    .filter_map(|snapshot| snapshot.id)
+                    .collect();
+                (repo_path.clone(), ids)
+            })
+            .collect()
+    }
+
+    /// Saves the snapshots, unless there are no new ones
+    pub async fn save(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let file = match (&state.file, state.dirty) {
+            (Some(file), true) => file.clone(),
+            _ => return Ok(()),
+        };
+
+        let saved: Vec<SavedHistory> = state
+            .repos
+            .iter()
+            .map(|(repo_path, snapshots)| SavedHistory {
+                site: repo_path.site.as_ref().to_owned(),
+                qual: repo_path.qual.as_ref().to_owned(),
+                name: repo_path.name.as_ref().to_owned(),
+                snapshots: snapshots.clone(),
+            })
+            .collect();
+
+        file.save(&saved).await?;
+        state.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(time: u64, outdated: usize) -> Snapshot {
+        Snapshot {
+            time,
+            total: 10,
+            outdated,
+            insecure: 0,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn keeps_one_snapshot_per_day() {
+        let repo_path = RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap();
+        let mut state = HistoryState::default();
+
+        state.record(repo_path.clone(), snapshot(SECS_PER_DAY, 3));
+        state.record(repo_path.clone(), snapshot(SECS_PER_DAY + 3600, 2));
+        state.record(repo_path.clone(), snapshot(2 * SECS_PER_DAY, 1));
+
+        assert_eq!(
+            state.repos[&repo_path],
+            vec![
+                snapshot(SECS_PER_DAY + 3600, 2),
+                snapshot(2 * SECS_PER_DAY, 1)
+            ]
+        );
+        assert!(state.dirty);
+
+        for day in 3..(MAX_SNAPSHOTS as u64 + 10) {
+            state.record(repo_path.clone(), snapshot(day * SECS_PER_DAY, 0));
+        }
+        assert_eq!(state.repos[&repo_path].len(), MAX_SNAPSHOTS);
+    }
+}
@@ -1,14 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Error;
 use indexmap::IndexMap;
-use relative_path::RelativePathBuf;
+use relative_path::{RelativePath, RelativePathBuf};
 
-use crate::models::crates::{CrateDep, CrateDeps, CrateManifest, CrateName};
-use crate::parsers::manifest::parse_manifest_toml;
+use crate::models::crates::{
+    CrateDep, CrateDeps, CrateManifest, CrateName, CratePackage, DependencyLines,
+    InternalVersionMismatch, ManifestError, PackageMetadata, RustVersion,
+};
+use crate::parsers::manifest::{find_dependency_lines, parse_manifest_toml};
 
 pub struct ManifestCrawlerOutput {
     pub crates: IndexMap<CrateName, CrateDeps>,
+    pub version_mismatches: Vec<InternalVersionMismatch>,
+    /// Path of each crate's `Cargo.toml`
+    pub manifest_paths: HashMap<CrateName, RelativePathBuf>,
+    /// Lines of each crate's `Cargo.toml` its dependencies are declared on
+    pub dependency_lines: HashMap<CrateName, DependencyLines>,
+    /// `rust-version` of each crate that declares one
+    pub rust_versions: HashMap<CrateName, RustVersion>,
+    /// Edition, description and repository of each crate that declares any of them
+    pub package_metadata: HashMap<CrateName, PackageMetadata>,
+    /// Path dependencies between the crates, from dependent to dependency
+    pub internal_deps: Vec<(CrateName, CrateName)>,
+    /// Manifests that weren't retrieved because the crawl reached its limit
+    pub skipped_manifests: usize,
+    /// Manifests that were left out because they couldn't be retrieved or parsed
+    pub manifest_errors: Vec<ManifestError>,
+    /// Manifests whose normal or build path dependencies lead back to themselves, each cycle
+    /// starting and ending with the same manifest
+    pub dependency_cycles: Vec<Vec<RelativePathBuf>>,
 }
 
 pub struct ManifestCrawlerStepOutput {
@@ -18,6 +39,16 @@ pub struct ManifestCrawlerStepOutput {
 pub struct ManifestCrawler {
     manifests: HashMap<RelativePathBuf, CrateManifest>,
     leaf_crates: IndexMap<CrateName, CrateDeps>,
+    leaf_paths: HashMap<CrateName, RelativePathBuf>,
+    leaf_lines: HashMap<CrateName, DependencyLines>,
+    /// Crates patched by any of the manifests. Cargo only applies the patches of the
+    /// workspace root, which is usually the only manifest that has any.
+    patched: HashSet<CrateName>,
+    manifest_errors: Vec<ManifestError>,
+    /// Crate directories each crate directory points to through normal or build path
+    /// dependencies. Cargo allows cycles through dev dependencies, so they aren't tracked.
+    path_deps: HashMap<RelativePathBuf, Vec<RelativePathBuf>>,
+    dependency_cycles: Vec<Vec<RelativePathBuf>>,
 }
 
 impl ManifestCrawler {
@@ -25,48 +56,136 @@ impl ManifestCrawler {
         ManifestCrawler {
             manifests: HashMap::new(),
             leaf_crates: IndexMap::new(),
+            leaf_paths: HashMap::new(),
+            leaf_lines: HashMap::new(),
+            patched: HashSet::new(),
+            manifest_errors: Vec::new(),
+            path_deps: HashMap::new(),
+            dependency_cycles: Vec::new(),
         }
     }
 
+    /// Processes the manifest of the crate directory `path`. A manifest that fails to parse is
+    /// recorded and left out, so the rest of the workspace can still be analyzed.
     pub fn step(
         &mut self,
         path: RelativePathBuf,
         raw_manifest: String,
-    ) -> Result<ManifestCrawlerStepOutput, Error> {
-        let manifest = parse_manifest_toml(&raw_manifest)?;
-        self.manifests.insert(path.clone(), manifest.clone());
-
+    ) -> ManifestCrawlerStepOutput {
         let mut output = ManifestCrawlerStepOutput {
             paths_of_interest: vec![],
         };
 
+        let manifest = match parse_manifest_toml(&raw_manifest) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                self.manifest_errors.push(ManifestError {
+                    path: manifest_path(&path),
+                    message: format!("{:#}", err),
+                });
+                return output;
+            }
+        };
+        self.manifests.insert(path.clone(), manifest.clone());
+        self.patched.extend(manifest.patched().iter().cloned());
+
+        if let Some(package) = manifest.package() {
+            self.leaf_lines
+                .insert(package.name.clone(), find_dependency_lines(&raw_manifest));
+        }
+
         match manifest {
-            CrateManifest::Package(name, deps) => {
-                self.process_package(&path, name, deps, &mut output);
+            CrateManifest::Package(package, deps) => {
+                self.process_package(&path, package.name, deps, &mut output);
             }
-            CrateManifest::Workspace { members } => {
+            CrateManifest::Workspace { members, .. } => {
                 self.process_workspace(&path, &members, &mut output);
             }
             CrateManifest::Mixed {
-                name,
+                package,
                 deps,
                 members,
             } => {
-                self.process_package(&path, name, deps, &mut output);
+                self.process_package(&path, package.name, deps, &mut output);
                 self.process_workspace(&path, &members, &mut output);
             }
         }
 
-        Ok(output)
+        output
+    }
+
+    /// Records that the manifest of the crate directory `path` couldn't be retrieved, the
+    /// crawl goes on without it
+    pub fn skip(&mut self, path: &RelativePath, err: &Error) {
+        self.manifest_errors.push(ManifestError {
+            path: manifest_path(path),
+            message: format!("could not be retrieved: {:#}", err),
+        });
     }
 
+    /// Returns the crate directories leading from `from` to `to` through the path dependencies
+    /// seen so far, both included
+    fn find_path_deps_route(
+        &self,
+        from: &RelativePathBuf,
+        to: &RelativePathBuf,
+    ) -> Option<Vec<RelativePathBuf>> {
+        let mut previous = HashMap::new();
+        let mut pending = vec![from];
+        previous.insert(from, from);
+
+        while let Some(current) = pending.pop() {
+            if current == to {
+                let mut route = vec![current.clone()];
+                let mut step = current;
+                while step != from {
+                    step = previous[step];
+                    route.push(step.clone());
+                }
+                route.reverse();
+                return Some(route);
+            }
+
+            for next in self.path_deps.get(current).into_iter().flatten() {
+                if !previous.contains_key(next) {
+                    previous.insert(next, current);
+                    pending.push(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Queues the crate directory a path dependency or workspace member points to. A normal
+    /// or build path dependency that leads back to the crate is recorded as a cycle instead.
     fn register_interest(
         &mut self,
         base_path: &RelativePathBuf,
         path: &RelativePathBuf,
+        track_cycles: bool,
         output: &mut ManifestCrawlerStepOutput,
     ) {
         let full_path = base_path.join_normalized(path);
+
+        if track_cycles {
+            let base_path = base_path.normalize();
+            if let Some(mut cycle) = self.find_path_deps_route(&full_path, &base_path) {
+                cycle.push(full_path);
+                let cycle = cycle
+                    .iter()
+                    .map(RelativePathBuf::as_relative_path)
+                    .map(manifest_path)
+                    .collect();
+                self.dependency_cycles.push(cycle);
+                return;
+            }
+            self.path_deps
+                .entry(base_path)
+                .or_default()
+                .push(full_path.clone());
+        }
+
         if !self.manifests.contains_key(&full_path) {
             output.paths_of_interest.push(full_path);
         }
@@ -79,17 +198,16 @@ impl ManifestCrawler {
         deps: CrateDeps,
         output: &mut ManifestCrawlerStepOutput,
     ) {
-        for (_, dep) in deps
-            .main
-            .iter()
-            .chain(deps.dev.iter())
-            .chain(deps.build.iter())
-        {
-            if let CrateDep::Internal(ref path) = dep {
-                self.register_interest(base_path, path, output);
+        let kinds = [(&deps.main, true), (&deps.dev, false), (&deps.build, true)];
+        for (kind_deps, track_cycles) in kinds.iter() {
+            for dep in kind_deps.values() {
+                if let CrateDep::Internal(ref path, _) = dep {
+                    self.register_interest(base_path, path, *track_cycles, output);
+                }
             }
         }
 
+        self.leaf_paths.insert(name.clone(), base_path.clone());
         self.leaf_crates.insert(name, deps);
     }
 
@@ -101,18 +219,137 @@ impl ManifestCrawler {
     ) {
         for path in members {
             if !path.ends_with("*") {
-                self.register_interest(base_path, path, output);
+                self.register_interest(base_path, path, false, output);
+            }
+        }
+    }
+
+    /// Finds path dependencies whose version requirement doesn't match the version of the
+    /// crate they point to
+    fn find_version_mismatches(&self) -> Vec<InternalVersionMismatch> {
+        let packages = self.packages_by_path();
+
+        let mut mismatches = vec![];
+
+        for (name, deps) in &self.leaf_crates {
+            let base_path = &self.leaf_paths[name];
+
+            for dep in deps
+                .main
+                .values()
+                .chain(deps.dev.values())
+                .chain(deps.build.values())
+            {
+                if let CrateDep::Internal(ref path, Some(ref required)) = dep {
+                    let target = match packages.get(&base_path.join_normalized(path)) {
+                        Some(target) => target,
+                        None => continue,
+                    };
+
+                    if let Some(ref actual) = target.version {
+                        if !required.matches(actual) {
+                            mismatches.push(InternalVersionMismatch {
+                                dependent: name.clone(),
+                                dependency: target.name.clone(),
+                                required: required.clone(),
+                                actual: actual.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    /// Finds the workspace crates each crate depends on through path dependencies
+    fn find_internal_deps(&self) -> Vec<(CrateName, CrateName)> {
+        let packages = self.packages_by_path();
+
+        let mut internal_deps = vec![];
+        for (name, deps) in &self.leaf_crates {
+            let base_path = &self.leaf_paths[name];
+
+            for dep in deps
+                .main
+                .values()
+                .chain(deps.dev.values())
+                .chain(deps.build.values())
+            {
+                if let CrateDep::Internal(ref path, _) = dep {
+                    if let Some(target) = packages.get(&base_path.join_normalized(path)) {
+                        // a crate pointing to itself is reported as a cycle
+                        if target.name == *name {
+                            continue;
+                        }
+                        let edge = (name.clone(), target.name.clone());
+                        if !internal_deps.contains(&edge) {
+                            internal_deps.push(edge);
+                        }
+                    }
+                }
             }
         }
+
+        internal_deps
     }
 
-    pub fn finalize(self) -> ManifestCrawlerOutput {
+    fn packages_by_path(&self) -> HashMap<RelativePathBuf, &CratePackage> {
+        self.manifests
+            .iter()
+            .filter_map(|(path, manifest)| Some((path.normalize(), manifest.package()?)))
+            .collect()
+    }
+
+    pub fn finalize(mut self) -> ManifestCrawlerOutput {
+        for deps in self.leaf_crates.values_mut() {
+            deps.patched.extend(self.patched.iter().cloned());
+        }
+
+        let version_mismatches = self.find_version_mismatches();
+        let internal_deps = self.find_internal_deps();
+        let manifest_paths = self
+            .leaf_paths
+            .iter()
+            .map(|(name, path)| (name.clone(), manifest_path(path)))
+            .collect();
+        let rust_versions = self
+            .manifests
+            .values()
+            .filter_map(CrateManifest::package)
+            .filter(|package| self.leaf_crates.contains_key(&package.name))
+            .filter_map(|package| Some((package.name.clone(), package.rust_version.clone()?)))
+            .collect();
+        let package_metadata = self
+            .manifests
+            .values()
+            .filter_map(CrateManifest::package)
+            .filter(|package| self.leaf_crates.contains_key(&package.name))
+            .filter(|package| !package.metadata.is_empty())
+            .map(|package| (package.name.clone(), package.metadata.clone()))
+            .collect();
+
         ManifestCrawlerOutput {
             crates: self.leaf_crates,
+            version_mismatches,
+            manifest_paths,
+            dependency_lines: self.leaf_lines,
+            rust_versions,
+            package_metadata,
+            internal_deps,
+            skipped_manifests: 0,
+            manifest_errors: self.manifest_errors,
+            dependency_cycles: self.dependency_cycles,
         }
     }
 }
 
+/// Returns the path of the `Cargo.toml` in the crate directory `path`
+fn manifest_path(path: &RelativePath) -> RelativePathBuf {
+    path.join_normalized("Cargo.toml")
+}
+
 #[cfg(test)]
 mod tests {
     use relative_path::RelativePath;
@@ -129,9 +366,7 @@ mod tests {
 name = "simpleton"
 "#;
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler
-            .step("Cargo.toml".into(), manifest.to_string())
-            .unwrap();
+        let step_output = crawler.step("Cargo.toml".into(), manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 0);
         let output = crawler.finalize();
         assert_eq!(output.crates.len(), 1);
@@ -154,7 +389,7 @@ quickcheck = "0.5"
 codegen = "0.0.1"
 "#;
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler.step("".into(), manifest.to_string()).unwrap();
+        let step_output = crawler.step("".into(), manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 0);
         let output = crawler.finalize();
         assert_eq!(output.crates.len(), 1);
@@ -199,7 +434,7 @@ version = "0.35.0"
 "#;
 
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler.step("".into(), manifest.to_string()).unwrap();
+        let step_output = crawler.step("".into(), manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 3);
         assert_eq!(step_output.paths_of_interest[0].as_str(), "src/input");
         assert_eq!(step_output.paths_of_interest[1].as_str(), "src/window");
@@ -217,7 +452,7 @@ members = [
 ]
 "#;
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler.step("".into(), manifest.to_string()).unwrap();
+        let step_output = crawler.step("".into(), manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 3);
         assert_eq!(step_output.paths_of_interest[0].as_str(), "lib");
         assert_eq!(step_output.paths_of_interest[1].as_str(), "codegen");
@@ -234,7 +469,7 @@ members = [
 ]
 "#;
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler.step("".into(), manifest.to_string()).unwrap();
+        let step_output = crawler.step("".into(), manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 1);
         assert_eq!(step_output.paths_of_interest[0].as_str(), "lib");
     }
@@ -266,17 +501,13 @@ features = ["use_std"]
 "#;
 
         let mut crawler = ManifestCrawler::new();
-        let step_output = crawler
-            .step("".into(), futures_manifest.to_string())
-            .unwrap();
+        let step_output = crawler.step("".into(), futures_manifest.to_string());
         assert_eq!(step_output.paths_of_interest.len(), 1);
         assert_eq!(step_output.paths_of_interest[0].as_str(), "futures-cpupool");
-        let step_output = crawler
-            .step(
-                "futures-cpupool".into(),
-                futures_cpupool_manifest.to_string(),
-            )
-            .unwrap();
+        let step_output = crawler.step(
+            "futures-cpupool".into(),
+            futures_cpupool_manifest.to_string(),
+        );
         assert_eq!(step_output.paths_of_interest.len(), 0);
         let output = crawler.finalize();
         assert_eq!(output.crates.len(), 2);
@@ -296,9 +527,219 @@ features = ["use_std"]
                 .main
                 .get("futures")
                 .unwrap(),
-            &CrateDep::Internal(RelativePath::new("..").to_relative_path_buf())
+            &CrateDep::Internal(
+                RelativePath::new("..").to_relative_path_buf(),
+                Some(VersionReq::parse("0.1").unwrap())
+            )
         );
         assert_eq!(output.crates["futures-cpupool"].dev.len(), 0);
         assert_eq!(output.crates["futures-cpupool"].build.len(), 0);
+        assert_eq!(output.manifest_paths["futures"].as_str(), "Cargo.toml");
+        assert_eq!(
+            output.manifest_paths["futures-cpupool"].as_str(),
+            "futures-cpupool/Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn workspace_patches_apply_to_members() {
+        let root_manifest = r#"
+[workspace]
+members = ["app"]
+
+[patch.crates-io]
+serde = { git = "https://github.com/serde-rs/serde" }
+my-log = { path = "vendor/log", package = "log" }
+"#;
+
+        let app_manifest = r#"
+[package]
+name = "app"
+
+[dependencies]
+serde = "1.0"
+log = "0.4"
+rand = "0.8"
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        crawler.step("".into(), root_manifest.to_string());
+        crawler.step("app".into(), app_manifest.to_string());
+        let output = crawler.finalize();
+
+        let mut patched: Vec<&str> = output.crates["app"]
+            .patched
+            .iter()
+            .map(|name| name.as_ref())
+            .collect();
+        patched.sort_unstable();
+        assert_eq!(patched, vec!["log", "serde"]);
+    }
+
+    #[test]
+    fn members_that_fail_to_parse_are_left_out() {
+        let root_manifest = r#"
+[workspace]
+members = ["app", "broken"]
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        let step_output = crawler.step("".into(), root_manifest.to_string());
+        assert_eq!(step_output.paths_of_interest.len(), 2);
+        crawler.step("app".into(), "[package]\nname = \"app\"\n".to_string());
+        let step_output = crawler.step("broken".into(), "[package\nname = ".to_string());
+        assert!(step_output.paths_of_interest.is_empty());
+        let output = crawler.finalize();
+
+        assert_eq!(output.crates.len(), 1);
+        assert!(output.crates.contains_key("app"));
+        assert_eq!(output.manifest_errors.len(), 1);
+        assert_eq!(output.manifest_errors[0].path.as_str(), "broken/Cargo.toml");
+    }
+
+    #[test]
+    fn path_dependency_cycles() {
+        let root_manifest = r#"
+[package]
+name = "app"
+
+[dependencies]
+app = { path = "." }
+core = { path = "core" }
+"#;
+
+        let core_manifest = r#"
+[package]
+name = "core"
+
+[dependencies]
+app = { path = ".." }
+
+[dev-dependencies]
+app = { path = ".." }
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        let step_output = crawler.step("".into(), root_manifest.to_string());
+        assert_eq!(
+            step_output.paths_of_interest,
+            vec![RelativePathBuf::from("core")]
+        );
+        let step_output = crawler.step("core".into(), core_manifest.to_string());
+        assert!(step_output.paths_of_interest.is_empty());
+        let output = crawler.finalize();
+
+        let cycles: Vec<Vec<&str>> = output
+            .dependency_cycles
+            .iter()
+            .map(|cycle| cycle.iter().map(RelativePathBuf::as_str).collect())
+            .collect();
+        assert_eq!(
+            cycles,
+            vec![
+                vec!["Cargo.toml", "Cargo.toml"],
+                vec!["Cargo.toml", "core/Cargo.toml", "Cargo.toml"]
+            ]
+        );
+        assert_eq!(
+            output.internal_deps,
+            vec![
+                ("app".parse().unwrap(), "core".parse().unwrap()),
+                ("core".parse().unwrap(), "app".parse().unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn members_that_fail_to_be_retrieved_are_left_out() {
+        let root_manifest = r#"
+[workspace]
+members = ["app", "gone"]
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        crawler.step("".into(), root_manifest.to_string());
+        crawler.step("app".into(), "[package]\nname = \"app\"\n".to_string());
+        crawler.skip(RelativePath::new("gone"), &anyhow::anyhow!("404 Not Found"));
+        let output = crawler.finalize();
+
+        assert_eq!(output.crates.len(), 1);
+        assert_eq!(
+            output.manifest_errors,
+            vec![ManifestError {
+                path: "gone/Cargo.toml".into(),
+                message: "could not be retrieved: 404 Not Found".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn internal_version_mismatch() {
+        let root_manifest = r#"
+[workspace]
+members = ["core", "cli"]
+"#;
+        let core_manifest = r#"
+[package]
+name = "app-core"
+version = "0.3.0"
+"#;
+        let cli_manifest = r#"
+[package]
+name = "app-cli"
+version = "0.3.0"
+
+[dependencies]
+app-core = { path = "../core", version = "0.2" }
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        crawler.step("".into(), root_manifest.to_string());
+        crawler.step("core".into(), core_manifest.to_string());
+        crawler.step("cli".into(), cli_manifest.to_string());
+        let output = crawler.finalize();
+
+        assert_eq!(
+            output.version_mismatches,
+            vec![InternalVersionMismatch {
+                dependent: "app-cli".parse().unwrap(),
+                dependency: "app-core".parse().unwrap(),
+                required: VersionReq::parse("0.2").unwrap(),
+                actual: "0.3.0".parse().unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn internal_dependencies() {
+        let root_manifest = r#"
+[workspace]
+members = ["core", "cli"]
+"#;
+        let core_manifest = r#"
+[package]
+name = "app-core"
+"#;
+        let cli_manifest = r#"
+[package]
+name = "app-cli"
+
+[dependencies]
+app-core = { path = "../core" }
+
+[dev-dependencies]
+app-core = { path = "../core" }
+"#;
+
+        let mut crawler = ManifestCrawler::new();
+        crawler.step("".into(), root_manifest.to_string());
+        crawler.step("core".into(), core_manifest.to_string());
+        crawler.step("cli".into(), cli_manifest.to_string());
+        let output = crawler.finalize();
+
+        assert_eq!(
+            output.internal_deps,
+            vec![("app-cli".parse().unwrap(), "app-core".parse().unwrap())]
+        );
     }
 }
@@ -1,2 +1,3 @@
-pub mod analyzer;
+pub use deps_rs_core::analyzer;
+
 pub mod crawler;
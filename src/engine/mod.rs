@@ -1,149 +1,804 @@
 use std::{
-    collections::HashSet,
-    panic::RefUnwindSafe,
-    sync::Arc,
-    time::{Duration, Instant},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    future::Future,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use anyhow::{anyhow, Error};
-use cadence::{MetricSink, NopMetricSink, StatsdClient};
-use crates_index::Index;
-use futures::{future::try_join_all, stream, StreamExt};
-use hyper::service::Service;
+use anyhow::{anyhow, Context as _, Error};
+use badge::BadgeStyle;
+use cadence::StatsdClient;
+use futures::{stream, StreamExt, TryStreamExt};
 use once_cell::sync::Lazy;
 use relative_path::{RelativePath, RelativePathBuf};
-use rustsec::database::Database;
+use rustsec::{cargo_lock::Lockfile, database::Database};
 use semver::VersionReq;
-use slog::Logger;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use slog::{debug, error, Logger};
 use stream::BoxStream;
+use tower::{timeout::error::Elapsed, Service, ServiceExt as _};
 
-use crate::interactors::crates::{GetPopularCrates, QueryCrate};
-use crate::interactors::github::GetPopularRepos;
+use crate::interactors::crates::{CrateMetadata, GetCrateMetadata, GetPopularCrates, QueryCrate};
+use crate::interactors::github::GetOrgRepos;
+use crate::interactors::pins::CompareGitPin;
+use crate::interactors::popular_repos::GetPopularRepos;
 use crate::interactors::rustsec::FetchAdvisoryDatabase;
-use crate::interactors::RetrieveFileAtPath;
-use crate::models::crates::{AnalyzedDependencies, CrateName, CratePath, CrateRelease};
-use crate::models::repo::{RepoPath, Repository};
-use crate::utils::cache::Cache;
+use crate::interactors::tree::FindManifestDirs;
+use crate::interactors::{RetrieveFileAtPath, Timeouts};
+use crate::models::crates::{
+    compatibility_key, AnalyzedDependencies, CrateDep, CrateDeps, CrateName, CratePath,
+    CrateRelease, CratesIoInfo, DepScope, DependencyChange, DuplicateDependency, GitPinLag,
+    InternalVersionMismatch, ManifestError, PackageMetadata, RequirementConflict, RustVersion,
+    VersionLag,
+};
+use crate::models::repo::{GitRef, RepoConfig, RepoPath, RepoQualifier, RepoSite, Repository};
+use crate::models::SubjectPath;
+use crate::parsers::repo_config::parse_repo_config;
+use crate::utils::cache::{Cache, CacheBackend, Store, TtlCache};
+use crate::utils::index::{CrateIndex, Registries, Registry};
+use crate::utils::middleware::{self, CircuitOpen, Stack, StackConfig};
+use crate::utils::pacer::Pacer;
+use crate::utils::single_flight::SingleFlight;
 
+mod deprecations;
+mod feed;
+mod frozen;
 mod fut;
+mod history;
 mod machines;
+mod recent;
+mod watchlists;
+mod webhooks;
+
+pub use self::feed::{FeedEntry, FeedEvent};
+pub use self::frozen::format_id as format_snapshot_id;
+pub use self::history::Snapshot;
+pub use self::watchlists::Watched;
+pub use self::webhooks::InvalidWebhook;
 
+use self::feed::RepoFeed;
+use self::frozen::FrozenAnalyses;
 use self::fut::{analyze_dependencies, crawl_manifest};
+use self::history::History;
+use self::machines::analyzer::{apply_lockfile, find_duplicates};
+use self::recent::RecentAnalyses;
+use self::watchlists::Watchlists;
+use self::webhooks::Webhooks;
+
+/// Limits on how much work analyses may do, and how much of it at the same time.
+#[derive(Clone, Copy, Debug)]
+pub struct Concurrency {
+    /// Crates whose releases or metadata a single analysis looks up at once
+    pub fetches_per_analysis: usize,
+    /// Workspace members a single analysis processes at once
+    pub members_per_analysis: usize,
+    /// Manifests a single analysis retrieves at once while crawling a repository
+    pub manifests_per_crawl: usize,
+    /// Manifests a single analysis retrieves at most, the rest of a repository is left out
+    pub max_manifests: usize,
+    /// Crate lookups and manifest retrievals across all running analyses
+    pub fetches_total: usize,
+    /// Crate lookups and manifest retrievals across all running background analyses, which
+    /// don't count towards `fetches_total`
+    pub background_fetches_total: usize,
+}
+
+/// How long the engine's caches keep their entries.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheTtls {
+    /// Releases of a crate, looked up in the index
+    pub query_crate: Duration,
+    /// Descriptions and licenses of crates from crates.io
+    pub crate_metadata: Duration,
+    pub popular_crates: Duration,
+    pub popular_repos: Duration,
+    /// Subdirectories with a manifest, suggested when a repository has none at its root
+    pub manifest_dirs: Duration,
+    /// Results of analyses, including failed ones unless the failure may go away by itself
+    pub analyses: Duration,
+}
+
+/// How many entries the engine's in-memory caches hold at most.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheSizes {
+    pub query_crate: usize,
+    pub crate_metadata: usize,
+    pub manifest_dirs: usize,
+    pub analyses: usize,
+}
+
+/// How a repository is analyzed
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RepoAnalysisOptions {
+    /// Directory to start at instead of the root
+    pub entry_point: Option<RelativePathBuf>,
+    /// Branch, tag or commit to analyze instead of the default branch
+    pub git_ref: Option<GitRef>,
+    /// Whether to compare the versions locked in `Cargo.lock` instead of the newest versions
+    /// the requirements allow
+    pub locked: bool,
+    /// Whether to also analyze the dependencies of the dependencies
+    pub deep: bool,
+    /// Whether dependencies that require a pre-release are compared against newer
+    /// pre-releases instead of only stable releases
+    pub include_prerelease: bool,
+}
+
+impl RepoAnalysisOptions {
+    fn entry_point(&self) -> RelativePathBuf {
+        self.entry_point
+            .clone()
+            .unwrap_or_else(|| RelativePath::new("/").to_relative_path_buf())
+    }
+}
+
+/// Levels of dependencies below the direct ones that deep analyses resolve
+const DEEP_ANALYSIS_MAX_DEPTH: usize = 3;
+/// Transitive dependencies after which deep analyses stop descending further
+const DEEP_ANALYSIS_MAX_CRATES: usize = 300;
+
+/// Time between calls to the crates.io API, as its crawler policy allows one per second
+const CRATES_API_INTERVAL: Duration = Duration::from_secs(1);
+/// How long an analysis waits for its turn to call the crates.io API. Metadata that would take
+/// longer is left out and fetched by a later analysis.
+const CRATES_API_MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Identifies an analysis by its subject and, for repositories, the options it runs with
+type AnalysisKey = (SubjectPath, RepoAnalysisOptions);
+
+/// How long git pins whose comparison failed aren't compared again, so that e.g. hitting the
+/// API rate limit doesn't make every analysis try again
+const FAILED_GIT_PIN_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Identifies an owner by site and lowercased qualifier
+type OrgKey = (RepoSite, String);
+
+/// How long a repository counts towards the summary of its owner after its last analysis
+const REPO_HEALTH_TTL: Duration = Duration::from_secs(7 * 86400);
+/// Time after re-analyzing the stale repositories of an owner in which they aren't
+/// re-analyzed again, however often the owner's summary is requested
+const ORG_REFRESH_COOLDOWN: Duration = Duration::from_secs(15 * 60);
 
 #[derive(Clone, Debug)]
 pub struct Engine {
     client: reqwest::Client,
+    concurrency: Concurrency,
+    /// Overall time retrieving the manifests of a repository may take
+    crawl_timeout: Duration,
+    /// Overall time looking up the releases of an analysis' dependencies may take
+    analysis_timeout: Duration,
     logger: Logger,
     metrics: StatsdClient,
-    query_crate: Cache<QueryCrate, CrateName>,
-    get_popular_crates: Cache<GetPopularCrates, ()>,
-    get_popular_repos: Cache<GetPopularRepos, ()>,
-    retrieve_file_at_path: RetrieveFileAtPath,
-    fetch_advisory_db: Cache<FetchAdvisoryDatabase, ()>,
+    query_crate: Cache<Stack<QueryCrate>, CrateName>,
+    registries: Registries,
+    /// Crate lookups in the indexes of alternative registries, by registry name
+    query_registry_crate: BTreeMap<String, Cache<Stack<QueryCrate>, CrateName>>,
+    get_popular_crates: Cache<Stack<GetPopularCrates>, ()>,
+    get_crate_metadata: Cache<Stack<GetCrateMetadata>, CrateName>,
+    /// Spaces out the crates.io API calls of all analyses
+    crates_api_pacer: Pacer,
+    get_popular_repos: Cache<Stack<GetPopularRepos>, ()>,
+    /// Rust repositories of GitHub organizations, kept for an hour as the API's rate limit is
+    /// low without authentication
+    get_org_repos: Cache<Stack<GetOrgRepos>, RepoQualifier>,
+    find_manifest_dirs: Cache<Stack<FindManifestDirs>, RepoPath>,
+    /// How far pinned git dependencies are behind, kept for a day to go easy on the hosts' APIs
+    compare_git_pin: Cache<Stack<CompareGitPin>, (RepoPath, String)>,
+    /// Git pins whose comparison failed recently
+    failed_git_pins: TtlCache<(RepoPath, String), ()>,
+    retrieve_file_at_path: Stack<RetrieveFileAtPath>,
+    /// Like `query_crate`, `query_registry_crate` and `retrieve_file_at_path`, but on the
+    /// background client with a concurrency budget of their own, swapped in by `background`
+    background_fetches: BackgroundFetches,
+    fetch_advisory_db: Stack<FetchAdvisoryDatabase>,
+    /// The current advisory database, replaced as a whole on refreshes so analyses always
+    /// work with one consistent snapshot
+    advisory_db: Arc<RwLock<Option<Arc<Database>>>>,
+    analysis_cache: Store<AnalysisKey, Result<AnalyzeDependenciesOutcome, String>>,
+    /// Analyses currently running, so that concurrent requests for the same subject share one
+    running_analyses: SingleFlight<AnalysisKey, Result<AnalyzeDependenciesOutcome, String>>,
+    /// Successful analyses kept for a day, served while the engine is in cached-only mode
+    last_analyses: Store<AnalysisKey, AnalyzeDependenciesOutcome>,
+    cached_only: Arc<AtomicBool>,
+    /// Whether the last successful analysis of each repository of an owner found any issues,
+    /// and when it ran. Kept much longer than the analyses themselves to summarize owners.
+    repo_health: TtlCache<OrgKey, HashMap<RepoPath, (bool, Instant)>>,
+    /// Owners whose stale repositories were re-analyzed recently
+    org_refreshes: TtlCache<OrgKey, ()>,
+    /// Issues found over time in each repository, for its Atom feed
+    repo_feeds: TtlCache<RepoPath, RepoFeed>,
+    webhooks: Webhooks,
+    history: History,
+    /// Analyses frozen along with the history snapshots, for their permalinks
+    frozen: FrozenAnalyses,
+    /// Subjects analyzed most recently, for the front page
+    recent_analyses: RecentAnalyses,
+    watchlists: Watchlists,
+}
+
+/// The services background analyses fetch crates and manifests through
+#[derive(Clone, Debug)]
+struct BackgroundFetches {
+    query_crate: Cache<Stack<QueryCrate>, CrateName>,
+    query_registry_crate: BTreeMap<String, Cache<Stack<QueryCrate>, CrateName>>,
+    retrieve_file_at_path: Stack<RetrieveFileAtPath>,
 }
 
 impl Engine {
-    pub fn new(client: reqwest::Client, index: Index, logger: Logger) -> Engine {
-        let metrics = StatsdClient::from_sink("engine", NopMetricSink);
+    /// Creates a new engine.
+    ///
+    /// `client` is used for requests made while serving a status page or badge, while
+    /// `background_client` handles everything that isn't latency-sensitive except for webhook
+    /// notifications, which go through `webhook_client`. `github_token` authenticates the
+    /// requests to the GitHub API comparing git pins. Crate lookups and analyses are cached on
+    /// `cache_backend`, so replicas can share them. The front page lists popular repositories
+    /// of the Gitea instances in `popular_gitea_hosts` besides GitHub's and GitLab's.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        client: reqwest::Client,
+        background_client: reqwest::Client,
+        webhook_client: reqwest::Client,
+        github_token: Option<String>,
+        timeouts: Timeouts,
+        concurrency: Concurrency,
+        cache_ttls: CacheTtls,
+        cache_sizes: CacheSizes,
+        index: CrateIndex,
+        registries: Registries,
+        popular_gitea_hosts: Vec<String>,
+        cache_backend: CacheBackend,
+        metrics: StatsdClient,
+        logger: Logger,
+    ) -> Engine {
+        let stack = |name, timeout, retries, concurrency| StackConfig {
+            name,
+            timeout,
+            retries,
+            concurrency,
+        };
 
-        let query_crate = Cache::new(
-            QueryCrate::new(index),
-            Duration::from_secs(10),
-            500,
+        let query_crate_store = cache_backend.store(
+            "query_crate",
+            cache_ttls.query_crate,
+            cache_sizes.query_crate,
             logger.clone(),
         );
+        let registry_stores: BTreeMap<_, _> = registries
+            .iter()
+            .map(|registry| {
+                let store = cache_backend.store(
+                    &format!("query_registry_crate:{}", registry.name),
+                    cache_ttls.query_crate,
+                    cache_sizes.query_crate,
+                    logger.clone(),
+                );
+                (registry.name.clone(), store)
+            })
+            .collect();
+        // interactive and background lookups share their caches, but not their clients and
+        // concurrency budgets
+        let query_crate = |client: &reqwest::Client, name: &'static str, concurrency: usize| {
+            Cache::with_store(
+                middleware::stack(
+                    QueryCrate::new(client.clone(), index.clone()),
+                    stack(name, timeouts.default, 0, concurrency),
+                    metrics.clone(),
+                ),
+                query_crate_store.clone(),
+                logger.clone(),
+            )
+        };
+        let query_registry_crate =
+            |client: &reqwest::Client, name: &'static str, concurrency: usize| {
+                registries
+                    .iter()
+                    .map(|registry| {
+                        let query = Cache::with_store(
+                            middleware::stack(
+                                QueryCrate::new(client.clone(), registry.index.clone()),
+                                stack(name, timeouts.default, 0, concurrency),
+                                metrics.clone(),
+                            ),
+                            registry_stores[&registry.name].clone(),
+                            logger.clone(),
+                        );
+                        (registry.name.clone(), query)
+                    })
+                    .collect::<BTreeMap<_, _>>()
+            };
         let get_popular_crates = Cache::new(
-            GetPopularCrates::new(client.clone()),
-            Duration::from_secs(120),
+            middleware::stack(
+                GetPopularCrates::new(background_client.clone()),
+                stack("get_popular_crates", timeouts.popular_crates, 2, 1),
+                metrics.clone(),
+            ),
+            cache_ttls.popular_crates,
             1,
             logger.clone(),
         );
+        let get_crate_metadata = Cache::new(
+            middleware::stack(
+                GetCrateMetadata::new(background_client.clone()),
+                stack("get_crate_metadata", timeouts.default, 1, 10),
+                metrics.clone(),
+            ),
+            cache_ttls.crate_metadata,
+            cache_sizes.crate_metadata,
+            logger.clone(),
+        );
         let get_popular_repos = Cache::new(
-            GetPopularRepos::new(client.clone()),
-            Duration::from_secs(120),
+            middleware::stack(
+                GetPopularRepos::new(background_client.clone(), popular_gitea_hosts),
+                stack("get_popular_repos", timeouts.popular_repos, 2, 1),
+                metrics.clone(),
+            ),
+            cache_ttls.popular_repos,
             1,
             logger.clone(),
         );
-        let retrieve_file_at_path = RetrieveFileAtPath::new(client.clone());
-        let fetch_advisory_db = Cache::new(
-            FetchAdvisoryDatabase::new(client.clone()),
-            Duration::from_secs(1800),
-            1,
+        let get_org_repos = Cache::new(
+            middleware::stack(
+                GetOrgRepos::new(background_client.clone()),
+                stack("get_org_repos", timeouts.default, 1, 4),
+                metrics.clone(),
+            ),
+            Duration::from_secs(3600),
+            100,
+            logger.clone(),
+        );
+        let find_manifest_dirs = Cache::new(
+            middleware::stack(
+                FindManifestDirs::new(background_client.clone()),
+                stack("find_manifest_dirs", timeouts.default, 1, 10),
+                metrics.clone(),
+            ),
+            cache_ttls.manifest_dirs,
+            cache_sizes.manifest_dirs,
+            logger.clone(),
+        );
+        let compare_git_pin = Cache::new(
+            middleware::stack(
+                CompareGitPin::new(client.clone(), github_token),
+                stack("compare_git_pin", timeouts.default, 1, 4),
+                metrics.clone(),
+            ),
+            Duration::from_secs(86400),
+            1000,
+            logger.clone(),
+        );
+        let (crawl_timeout, analysis_timeout) = (timeouts.crawl, timeouts.analysis);
+        // the per-site timeouts apply to the individual attempts, which the interactor retries
+        // itself as far as they are worth retrying
+        let retrieve_timeout = RetrieveFileAtPath::total_timeout(&timeouts);
+        let retrieve_file_at_path =
+            |client: &reqwest::Client, name: &'static str, concurrency: usize| {
+                middleware::stack(
+                    RetrieveFileAtPath::new(client.clone(), timeouts.clone()),
+                    stack(name, retrieve_timeout, 0, concurrency),
+                    metrics.clone(),
+                )
+            };
+        let background_fetches = BackgroundFetches {
+            query_crate: query_crate(
+                &background_client,
+                "background_query_crate",
+                concurrency.background_fetches_total,
+            ),
+            query_registry_crate: query_registry_crate(
+                &background_client,
+                "background_query_registry_crate",
+                concurrency.background_fetches_total,
+            ),
+            retrieve_file_at_path: retrieve_file_at_path(
+                &background_client,
+                "background_retrieve_file_at_path",
+                concurrency.background_fetches_total,
+            ),
+        };
+        let query_crate = query_crate(&client, "query_crate", concurrency.fetches_total);
+        let query_registry_crate =
+            query_registry_crate(&client, "query_registry_crate", concurrency.fetches_total);
+        let retrieve_file_at_path =
+            retrieve_file_at_path(&client, "retrieve_file_at_path", concurrency.fetches_total);
+        let fetch_advisory_db = middleware::stack(
+            FetchAdvisoryDatabase::new(background_client.clone()),
+            stack("fetch_advisory_db", Duration::from_secs(300), 1, 1),
+            metrics.clone(),
+        );
+        let analysis_cache = cache_backend.store(
+            "analysis",
+            cache_ttls.analyses,
+            cache_sizes.analyses,
+            logger.clone(),
+        );
+        let last_analyses = cache_backend.store(
+            "last_analysis",
+            Duration::from_secs(86400),
+            cache_sizes.analyses,
             logger.clone(),
         );
+        let repo_health = TtlCache::new(REPO_HEALTH_TTL, 10000);
+        let org_refreshes = TtlCache::new(ORG_REFRESH_COOLDOWN, 10000);
+        let repo_feeds = TtlCache::new(Duration::from_secs(30 * 86400), 10000);
+        let webhooks = Webhooks::new(webhook_client);
 
         Engine {
             client,
+            concurrency,
+            crawl_timeout,
+            analysis_timeout,
             logger,
             metrics,
             query_crate,
+            registries,
+            query_registry_crate,
             get_popular_crates,
+            get_crate_metadata,
+            crates_api_pacer: Pacer::new(CRATES_API_INTERVAL),
             get_popular_repos,
+            get_org_repos,
+            find_manifest_dirs,
+            compare_git_pin,
+            failed_git_pins: TtlCache::new(FAILED_GIT_PIN_TTL, 1000),
             retrieve_file_at_path,
+            background_fetches,
             fetch_advisory_db,
+            advisory_db: Arc::new(RwLock::new(None)),
+            analysis_cache,
+            running_analyses: SingleFlight::default(),
+            last_analyses,
+            cached_only: Arc::new(AtomicBool::new(false)),
+            repo_health,
+            org_refreshes,
+            repo_feeds,
+            webhooks,
+            history: History::default(),
+            frozen: FrozenAnalyses::default(),
+            recent_analyses: RecentAnalyses::default(),
+            watchlists: Watchlists::default(),
         }
     }
-
-    pub fn set_metrics<M: MetricSink + Send + Sync + RefUnwindSafe + 'static>(&mut self, sink: M) {
-        self.metrics = StatsdClient::from_sink("engine", sink);
-    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AnalyzeDependenciesOutcome {
     pub crates: Vec<(CrateName, AnalyzedDependencies)>,
+    /// Path dependencies between workspace members whose requirements have drifted
+    pub version_mismatches: Vec<InternalVersionMismatch>,
+    /// Location of each crate's `Cargo.toml` in the repository, empty when analyzing a crate
+    pub manifest_paths: HashMap<CrateName, RelativePathBuf>,
+    /// `rust-version` of each analyzed crate that declares one
+    pub rust_versions: HashMap<CrateName, RustVersion>,
+    /// Edition, description and repository of each analyzed crate that declares any of them,
+    /// empty when analyzing a crate
+    pub package_metadata: HashMap<CrateName, PackageMetadata>,
+    /// What crates.io lists about the analyzed release, only set when analyzing a crate
+    pub crates_io: Option<CratesIoInfo>,
+    /// Path dependencies between the analyzed crates, from dependent to dependency
+    pub internal_deps: Vec<(CrateName, CrateName)>,
+    /// Crates the repository's `Cargo.lock` contains in several incompatible versions, empty
+    /// if it has none
+    pub duplicates: Vec<DuplicateDependency>,
+    /// Dependencies of the dependencies, by the requirement closest to the analyzed crates.
+    /// Only resolved in deep analyses, as `main` dependencies.
+    pub transitive: Option<AnalyzedDependencies>,
     pub duration: Duration,
+    /// When the analysis finished
+    pub analyzed_at: SystemTime,
+    /// Set when an older analysis is served because the engine is in cached-only mode
+    pub stale: bool,
+    /// ID of the frozen copy of a repository analysis, for its permalink
+    pub snapshot: Option<u64>,
+    /// Set when the analysis is a frozen copy served through its permalink
+    pub frozen: bool,
+    /// Manifests left out because the repository has more than an analysis retrieves
+    pub skipped_manifests: usize,
+    /// Manifests of the repository that were left out because they couldn't be analyzed
+    pub manifest_errors: Vec<ManifestError>,
+    /// `Cargo.toml`s whose path dependencies lead back to themselves, each cycle starting and
+    /// ending with the same one
+    pub dependency_cycles: Vec<Vec<RelativePathBuf>>,
+    /// Badge style the repository's `.deps-rs.toml` asks for
+    pub badge_style: Option<BadgeStyle>,
+}
+
+/// Dependency health of all analyzed repositories of one owner
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrgSummary {
+    pub repos: usize,
+    pub with_issues: usize,
 }
 
 impl AnalyzeDependenciesOutcome {
+    /// Removes dependencies from the outcome, so they don't count towards any issues
+    pub fn ignore_dependencies(&mut self, names: &[CrateName]) {
+        let all_deps = self
+            .crates
+            .iter_mut()
+            .map(|(_, deps)| deps)
+            .chain(self.transitive.as_mut());
+
+        for deps in all_deps {
+            for name in names {
+                deps.main.shift_remove(name);
+                deps.dev.shift_remove(name);
+                deps.build.shift_remove(name);
+            }
+        }
+    }
+
+    /// Removes the optional dependencies of the analyzed crates that none of `features` enables
+    pub fn remove_disabled_optional(&mut self, features: &[String]) {
+        for (_, deps) in &mut self.crates {
+            for deps in [&mut deps.main, &mut deps.dev, &mut deps.build] {
+                deps.retain(|_, dep| {
+                    dep.usage
+                        .as_ref()
+                        .map_or(true, |usage| usage.is_enabled_by(features))
+                });
+            }
+        }
+    }
+
+    /// Lists the changed dependencies of each crate from `before` to this outcome, leaving out
+    /// crates without changes. All dependencies of a crate only one of them has count as added
+    /// or removed.
+    pub fn changes_since(
+        &self,
+        before: &AnalyzeDependenciesOutcome,
+    ) -> Vec<(CrateName, Vec<DependencyChange>)> {
+        fn find<'a>(
+            outcome: &'a AnalyzeDependenciesOutcome,
+            name: &CrateName,
+        ) -> Option<&'a AnalyzedDependencies> {
+            outcome
+                .crates
+                .iter()
+                .find(|(crate_name, _)| crate_name == name)
+                .map(|(_, deps)| deps)
+        }
+
+        let mut names: Vec<&CrateName> = self.crates.iter().map(|(name, _)| name).collect();
+        for (name, _) in &before.crates {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let empty = AnalyzedDependencies::default();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let after = find(self, name).unwrap_or(&empty);
+                let changes = after.changes_since(find(before, name).unwrap_or(&empty));
+                if changes.is_empty() {
+                    None
+                } else {
+                    Some((name.clone(), changes))
+                }
+            })
+            .collect()
+    }
+
     pub fn any_outdated(&self) -> bool {
         self.crates.iter().any(|&(_, ref deps)| deps.any_outdated())
     }
 
+    /// Checks if there is anything that would keep the status badge from being green
+    pub fn any_issues(&self) -> bool {
+        self.any_insecure() || self.any_unavailable() || self.any_yanked() || self.any_outdated()
+    }
+
+    /// Checks if any dependencies in the scanned crates have no available releases
+    pub fn any_unavailable(&self) -> bool {
+        self.crates
+            .iter()
+            .any(|&(_, ref deps)| deps.any_unavailable())
+    }
+
+    /// Checks if any dependencies in the scanned crates can only be resolved to yanked releases
+    pub fn any_yanked(&self) -> bool {
+        self.crates.iter().any(|&(_, ref deps)| deps.any_yanked())
+    }
+
+    /// Returns the number of dependencies that can only be resolved to yanked releases
+    pub fn count_yanked(&self) -> usize {
+        self.crates
+            .iter()
+            .flat_map(|&(_, ref deps)| {
+                deps.main
+                    .values()
+                    .chain(deps.dev.values())
+                    .chain(deps.build.values())
+            })
+            .filter(|dep| dep.is_yanked())
+            .count()
+    }
+
     // TODO(feliix42): Why is this different from the any_outdated() function above?
     /// Checks if any insecure main or build dependencies exist in the scanned crates
     pub fn any_insecure(&self) -> bool {
+        self.count_insecure(DepScope::STATUS) > 0
+    }
+
+    /// Returns the number of insecure dependencies in `scope`, including transitive ones in
+    /// deep analyses if it covers main dependencies
+    pub fn count_insecure(&self, scope: DepScope) -> usize {
         self.crates
             .iter()
-            .any(|&(_, ref deps)| deps.count_insecure() > 0)
+            .map(|&(_, ref deps)| deps)
+            .chain(&self.transitive)
+            .map(|deps| deps.count_insecure(scope))
+            .sum()
     }
 
-    /// Checks if any dev-dependencies in the scanned crates are either outdated or insecure
-    pub fn any_dev_issues(&self) -> bool {
+    /// Returns the number of git dependencies pinned to a commit far behind their repository
+    pub fn count_stale_git(&self) -> usize {
         self.crates
             .iter()
-            .any(|&(_, ref deps)| deps.any_dev_issues())
+            .map(|(_, deps)| deps.count_stale_git())
+            .sum()
     }
 
-    /// Returns the number of outdated dev-dependencies
-    pub fn count_dev_outdated(&self) -> usize {
+    /// Returns the number of dependencies without any available releases
+    pub fn count_unavailable(&self) -> usize {
         self.crates
             .iter()
-            .map(|&(_, ref deps)| deps.count_dev_outdated())
-            .sum()
+            .flat_map(|&(_, ref deps)| {
+                deps.main
+                    .values()
+                    .chain(deps.dev.values())
+                    .chain(deps.build.values())
+            })
+            .filter(|dep| dep.is_unavailable())
+            .count()
+    }
+
+    /// Returns the Rust version needed to build all analyzed crates, i.e. the highest
+    /// `rust-version` any of them declares
+    pub fn msrv(&self) -> Option<&RustVersion> {
+        self.rust_versions.values().max()
     }
 
-    /// Returns the number of insecure dev-dependencies
-    pub fn count_dev_insecure(&self) -> usize {
+    /// Returns the number of main and build dependencies that need a newer Rust than the
+    /// crate depending on them declares
+    pub fn count_exceeding_msrv(&self) -> usize {
         self.crates
             .iter()
-            .map(|&(_, ref deps)| deps.count_dev_insecure())
+            .filter_map(|(name, deps)| Some((deps, self.rust_versions.get(name)?)))
+            .map(|(deps, msrv)| deps.count_exceeding_msrv(msrv))
             .sum()
     }
 
-    /// Returns the number of outdated and the number of total main and build dependencies
-    pub fn outdated_ratio(&self) -> (usize, usize) {
+    /// Returns how many of the main and build dependencies are under each license, most common
+    /// first. Dependencies with an unknown license are counted under `None`.
+    pub fn license_counts(&self) -> Vec<(Option<&str>, usize)> {
+        // a crate several workspace members depend on is only counted once
+        let licenses: BTreeMap<&CrateName, Option<&str>> = self
+            .crates
+            .iter()
+            .flat_map(|(_, deps)| deps.main.iter().chain(&deps.build))
+            .filter(|(_, dep)| !dep.is_unanalyzed())
+            .map(|(name, dep)| (name, dep.license.as_deref()))
+            .collect();
+
+        let mut counts = BTreeMap::new();
+        for license in licenses.into_values() {
+            *counts.entry(license).or_insert(0) += 1;
+        }
+
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        counts
+    }
+
+    /// Checks if any dev-dependencies in the scanned crates are either outdated or insecure
+    pub fn any_dev_issues(&self) -> bool {
+        self.crates
+            .iter()
+            .any(|&(_, ref deps)| deps.any_dev_issues())
+    }
+
+    /// Finds external crates which different crates of the analysis require in
+    /// semver-incompatible versions
+    pub fn requirement_conflicts(&self) -> Vec<RequirementConflict> {
+        let mut requirements = BTreeMap::new();
+
+        for (member, deps) in &self.crates {
+            for (name, dep) in deps.main.iter().chain(&deps.dev).chain(&deps.build) {
+                if let Some(ref resolved) = dep.latest_that_matches {
+                    requirements.entry(name).or_insert_with(Vec::new).push((
+                        member,
+                        &dep.required,
+                        resolved,
+                    ));
+                }
+            }
+        }
+
+        requirements
+            .into_iter()
+            .filter(|(_, reqs)| {
+                let versions: BTreeSet<_> = reqs
+                    .iter()
+                    .map(|&(_, _, resolved)| compatibility_key(resolved))
+                    .collect();
+                versions.len() > 1
+            })
+            .map(|(name, reqs)| {
+                let mut requirements = Vec::new();
+                for (member, req, _) in reqs {
+                    let entry = (member.clone(), req.clone());
+                    if !requirements.contains(&entry) {
+                        requirements.push(entry);
+                    }
+                }
+
+                RequirementConflict {
+                    name: name.clone(),
+                    requirements,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the largest lag of the outdated dependencies in `scope`
+    pub fn max_lag(&self, scope: DepScope) -> Option<VersionLag> {
+        self.crates
+            .iter()
+            .filter_map(|(_, deps)| deps.max_lag(scope))
+            .max()
+    }
+
+    /// Returns the number of outdated and the number of total dependencies in `scope`
+    pub fn outdated_ratio(&self, scope: DepScope) -> (usize, usize) {
         self.crates
             .iter()
             .fold((0, 0), |(outdated, total), &(_, ref deps)| {
-                (outdated + deps.count_outdated(), total + deps.count_total())
+                (
+                    outdated + deps.count_outdated(scope),
+                    total + deps.count_total(scope),
+                )
             })
     }
 }
 
 impl Engine {
+    /// Switches cached-only mode on or off. While it is on, nothing is fetched from upstream
+    /// services; analyses are served from the cache, even if they are outdated.
+    pub fn set_cached_only(&self, cached_only: bool) {
+        self.cached_only.store(cached_only, Ordering::Relaxed);
+    }
+
+    pub fn is_cached_only(&self) -> bool {
+        self.cached_only.load(Ordering::Relaxed)
+    }
+
+    /// Returns an engine whose analyses fetch crates and manifests through the background
+    /// client and budget, for work nobody is waiting on, so it can't starve interactive
+    /// requests. Everything else, including the caches, is shared.
+    pub fn background(&self) -> Engine {
+        let fetches = self.background_fetches.clone();
+        Engine {
+            query_crate: fetches.query_crate,
+            query_registry_crate: fetches.query_registry_crate,
+            retrieve_file_at_path: fetches.retrieve_file_at_path,
+            ..self.clone()
+        }
+    }
+
     pub async fn get_popular_repos(&self) -> Result<Vec<Repository>, Error> {
-        let repos = self.get_popular_repos.cached_query(()).await?;
+        let repos = if self.is_cached_only() {
+            self.get_popular_repos.cached(&()).await.unwrap_or_default()
+        } else {
+            self.get_popular_repos.cached_query(()).await?
+        };
 
         let filtered_repos = repos
             .iter()
@@ -154,33 +809,363 @@ impl Engine {
         Ok(filtered_repos)
     }
 
+    /// Lists the Rust repositories of a GitHub organization or user
+    pub async fn get_org_repos(&self, qual: RepoQualifier) -> Result<Vec<Repository>, Error> {
+        if self.is_cached_only() {
+            return Ok(self.get_org_repos.cached(&qual).await.unwrap_or_default());
+        }
+
+        Ok(self.get_org_repos.cached_query(qual).await?)
+    }
+
     pub async fn get_popular_crates(&self) -> Result<Vec<CratePath>, Error> {
+        if self.is_cached_only() {
+            return Ok(self
+                .get_popular_crates
+                .cached(&())
+                .await
+                .unwrap_or_default());
+        }
+
         let crates = self.get_popular_crates.cached_query(()).await?;
         Ok(crates)
     }
 
+    /// Returns whether the result of a recent analysis of the subject is cached,
+    /// regardless of whether that analysis was successful
+    pub async fn has_cached_analysis(
+        &self,
+        subject_path: &SubjectPath,
+        options: &RepoAnalysisOptions,
+    ) -> bool {
+        let key = (subject_path.clone(), options.clone());
+        self.analysis_cache.get(&key).await.is_some()
+    }
+
+    /// Analyzes a repository, starting at the manifest in the options' entry point or else
+    /// the root
     pub async fn analyze_repo_dependencies(
         &self,
         repo_path: RepoPath,
+        options: RepoAnalysisOptions,
+    ) -> Result<AnalyzeDependenciesOutcome, Error> {
+        let key = (SubjectPath::Repo(repo_path.clone()), options.clone());
+        if let Some(cached) = self.analysis_cache.get(&key).await {
+            return cached.map_err(|err| anyhow!(err));
+        }
+        if self.is_cached_only() {
+            return self.stale_analysis(&key).await;
+        }
+
+        let engine = self.clone();
+        self.running_analyses
+            .run(key.clone(), move || {
+                Box::pin(async move {
+                    let is_default = options == RepoAnalysisOptions::default();
+                    let mut result = engine.run_repo_analysis(repo_path.clone(), options).await;
+                    let now = SystemTime::now();
+                    if let (Ok(outcome), true) = (&mut result, is_default) {
+                        outcome.snapshot = engine.record_snapshot(&repo_path, outcome, now).await;
+                    }
+                    engine.cache_analysis(key, &result).await;
+                    if let (Ok(outcome), true) = (&result, is_default) {
+                        let has_issues = outcome.any_issues();
+                        engine
+                            .repo_health
+                            .update(org_key(&repo_path.site, &repo_path.qual), |repos| {
+                                let mut repos = repos.unwrap_or_default();
+                                repos.retain(|_, (_, analyzed)| {
+                                    analyzed.elapsed() < REPO_HEALTH_TTL
+                                });
+                                repos.insert(repo_path.clone(), (has_issues, Instant::now()));
+                                repos
+                            })
+                            .await;
+                        engine
+                            .recent_analyses
+                            .record(SubjectPath::Repo(repo_path.clone()));
+
+                        let mut feed = engine.repo_feeds.get(&repo_path).await.unwrap_or_default();
+                        feed.update(outcome, now);
+                        engine.repo_feeds.insert(repo_path, feed).await;
+                    }
+                    result.map_err(|err| err.to_string())
+                })
+            })
+            .await
+            .map_err(|err| anyhow!(err))
+    }
+
+    /// Freezes an analysis of a repository and records it in its history. Returns the ID of
+    /// the frozen analysis, for its permalink.
+    async fn record_snapshot(
+        &self,
+        repo_path: &RepoPath,
+        outcome: &AnalyzeDependenciesOutcome,
+        time: SystemTime,
+    ) -> Option<u64> {
+        // not kept in the history, so they aren't frozen either
+        if matches!(repo_path.site, RepoSite::Custom(_)) {
+            return None;
+        }
+
+        let id = match self.frozen.freeze(repo_path, outcome).await {
+            Ok(id) => id,
+            Err(err) => {
+                error!(self.logger, "failed freezing an analysis: {:#}", err);
+                None
+            }
+        };
+        self.history.record(repo_path, outcome, time, id).await;
+        id
+    }
+
+    /// Returns a frozen analysis of a repository, if the history still keeps it
+    pub async fn frozen_analysis(
+        &self,
+        repo_path: &RepoPath,
+        id: &str,
+    ) -> Option<AnalyzeDependenciesOutcome> {
+        let id = frozen::parse_id(id)?;
+        let outcome = match self.frozen.get(repo_path, id).await {
+            Ok(outcome) => outcome?,
+            Err(err) => {
+                error!(self.logger, "failed loading a frozen analysis: {:#}", err);
+                return None;
+            }
+        };
+        Some(AnalyzeDependenciesOutcome {
+            snapshot: Some(id),
+            frozen: true,
+            ..outcome
+        })
+    }
+
+    /// Returns the feed entries of a repository, newest first. Only analyses with the default
+    /// options are recorded.
+    pub async fn repo_feed(&self, repo_path: &RepoPath) -> Vec<FeedEntry> {
+        self.repo_feeds
+            .get(repo_path)
+            .await
+            .map(|feed| feed.entries)
+            .unwrap_or_default()
+    }
+
+    /// Returns the daily snapshots of a repository's dependency counts, oldest first. Like the
+    /// feed, only analyses with the default options are recorded.
+    pub async fn repo_history(&self, repo_path: &RepoPath) -> Vec<Snapshot> {
+        self.history.snapshots(repo_path).await
+    }
+
+    /// Returns the repositories and crates whose analysis with the default options succeeded
+    /// most recently, newest first
+    pub fn recent_analyses(&self) -> Vec<SubjectPath> {
+        self.recent_analyses.subjects()
+    }
+
+    /// Summarizes the repositories of an owner that have been analyzed recently.
+    ///
+    /// Repositories whose analysis has dropped out of the cache are counted with their last
+    /// known state and re-analyzed in the background, one after the other and at most once
+    /// per `ORG_REFRESH_COOLDOWN`.
+    pub async fn summarize_org(&self, site: RepoSite, qual: &RepoQualifier) -> OrgSummary {
+        let key = org_key(&site, qual);
+        let repos = self.repo_health.peek(&key).await.unwrap_or_default();
+
+        let mut summary = OrgSummary::default();
+        let mut stale = Vec::new();
+        for (repo_path, (has_issues, analyzed)) in repos {
+            if analyzed.elapsed() >= REPO_HEALTH_TTL {
+                continue;
+            }
+
+            summary.repos += 1;
+            if has_issues {
+                summary.with_issues += 1;
+            }
+
+            let subject_path = SubjectPath::Repo(repo_path.clone());
+            if !self
+                .has_cached_analysis(&subject_path, &RepoAnalysisOptions::default())
+                .await
+            {
+                stale.push(repo_path);
+            }
+        }
+
+        if !stale.is_empty()
+            && !self.is_cached_only()
+            && self.org_refreshes.insert_new(key, ()).await
+        {
+            let engine = self.background();
+            tokio::spawn(async move {
+                for repo_path in stale {
+                    let options = RepoAnalysisOptions::default();
+                    if let Err(err) = engine.analyze_repo_dependencies(repo_path, options).await {
+                        debug!(engine.logger, "failed to refresh analysis"; "error" => err.to_string());
+                    }
+                }
+            });
+        }
+
+        summary
+    }
+
+    pub async fn analyze_crate_dependencies(
+        &self,
+        crate_path: CratePath,
+    ) -> Result<AnalyzeDependenciesOutcome, Error> {
+        let key = (
+            SubjectPath::Crate(crate_path.clone()),
+            RepoAnalysisOptions::default(),
+        );
+        if let Some(cached) = self.analysis_cache.get(&key).await {
+            return cached.map_err(|err| anyhow!(err));
+        }
+        if self.is_cached_only() {
+            return self.stale_analysis(&key).await;
+        }
+
+        let engine = self.clone();
+        self.running_analyses
+            .run(key.clone(), move || {
+                Box::pin(async move {
+                    let result = engine.run_crate_analysis(crate_path.clone()).await;
+                    engine.cache_analysis(key, &result).await;
+                    if result.is_ok() {
+                        engine
+                            .recent_analyses
+                            .record(SubjectPath::Crate(crate_path));
+                    }
+                    result.map_err(|err| err.to_string())
+                })
+            })
+            .await
+            .map_err(|err| anyhow!(err))
+    }
+
+    async fn cache_analysis(
+        &self,
+        key: AnalysisKey,
+        result: &Result<AnalyzeDependenciesOutcome, Error>,
+    ) {
+        let cached = match result {
+            Ok(outcome) => {
+                self.last_analyses
+                    .insert(key.clone(), outcome.clone())
+                    .await;
+                Ok(outcome.clone())
+            }
+            // failures that may go away by themselves are retried by the next request
+            Err(err) if is_temporary(err) => return,
+            Err(err) => Err(err.to_string()),
+        };
+        self.analysis_cache.insert(key, cached).await;
+    }
+
+    async fn stale_analysis(&self, key: &AnalysisKey) -> Result<AnalyzeDependenciesOutcome, Error> {
+        match self.last_analyses.get(key).await {
+            Some(outcome) => Ok(AnalyzeDependenciesOutcome {
+                stale: true,
+                ..outcome
+            }),
+            None => Err(anyhow!("no cached analysis available in cached-only mode")),
+        }
+    }
+
+    async fn run_repo_analysis(
+        &self,
+        repo_path: RepoPath,
+        options: RepoAnalysisOptions,
     ) -> Result<AnalyzeDependenciesOutcome, Error> {
         let start = Instant::now();
 
-        let entry_point = RelativePath::new("/").to_relative_path_buf();
+        let repo_config = self
+            .retrieve_repo_config(&repo_path, options.git_ref.as_ref())
+            .await?;
+        let entry_point = options.entry_point();
+        // manifests listed in the repository's configuration replace the root, but an
+        // explicitly requested path still wins
+        let entry_points = match (&options.entry_point, repo_config.manifests.is_empty()) {
+            (None, false) => repo_config.manifests.clone(),
+            _ => vec![entry_point.clone()],
+        };
         let engine = self.clone();
 
-        let manifest_output = crawl_manifest(self.clone(), repo_path.clone(), entry_point).await?;
+        // the lockfile is only required when comparing against it, otherwise it's just used to
+        // find duplicate dependencies
+        let lockfile = match self
+            .retrieve_lockfile(&repo_path, options.git_ref.as_ref(), &entry_point)
+            .await
+        {
+            Ok(lockfile) => Some(lockfile),
+            Err(err) if options.locked => return Err(err),
+            Err(_) => None,
+        };
+        let advisory_db = if options.locked {
+            Some(self.fetch_advisory_db().await?)
+        } else {
+            None
+        };
+        let duplicates = lockfile.as_ref().map(find_duplicates).unwrap_or_default();
+
+        let manifest_output = within(
+            self.crawl_timeout,
+            "retrieving the manifests",
+            crawl_manifest(
+                self.clone(),
+                repo_path.clone(),
+                options.git_ref.clone(),
+                entry_points,
+            ),
+        )
+        .await?;
+        let version_mismatches = manifest_output.version_mismatches;
+        let manifest_paths = manifest_output.manifest_paths;
+        let dependency_lines = manifest_output.dependency_lines;
+        let rust_versions = manifest_output.rust_versions;
+        let package_metadata = manifest_output.package_metadata;
+        let internal_deps = manifest_output.internal_deps;
+        let skipped_manifests = manifest_output.skipped_manifests;
+        let manifest_errors = manifest_output.manifest_errors;
+        let dependency_cycles = manifest_output.dependency_cycles;
 
         let engine_for_analyze = engine.clone();
-        let futures = manifest_output
-            .crates
-            .into_iter()
-            .map(|(crate_name, deps)| async {
-                let analyzed_deps = analyze_dependencies(engine_for_analyze.clone(), deps).await?;
+        let analyze_crates = stream::iter(manifest_output.crates)
+            .map(|(crate_name, mut deps)| async {
+                repo_config.remove_ignored(&mut deps);
+                let mut analyzed_deps = analyze_dependencies(
+                    engine_for_analyze.clone(),
+                    deps,
+                    options.include_prerelease,
+                )
+                .await?;
+                if let Some(lines) = dependency_lines.get(&crate_name) {
+                    analyzed_deps.set_manifest_lines(lines);
+                }
+                if let (Some(lockfile), true) = (&lockfile, options.locked) {
+                    apply_lockfile(&mut analyzed_deps, lockfile, advisory_db.as_deref());
+                }
+                repo_config.remove_ignored_advisories(&mut analyzed_deps);
                 Ok::<_, Error>((crate_name, analyzed_deps))
             })
-            .collect::<Vec<_>>();
+            .buffered(self.concurrency.members_per_analysis)
+            .try_collect::<Vec<_>>();
 
-        let crates = try_join_all(futures).await?;
+        // the transitive dependencies of deep analyses count towards the same deadline
+        let (crates, transitive) =
+            within(self.analysis_timeout, "analyzing the dependencies", async {
+                let crates = analyze_crates.await?;
+                let transitive = if options.deep {
+                    let mut transitive = self.analyze_transitive_dependencies(&crates).await?;
+                    repo_config.remove_ignored_advisories(&mut transitive);
+                    Some(transitive)
+                } else {
+                    None
+                };
+                Ok::<_, Error>((crates, transitive))
+            })
+            .await?;
 
         let duration = start.elapsed();
         // engine
@@ -191,10 +1176,29 @@ impl Engine {
         //     .with_tag("repo_name", repo_path.name.as_ref())
         //     .send()?;
 
-        Ok(AnalyzeDependenciesOutcome { crates, duration })
+        Ok(AnalyzeDependenciesOutcome {
+            crates,
+            version_mismatches,
+            manifest_paths,
+            rust_versions,
+            package_metadata,
+            crates_io: None,
+            internal_deps,
+            duplicates,
+            transitive,
+            duration,
+            analyzed_at: SystemTime::now(),
+            stale: false,
+            snapshot: None,
+            frozen: false,
+            skipped_manifests,
+            manifest_errors,
+            dependency_cycles,
+            badge_style: repo_config.badge_style,
+        })
     }
 
-    pub async fn analyze_crate_dependencies(
+    async fn run_crate_analysis(
         &self,
         crate_path: CratePath,
     ) -> Result<AnalyzeDependenciesOutcome, Error> {
@@ -218,15 +1222,105 @@ impl Engine {
             )),
 
             Some(release) => {
-                let analyzed_deps =
-                    analyze_dependencies(engine.clone(), release.deps.clone()).await?;
+                let analyzed_deps = within(
+                    self.analysis_timeout,
+                    "analyzing the dependencies",
+                    analyze_dependencies(engine.clone(), release.deps.clone(), false),
+                )
+                .await?;
 
+                let rust_versions = release
+                    .rust_version
+                    .iter()
+                    .map(|rust_version| (crate_path.name.clone(), rust_version.clone()))
+                    .collect();
+                let metadata = self.fetch_crate_metadata(&crate_path.name).await;
+                let crates_io = CratesIoInfo {
+                    license: metadata.licenses.get(&crate_path.version).cloned(),
+                    description: metadata.description,
+                    repository: metadata.repository,
+                    downloads: metadata.downloads,
+                };
                 let crates = vec![(crate_path.name, analyzed_deps)];
                 let duration = start.elapsed();
 
-                Ok(AnalyzeDependenciesOutcome { crates, duration })
+                Ok(AnalyzeDependenciesOutcome {
+                    crates,
+                    version_mismatches: Vec::new(),
+                    manifest_paths: HashMap::new(),
+                    rust_versions,
+                    package_metadata: HashMap::new(),
+                    crates_io: Some(crates_io),
+                    internal_deps: Vec::new(),
+                    duplicates: Vec::new(),
+                    transitive: None,
+                    duration,
+                    analyzed_at: SystemTime::now(),
+                    stale: false,
+                    snapshot: None,
+                    frozen: false,
+                    skipped_manifests: 0,
+                    manifest_errors: Vec::new(),
+                    dependency_cycles: Vec::new(),
+                    badge_style: None,
+                })
+            }
+        }
+    }
+
+    /// Resolves the dependencies of the direct main and build dependencies level by level,
+    /// skipping optional ones, and analyzes them. Each crate is analyzed once, with the
+    /// requirement found closest to the analyzed crates.
+    async fn analyze_transitive_dependencies(
+        &self,
+        crates: &[(CrateName, AnalyzedDependencies)],
+    ) -> Result<AnalyzedDependencies, Error> {
+        let direct = crates
+            .iter()
+            .flat_map(|(_, deps)| deps.main.iter().chain(&deps.build))
+            .filter(|(_, dep)| !dep.is_from_external_registry());
+
+        let mut seen: HashSet<CrateName> = HashSet::new();
+        let mut frontier = Vec::new();
+        for (name, dep) in direct {
+            if seen.insert(name.clone()) {
+                frontier.push((name.clone(), dep.required.clone()));
+            }
+        }
+
+        let mut transitive = CrateDeps::default();
+        for _ in 0..DEEP_ANALYSIS_MAX_DEPTH {
+            if frontier.is_empty() || transitive.main.len() >= DEEP_ANALYSIS_MAX_CRATES {
+                break;
+            }
+
+            let releases = stream::iter(frontier)
+                .map(|(name, req)| self.find_latest_crate_release(name, req))
+                .buffer_unordered(self.concurrency.fetches_per_analysis)
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            frontier = Vec::new();
+            for release in releases.into_iter().flatten() {
+                let deps = &release.deps;
+                for (kind, usage) in [
+                    (&deps.main, &deps.usage.main),
+                    (&deps.build, &deps.usage.build),
+                ] {
+                    for (name, dep) in kind {
+                        let optional = usage.get(name).map_or(false, |usage| usage.optional);
+                        if let (CrateDep::External(req), false) = (dep, optional) {
+                            if seen.insert(name.clone()) {
+                                transitive.main.insert(name.clone(), dep.clone());
+                                frontier.push((name.clone(), req.clone()));
+                            }
+                        }
+                    }
+                }
             }
         }
+
+        analyze_dependencies(self.clone(), transitive, false).await
     }
 
     pub async fn find_latest_crate_release(
@@ -246,6 +1340,37 @@ impl Engine {
         Ok(latest)
     }
 
+    /// Finds the newest release that isn't yanked, including pre-releases
+    pub async fn find_newest_crate_release(
+        &self,
+        name: CrateName,
+    ) -> Result<Option<CrateRelease>, Error> {
+        let query_response = self.query_crate.cached_query(name).await?;
+
+        let newest = query_response
+            .releases
+            .iter()
+            .filter(|release| !release.yanked)
+            .max_by(|r1, r2| r1.version.cmp(&r2.version))
+            .cloned();
+
+        Ok(newest)
+    }
+
+    /// Looks up all releases of a crate from an alternative registry
+    async fn fetch_registry_releases(
+        &self,
+        registry: Registry,
+        name: CrateName,
+    ) -> anyhow::Result<Vec<CrateRelease>> {
+        let query = self
+            .query_registry_crate
+            .get(&registry.name)
+            .ok_or_else(|| anyhow!("unknown registry {}", registry.name))?;
+
+        Ok(query.cached_query(name).await?.releases)
+    }
+
     fn fetch_releases<'a, I>(&'a self, names: I) -> BoxStream<'a, anyhow::Result<Vec<CrateRelease>>>
     where
         I: IntoIterator<Item = CrateName>,
@@ -256,25 +1381,395 @@ impl Engine {
         let s = stream::iter(names)
             .zip(stream::repeat(engine))
             .map(resolve_crate_with_engine)
-            .buffer_unordered(25);
+            .buffer_unordered(self.concurrency.fetches_per_analysis);
 
         Box::pin(s)
     }
 
+    /// Attaches deprecation notices and licenses to all analyzed dependencies. Failing to fetch
+    /// a crate's metadata isn't fatal, the crate is just treated as not deprecated and its
+    /// license as unknown.
+    async fn attach_crate_metadata(&self, deps: &mut AnalyzedDependencies) {
+        let names: BTreeSet<CrateName> = deps
+            .main
+            .iter()
+            .chain(&deps.dev)
+            .chain(&deps.build)
+            .filter(|(_, dep)| !dep.is_from_external_registry())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let metadata: HashMap<CrateName, CrateMetadata> = stream::iter(names)
+            .map(|name| async move {
+                let metadata = self.fetch_crate_metadata(&name).await;
+                (name, metadata)
+            })
+            .buffer_unordered(self.concurrency.fetches_per_analysis)
+            .collect()
+            .await;
+
+        for (name, dep) in deps
+            .main
+            .iter_mut()
+            .chain(deps.dev.iter_mut())
+            .chain(deps.build.iter_mut())
+        {
+            // crates from alternative registries aren't on crates.io
+            let metadata = match metadata.get(name) {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+
+            dep.deprecation = deprecations::curated_deprecation(name).or_else(|| {
+                metadata
+                    .description
+                    .as_deref()
+                    .and_then(deprecations::description_deprecation)
+            });
+            dep.repository = metadata.repository.clone();
+            dep.description = metadata.description.clone();
+            dep.license = dep
+                .latest_that_matches
+                .as_ref()
+                .and_then(|version| metadata.licenses.get(version))
+                .cloned();
+        }
+    }
+
+    /// Finds out how far the git dependencies pinned to a tag or commit are behind their
+    /// repository. Pins that can't be compared, e.g. on unsupported hosts, are left without a
+    /// lag.
+    async fn attach_git_pin_lags(&self, deps: &mut AnalyzedDependencies) {
+        let pins: Vec<(CrateName, RepoPath, String)> = deps
+            .git
+            .iter()
+            .filter_map(|(name, dep)| {
+                let repo_path = RepoPath::from_url(&dep.source.url)?;
+                let pin = dep.source.pinned_ref()?;
+                Some((name.clone(), repo_path, pin.to_owned()))
+            })
+            .collect();
+
+        let lags: Vec<(CrateName, Option<GitPinLag>)> = stream::iter(pins)
+            .map(|(name, repo_path, pin)| async move {
+                let key = (repo_path, pin);
+                if self.is_cached_only() || self.failed_git_pins.peek(&key).await.is_some() {
+                    let lag = self.compare_git_pin.cached(&key).await;
+                    return (name, lag);
+                }
+
+                match self.compare_git_pin.cached_query(key.clone()).await {
+                    Ok(lag) => (name, Some(lag)),
+                    Err(err) => {
+                        debug!(
+                            self.logger, "failed to compare git pin";
+                            "crate" => name.as_ref(),
+                            "error" => err.to_string()
+                        );
+                        self.failed_git_pins.insert(key, ()).await;
+                        (name, None)
+                    }
+                }
+            })
+            .buffer_unordered(self.concurrency.fetches_per_analysis)
+            .collect()
+            .await;
+
+        for (name, lag) in lags {
+            if let Some(dep) = deps.git.get_mut(&name) {
+                dep.lag = lag;
+            }
+        }
+    }
+
+    async fn fetch_crate_metadata(&self, name: &CrateName) -> CrateMetadata {
+        if let Some(metadata) = self.get_crate_metadata.cached(name).await {
+            return metadata;
+        }
+        if !self.crates_api_pacer.wait(CRATES_API_MAX_WAIT).await {
+            debug!(
+                self.logger, "too many crates.io API calls queued, leaving out crate metadata";
+                "crate" => name.as_ref()
+            );
+            return CrateMetadata::default();
+        }
+
+        match self.get_crate_metadata.cached_query(name.clone()).await {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                debug!(
+                    self.logger, "failed to fetch crate metadata";
+                    "crate" => name.as_ref(),
+                    "error" => err.to_string()
+                );
+                CrateMetadata::default()
+            }
+        }
+    }
+
+    /// Retrieves and parses the `Cargo.lock` next to the manifest in `path`
+    async fn retrieve_lockfile(
+        &self,
+        repo_path: &RepoPath,
+        git_ref: Option<&GitRef>,
+        path: &RelativePathBuf,
+    ) -> Result<Lockfile, Error> {
+        let lockfile_path = path.join(RelativePath::new("Cargo.lock"));
+
+        let mut service = self.retrieve_file_at_path.clone();
+        let raw = service
+            .ready()
+            .await?
+            .call((repo_path.clone(), git_ref.cloned(), lockfile_path))
+            .await
+            .context("failed to retrieve Cargo.lock")?;
+
+        raw.parse().context("failed to parse Cargo.lock")
+    }
+
+    /// Retrieves and parses the `.deps-rs.toml` at the root of a repository. Repositories
+    /// without one get the default configuration, but one that doesn't parse fails the
+    /// analysis so mistakes in it don't go unnoticed.
+    async fn retrieve_repo_config(
+        &self,
+        repo_path: &RepoPath,
+        git_ref: Option<&GitRef>,
+    ) -> Result<RepoConfig, Error> {
+        let mut service = self.retrieve_file_at_path.clone();
+        let raw = service
+            .ready()
+            .await?
+            .call((
+                repo_path.clone(),
+                git_ref.cloned(),
+                RelativePathBuf::from(".deps-rs.toml"),
+            ))
+            .await;
+
+        match raw {
+            Ok(raw) => parse_repo_config(&raw).context("failed to parse .deps-rs.toml"),
+            Err(_) => Ok(RepoConfig::default()),
+        }
+    }
+
     async fn retrieve_manifest_at_path(
         &self,
         repo_path: &RepoPath,
+        git_ref: Option<&GitRef>,
         path: &RelativePathBuf,
     ) -> Result<String, Error> {
         let manifest_path = path.join(RelativePath::new("Cargo.toml"));
 
         let mut service = self.retrieve_file_at_path.clone();
-        Ok(service.call((repo_path.clone(), manifest_path)).await?)
+        service
+            .ready()
+            .await?
+            .call((repo_path.clone(), git_ref.cloned(), manifest_path))
+            .await
+    }
+
+    /// Finds subdirectories of a repository with a `Cargo.toml`, to suggest when there is
+    /// none at the root
+    pub async fn find_manifest_dirs(
+        &self,
+        repo_path: RepoPath,
+    ) -> Result<Vec<RelativePathBuf>, Error> {
+        if self.is_cached_only() {
+            return Ok(self
+                .find_manifest_dirs
+                .cached(&repo_path)
+                .await
+                .unwrap_or_default());
+        }
+
+        Ok(self.find_manifest_dirs.cached_query(repo_path).await?)
     }
 
+    /// Returns the current advisory database, only fetching it if there is none yet
     async fn fetch_advisory_db(&self) -> Result<Arc<Database>, Error> {
-        Ok(self.fetch_advisory_db.cached_query(()).await?)
+        let current = self.advisory_db.read().unwrap().clone();
+
+        match current {
+            Some(advisory_db) => Ok(advisory_db),
+            None => self.refresh_advisory_db().await,
+        }
+    }
+
+    /// Fetches a fresh advisory database and swaps it in.
+    async fn refresh_advisory_db(&self) -> Result<Arc<Database>, Error> {
+        let mut service = self.fetch_advisory_db.clone();
+        let advisory_db = service.ready().await?.call(()).await?;
+
+        *self.advisory_db.write().unwrap() = Some(advisory_db.clone());
+        Ok(advisory_db)
+    }
+
+    /// Keeps the advisory database up to date in the background.
+    pub async fn refresh_advisory_db_at_interval(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+            if self.is_cached_only() {
+                continue;
+            }
+
+            if let Err(err) = self.refresh_advisory_db().await {
+                error!(
+                    self.logger,
+                    "failed refreshing the advisory database, the operation will be retried: {}",
+                    err
+                );
+            }
+        }
+    }
+
+    /// Loads the webhook subscriptions saved at `path`, where they are saved to from now on
+    pub async fn load_webhooks(&self, path: PathBuf) -> anyhow::Result<()> {
+        self.webhooks.load(path).await
+    }
+
+    /// Registers a webhook notified about new issues in a repository. Returns `false` if it
+    /// already was.
+    pub async fn subscribe_webhook(&self, repo_path: &RepoPath, url: &str) -> anyhow::Result<bool> {
+        self.webhooks.subscribe(repo_path, url).await
+    }
+
+    /// Checks `token` against the hash of the webhook token in the `.deps-rs.toml` of a
+    /// repository's default branch. Only those who can commit to a repository know the token.
+    pub async fn verify_webhook_token(
+        &self,
+        repo_path: &RepoPath,
+        token: &str,
+    ) -> anyhow::Result<bool> {
+        let config = self.retrieve_repo_config(repo_path, None).await?;
+        let hash = hex::encode(Sha256::digest(token.as_bytes()));
+        Ok(config.webhook_token_sha256 == Some(hash))
+    }
+
+    /// Removes a webhook of a repository. Returns `false` if it wasn't registered.
+    pub async fn unsubscribe_webhook(
+        &self,
+        repo_path: &RepoPath,
+        url: &str,
+    ) -> anyhow::Result<bool> {
+        self.webhooks.unsubscribe(repo_path, url).await
+    }
+
+    /// Loads the watchlists saved at `path`, where they are saved to from now on
+    pub async fn load_watchlists(&self, path: PathBuf) -> anyhow::Result<()> {
+        self.watchlists.load(path).await
+    }
+
+    /// Returns the repositories and crates a logged-in user follows
+    pub async fn watchlist(&self, user: &str) -> Vec<Watched> {
+        self.watchlists.get(user).await
+    }
+
+    /// Adds a repository or crate to a user's watchlist. Returns `false` if it already was on
+    /// it.
+    pub async fn watch(&self, user: &str, watched: &Watched) -> anyhow::Result<bool> {
+        self.watchlists.watch(user, watched).await
+    }
+
+    /// Removes a repository or crate from a user's watchlist. Returns `false` if it wasn't on
+    /// it.
+    pub async fn unwatch(&self, user: &str, watched: &Watched) -> anyhow::Result<bool> {
+        self.watchlists.unwatch(user, watched).await
     }
+
+    /// Loads the history snapshots saved at `path`, where they are saved to from now on
+    pub async fn load_history(&self, path: PathBuf) -> anyhow::Result<()> {
+        self.history.load(path).await
+    }
+
+    /// Saves the analyses frozen from now on in `dir`, where the earlier ones are served from
+    pub async fn open_frozen_analyses(&self, dir: PathBuf) -> anyhow::Result<()> {
+        self.frozen.open(dir).await
+    }
+
+    /// Re-analyzes the repositories with a history that have no snapshot from the current day
+    /// at every interval, so their history doesn't have gaps, and saves the new snapshots.
+    pub async fn record_history_at_interval(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+            if self.is_cached_only() {
+                continue;
+            }
+
+            for repo_path in self.history.repos_due(SystemTime::now()).await {
+                let result = self
+                    .background()
+                    .analyze_repo_dependencies(repo_path.clone(), RepoAnalysisOptions::default())
+                    .await;
+
+                // a cached analysis isn't recorded again by analyze_repo_dependencies
+                match result {
+                    Ok(outcome) => {
+                        self.record_snapshot(&repo_path, &outcome, SystemTime::now())
+                            .await;
+                    }
+                    Err(err) => error!(
+                        self.logger,
+                        "failed recording the history of {}/{}/{}: {}",
+                        repo_path.site.as_ref(),
+                        repo_path.qual.as_ref(),
+                        repo_path.name.as_ref(),
+                        err
+                    ),
+                }
+            }
+
+            if let Err(err) = self.history.save().await {
+                error!(self.logger, "failed saving the history: {}", err);
+            }
+            let frozen_ids = self.history.frozen_ids().await;
+            if let Err(err) = self.frozen.prune(&frozen_ids).await {
+                error!(self.logger, "failed pruning the frozen analyses: {:#}", err);
+            }
+        }
+    }
+
+    /// Re-analyzes the repositories with webhooks at every interval and notifies the webhooks
+    /// about new outdated dependencies and advisories.
+    pub async fn notify_webhooks_at_interval(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+            if self.is_cached_only() {
+                continue;
+            }
+
+            for repo_path in self.webhooks.repos().await {
+                let result = self
+                    .background()
+                    .analyze_repo_dependencies(repo_path.clone(), RepoAnalysisOptions::default())
+                    .await;
+
+                let result = match result {
+                    Ok(outcome) => self.webhooks.notify(&repo_path, &outcome).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = result {
+                    error!(
+                        self.logger,
+                        "failed notifying the webhooks of {}/{}/{}: {}",
+                        repo_path.site.as_ref(),
+                        repo_path.qual.as_ref(),
+                        repo_path.name.as_ref(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn org_key(site: &RepoSite, qual: &RepoQualifier) -> OrgKey {
+    (site.clone(), qual.as_ref().to_ascii_lowercase())
 }
 
 async fn resolve_crate_with_engine(
@@ -284,6 +1779,50 @@ async fn resolve_crate_with_engine(
     Ok(crate_res.releases)
 }
 
+/// Error of work that took longer than it may
+#[derive(Debug)]
+struct Overdue {
+    what: String,
+    timeout: Duration,
+}
+
+impl fmt::Display for Overdue {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{} took longer than {} seconds",
+            self.what,
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for Overdue {}
+
+/// Fails if `work` takes longer than `timeout`. Dropping it cancels the lookups it was still
+/// waiting for.
+async fn within<T>(
+    timeout: Duration,
+    what: &str,
+    work: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    tokio::time::timeout(timeout, work).await.map_err(|_| {
+        Error::new(Overdue {
+            what: what.to_owned(),
+            timeout,
+        })
+    })?
+}
+
+/// Whether an analysis failed because something took too long or a host is in trouble, rather
+/// than because of the repository or crate itself
+fn is_temporary(err: &Error) -> bool {
+    middleware::is_transient(err)
+        || err.chain().any(|cause| {
+            cause.is::<Overdue>() || cause.is::<Elapsed>() || cause.is::<CircuitOpen>()
+        })
+}
+
 static POPULAR_REPO_BLOCK_LIST: Lazy<HashSet<RepoPath>> = Lazy::new(|| {
     vec![
         RepoPath::from_parts("github", "rust-lang", "rust"),
@@ -298,3 +1837,148 @@ static POPULAR_REPO_BLOCK_LIST: Lazy<HashSet<RepoPath>> = Lazy::new(|| {
     .collect::<Result<HashSet<_>, _>>()
     .unwrap()
 });
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::models::crates::{AnalyzedDependency, CrateDep, CrateDeps};
+
+    use super::*;
+
+    fn analyzed(name: &str, req: &str, resolved: &str) -> AnalyzedDependencies {
+        let mut deps = CrateDeps::default();
+        deps.main.insert(
+            name.parse().unwrap(),
+            CrateDep::External(req.parse().unwrap()),
+        );
+
+        let mut analyzed = AnalyzedDependencies::new(&deps);
+        let dep: &mut AnalyzedDependency = analyzed.main.get_mut(name).unwrap();
+        dep.latest_that_matches = Some(resolved.parse().unwrap());
+        analyzed
+    }
+
+    fn outcome(crates: Vec<(&str, AnalyzedDependencies)>) -> AnalyzeDependenciesOutcome {
+        AnalyzeDependenciesOutcome {
+            crates: crates
+                .into_iter()
+                .map(|(name, deps)| (name.parse().unwrap(), deps))
+                .collect(),
+            version_mismatches: Vec::new(),
+            manifest_paths: HashMap::new(),
+            rust_versions: HashMap::new(),
+            package_metadata: HashMap::new(),
+            crates_io: None,
+            internal_deps: Vec::new(),
+            duplicates: Vec::new(),
+            transitive: None,
+            duration: Duration::from_secs(0),
+            analyzed_at: SystemTime::UNIX_EPOCH,
+            stale: false,
+            snapshot: None,
+            frozen: false,
+            skipped_manifests: 0,
+            manifest_errors: Vec::new(),
+            dependency_cycles: Vec::new(),
+            badge_style: None,
+        }
+    }
+
+    #[test]
+    fn detects_incompatible_requirements() {
+        let outcome = outcome(vec![
+            ("a", analyzed("hyper", "0.13", "0.13.10")),
+            ("b", analyzed("hyper", "0.14", "0.14.8")),
+        ]);
+
+        let conflicts = outcome.requirement_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].name.as_ref(), "hyper");
+        assert_eq!(conflicts[0].requirements.len(), 2);
+    }
+
+    #[test]
+    fn ignores_compatible_requirements() {
+        let outcome = outcome(vec![
+            ("a", analyzed("serde", "1.0.100", "1.0.126")),
+            ("b", analyzed("serde", "1", "1.0.126")),
+        ]);
+
+        assert!(outcome.requirement_conflicts().is_empty());
+    }
+
+    #[test]
+    fn ignores_dependencies() {
+        let mut a = analyzed("hyper", "0.13", "0.13.10");
+        a.main.get_mut("hyper").unwrap().latest = Some("0.14.8".parse().unwrap());
+        let b = analyzed("serde", "1", "1.0.126");
+
+        let mut outcome = outcome(vec![("a", a), ("b", b)]);
+        assert!(outcome.any_outdated());
+
+        outcome.ignore_dependencies(&["hyper".parse().unwrap()]);
+        assert!(!outcome.any_outdated());
+        assert_eq!(outcome.outdated_ratio(DepScope::STATUS), (0, 1));
+    }
+
+    #[test]
+    fn counts_licenses_once_per_crate() {
+        let mut a = analyzed("serde", "1", "1.0.126");
+        a.main.get_mut("serde").unwrap().license = Some("MIT OR Apache-2.0".into());
+        let mut b = analyzed("serde", "1", "1.0.126");
+        b.main.get_mut("serde").unwrap().license = Some("MIT OR Apache-2.0".into());
+        let c = analyzed("hyper", "0.14", "0.14.8");
+
+        let outcome = outcome(vec![("a", a), ("b", b), ("c", c)]);
+
+        assert_eq!(
+            outcome.license_counts(),
+            vec![(None, 1), (Some("MIT OR Apache-2.0"), 1)]
+        );
+    }
+
+    #[test]
+    fn feed_records_new_issues_only() {
+        let mut deps = analyzed("serde", "1", "1.0.126");
+        deps.main.get_mut("serde").unwrap().latest = Some("2.0.0".parse().unwrap());
+        let first = outcome(vec![("a", deps)]);
+
+        let mut deps = analyzed("serde", "1", "1.0.126");
+        deps.main.extend(analyzed("hyper", "0.13", "0.13.10").main);
+        deps.main.get_mut("serde").unwrap().latest = Some("2.0.0".parse().unwrap());
+        deps.main.get_mut("hyper").unwrap().latest = Some("0.14.8".parse().unwrap());
+        let second = outcome(vec![("a", deps)]);
+
+        let mut feed = RepoFeed::default();
+        feed.update(&first, SystemTime::UNIX_EPOCH);
+        feed.update(&first, SystemTime::UNIX_EPOCH);
+        assert_eq!(feed.entries.len(), 1);
+
+        feed.update(&second, SystemTime::now());
+        assert_eq!(feed.entries.len(), 2);
+        assert_eq!(
+            feed.entries[0].event,
+            FeedEvent::Outdated {
+                name: "hyper".parse().unwrap(),
+                latest: "0.14.8".parse().unwrap(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn temporary_failures_are_told_apart() {
+        let overdue = within(
+            Duration::from_millis(1),
+            "crawling",
+            futures::future::pending::<Result<(), Error>>(),
+        )
+        .await
+        .unwrap_err()
+        .context("failed analyzing the repository");
+        assert!(is_temporary(&overdue), "{:#}", overdue);
+
+        let missing = anyhow!("Cargo.toml not found");
+        assert!(!is_temporary(&missing));
+    }
+}
@@ -0,0 +1,56 @@
+//! The subjects analyzed most recently, listed on the front page.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::models::SubjectPath;
+
+/// Subjects kept, as many as the front page lists
+const MAX_RECENT: usize = 10;
+
+/// The subjects whose analysis with the default options succeeded last, newest first
+#[derive(Clone, Debug, Default)]
+pub struct RecentAnalyses {
+    subjects: Arc<Mutex<VecDeque<SubjectPath>>>,
+}
+
+impl RecentAnalyses {
+    /// Moves a subject to the front, dropping the oldest one if there are too many
+    pub fn record(&self, subject_path: SubjectPath) {
+        let mut subjects = self.subjects.lock().unwrap();
+        subjects.retain(|recent| *recent != subject_path);
+        subjects.push_front(subject_path);
+        subjects.truncate(MAX_RECENT);
+    }
+
+    pub fn subjects(&self) -> Vec<SubjectPath> {
+        self.subjects.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::repo::RepoPath;
+
+    #[test]
+    fn keeps_newest_subjects_once() {
+        let recent = RecentAnalyses::default();
+        let repo = |name: &str| {
+            SubjectPath::Repo(RepoPath::from_parts("github", "deps-rs", name).unwrap())
+        };
+
+        for i in 0..MAX_RECENT + 2 {
+            recent.record(repo(&format!("repo-{}", i)));
+        }
+        recent.record(repo("repo-5"));
+
+        let subjects = recent.subjects();
+        assert_eq!(subjects.len(), MAX_RECENT);
+        assert_eq!(subjects[0], repo("repo-5"));
+        assert_eq!(subjects[1], repo(&format!("repo-{}", MAX_RECENT + 1)));
+        assert_eq!(subjects.iter().filter(|s| **s == repo("repo-5")).count(), 1);
+        assert!(!subjects.contains(&repo("repo-1")));
+    }
+}
@@ -0,0 +1,243 @@
+//! Repositories and crates that logged-in users follow, persisted to a JSON file.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Error};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::models::crates::CrateName;
+use crate::models::repo::{RepoPath, RepoSite};
+use crate::utils::json_file::JsonFileStore;
+
+/// Subjects a single user can follow
+const MAX_WATCHED: usize = 100;
+
+/// A repository or crate on a watchlist. Crates are followed by name, so their latest release
+/// is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Watched {
+    Repo(RepoPath),
+    Crate(CrateName),
+}
+
+/// Parses `github/deps-rs/deps.rs` as a repository and `serde` as a crate
+impl FromStr for Watched {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Watched, Error> {
+        let parts = input.trim().split('/').collect::<Vec<_>>();
+        match parts.as_slice() {
+            [site, qual, name] => Ok(Watched::Repo(RepoPath::from_parts(site, qual, name)?)),
+            [name] if !name.is_empty() => Ok(Watched::Crate(name.parse()?)),
+            _ => Err(anyhow!(
+                "expected a repository like github/owner/name or a crate name"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Watched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watched::Repo(repo_path) => write!(
+                f,
+                "{}/{}/{}",
+                repo_path.site.as_ref(),
+                repo_path.qual.as_ref(),
+                repo_path.name.as_ref()
+            ),
+            Watched::Crate(name) => f.write_str(name.as_ref()),
+        }
+    }
+}
+
+/// A watched subject as it is saved
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum SavedWatched {
+    Repo {
+        site: String,
+        qual: String,
+        name: String,
+    },
+    Crate {
+        name: String,
+    },
+}
+
+impl SavedWatched {
+    fn new(watched: &Watched) -> SavedWatched {
+        match watched {
+            Watched::Repo(repo_path) => SavedWatched::Repo {
+                site: repo_path.site.as_ref().to_owned(),
+                qual: repo_path.qual.as_ref().to_owned(),
+                name: repo_path.name.as_ref().to_owned(),
+            },
+            Watched::Crate(name) => SavedWatched::Crate {
+                name: name.as_ref().to_owned(),
+            },
+        }
+    }
+
+    fn parse(&self) -> Option<Watched> {
+        match self {
+            SavedWatched::Repo { site, qual, name } => RepoPath::from_parts(site, qual, name)
+                .ok()
+                .map(Watched::Repo),
+            SavedWatched::Crate { name } => name.parse().ok().map(Watched::Crate),
+        }
+    }
+}
+
+/// The watchlist of a user, identified by their ID with the provider like `github:583231`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SavedWatchlist {
+    user: String,
+    watched: Vec<SavedWatched>,
+}
+
+#[derive(Debug, Default)]
+struct WatchlistState {
+    /// File the watchlists are saved to, if any
+    file: Option<JsonFileStore>,
+    watchlists: Vec<SavedWatchlist>,
+}
+
+impl WatchlistState {
+    /// Adds a subject to the end of a user's watchlist. Returns `false` if it already was on it.
+    fn watch(&mut self, user: &str, watched: &Watched) -> Result<bool, Error> {
+        ensure!(
+            !matches!(watched, Watched::Repo(repo_path) if matches!(repo_path.site, RepoSite::Custom(_))),
+            "repositories on custom git hosts can't be watched"
+        );
+
+        let saved = SavedWatched::new(watched);
+        let index = match self.watchlists.iter().position(|list| list.user == user) {
+            Some(index) => index,
+            None => {
+                self.watchlists.push(SavedWatchlist {
+                    user: user.to_owned(),
+                    watched: Vec::new(),
+                });
+                self.watchlists.len() - 1
+            }
+        };
+
+        let list = &mut self.watchlists[index];
+        if list.watched.contains(&saved) {
+            return Ok(false);
+        }
+        ensure!(
+            list.watched.len() < MAX_WATCHED,
+            "watchlists are limited to {} entries",
+            MAX_WATCHED
+        );
+        list.watched.push(saved);
+        Ok(true)
+    }
+
+    /// Removes a subject from a user's watchlist. Returns `false` if it wasn't on it.
+    fn unwatch(&mut self, user: &str, watched: &Watched) -> bool {
+        let saved = SavedWatched::new(watched);
+        let list = match self.watchlists.iter_mut().find(|list| list.user == user) {
+            Some(list) => list,
+            None => return false,
+        };
+
+        let count = list.watched.len();
+        list.watched.retain(|entry| *entry != saved);
+        let removed = list.watched.len() != count;
+        self.watchlists.retain(|list| !list.watched.is_empty());
+        removed
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Watchlists {
+    state: Arc<Mutex<WatchlistState>>,
+}
+
+impl Watchlists {
+    /// Loads the watchlists saved at `path`, where they are saved to from now on
+    pub async fn load(&self, path: PathBuf) -> Result<(), Error> {
+        let file = JsonFileStore::new(path);
+        let watchlists = file.load().await?;
+
+        let mut state = self.state.lock().await;
+        state.file = Some(file);
+        state.watchlists = watchlists;
+        Ok(())
+    }
+
+    /// Returns what a user follows, in the order it was added
+    pub async fn get(&self, user: &str) -> Vec<Watched> {
+        let state = self.state.lock().await;
+        state
+            .watchlists
+            .iter()
+            .find(|list| list.user == user)
+            .map(|list| {
+                list.watched
+                    .iter()
+                    .filter_map(SavedWatched::parse)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Adds a subject to a user's watchlist. Returns `false` if it already was on it.
+    pub async fn watch(&self, user: &str, watched: &Watched) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+        let added = state.watch(user, watched)?;
+        if added {
+            save(&state).await?;
+        }
+        Ok(added)
+    }
+
+    /// Removes a subject from a user's watchlist. Returns `false` if it wasn't on it.
+    pub async fn unwatch(&self, user: &str, watched: &Watched) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+        let removed = state.unwatch(user, watched);
+        if removed {
+            save(&state).await?;
+        }
+        Ok(removed)
+    }
+}
+
+async fn save(state: &WatchlistState) -> Result<(), Error> {
+    match state.file {
+        Some(ref file) => file.save(&state.watchlists).await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watches_each_subject_once() {
+        let mut state = WatchlistState::default();
+        let repo: Watched = "github/deps-rs/deps.rs".parse().unwrap();
+        let krate: Watched = "serde".parse().unwrap();
+        assert_eq!(repo.to_string(), "github/deps-rs/deps.rs");
+        assert!("github/deps-rs".parse::<Watched>().is_err());
+
+        assert!(state.watch("github:583231", &repo).unwrap());
+        assert!(state.watch("github:583231", &krate).unwrap());
+        assert!(!state.watch("github:583231", &repo).unwrap());
+        assert_eq!(state.watchlists[0].watched.len(), 2);
+
+        assert!(state.unwatch("github:583231", &repo));
+        assert!(!state.unwatch("github:583231", &repo));
+        assert!(!state.unwatch("gitlab:583231", &krate));
+        assert!(state.unwatch("github:583231", &krate));
+        assert!(state.watchlists.is_empty());
+    }
+}
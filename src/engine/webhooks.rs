@@ -0,0 +1,331 @@
+//! Webhooks notified when a repository's analysis finds new issues, persisted to a JSON file.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use reqwest::Url;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::engine::feed::{events, FeedEvent};
+use crate::engine::AnalyzeDependenciesOutcome;
+use crate::models::repo::{RepoPath, RepoSite};
+use crate::utils::json_file::JsonFileStore;
+
+/// Webhooks a single repository can have
+const MAX_WEBHOOKS_PER_REPO: usize = 5;
+
+/// Webhooks of all repositories together
+const MAX_WEBHOOKS: usize = 10_000;
+
+/// Why a webhook can't be registered, as opposed to failing to save it
+#[derive(Debug)]
+pub struct InvalidWebhook(&'static str);
+
+impl fmt::Display for InvalidWebhook {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.0)
+    }
+}
+
+impl std::error::Error for InvalidWebhook {}
+
+/// Accepts https URLs with a host name that isn't local. Host names are only checked for
+/// public addresses when notifying, as they could resolve differently by then.
+fn check_url(url: &str) -> Result<(), InvalidWebhook> {
+    let url = Url::parse(url).map_err(|_| InvalidWebhook("invalid webhook URL"))?;
+    if url.scheme() != "https" {
+        return Err(InvalidWebhook("webhook URLs must use https"));
+    }
+
+    let domain = url.domain().unwrap_or_default().trim_end_matches('.');
+    let is_local = !domain.contains('.')
+        || [".localhost", ".local", ".internal"]
+            .iter()
+            .any(|suffix| domain.ends_with(suffix));
+    if is_local {
+        return Err(InvalidWebhook(
+            "webhook URLs must have a public host name, not an IP address",
+        ));
+    }
+    Ok(())
+}
+
+/// A webhook registered for a repository
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Subscription {
+    site: String,
+    qual: String,
+    name: String,
+    url: String,
+    /// Issues the webhook has been notified about, unset until the first analysis after
+    /// subscribing so that existing issues aren't reported as new
+    #[serde(default)]
+    known: Option<BTreeSet<String>>,
+}
+
+impl Subscription {
+    fn is_for(&self, repo_path: &RepoPath) -> bool {
+        self.site == repo_path.site.as_ref()
+            && self.qual == repo_path.qual.as_ref()
+            && self.name == repo_path.name.as_ref()
+    }
+}
+
+#[derive(Debug, Default)]
+struct WebhookState {
+    /// File the subscriptions are saved to, if any
+    file: Option<JsonFileStore>,
+    subscriptions: Vec<Subscription>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Event<'a> {
+    Outdated {
+        dependency: &'a str,
+        latest: &'a Version,
+    },
+    Advisory {
+        dependency: &'a str,
+        id: &'a str,
+        title: &'a str,
+    },
+}
+
+impl<'a> Event<'a> {
+    fn new(event: &'a FeedEvent) -> Event<'a> {
+        match event {
+            FeedEvent::Outdated { name, latest } => Event::Outdated {
+                dependency: name.as_ref(),
+                latest,
+            },
+            FeedEvent::Advisory { name, id, title } => Event::Advisory {
+                dependency: name.as_ref(),
+                id,
+                title,
+            },
+        }
+    }
+}
+
+/// The JSON body POSTed to a webhook
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    site: &'a str,
+    qual: &'a str,
+    name: &'a str,
+    events: Vec<Event<'a>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    state: Arc<Mutex<WebhookState>>,
+}
+
+impl Webhooks {
+    pub fn new(client: reqwest::Client) -> Webhooks {
+        Webhooks {
+            client,
+            state: Arc::new(Mutex::new(WebhookState::default())),
+        }
+    }
+
+    /// Loads the subscriptions saved at `path`, where they are saved to from now on
+    pub async fn load(&self, path: PathBuf) -> Result<(), Error> {
+        let file = JsonFileStore::new(path);
+        let subscriptions = file.load().await?;
+
+        let mut state = self.state.lock().await;
+        state.file = Some(file);
+        state.subscriptions = subscriptions;
+        Ok(())
+    }
+
+    /// Registers `url` to be notified about new issues in a repository. Returns `false` if it
+    /// already was. Rejected webhooks fail with an [`InvalidWebhook`] error.
+    pub async fn subscribe(&self, repo_path: &RepoPath, url: &str) -> Result<bool, Error> {
+        if matches!(repo_path.site, RepoSite::Custom(_)) {
+            return Err(InvalidWebhook(
+                "webhooks are not supported for repositories on custom git hosts",
+            )
+            .into());
+        }
+        check_url(url)?;
+
+        let mut state = self.state.lock().await;
+        let repo_subscriptions: Vec<&Subscription> = state
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.is_for(repo_path))
+            .collect();
+        if repo_subscriptions.iter().any(|sub| sub.url == url) {
+            return Ok(false);
+        }
+        if repo_subscriptions.len() >= MAX_WEBHOOKS_PER_REPO {
+            return Err(InvalidWebhook("the repository has too many webhooks already").into());
+        }
+        if state.subscriptions.len() >= MAX_WEBHOOKS {
+            return Err(InvalidWebhook("no more webhooks can be registered").into());
+        }
+
+        state.subscriptions.push(Subscription {
+            site: repo_path.site.as_ref().to_owned(),
+            qual: repo_path.qual.as_ref().to_owned(),
+            name: repo_path.name.as_ref().to_owned(),
+            url: url.to_owned(),
+            known: None,
+        });
+        save(&state).await?;
+        Ok(true)
+    }
+
+    /// Removes a webhook of a repository. Returns `false` if it wasn't registered.
+    pub async fn unsubscribe(&self, repo_path: &RepoPath, url: &str) -> Result<bool, Error> {
+        let mut state = self.state.lock().await;
+        let count = state.subscriptions.len();
+        state
+            .subscriptions
+            .retain(|sub| !(sub.is_for(repo_path) && sub.url == url));
+
+        if state.subscriptions.len() == count {
+            return Ok(false);
+        }
+        save(&state).await?;
+        Ok(true)
+    }
+
+    /// Returns the repositories with at least one webhook
+    pub async fn repos(&self) -> Vec<RepoPath> {
+        let state = self.state.lock().await;
+        let repos: BTreeSet<RepoPath> = state
+            .subscriptions
+            .iter()
+            .filter_map(|sub| RepoPath::from_parts(&sub.site, &sub.qual, &sub.name).ok())
+            .collect();
+        repos.into_iter().collect()
+    }
+
+    /// Notifies the webhooks of a repository about the issues in `outcome` they haven't been
+    /// notified about yet. Webhooks that couldn't be reached are notified again next time.
+    pub async fn notify(
+        &self,
+        repo_path: &RepoPath,
+        outcome: &AnalyzeDependenciesOutcome,
+    ) -> Result<(), Error> {
+        let current = events(outcome);
+        let current_keys: BTreeSet<String> = current.keys().cloned().collect();
+
+        let subscriptions: Vec<Subscription> = {
+            let state = self.state.lock().await;
+            state
+                .subscriptions
+                .iter()
+                .filter(|sub| sub.is_for(repo_path))
+                .cloned()
+                .collect()
+        };
+
+        let mut notified = Vec::new();
+        let mut errors = Vec::new();
+        for sub in subscriptions {
+            let new_events: Vec<Event<'_>> = match sub.known {
+                None => Vec::new(),
+                Some(ref known) => current
+                    .iter()
+                    .filter(|(key, _)| !known.contains(*key))
+                    .map(|(_, event)| Event::new(event))
+                    .collect(),
+            };
+
+            if !new_events.is_empty() {
+                let payload = Payload {
+                    site: &sub.site,
+                    qual: &sub.qual,
+                    name: &sub.name,
+                    events: new_events,
+                };
+                let result = self
+                    .client
+                    .post(&sub.url)
+                    .json(&payload)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status());
+                if let Err(err) = result {
+                    errors.push(format!("{}: {}", sub.url, err));
+                    continue;
+                }
+            }
+            notified.push(sub.url);
+        }
+
+        let mut state = self.state.lock().await;
+        for sub in state.subscriptions.iter_mut() {
+            if sub.is_for(repo_path) && notified.contains(&sub.url) {
+                sub.known = Some(current_keys.clone());
+            }
+        }
+        save(&state).await?;
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("could not notify webhooks: {}", errors.join(", ")))
+        }
+    }
+}
+
+async fn save(state: &WebhookState) -> Result<(), Error> {
+    match state.file {
+        Some(ref file) => file.save(&state.subscriptions).await,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_urls() {
+        assert!(check_url("https://example.com/hooks/deps").is_ok());
+        assert!(check_url("https://ci.example.com.:8443/").is_ok());
+
+        assert!(check_url("http://example.com/").is_err());
+        assert!(check_url("https://127.0.0.1/").is_err());
+        assert!(check_url("https://[::1]/").is_err());
+        assert!(check_url("https://0x7f.1/").is_err());
+        assert!(check_url("https://localhost/").is_err());
+        assert!(check_url("https://api.localhost/").is_err());
+        assert!(check_url("https://metadata.google.internal/").is_err());
+        assert!(check_url("https://intranet/").is_err());
+    }
+
+    #[tokio::test]
+    async fn webhooks_per_repo_are_capped() {
+        let webhooks = Webhooks::new(reqwest::Client::new());
+        let repo_path = RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap();
+
+        for i in 0..MAX_WEBHOOKS_PER_REPO {
+            let url = format!("https://example.com/{}", i);
+            assert!(webhooks.subscribe(&repo_path, &url).await.unwrap());
+        }
+
+        let err = webhooks
+            .subscribe(&repo_path, "https://example.com/more")
+            .await
+            .unwrap_err();
+        assert!(err.is::<InvalidWebhook>());
+        // registering an existing one again is fine
+        assert!(!webhooks
+            .subscribe(&repo_path, "https://example.com/0")
+            .await
+            .unwrap());
+    }
+}
@@ -1,15 +1,19 @@
-use std::{fmt, str, task::Context, task::Poll};
+use std::{collections::HashMap, fmt, str, task::Context, task::Poll};
 
-use anyhow::{anyhow, Error};
-use crates_index::{Crate, DependencyKind, Index};
+use anyhow::Error;
+use crates_index::{Crate, DependencyKind};
 use futures::FutureExt as _;
 use hyper::service::Service;
+use indexmap::IndexMap;
+use reqwest::StatusCode;
 use semver::{Version, VersionReq};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::task::spawn_blocking;
 
 use crate::{
-    models::crates::{CrateDep, CrateDeps, CrateName, CratePath, CrateRelease},
+    models::crates::{CrateDep, CrateDeps, CrateName, CratePath, CrateRelease, DepUsage},
+    parsers::manifest::enabling_features,
+    utils::index::CrateIndex,
     BoxFuture,
 };
 
@@ -23,6 +27,16 @@ struct RegistryPackageDep {
     kind: Option<String>,
     #[serde(default)]
     package: Option<String>,
+    #[serde(default)]
+    optional: bool,
+    #[serde(default = "default_features")]
+    default_features: bool,
+    #[serde(default)]
+    target: Option<String>,
+}
+
+fn default_features() -> bool {
+    true
 }
 
 #[derive(Deserialize, Debug)]
@@ -32,6 +46,44 @@ struct RegistryPackage {
     deps: Vec<RegistryPackageDep>,
     #[serde(default)]
     yanked: bool,
+    #[serde(default)]
+    rust_version: Option<String>,
+    #[serde(default)]
+    features: IndexMap<String, Vec<String>>,
+    /// Features using newer syntax like `dep:`, kept apart for older versions of Cargo
+    #[serde(default)]
+    features2: IndexMap<String, Vec<String>>,
+}
+
+/// Returns the features that enable a dependency declared as `key`, if it is optional
+fn optional_features(
+    optional: bool,
+    key: &str,
+    features: &IndexMap<String, Vec<String>>,
+) -> Vec<String> {
+    if optional {
+        enabling_features(key, features)
+    } else {
+        Vec::new()
+    }
+}
+
+fn add_dep(
+    deps: &mut CrateDeps,
+    name: CrateName,
+    req: VersionReq,
+    kind: DependencyKind,
+    usage: DepUsage,
+) {
+    let (deps, usages) = match kind {
+        DependencyKind::Normal => (&mut deps.main, &mut deps.usage.main),
+        DependencyKind::Dev => (&mut deps.dev, &mut deps.usage.dev),
+        DependencyKind::Build => (&mut deps.build, &mut deps.usage.build),
+    };
+    if !usage.is_plain() {
+        usages.entry(name.clone()).or_insert(usage);
+    }
+    deps.insert(name, CrateDep::External(req));
 }
 
 fn convert_pkgs(krate: Crate) -> Result<QueryCrateResponse, Error> {
@@ -41,16 +93,28 @@ fn convert_pkgs(krate: Crate) -> Result<QueryCrateResponse, Error> {
         .versions()
         .iter()
         .map(|package| {
+            let mut features: IndexMap<String, Vec<String>> = package
+                .features()
+                .iter()
+                .map(|(name, items)| (name.clone(), items.clone()))
+                .collect();
+            features.sort_keys();
+
             let mut deps = CrateDeps::default();
             for dep in package.dependencies() {
-                let name = dep.crate_name().parse()?;
-                let req = VersionReq::parse(dep.requirement())?;
-
-                match dep.kind() {
-                    DependencyKind::Normal => deps.main.insert(name, CrateDep::External(req)),
-                    DependencyKind::Dev => deps.dev.insert(name, CrateDep::External(req)),
-                    _ => None,
+                let usage = DepUsage {
+                    optional: dep.is_optional(),
+                    default_features: dep.has_default_features(),
+                    target: dep.target().map(str::to_owned),
+                    features: optional_features(dep.is_optional(), dep.name(), &features),
                 };
+                add_dep(
+                    &mut deps,
+                    dep.crate_name().parse()?,
+                    VersionReq::parse(dep.requirement())?,
+                    dep.kind(),
+                    usage,
+                );
             }
             let version = Version::parse(package.version())?;
             Ok(CrateRelease {
@@ -58,6 +122,8 @@ fn convert_pkgs(krate: Crate) -> Result<QueryCrateResponse, Error> {
                 version,
                 deps,
                 yanked: package.is_yanked(),
+                // not exposed by the git index reader
+                rust_version: None,
             })
         })
         .collect::<Result<_, Error>>()?;
@@ -65,28 +131,96 @@ fn convert_pkgs(krate: Crate) -> Result<QueryCrateResponse, Error> {
     Ok(QueryCrateResponse { releases })
 }
 
-#[derive(Debug, Clone)]
+/// Converts an index file fetched from a sparse index, with one JSON line per release
+fn convert_registry_pkgs(name: &CrateName, index_file: &str) -> Result<QueryCrateResponse, Error> {
+    let releases = index_file
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut package: RegistryPackage = serde_json::from_str(line)?;
+            let features2 = std::mem::take(&mut package.features2);
+            package.features.extend(features2);
+
+            let mut deps = CrateDeps::default();
+            for dep in package.deps {
+                let kind = match dep.kind.as_deref() {
+                    Some("dev") => DependencyKind::Dev,
+                    Some("build") => DependencyKind::Build,
+                    _ => DependencyKind::Normal,
+                };
+                let usage = DepUsage {
+                    optional: dep.optional,
+                    default_features: dep.default_features,
+                    target: dep.target,
+                    features: optional_features(dep.optional, &dep.name, &package.features),
+                };
+                let dep_name = dep.package.unwrap_or(dep.name).parse()?;
+                add_dep(&mut deps, dep_name, dep.req, kind, usage);
+            }
+
+            Ok(CrateRelease {
+                name: name.clone(),
+                version: package.vers,
+                deps,
+                yanked: package.yanked,
+                rust_version: package
+                    .rust_version
+                    .and_then(|rust_version| rust_version.parse().ok()),
+            })
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(QueryCrateResponse { releases })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryCrateResponse {
     pub releases: Vec<CrateRelease>,
 }
 
 #[derive(Clone)]
 pub struct QueryCrate {
-    index: Index,
+    client: reqwest::Client,
+    index: CrateIndex,
 }
 
 impl QueryCrate {
-    pub fn new(index: Index) -> Self {
-        Self { index }
+    pub fn new(client: reqwest::Client, index: CrateIndex) -> Self {
+        Self { client, index }
     }
 
-    pub async fn query(index: Index, crate_name: CrateName) -> anyhow::Result<QueryCrateResponse> {
-        let crate_name2 = crate_name.clone();
-        let krate = spawn_blocking(move || index.crate_(crate_name2.as_ref()))
-            .await?
-            .ok_or_else(|| anyhow!("crate '{}' not found", crate_name.as_ref()))?;
+    /// Looks up all releases of a crate; crates that aren't in the index have none.
+    pub async fn query(
+        client: reqwest::Client,
+        index: CrateIndex,
+        crate_name: CrateName,
+    ) -> anyhow::Result<QueryCrateResponse> {
+        match index {
+            CrateIndex::Git(index) => {
+                let krate = spawn_blocking(move || index.crate_(crate_name.as_ref())).await?;
+
+                match krate {
+                    Some(krate) => convert_pkgs(krate),
+                    None => Ok(QueryCrateResponse {
+                        releases: Vec::new(),
+                    }),
+                }
+            }
+            CrateIndex::Sparse(index) => {
+                let url = index.crate_url(crate_name.as_ref());
+                let res = client.get(&url).send().await?;
+
+                // sparse indexes may answer with either for crates that don't exist
+                if matches!(res.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+                    return Ok(QueryCrateResponse {
+                        releases: Vec::new(),
+                    });
+                }
 
-        convert_pkgs(krate)
+                let index_file = res.error_for_status()?.text().await?;
+                convert_registry_pkgs(&crate_name, &index_file)
+            }
+        }
     }
 }
 
@@ -106,8 +240,9 @@ impl Service<CrateName> for QueryCrate {
     }
 
     fn call(&mut self, crate_name: CrateName) -> Self::Future {
+        let client = self.client.clone();
         let index = self.index.clone();
-        Self::query(index, crate_name).boxed()
+        Self::query(client, index, crate_name).boxed()
     }
 }
 
@@ -136,7 +271,7 @@ fn convert_summary(response: SummaryResponse) -> Result<Vec<CratePath>, Error> {
         .collect()
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct GetPopularCrates {
     client: reqwest::Client,
 }
@@ -174,3 +309,197 @@ impl Service<()> for GetPopularCrates {
         Self::query(client).boxed()
     }
 }
+
+#[derive(Deserialize)]
+struct CrateResponseDetail {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    repository: Option<String>,
+    #[serde(default)]
+    downloads: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CrateResponseVersion {
+    num: Version,
+    #[serde(default)]
+    license: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CrateResponse {
+    #[serde(rename = "crate")]
+    krate: CrateResponseDetail,
+    #[serde(default)]
+    versions: Vec<CrateResponseVersion>,
+}
+
+/// Metadata of a crate which is only available from the crates.io API, not the index
+#[derive(Clone, Debug, Default)]
+pub struct CrateMetadata {
+    pub description: Option<String>,
+    /// URL of the crate's source repository, if it links one
+    pub repository: Option<String>,
+    /// SPDX license expression of each release that declares one
+    pub licenses: HashMap<Version, String>,
+    /// Downloads of all releases
+    pub downloads: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct GetCrateMetadata {
+    client: reqwest::Client,
+}
+
+impl GetCrateMetadata {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    /// Fetches the metadata of a crate; crates unknown to crates.io yield empty metadata.
+    pub async fn query(
+        client: reqwest::Client,
+        crate_name: CrateName,
+    ) -> anyhow::Result<CrateMetadata> {
+        let url = format!("{}/crates/{}", CRATES_API_BASE_URI, crate_name.as_ref());
+        let res = client.get(&url).send().await?;
+
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(CrateMetadata::default());
+        }
+
+        let response: CrateResponse = res.error_for_status()?.json().await?;
+        let licenses = response
+            .versions
+            .into_iter()
+            .filter_map(|version| Some((version.num, version.license?)))
+            .collect();
+
+        Ok(CrateMetadata {
+            description: response.krate.description,
+            repository: response
+                .krate
+                .repository
+                .filter(|url| url.starts_with("https://") || url.starts_with("http://")),
+            licenses,
+            downloads: response.krate.downloads,
+        })
+    }
+}
+
+impl fmt::Debug for GetCrateMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GetCrateMetadata")
+    }
+}
+
+impl Service<CrateName> for GetCrateMetadata {
+    type Response = CrateMetadata;
+    type Error = Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, crate_name: CrateName) -> Self::Future {
+        let client = self.client.clone();
+        Self::query(client, crate_name).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use crate::models::crates::CrateManifest;
+    use crate::parsers::manifest::parse_manifest_toml;
+
+    use super::*;
+
+    const INDEX_ENTRY: &str = r#"{"name":"demo","vers":"0.1.0","deps":[{"name":"serde","req":"^1.0","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"},{"name":"quickcheck","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"dev"},{"name":"cc","req":"^1.0.50","features":[],"optional":false,"default_features":true,"target":null,"kind":"build"}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{},"yanked":false}"#;
+
+    const MANIFEST: &str = r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0"
+
+[dev-dependencies]
+quickcheck = "1"
+
+[build-dependencies]
+cc = "1.0.50"
+"#;
+
+    fn names(deps: &IndexMap<CrateName, CrateDep>) -> Vec<&str> {
+        deps.keys().map(|name| name.as_ref()).collect()
+    }
+
+    #[test]
+    fn registry_release_matches_manifest() {
+        let krate = Crate::from_slice(INDEX_ENTRY.as_bytes()).unwrap();
+        let response = convert_pkgs(krate).unwrap();
+        let release_deps = &response.releases[0].deps;
+
+        let manifest_deps = match parse_manifest_toml(MANIFEST).unwrap() {
+            CrateManifest::Package(_, deps) => deps,
+            _ => panic!("expected package manifest"),
+        };
+
+        assert_eq!(names(&release_deps.main), names(&manifest_deps.main));
+        assert_eq!(names(&release_deps.dev), names(&manifest_deps.dev));
+        assert_eq!(names(&release_deps.build), names(&manifest_deps.build));
+        assert_eq!(names(&release_deps.build), vec!["cc"]);
+    }
+
+    #[test]
+    fn registry_release_dependency_usage() {
+        let entry = r#"{"name":"demo","vers":"0.1.0","deps":[{"name":"serde","req":"^1.0","features":[],"optional":true,"default_features":false,"target":null,"kind":"normal"},{"name":"libc","req":"^0.2","features":[],"optional":false,"default_features":true,"target":"cfg(unix)","kind":"normal"},{"name":"log","req":"^0.4","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{},"yanked":false}"#;
+
+        let krate = Crate::from_slice(entry.as_bytes()).unwrap();
+        let response = convert_pkgs(krate).unwrap();
+        let deps = &response.releases[0].deps;
+
+        assert_eq!(
+            deps.usage.main.get("serde"),
+            Some(&DepUsage {
+                optional: true,
+                default_features: false,
+                target: None,
+                features: vec!["serde".to_owned()],
+            })
+        );
+        assert_eq!(
+            deps.usage
+                .main
+                .get("libc")
+                .and_then(|usage| usage.target.as_deref()),
+            Some("cfg(unix)")
+        );
+        assert_eq!(deps.usage.main.get("log"), None);
+    }
+
+    #[test]
+    fn sparse_index_file_matches_git_index() {
+        let index_file = format!(
+            "{}\n{}\n",
+            INDEX_ENTRY,
+            INDEX_ENTRY.replace("0.1.0", "0.2.0")
+        );
+        let name: CrateName = "demo".parse().unwrap();
+        let sparse = convert_registry_pkgs(&name, &index_file).unwrap();
+
+        let krate = Crate::from_slice(INDEX_ENTRY.as_bytes()).unwrap();
+        let git = convert_pkgs(krate).unwrap();
+
+        assert_eq!(sparse.releases.len(), 2);
+        assert_eq!(sparse.releases[1].version, "0.2.0".parse().unwrap());
+        let (sparse_deps, git_deps) = (&sparse.releases[0].deps, &git.releases[0].deps);
+        assert_eq!(names(&sparse_deps.main), names(&git_deps.main));
+        assert_eq!(names(&sparse_deps.dev), names(&git_deps.dev));
+        assert_eq!(names(&sparse_deps.build), names(&git_deps.build));
+    }
+}
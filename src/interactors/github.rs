@@ -1,79 +1,95 @@
 use std::{
     fmt,
+    sync::Arc,
     task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Error;
-
 use futures::FutureExt as _;
 use hyper::service::Service;
-use serde::Deserialize;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::header::ACCEPT;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    models::repo::{RepoPath, Repository},
+    models::repo::{RepoPath, RepoQualifier, Repository},
     BoxFuture,
 };
 
 const GITHUB_API_BASE_URI: &str = "https://api.github.com";
+const GITHUB_API_MEDIA_TYPE: &str = "application/vnd.github+json";
 
-#[derive(Deserialize)]
-struct GithubSearchResponse {
-    items: Vec<GithubRepo>,
-}
+/// Most repositories GitHub lists on one page
+const REPOS_PER_PAGE: usize = 100;
 
 #[derive(Deserialize)]
-struct GithubRepo {
+struct GithubOwnerRepo {
     name: String,
-    owner: GithubOwner,
-    description: String,
-}
-
-#[derive(Deserialize)]
-struct GithubOwner {
-    login: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    fork: bool,
+    #[serde(default)]
+    archived: bool,
 }
 
+/// Lists the Rust repositories of a GitHub organization or user, leaving out forks and
+/// archived ones. Only the most recently pushed page of repositories is looked at.
 #[derive(Clone)]
-pub struct GetPopularRepos {
+pub struct GetOrgRepos {
     client: reqwest::Client,
 }
 
-impl GetPopularRepos {
+impl GetOrgRepos {
     pub fn new(client: reqwest::Client) -> Self {
         Self { client }
     }
 
-    pub async fn query(client: reqwest::Client) -> anyhow::Result<Vec<Repository>> {
+    pub async fn query(
+        client: reqwest::Client,
+        qual: RepoQualifier,
+    ) -> anyhow::Result<Vec<Repository>> {
+        // also works for organizations, unlike the `/orgs` endpoint for users
         let url = format!(
-            "{}/search/repositories?q=language:rust&sort=stars",
-            GITHUB_API_BASE_URI
+            "{}/users/{}/repos?type=owner&sort=pushed&per_page={}",
+            GITHUB_API_BASE_URI,
+            qual.as_ref(),
+            REPOS_PER_PAGE
         );
 
-        let res = client.get(&url).send().await?.error_for_status()?;
-        let summary: GithubSearchResponse = res.json().await?;
+        let res = client
+            .get(&url)
+            .header(ACCEPT, GITHUB_API_MEDIA_TYPE)
+            .send()
+            .await?
+            .error_for_status()?;
+        let repos: Vec<GithubOwnerRepo> = res.json().await?;
 
-        summary
-            .items
+        repos
             .into_iter()
-            .map(|item| {
-                let path = RepoPath::from_parts("github", &item.owner.login, &item.name)?;
+            .filter(|repo| repo.language.as_deref() == Some("Rust") && !repo.fork && !repo.archived)
+            .map(|repo| {
+                let path = RepoPath::from_parts("github", qual.as_ref(), &repo.name)?;
 
                 Ok(Repository {
                     path,
-                    description: item.description,
+                    description: repo.description.unwrap_or_default(),
                 })
             })
             .collect::<Result<Vec<_>, Error>>()
     }
 }
 
-impl fmt::Debug for GetPopularRepos {
+impl fmt::Debug for GetOrgRepos {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str("GetPopularRepos")
+        f.write_str("GetOrgRepos")
     }
 }
 
-impl Service<()> for GetPopularRepos {
+impl Service<RepoQualifier> for GetOrgRepos {
     type Response = Vec<Repository>;
     type Error = Error;
     type Future = BoxFuture<Result<Self::Response, Self::Error>>;
@@ -82,8 +98,138 @@ impl Service<()> for GetPopularRepos {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, _req: ()) -> Self::Future {
+    fn call(&mut self, qual: RepoQualifier) -> Self::Future {
         let client = self.client.clone();
-        Self::query(client).boxed()
+        Self::query(client, qual).boxed()
+    }
+}
+
+/// Lifetime of the JWTs the app authenticates with, GitHub accepts at most ten minutes
+const APP_JWT_LIFETIME_SECS: u64 = 540;
+
+#[derive(Serialize)]
+struct AppClaims<'a> {
+    iat: u64,
+    exp: u64,
+    iss: &'a str,
+}
+
+#[derive(Deserialize)]
+struct InstallationToken {
+    token: String,
+}
+
+/// State of a commit status, as shown next to commits and pull requests
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CommitStatus {
+    pub state: CommitState,
+    pub target_url: String,
+    pub description: String,
+    pub context: &'static str,
+}
+
+/// Posts commit statuses as a GitHub App, authenticated as its installation in a repository
+#[derive(Clone)]
+pub struct GithubApp {
+    client: reqwest::Client,
+    app_id: String,
+    key: Arc<EncodingKey>,
+}
+
+impl GithubApp {
+    /// Creates the app from its ID and PEM-encoded private key
+    pub fn new(
+        client: reqwest::Client,
+        app_id: String,
+        private_key: &[u8],
+    ) -> anyhow::Result<Self> {
+        let key = EncodingKey::from_rsa_pem(private_key)?;
+
+        Ok(Self {
+            client,
+            app_id,
+            key: Arc::new(key),
+        })
+    }
+
+    fn jwt(&self) -> anyhow::Result<String> {
+        // backdated a minute to allow for clock drift
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() - 60;
+        let claims = AppClaims {
+            iat: now,
+            exp: now + APP_JWT_LIFETIME_SECS,
+            iss: &self.app_id,
+        };
+
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &self.key,
+        )?)
+    }
+
+    async fn installation_token(&self, installation_id: u64) -> anyhow::Result<String> {
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            GITHUB_API_BASE_URI, installation_id
+        );
+
+        let res = self
+            .client
+            .post(&url)
+            .bearer_auth(self.jwt()?)
+            .header(ACCEPT, GITHUB_API_MEDIA_TYPE)
+            .send()
+            .await?
+            .error_for_status()?;
+        let token: InstallationToken = res.json().await?;
+
+        Ok(token.token)
+    }
+
+    /// Sets the status of commit `sha` in a repository the app is installed in
+    pub async fn create_commit_status(
+        &self,
+        installation_id: u64,
+        repo_path: &RepoPath,
+        sha: &str,
+        status: &CommitStatus,
+    ) -> anyhow::Result<()> {
+        let token = self.installation_token(installation_id).await?;
+        let url = format!(
+            "{}/repos/{}/{}/statuses/{}",
+            GITHUB_API_BASE_URI,
+            repo_path.qual.as_ref(),
+            repo_path.name.as_ref(),
+            sha
+        );
+
+        self.client
+            .post(&url)
+            .bearer_auth(token)
+            .header(ACCEPT, GITHUB_API_MEDIA_TYPE)
+            .json(status)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for GithubApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GithubApp")
+            .field("app_id", &self.app_id)
+            .finish()
     }
 }
@@ -1,46 +1,174 @@
 use std::{
+    collections::HashMap,
     fmt,
+    sync::Arc,
     task::{Context, Poll},
+    time::Duration,
 };
 
-use anyhow::{anyhow, Error};
+use anyhow::Error;
 use futures::FutureExt as _;
 use hyper::service::Service;
+use lru_time_cache::LruCache;
 use relative_path::RelativePathBuf;
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_NONE_MATCH},
+    StatusCode,
+};
+use tokio::sync::Mutex;
 
-use crate::{models::repo::RepoPath, BoxFuture};
+use crate::{
+    models::repo::{GitRef, RepoPath, RepoSite},
+    utils::middleware::{backoff, is_transient},
+    BoxFuture,
+};
 
 pub mod crates;
 pub mod github;
+pub mod oauth;
+pub mod pins;
+pub mod popular_repos;
 pub mod rustsec;
+pub mod tree;
+
+/// Timeouts for requests to the individual upstream providers.
+#[derive(Clone, Debug)]
+pub struct Timeouts {
+    /// Timeout for retrieving files from a repository site without an entry in `sites`
+    pub default: Duration,
+    /// Per-site timeouts for retrieving files
+    pub sites: HashMap<RepoSite, Duration>,
+    /// Timeout for the popular repository searches of all forges together
+    pub popular_repos: Duration,
+    /// Timeout for the crates.io summary
+    pub popular_crates: Duration,
+    /// Overall timeout for retrieving all manifests of a repository
+    pub crawl: Duration,
+    /// Overall timeout for looking up the releases of all dependencies of an analysis
+    pub analysis: Duration,
+}
+
+impl Timeouts {
+    /// Returns the timeout for retrieving files from `site`
+    pub fn for_site(&self, site: &RepoSite) -> Duration {
+        self.sites.get(site).copied().unwrap_or(self.default)
+    }
+}
+
+/// Files larger than this aren't kept in the body cache
+const MAX_CACHED_BODY_LEN: usize = 512 * 1024;
+
+/// A previously retrieved file, along with the `ETag` to revalidate it with
+#[derive(Clone)]
+struct CachedBody {
+    etag: HeaderValue,
+    body: String,
+}
+
+/// Attempts at retrieving a file, as long as the earlier ones failed with transient errors
+const RETRIEVE_ATTEMPTS: u32 = 3;
+
+/// Wait before retrying to retrieve a file, doubled for each further retry
+const RETRIEVE_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Retrieved files keyed by URL, shared by all analyses
+type BodyCache = Arc<Mutex<LruCache<String, CachedBody>>>;
 
 #[derive(Clone)]
 pub struct RetrieveFileAtPath {
     client: reqwest::Client,
+    timeouts: Timeouts,
+    body_cache: BodyCache,
 }
 
 impl RetrieveFileAtPath {
-    pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+    pub fn new(client: reqwest::Client, timeouts: Timeouts) -> Self {
+        let body_cache =
+            LruCache::with_expiry_duration_and_capacity(Duration::from_secs(86400), 2000);
+
+        Self {
+            client,
+            timeouts,
+            body_cache: Arc::new(Mutex::new(body_cache)),
+        }
+    }
+
+    /// Upper bound for retrieving a file with all retries, given the timeouts of the single
+    /// attempts
+    pub fn total_timeout(timeouts: &Timeouts) -> Duration {
+        let attempt_timeout = timeouts
+            .sites
+            .values()
+            .copied()
+            .fold(timeouts.default, Duration::max);
+
+        attempt_timeout * RETRIEVE_ATTEMPTS + RETRIEVE_BACKOFF * (2u32.pow(RETRIEVE_ATTEMPTS - 1))
     }
 
-    pub async fn query(
+    /// Retrieves a file, retrying with backoff if the site seems to have a hiccup. Missing
+    /// files and other client errors aren't retried.
+    async fn query(
         client: reqwest::Client,
+        timeout: Duration,
+        body_cache: BodyCache,
         repo_path: RepoPath,
+        git_ref: Option<GitRef>,
         path: RelativePathBuf,
     ) -> anyhow::Result<String> {
-        let url = repo_path.to_usercontent_file_url(&path);
-        let res = client.get(&url).send().await?;
+        let url = repo_path.to_usercontent_file_url(&path, git_ref.as_ref());
+
+        let mut attempt = 0;
+        loop {
+            match Self::query_once(&client, timeout, &body_cache, &url).await {
+                Err(err) if attempt + 1 < RETRIEVE_ATTEMPTS && is_transient(&err) => {
+                    tokio::time::sleep(backoff(RETRIEVE_BACKOFF, attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Retrieves a file once, revalidating a cached copy with its `ETag` instead of
+    /// downloading it again if possible.
+    async fn query_once(
+        client: &reqwest::Client,
+        timeout: Duration,
+        body_cache: &BodyCache,
+        url: &str,
+    ) -> anyhow::Result<String> {
+        let cached = body_cache.lock().await.get(url).cloned();
+
+        let mut req = client.get(url).timeout(timeout);
+        if let Some(ref cached) = cached {
+            req = req.header(IF_NONE_MATCH, cached.etag.clone());
+        }
+        let res = req.send().await?;
+
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (res.status(), cached) {
+            return Ok(cached.body);
+        }
+        // keeps the status in the error, so the circuit breaker can tell outages apart
+        let res = res.error_for_status()?;
+
+        let etag = res.headers().get(ETAG).cloned();
+        let body = res.text().await?;
 
-        if !res.status().is_success() {
-            return Err(anyhow!("Status code {} for URI {}", res.status(), url));
+        if let Some(etag) = etag {
+            if body.len() <= MAX_CACHED_BODY_LEN {
+                let cached = CachedBody {
+                    etag,
+                    body: body.clone(),
+                };
+                body_cache.lock().await.insert(url.to_owned(), cached);
+            }
         }
 
-        Ok(res.text().await?)
+        Ok(body)
     }
 }
 
-impl Service<(RepoPath, RelativePathBuf)> for RetrieveFileAtPath {
+impl Service<(RepoPath, Option<GitRef>, RelativePathBuf)> for RetrieveFileAtPath {
     type Response = String;
     type Error = Error;
     type Future = BoxFuture<Result<Self::Response, Self::Error>>;
@@ -49,9 +177,14 @@ impl Service<(RepoPath, RelativePathBuf)> for RetrieveFileAtPath {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, (repo_path, path): (RepoPath, RelativePathBuf)) -> Self::Future {
+    fn call(
+        &mut self,
+        (repo_path, git_ref, path): (RepoPath, Option<GitRef>, RelativePathBuf),
+    ) -> Self::Future {
         let client = self.client.clone();
-        Self::query(client, repo_path, path).boxed()
+        let timeout = self.timeouts.for_site(&repo_path.site);
+        let body_cache = self.body_cache.clone();
+        Self::query(client, timeout, body_cache, repo_path, git_ref, path).boxed()
     }
 }
 
@@ -0,0 +1,214 @@
+//! Logging in with a GitHub or GitLab account through the OAuth web application flow.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, Error};
+use reqwest::{header::ACCEPT, StatusCode};
+use serde::Deserialize;
+
+/// A site users can log in with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OauthProvider {
+    Github,
+    Gitlab,
+}
+
+impl OauthProvider {
+    fn authorize_uri(self) -> &'static str {
+        match self {
+            OauthProvider::Github => "https://github.com/login/oauth/authorize",
+            OauthProvider::Gitlab => "https://gitlab.com/oauth/authorize",
+        }
+    }
+
+    fn token_uri(self) -> &'static str {
+        match self {
+            OauthProvider::Github => "https://github.com/login/oauth/access_token",
+            OauthProvider::Gitlab => "https://gitlab.com/oauth/token",
+        }
+    }
+
+    fn user_uri(self) -> &'static str {
+        match self {
+            OauthProvider::Github => "https://api.github.com/user",
+            OauthProvider::Gitlab => "https://gitlab.com/api/v4/user",
+        }
+    }
+
+    /// Where users are looked up by login
+    fn users_uri(self) -> &'static str {
+        match self {
+            OauthProvider::Github => "https://api.github.com/users",
+            OauthProvider::Gitlab => "https://gitlab.com/api/v4/users",
+        }
+    }
+
+    /// Narrowest scope that still allows reading the user name, GitHub's public profile needs
+    /// none
+    fn scope(self) -> &'static str {
+        match self {
+            OauthProvider::Github => "",
+            OauthProvider::Gitlab => "read_user",
+        }
+    }
+}
+
+impl FromStr for OauthProvider {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<OauthProvider, Error> {
+        match input {
+            "github" => Ok(OauthProvider::Github),
+            "gitlab" => Ok(OauthProvider::Gitlab),
+            _ => Err(anyhow!("unknown login provider")),
+        }
+    }
+}
+
+impl AsRef<str> for OauthProvider {
+    fn as_ref(&self) -> &str {
+        match self {
+            OauthProvider::Github => "github",
+            OauthProvider::Gitlab => "gitlab",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: Option<String>,
+    /// GitHub reports failed exchanges with a successful status
+    error_description: Option<String>,
+}
+
+/// A user of a provider. Only the ID stays the same when the user is renamed.
+#[derive(Debug, Deserialize)]
+pub struct OauthUser {
+    pub id: u64,
+    #[serde(alias = "username")]
+    pub login: String,
+}
+
+/// An OAuth application registered with a provider
+#[derive(Clone)]
+pub struct OauthApp {
+    client: reqwest::Client,
+    provider: OauthProvider,
+    client_id: String,
+    client_secret: String,
+}
+
+impl OauthApp {
+    pub fn new(
+        client: reqwest::Client,
+        provider: OauthProvider,
+        client_id: String,
+        client_secret: String,
+    ) -> Self {
+        Self {
+            client,
+            provider,
+            client_id,
+            client_secret,
+        }
+    }
+
+    pub fn provider(&self) -> OauthProvider {
+        self.provider
+    }
+
+    /// URL of the provider's page asking the user to authorize the app. The provider sends
+    /// them back to `redirect_uri` with a code and the given `state`.
+    pub fn authorize_url(&self, redirect_uri: &str, state: &str) -> String {
+        let query = serde_urlencoded::to_string([
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("response_type", "code"),
+            ("scope", self.provider.scope()),
+            ("state", state),
+        ])
+        .expect("query parameters are strings");
+
+        format!("{}?{}", self.provider.authorize_uri(), query)
+    }
+
+    /// Exchanges the code the provider redirected the user back with for an access token, and
+    /// returns the user it belongs to
+    pub async fn login(&self, code: &str, redirect_uri: &str) -> anyhow::Result<OauthUser> {
+        let res = self
+            .client
+            .post(self.provider.token_uri())
+            .header(ACCEPT, "application/json")
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("code", code),
+                ("grant_type", "authorization_code"),
+                ("redirect_uri", redirect_uri),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+        let token: AccessToken = res.json().await?;
+
+        let access_token = match (token.access_token, token.error_description) {
+            (Some(access_token), _) => access_token,
+            (None, description) => {
+                return Err(anyhow!(
+                    "could not get an access token: {}",
+                    description.as_deref().unwrap_or("no token returned")
+                ))
+            }
+        };
+
+        let res = self
+            .client
+            .get(self.provider.user_uri())
+            .bearer_auth(access_token)
+            .header(ACCEPT, "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        let user: OauthUser = res.json().await?;
+
+        Ok(user)
+    }
+
+    /// Looks up the ID of the user with a login, or `None` if there is no such user
+    pub async fn user_id(&self, login: &str) -> anyhow::Result<Option<u64>> {
+        let request = match self.provider {
+            OauthProvider::Github => {
+                self.client
+                    .get(format!("{}/{}", self.provider.users_uri(), login))
+            }
+            OauthProvider::Gitlab => self
+                .client
+                .get(self.provider.users_uri())
+                .query(&[("username", login)]),
+        };
+        let res = request.header(ACCEPT, "application/json").send().await?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let res = res.error_for_status()?;
+
+        // GitLab answers with the list of matching users
+        Ok(match self.provider {
+            OauthProvider::Github => Some(res.json::<OauthUser>().await?.id),
+            OauthProvider::Gitlab => res
+                .json::<Vec<OauthUser>>()
+                .await?
+                .first()
+                .map(|user| user.id),
+        })
+    }
+}
+
+impl fmt::Debug for OauthApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OauthApp")
+            .field("provider", &self.provider)
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fmt,
+    task::{Context, Poll},
+};
+
+use anyhow::{anyhow, Error};
+use futures::FutureExt as _;
+use hyper::service::Service;
+use reqwest::{header::ACCEPT, Url};
+use serde::Deserialize;
+
+use crate::{
+    models::crates::GitPinLag,
+    models::repo::{RepoPath, RepoSite},
+    BoxFuture,
+};
+
+const GITHUB_API_BASE_URI: &str = "https://api.github.com";
+const GITHUB_API_MEDIA_TYPE: &str = "application/vnd.github+json";
+const GITLAB_API_BASE_URI: &str = "https://gitlab.com/api/v4";
+
+#[derive(Deserialize)]
+struct Project {
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GithubCommit {
+    commit: GithubCommitDetails,
+}
+
+#[derive(Deserialize)]
+struct GithubCommitDetails {
+    committer: GithubCommitter,
+}
+
+#[derive(Deserialize)]
+struct GithubCommitter {
+    date: String,
+}
+
+#[derive(Deserialize)]
+struct GithubComparison {
+    /// Commits the compared head has that the base doesn't
+    ahead_by: usize,
+    base_commit: GithubCommit,
+}
+
+#[derive(Deserialize)]
+struct GitlabCommit {
+    committed_date: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabComparison {
+    /// Newest commit of the compared head
+    commit: Option<GitlabCommit>,
+    commits: Vec<serde_json::Value>,
+}
+
+/// Returns the day of a timestamp like `2021-03-14T15:09:26Z`, counted from the Unix epoch
+fn epoch_day(timestamp: &str) -> Option<i64> {
+    let date = timestamp.get(..10)?;
+    let mut parts = date.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+
+    // days from civil, with years starting in March so that leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+/// Appends `segments` to the path of `base`, percent-encoding each of them so that e.g. slashes
+/// in a branch name stay part of it
+fn api_url(base: &str, segments: &[&str]) -> Result<Url, Error> {
+    let mut url = Url::parse(base)?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("{} can't have a path", base))?
+        .extend(segments);
+    Ok(url)
+}
+
+fn lag(commits: usize, pinned_date: &str, newest_date: &str) -> Result<GitPinLag, Error> {
+    let pinned = epoch_day(pinned_date).ok_or_else(|| anyhow!("invalid commit date"))?;
+    let newest = epoch_day(newest_date).ok_or_else(|| anyhow!("invalid commit date"))?;
+
+    Ok(GitPinLag {
+        commits,
+        days: (newest - pinned).max(0) as u64,
+    })
+}
+
+/// Finds out how far the tag or commit a git dependency is pinned to is behind the default
+/// branch of its repository, through the hosting provider's API. Only GitHub and GitLab are
+/// supported. GitHub requests are authenticated with `github_token` if given, which raises
+/// their rate limit.
+#[derive(Clone)]
+pub struct CompareGitPin {
+    client: reqwest::Client,
+    github_token: Option<String>,
+}
+
+impl CompareGitPin {
+    pub fn new(client: reqwest::Client, github_token: Option<String>) -> Self {
+        Self {
+            client,
+            github_token,
+        }
+    }
+
+    pub async fn query(
+        client: reqwest::Client,
+        github_token: Option<String>,
+        repo_path: RepoPath,
+        pin: String,
+    ) -> anyhow::Result<GitPinLag> {
+        let (qual, name) = (repo_path.qual.as_ref(), repo_path.name.as_ref());
+
+        match repo_path.site {
+            RepoSite::Github => {
+                let get = |url: Url| {
+                    let mut req = client.get(url).header(ACCEPT, GITHUB_API_MEDIA_TYPE);
+                    if let Some(ref token) = github_token {
+                        req = req.bearer_auth(token);
+                    }
+                    req.send()
+                };
+                let repo_url = |segments: &[&str]| {
+                    let repo = ["repos", qual, name];
+                    api_url(GITHUB_API_BASE_URI, &[&repo[..], segments].concat())
+                };
+
+                let project: Project = get(repo_url(&[])?)
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let basehead = format!("{}...{}", pin, project.default_branch);
+                let mut compare_url = repo_url(&["compare", basehead.as_str()])?;
+                compare_url.set_query(Some("per_page=1"));
+                let comparison: GithubComparison =
+                    get(compare_url).await?.error_for_status()?.json().await?;
+                let newest: GithubCommit =
+                    get(repo_url(&["commits", project.default_branch.as_str()])?)
+                        .await?
+                        .error_for_status()?
+                        .json()
+                        .await?;
+
+                lag(
+                    comparison.ahead_by,
+                    &comparison.base_commit.commit.committer.date,
+                    &newest.commit.committer.date,
+                )
+            }
+            RepoSite::Gitlab => {
+                let project_id = format!("{}/{}", qual, name);
+                let project_url = |segments: &[&str]| {
+                    let project = ["projects", project_id.as_str()];
+                    api_url(GITLAB_API_BASE_URI, &[&project[..], segments].concat())
+                };
+
+                let project: Project = client
+                    .get(project_url(&[])?)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let comparison: GitlabComparison = client
+                    .get(project_url(&["repository", "compare"])?)
+                    .query(&[("from", &pin), ("to", &project.default_branch)])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let pinned: GitlabCommit = client
+                    .get(project_url(&["repository", "commits", pin.as_str()])?)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                let newest = comparison.commit.as_ref().unwrap_or(&pinned);
+                lag(
+                    comparison.commits.len(),
+                    &pinned.committed_date,
+                    &newest.committed_date,
+                )
+            }
+            RepoSite::Bitbucket | RepoSite::Sourceforge | RepoSite::Custom(_) => Err(anyhow!(
+                "comparing commits is not supported on {}",
+                repo_path.site.as_ref()
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for CompareGitPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CompareGitPin")
+    }
+}
+
+impl Service<(RepoPath, String)> for CompareGitPin {
+    type Response = GitPinLag;
+    type Error = Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, (repo_path, pin): (RepoPath, String)) -> Self::Future {
+        let client = self.client.clone();
+        let github_token = self.github_token.clone();
+        Self::query(client, github_token, repo_path, pin).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_api_url_segments() {
+        let url = api_url(
+            GITLAB_API_BASE_URI,
+            &[
+                "projects",
+                "deps-rs/deps.rs",
+                "repository",
+                "commits",
+                "v1.0 #2?",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://gitlab.com/api/v4/projects/deps-rs%2Fdeps.rs/repository/commits/v1.0%20%232%3F"
+        );
+    }
+
+    #[test]
+    fn counts_days_between_commits() {
+        assert_eq!(epoch_day("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(epoch_day("2000-03-01T12:00:00.000+02:00"), Some(11017));
+        assert_eq!(epoch_day("2021-03"), None);
+
+        let pin_lag = lag(42, "2020-02-28T10:00:00Z", "2020-03-01T09:00:00Z").unwrap();
+        assert_eq!(
+            pin_lag,
+            GitPinLag {
+                commits: 42,
+                days: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! The most starred Rust repositories on GitHub, GitLab and Gitea instances like Codeberg,
+//! listed on the front page.
+
+use std::{
+    fmt,
+    task::{Context, Poll},
+};
+
+use anyhow::Error;
+use futures::{future, FutureExt as _};
+use hyper::service::Service;
+use serde::Deserialize;
+
+use crate::{
+    models::repo::{RawBase, RepoPath, Repository},
+    BoxFuture,
+};
+
+const GITHUB_API_BASE_URI: &str = "https://api.github.com";
+const GITLAB_API_BASE_URI: &str = "https://gitlab.com/api/v4";
+
+/// Repositories requested from each GitLab project list and Gitea search
+const PER_SOURCE: usize = 30;
+
+#[derive(Deserialize)]
+struct GithubSearchResponse {
+    items: Vec<GithubRepo>,
+}
+
+#[derive(Deserialize)]
+struct GithubRepo {
+    name: String,
+    owner: GithubOwner,
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct GithubOwner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabProject {
+    path: String,
+    namespace: GitlabNamespace,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitlabNamespace {
+    full_path: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaSearchResponse {
+    data: Vec<GiteaRepo>,
+}
+
+#[derive(Deserialize)]
+struct GiteaRepo {
+    name: String,
+    owner: GiteaOwner,
+    #[serde(default)]
+    description: String,
+    default_branch: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaOwner {
+    login: String,
+}
+
+/// Takes one item of each list in turn, until all of them are exhausted
+fn interleave<T>(lists: Vec<Vec<T>>) -> Vec<T> {
+    let len = lists.iter().map(Vec::len).sum();
+    let mut iters = lists.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+    let mut interleaved = Vec::with_capacity(len);
+
+    while interleaved.len() < len {
+        interleaved.extend(iters.iter_mut().filter_map(Iterator::next));
+    }
+
+    interleaved
+}
+
+#[derive(Clone)]
+pub struct GetPopularRepos {
+    client: reqwest::Client,
+    /// Gitea instances searched in addition to GitHub and GitLab
+    gitea_hosts: Vec<String>,
+}
+
+impl GetPopularRepos {
+    pub fn new(client: reqwest::Client, gitea_hosts: Vec<String>) -> Self {
+        Self {
+            client,
+            gitea_hosts,
+        }
+    }
+
+    /// Interleaves the repositories of all sources, so that none of them dominates the list.
+    /// Sources that fail are left out, unless all of them do.
+    pub async fn query(
+        client: reqwest::Client,
+        gitea_hosts: Vec<String>,
+    ) -> anyhow::Result<Vec<Repository>> {
+        let mut queries = vec![
+            Self::query_github(client.clone()).boxed(),
+            Self::query_gitlab(client.clone()).boxed(),
+        ];
+        queries.extend(
+            gitea_hosts
+                .into_iter()
+                .map(|host| Self::query_gitea(client.clone(), host).boxed()),
+        );
+
+        let mut lists = Vec::new();
+        let mut last_err = None;
+        for result in future::join_all(queries).await {
+            match result {
+                Ok(repos) => lists.push(repos),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        match (lists.is_empty(), last_err) {
+            (true, Some(err)) => Err(err),
+            _ => Ok(interleave(lists)),
+        }
+    }
+
+    async fn query_github(client: reqwest::Client) -> anyhow::Result<Vec<Repository>> {
+        let url = format!(
+            "{}/search/repositories?q=language:rust&sort=stars",
+            GITHUB_API_BASE_URI
+        );
+
+        let res = client.get(&url).send().await?.error_for_status()?;
+        let summary: GithubSearchResponse = res.json().await?;
+
+        summary
+            .items
+            .into_iter()
+            .map(|item| {
+                let path = RepoPath::from_parts("github", &item.owner.login, &item.name)?;
+
+                Ok(Repository {
+                    path,
+                    description: item.description,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    }
+
+    /// Projects in subgroups are skipped, their paths can't be represented
+    async fn query_gitlab(client: reqwest::Client) -> anyhow::Result<Vec<Repository>> {
+        let url = format!(
+            "{}/projects?with_programming_language=Rust&order_by=star_count&sort=desc&per_page={}",
+            GITLAB_API_BASE_URI, PER_SOURCE
+        );
+
+        let res = client.get(&url).send().await?.error_for_status()?;
+        let projects: Vec<GitlabProject> = res.json().await?;
+
+        Ok(projects
+            .into_iter()
+            .filter_map(|project| {
+                let path =
+                    RepoPath::from_parts("gitlab", &project.namespace.full_path, &project.path)
+                        .ok()?;
+
+                Some(Repository {
+                    path,
+                    description: project.description.unwrap_or_default(),
+                })
+            })
+            .collect())
+    }
+
+    /// Searches for repositories with the `rust` topic, which are analyzed on their default
+    /// branch as repositories on a custom git host
+    async fn query_gitea(client: reqwest::Client, host: String) -> anyhow::Result<Vec<Repository>> {
+        let url = format!(
+            "https://{}/api/v1/repos/search?q=rust&topic=true&sort=stars&order=desc&limit={}",
+            host, PER_SOURCE
+        );
+
+        let res = client.get(&url).send().await?.error_for_status()?;
+        let summary: GiteaSearchResponse = res.json().await?;
+
+        Ok(summary
+            .data
+            .into_iter()
+            .filter_map(|repo| {
+                let raw_base: RawBase = format!(
+                    "https://{}/{{qual}}/{{name}}/raw/branch/{}/",
+                    host, repo.default_branch
+                )
+                .parse()
+                .ok()?;
+                let path = RepoPath::custom(raw_base, &repo.owner.login, &repo.name).ok()?;
+
+                Some(Repository {
+                    path,
+                    description: repo.description,
+                })
+            })
+            .collect())
+    }
+}
+
+impl fmt::Debug for GetPopularRepos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GetPopularRepos")
+    }
+}
+
+impl Service<()> for GetPopularRepos {
+    type Response = Vec<Repository>;
+    type Error = Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: ()) -> Self::Future {
+        let client = self.client.clone();
+        let gitea_hosts = self.gitea_hosts.clone();
+        Self::query(client, gitea_hosts).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleaves_sources() {
+        let lists = vec![vec![1, 2, 3], vec![], vec![10, 20], vec![100]];
+        assert_eq!(interleave(lists), vec![1, 10, 100, 2, 20, 3]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    fmt,
+    task::{Context, Poll},
+};
+
+use anyhow::Error;
+use futures::FutureExt as _;
+use hyper::service::Service;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::Deserialize;
+
+use crate::{
+    models::repo::{RepoPath, RepoSite},
+    BoxFuture,
+};
+
+const GITHUB_API_BASE_URI: &str = "https://api.github.com";
+const GITLAB_API_BASE_URI: &str = "https://gitlab.com/api/v4";
+
+/// Upper bound on the number of suggested manifest directories
+const MAX_MANIFEST_DIRS: usize = 10;
+
+#[derive(Deserialize)]
+struct GithubTreeResponse {
+    tree: Vec<TreeEntry>,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Selects the subdirectories containing a `Cargo.toml`, shallowest first
+fn manifest_dirs(entries: Vec<TreeEntry>) -> Vec<RelativePathBuf> {
+    let mut dirs: Vec<RelativePathBuf> = entries
+        .into_iter()
+        .filter(|entry| entry.kind == "blob")
+        .map(|entry| RelativePathBuf::from(entry.path))
+        .filter(|path| path.file_name() == Some("Cargo.toml"))
+        .filter_map(|path| path.parent().map(RelativePath::to_relative_path_buf))
+        .filter(|dir| !dir.as_str().is_empty())
+        .collect();
+
+    dirs.sort_by_key(|dir| (dir.components().count(), dir.to_string()));
+    dirs.truncate(MAX_MANIFEST_DIRS);
+    dirs
+}
+
+/// Finds the directories of a repository that contain a `Cargo.toml`, through the hosting
+/// provider's API. Only GitHub and GitLab are supported, other sites yield no directories.
+#[derive(Clone)]
+pub struct FindManifestDirs {
+    client: reqwest::Client,
+}
+
+impl FindManifestDirs {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn query(
+        client: reqwest::Client,
+        repo_path: RepoPath,
+    ) -> anyhow::Result<Vec<RelativePathBuf>> {
+        let entries = match repo_path.site {
+            RepoSite::Github => {
+                let url = format!(
+                    "{}/repos/{}/{}/git/trees/HEAD?recursive=1",
+                    GITHUB_API_BASE_URI,
+                    repo_path.qual.as_ref(),
+                    repo_path.name.as_ref()
+                );
+                let res = client.get(&url).send().await?.error_for_status()?;
+                res.json::<GithubTreeResponse>().await?.tree
+            }
+            RepoSite::Gitlab => {
+                let url = format!(
+                    "{}/projects/{}%2F{}/repository/tree?recursive=true&per_page=100",
+                    GITLAB_API_BASE_URI,
+                    repo_path.qual.as_ref(),
+                    repo_path.name.as_ref()
+                );
+                let res = client.get(&url).send().await?.error_for_status()?;
+                res.json::<Vec<TreeEntry>>().await?
+            }
+            RepoSite::Bitbucket | RepoSite::Sourceforge | RepoSite::Custom(_) => Vec::new(),
+        };
+
+        Ok(manifest_dirs(entries))
+    }
+}
+
+impl fmt::Debug for FindManifestDirs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FindManifestDirs")
+    }
+}
+
+impl Service<RepoPath> for FindManifestDirs {
+    type Response = Vec<RelativePathBuf>;
+    type Error = Error;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, repo_path: RepoPath) -> Self::Future {
+        let client = self.client.clone();
+        Self::query(client, repo_path).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, kind: &str) -> TreeEntry {
+        TreeEntry {
+            path: path.to_owned(),
+            kind: kind.to_owned(),
+        }
+    }
+
+    #[test]
+    fn finds_manifest_dirs() {
+        let dirs = manifest_dirs(vec![
+            entry("README.md", "blob"),
+            entry("crates/b/Cargo.toml", "blob"),
+            entry("rust/Cargo.toml", "blob"),
+            entry("crates/a/Cargo.toml", "blob"),
+            entry("Cargo.toml", "blob"),
+            entry("tests/Cargo.toml", "tree"),
+            entry("docs/NotCargo.toml", "blob"),
+        ]);
+
+        let dirs: Vec<&str> = dirs.iter().map(|dir| dir.as_str()).collect();
+        assert_eq!(dirs, vec!["rust", "crates/a", "crates/b"]);
+    }
+}
@@ -2,14 +2,18 @@
 #![warn(missing_debug_implementations)]
 
 use std::{
-    env,
+    env, fs,
     future::Future,
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    net::{IpAddr, SocketAddr, UdpSocket},
+    path::Path,
     pin::Pin,
+    process,
+    str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 
-use cadence::{QueuingMetricSink, UdpMetricSink};
+use cadence::{QueuingMetricSink, StatsdClient, UdpMetricSink};
 use hyper::{
     server::conn::AddrStream,
     service::{make_service_fn, service_fn},
@@ -18,7 +22,12 @@ use hyper::{
 
 use reqwest::redirect::Policy as RedirectPolicy;
 use slog::{error, info, o, Drain, Logger};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::spawn_blocking,
+};
 
+mod config;
 mod engine;
 mod interactors;
 mod models;
@@ -26,9 +35,16 @@ mod parsers;
 mod server;
 mod utils;
 
-use self::engine::Engine;
-use self::server::App;
-use self::utils::index::ManagedIndex;
+use self::config::Config;
+use self::engine::{CacheSizes, CacheTtls, Concurrency, Engine};
+use self::interactors::github::GithubApp;
+use self::interactors::oauth::{OauthApp, OauthProvider};
+use self::interactors::Timeouts;
+use self::models::repo::RepoSite;
+use self::server::{Accounts, App, GithubIntegration, Quota, RateLimitConfig, RateLimiter};
+use self::utils::cache::CacheBackend;
+use self::utils::dns::{CachingResolver, PublicResolver};
+use self::utils::index::{ManagedIndex, Registries, RegistryConfig};
 
 /// Future crate's BoxFuture without the explicit lifetime parameter.
 pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
@@ -38,11 +54,359 @@ const DEPS_RS_UA: &str = "deps.rs";
 fn init_metrics() -> QueuingMetricSink {
     let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
     socket.set_nonblocking(true).unwrap();
-    let host = ("127.0.0.1", 8125);
-    let sink = UdpMetricSink::from(host, socket).unwrap();
+    let host =
+        env::var("STATSD_HOST").unwrap_or_else(|_| config::get().metrics.statsd_host.clone());
+    let sink = UdpMetricSink::from(host.as_str(), socket).unwrap();
     QueuingMetricSink::from(sink)
 }
 
+/// Loads the configuration file given by `CONFIG_FILE`, if any
+fn init_config() -> anyhow::Result<()> {
+    if let Ok(path) = env::var("CONFIG_FILE") {
+        config::init(Config::load(Path::new(&path))?);
+    }
+    Ok(())
+}
+
+/// Reads and parses the environment variable `name`, falling back to `default` if unset.
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("could not parse environment variable {}", name)),
+        Err(_) => default,
+    }
+}
+
+fn init_client(resolver: Option<Arc<CachingResolver>>, timeout: Duration) -> reqwest::Client {
+    let keep_alive_interval = match env_or("HTTP2_KEEP_ALIVE_INTERVAL_SECS", 0) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+
+    let mut builder = reqwest::Client::builder()
+        .user_agent(DEPS_RS_UA)
+        .redirect(RedirectPolicy::limited(5))
+        .timeout(timeout)
+        .pool_max_idle_per_host(env_or("HTTP_POOL_MAX_IDLE_PER_HOST", 32))
+        .pool_idle_timeout(Duration::from_secs(env_or(
+            "HTTP_POOL_IDLE_TIMEOUT_SECS",
+            90,
+        )))
+        .http2_keep_alive_interval(keep_alive_interval)
+        .http2_keep_alive_while_idle(keep_alive_interval.is_some());
+
+    if let Some(resolver) = resolver {
+        builder = builder.dns_resolver(resolver);
+    }
+
+    builder.build().unwrap()
+}
+
+/// Creates the client notifying webhooks. Their URLs come from users, so it only connects to
+/// public addresses and doesn't follow redirects, which could lead anywhere.
+fn init_webhook_client(
+    resolver: Option<Arc<CachingResolver>>,
+    timeout: Duration,
+) -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(DEPS_RS_UA)
+        .redirect(RedirectPolicy::none())
+        .timeout(timeout)
+        .dns_resolver(Arc::new(PublicResolver::new(resolver)))
+        .build()
+        .unwrap()
+}
+
+fn init_timeouts() -> Timeouts {
+    let env_secs = |name, default| Duration::from_secs(env_or(name, default));
+    let config = &config::get().timeouts;
+
+    let default = env_secs("HTTP_TIMEOUT_SECS", config.default);
+    let sites = vec![
+        (
+            RepoSite::Github,
+            env_secs("GITHUB_TIMEOUT_SECS", config.github),
+        ),
+        (
+            RepoSite::Gitlab,
+            env_secs("GITLAB_TIMEOUT_SECS", config.gitlab),
+        ),
+        (
+            RepoSite::Bitbucket,
+            env_secs("BITBUCKET_TIMEOUT_SECS", config.bitbucket),
+        ),
+        (
+            RepoSite::Sourceforge,
+            env_secs("SOURCEFORGE_TIMEOUT_SECS", config.sourceforge),
+        ),
+    ]
+    .into_iter()
+    .collect();
+
+    Timeouts {
+        default,
+        sites,
+        popular_repos: env_secs("POPULAR_REPOS_TIMEOUT_SECS", config.popular_repos),
+        popular_crates: env_secs("POPULAR_CRATES_TIMEOUT_SECS", config.popular_crates),
+        crawl: env_secs("CRAWL_TIMEOUT_SECS", config.crawl),
+        analysis: env_secs("ANALYSIS_TIMEOUT_SECS", config.analysis),
+    }
+}
+
+/// Reads alternative registries from `REGISTRIES_<NAME>_INDEX` variables, like Cargo's
+/// `CARGO_REGISTRIES_<NAME>_INDEX`. Their indexes are kept in `REGISTRY_INDEX_DIR`.
+fn init_registries() -> Registries {
+    let configs = env::vars()
+        .filter_map(|(key, index_url)| {
+            let name = key.strip_prefix("REGISTRIES_")?.strip_suffix("_INDEX")?;
+            let name = name.to_lowercase().replace('_', "-");
+
+            Some(RegistryConfig { name, index_url })
+        })
+        .collect();
+
+    let dir = env::var("REGISTRY_INDEX_DIR").unwrap_or_else(|_| "registries".to_string());
+    Registries::new(configs, dir.into())
+}
+
+/// Whether crates.io is queried through its sparse index instead of a clone of the git index,
+/// chosen like in Cargo with `REGISTRIES_CRATES_IO_PROTOCOL` set to `sparse` or `git`
+fn use_sparse_index() -> bool {
+    env::var("REGISTRIES_CRATES_IO_PROTOCOL").map_or(true, |protocol| protocol != "git")
+}
+
+fn init_concurrency() -> Concurrency {
+    Concurrency {
+        fetches_per_analysis: env_or("ANALYSIS_FETCH_CONCURRENCY", 25).max(1),
+        members_per_analysis: env_or("ANALYSIS_MEMBER_CONCURRENCY", 8).max(1),
+        manifests_per_crawl: env_or("ANALYSIS_CRAWL_CONCURRENCY", 10).max(1),
+        max_manifests: env_or("ANALYSIS_MAX_MANIFESTS", 200).max(1),
+        fetches_total: env_or("ANALYSIS_TOTAL_FETCH_CONCURRENCY", 100).max(1),
+        background_fetches_total: env_or("BACKGROUND_TOTAL_FETCH_CONCURRENCY", 25).max(1),
+    }
+}
+
+/// Connects to the Redis server at `REDIS_URL` to share caches between replicas, or else keeps
+/// them in memory.
+async fn init_cache_backend(logger: &Logger) -> CacheBackend {
+    let url = match env::var("REDIS_URL") {
+        Ok(url) => url,
+        Err(_) => return CacheBackend::Memory,
+    };
+
+    match CacheBackend::redis(&url).await {
+        Ok(backend) => {
+            info!(logger, "Caching in redis");
+            backend
+        }
+        Err(e) => {
+            error!(
+                logger,
+                "failed connecting to redis, falling back to in-memory caches: {}", e
+            );
+            CacheBackend::Memory
+        }
+    }
+}
+
+fn init_cache_ttls() -> CacheTtls {
+    let env_secs = |name, default| Duration::from_secs(env_or(name, default));
+    let config = &config::get().cache;
+
+    CacheTtls {
+        query_crate: env_secs("QUERY_CRATE_CACHE_TTL_SECS", config.query_crate_ttl),
+        crate_metadata: env_secs("CRATE_METADATA_CACHE_TTL_SECS", config.crate_metadata_ttl),
+        popular_crates: env_secs("POPULAR_CRATES_CACHE_TTL_SECS", config.popular_crates_ttl),
+        popular_repos: env_secs("POPULAR_REPOS_CACHE_TTL_SECS", config.popular_repos_ttl),
+        manifest_dirs: env_secs("MANIFEST_DIRS_CACHE_TTL_SECS", config.manifest_dirs_ttl),
+        analyses: env_secs("ANALYSIS_CACHE_TTL_SECS", config.analysis_ttl),
+    }
+}
+
+fn init_cache_sizes() -> CacheSizes {
+    let config = &config::get().cache;
+
+    CacheSizes {
+        query_crate: env_or("QUERY_CRATE_CACHE_SIZE", config.query_crate_size),
+        crate_metadata: env_or("CRATE_METADATA_CACHE_SIZE", config.crate_metadata_size),
+        manifest_dirs: env_or("MANIFEST_DIRS_CACHE_SIZE", config.manifest_dirs_size),
+        analyses: env_or("ANALYSIS_CACHE_SIZE", config.analysis_size),
+    }
+}
+
+/// Loads the badge font configured through `BADGE_FONT_PATH`, if any.
+fn init_badge_font(logger: &Logger) {
+    let path = match env::var("BADGE_FONT_PATH") {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let font_family = env::var("BADGE_FONT_FAMILY").unwrap_or_else(|_| "sans-serif".to_string());
+
+    let result = fs::read(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| Ok(badge::set_font(data, font_family)?));
+
+    match result {
+        Ok(()) => info!(logger, "Using badge font from {}", path),
+        Err(e) => error!(logger, "failed loading badge font from {}: {}", path, e),
+    }
+}
+
+/// Reads a rate limit quota from `<PREFIX>_PER_MINUTE` and `<PREFIX>_BURST`. The limit is
+/// disabled if the rate is unset or zero.
+fn env_quota(prefix: &str, default_burst: u32) -> Option<Quota> {
+    let per_minute = env_or(&format!("{}_PER_MINUTE", prefix), 0);
+    if per_minute == 0 {
+        return None;
+    }
+
+    Some(Quota {
+        burst: env_or(&format!("{}_BURST", prefix), default_burst).max(1),
+        per_minute,
+    })
+}
+
+fn init_rate_limits() -> RateLimitConfig {
+    RateLimitConfig {
+        per_client: env_quota("RATE_LIMIT_CLIENT", 60),
+        per_repo: env_quota("RATE_LIMIT_REPO", 10),
+        trust_forwarded_for: env_or("RATE_LIMIT_TRUST_FORWARDED_FOR", false),
+    }
+}
+
+/// Sets up the GitHub App mode if `GITHUB_APP_ID`, `GITHUB_APP_PRIVATE_KEY_PATH` and
+/// `GITHUB_APP_WEBHOOK_SECRET` are all set.
+fn init_github_app(client: reqwest::Client, logger: &Logger) -> Option<GithubIntegration> {
+    let app_id = env::var("GITHUB_APP_ID").ok()?;
+    let key_path = env::var("GITHUB_APP_PRIVATE_KEY_PATH").ok()?;
+    let webhook_secret = env::var("GITHUB_APP_WEBHOOK_SECRET").ok()?;
+    if webhook_secret.trim().is_empty() {
+        error!(
+            logger,
+            "GITHUB_APP_WEBHOOK_SECRET is empty, the GitHub App stays disabled as its webhooks could be forged"
+        );
+        return None;
+    }
+
+    let result = fs::read(&key_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|key| GithubApp::new(client, app_id.clone(), &key));
+
+    match result {
+        Ok(app) => {
+            info!(logger, "Running as GitHub App {}", app_id);
+            Some(GithubIntegration {
+                app,
+                webhook_secret: webhook_secret.into_bytes(),
+            })
+        }
+        Err(e) => {
+            error!(logger, "failed setting up the GitHub App: {}", e);
+            None
+        }
+    }
+}
+
+/// Sets up logins for watchlists if `SESSION_SECRET` is set, with the OAuth apps configured
+/// through `GITHUB_OAUTH_CLIENT_ID` and `GITHUB_OAUTH_CLIENT_SECRET` or their `GITLAB_`
+/// counterparts.
+fn init_accounts(client: reqwest::Client, logger: &Logger) -> Option<Accounts> {
+    let session_secret = env::var("SESSION_SECRET").ok()?;
+    if session_secret.trim().is_empty() {
+        error!(
+            logger,
+            "SESSION_SECRET is empty, logins stay disabled as sessions could be forged"
+        );
+        return None;
+    }
+
+    let apps: Vec<OauthApp> = [
+        (OauthProvider::Github, "GITHUB"),
+        (OauthProvider::Gitlab, "GITLAB"),
+    ]
+    .iter()
+    .filter_map(|&(provider, prefix)| {
+        let client_id = env::var(format!("{}_OAUTH_CLIENT_ID", prefix)).ok()?;
+        let client_secret = env::var(format!("{}_OAUTH_CLIENT_SECRET", prefix)).ok()?;
+        Some(OauthApp::new(
+            client.clone(),
+            provider,
+            client_id,
+            client_secret,
+        ))
+    })
+    .collect();
+
+    if apps.is_empty() {
+        error!(
+            logger,
+            "SESSION_SECRET is set, but no OAuth app is configured to log in with"
+        );
+        return None;
+    }
+
+    info!(logger, "Logins enabled for {} providers", apps.len());
+    Some(Accounts {
+        apps,
+        session_secret: session_secret.into_bytes(),
+    })
+}
+
+/// Clones or updates the git indexes and fetches the advisory database, so that both can be
+/// baked into an image or volume ahead of time. Used by the `index init` mode.
+async fn init_index(logger: &Logger) -> anyhow::Result<()> {
+    let managed_index = ManagedIndex::new(
+        Duration::from_secs(20),
+        use_sparse_index(),
+        init_registries(),
+        logger.clone(),
+    );
+    info!(logger, "Updating git indexes");
+    managed_index.refresh().await?;
+
+    info!(logger, "Fetching advisory database");
+    spawn_blocking(rustsec::Database::fetch).await??;
+
+    Ok(())
+}
+
+/// Switches the engine into cached-only mode on `SIGUSR1` and back on `SIGUSR2`, so
+/// operators can stop hammering upstream services while those have an outage.
+fn spawn_maintenance_toggle(engine: Engine, logger: Logger) {
+    let signals = signal(SignalKind::user_defined1()).and_then(|enable| {
+        let disable = signal(SignalKind::user_defined2())?;
+        Ok((enable, disable))
+    });
+
+    let (mut enable, mut disable) = match signals {
+        Ok(signals) => signals,
+        Err(e) => {
+            error!(
+                logger,
+                "failed to listen for maintenance mode signals: {}", e
+            );
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let cached_only = tokio::select! {
+                _ = enable.recv() => true,
+                _ = disable.recv() => false,
+            };
+
+            engine.set_cached_only(cached_only);
+            info!(
+                logger,
+                "cached-only mode {}",
+                if cached_only { "enabled" } else { "disabled" }
+            );
+        }
+    });
+}
+
 fn init_root_logger() -> Logger {
     let decorator = slog_term::TermDecorator::new().build();
     let drain = slog_term::FullFormat::new(decorator).build().fuse();
@@ -55,23 +419,81 @@ fn init_root_logger() -> Logger {
 async fn main() {
     let logger = init_root_logger();
 
-    let metrics = init_metrics();
+    if let Err(e) = init_config() {
+        error!(logger, "failed loading the configuration: {:#}", e);
+        drop(logger);
+        process::exit(1);
+    }
 
-    let client = reqwest::Client::builder()
-        .user_agent(DEPS_RS_UA)
-        .redirect(RedirectPolicy::limited(5))
-        .timeout(Duration::from_secs(5))
-        .build()
-        .unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["index", "init"] => {
+            let result = init_index(&logger).await;
+            if let Err(e) = &result {
+                error!(logger, "failed initializing the index: {:#}", e);
+            }
+            // give the async drain a chance to flush before exiting
+            drop(logger);
+            process::exit(if result.is_ok() { 0 } else { 1 });
+        }
+        _ => {
+            eprintln!("usage: shiny-robots [index init]");
+            process::exit(2);
+        }
+    }
 
-    let port = env::var("PORT")
-        .unwrap_or_else(|_| "8080".to_string())
-        .parse()
-        .expect("could not read port");
+    let metrics = StatsdClient::from_sink("engine", init_metrics());
 
-    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
+    init_badge_font(&logger);
 
-    let mut managed_index = ManagedIndex::new(Duration::from_secs(20), logger.clone());
+    let resolver = match CachingResolver::from_system_conf(
+        env_or("DNS_CACHE_SIZE", 64),
+        Duration::from_secs(env_or("DNS_CACHE_MIN_TTL_SECS", 60)),
+        Duration::from_secs(env_or("DNS_CACHE_NEGATIVE_TTL_SECS", 10)),
+    ) {
+        Ok(resolver) => Some(Arc::new(resolver)),
+        Err(e) => {
+            error!(
+                logger,
+                "failed to set up caching DNS resolver, falling back to the default: {}", e
+            );
+            None
+        }
+    };
+
+    let timeouts = init_timeouts();
+
+    // interactive requests (manifest retrieval for status pages and badges) get their own
+    // connection pool, so background work like fetching popular lists can't starve them
+    let client = init_client(resolver.clone(), timeouts.default);
+    // background requests are bounded by the timeouts of the individual interactors instead
+    let background_timeout = timeouts
+        .default
+        .max(timeouts.popular_repos)
+        .max(timeouts.popular_crates);
+    let webhook_client = init_webhook_client(resolver.clone(), timeouts.default);
+    let background_client = init_client(resolver, background_timeout);
+    let github = init_github_app(background_client.clone(), &logger);
+    let accounts = init_accounts(background_client.clone(), &logger);
+
+    let server_config = &config::get().server;
+    let port = env_or("PORT", server_config.port);
+    let address: IpAddr = env_or("BIND_ADDRESS", server_config.address);
+
+    let addr = SocketAddr::new(address, port);
+
+    let mut managed_index = ManagedIndex::new(
+        Duration::from_secs(20),
+        use_sparse_index(),
+        init_registries(),
+        logger.clone(),
+    );
     if let Err(e) = managed_index.initial_clone().await {
         error!(
             logger,
@@ -80,23 +502,102 @@ async fn main() {
     }
 
     let index = managed_index.index();
+    let registries = managed_index.registries();
     tokio::spawn(async move {
         managed_index.refresh_at_interval().await;
     });
 
-    let mut engine = Engine::new(client.clone(), index, logger.new(o!()));
-    engine.set_metrics(metrics);
+    let engine = Engine::new(
+        client,
+        background_client,
+        webhook_client,
+        env::var("GITHUB_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty()),
+        timeouts,
+        init_concurrency(),
+        init_cache_ttls(),
+        init_cache_sizes(),
+        index,
+        registries,
+        config::hosts_from_env("POPULAR_GITEA_HOSTS", &server_config.popular_gitea_hosts),
+        init_cache_backend(&logger).await,
+        metrics,
+        logger.new(o!()),
+    );
+
+    let advisory_engine = engine.clone();
+    let advisory_interval = Duration::from_secs(env_or("ADVISORY_DB_REFRESH_SECS", 1800));
+    tokio::spawn(async move {
+        advisory_engine
+            .refresh_advisory_db_at_interval(advisory_interval)
+            .await;
+    });
+
+    let webhooks_path = env::var("WEBHOOKS_FILE").unwrap_or_else(|_| "webhooks.json".to_string());
+    if let Err(e) = engine.load_webhooks(webhooks_path.into()).await {
+        error!(logger, "failed loading webhook subscriptions: {:#}", e);
+    }
+
+    let webhook_engine = engine.clone();
+    let webhook_interval = Duration::from_secs(env_or("WEBHOOK_INTERVAL_SECS", 3600));
+    tokio::spawn(async move {
+        webhook_engine
+            .notify_webhooks_at_interval(webhook_interval)
+            .await;
+    });
+
+    let history_path = env::var("HISTORY_FILE").unwrap_or_else(|_| "history.json".to_string());
+    match engine.load_history(history_path.into()).await {
+        Ok(()) => {
+            // only opened along with the history, as the analyses it doesn't point to are pruned
+            let snapshots_dir =
+                env::var("SNAPSHOTS_DIR").unwrap_or_else(|_| "snapshots".to_string());
+            if let Err(e) = engine.open_frozen_analyses(snapshots_dir.into()).await {
+                error!(logger, "failed opening the frozen analyses: {:#}", e);
+            }
+        }
+        Err(e) => error!(logger, "failed loading the history: {:#}", e),
+    }
+
+    let history_engine = engine.clone();
+    let history_interval = Duration::from_secs(env_or("HISTORY_INTERVAL_SECS", 3600));
+    tokio::spawn(async move {
+        history_engine
+            .record_history_at_interval(history_interval)
+            .await;
+    });
+
+    let watchlists_path =
+        env::var("WATCHLISTS_FILE").unwrap_or_else(|_| "watchlists.json".to_string());
+    if let Err(e) = engine.load_watchlists(watchlists_path.into()).await {
+        error!(logger, "failed loading the watchlists: {:#}", e);
+    }
+
+    spawn_maintenance_toggle(engine.clone(), logger.new(o!()));
+
+    let rate_limiter = RateLimiter::new(init_rate_limits());
 
     let svc_logger = logger.new(o!());
-    let make_svc = make_service_fn(move |_socket: &AddrStream| {
+    let make_svc = make_service_fn(move |socket: &AddrStream| {
         let engine = engine.clone();
+        let github = github.clone();
+        let accounts = accounts.clone();
+        let rate_limiter = rate_limiter.clone();
         let logger = svc_logger.clone();
+        let remote_ip = socket.remote_addr().ip();
 
         async move {
-            let server = App::new(logger.clone(), engine.clone());
+            let server = App::new(
+                logger.clone(),
+                engine.clone(),
+                github,
+                accounts,
+                rate_limiter,
+            );
             Ok::<_, hyper::Error>(service_fn(move |req| {
                 let server = server.clone();
-                async move { server.handle(req).await }
+                async move { server.handle(req, remote_ip).await }
             }))
         }
     });
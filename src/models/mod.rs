@@ -1,6 +1,10 @@
-pub mod crates;
+use serde::Serialize;
+
+pub use deps_rs_core::crates;
+
 pub mod repo;
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum SubjectPath {
     Repo(self::repo::RepoPath),
     Crate(self::crates::CratePath),
@@ -1,7 +1,12 @@
 use std::{fmt, str::FromStr};
 
 use anyhow::{anyhow, ensure, Error};
-use relative_path::RelativePath;
+use badge::BadgeStyle;
+use relative_path::{RelativePath, RelativePathBuf};
+use rustsec::Advisory;
+use serde::Serialize;
+
+use crate::models::crates::{AnalyzedDependencies, CrateDeps, CrateName};
 
 #[derive(Clone, Debug)]
 pub struct Repository {
@@ -9,7 +14,7 @@ pub struct Repository {
     pub description: String,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct RepoPath {
     pub site: RepoSite,
     pub qual: RepoQualifier,
@@ -25,16 +30,125 @@ impl RepoPath {
         })
     }
 
-    pub fn to_usercontent_file_url(&self, path: &RelativePath) -> String {
+    /// Creates the path of a repository on a custom git host
+    pub fn custom(raw_base: RawBase, qual: &str, name: &str) -> Result<RepoPath, Error> {
+        Ok(RepoPath {
+            site: RepoSite::Custom(raw_base),
+            qual: qual.parse()?,
+            name: name.parse()?,
+        })
+    }
+
+    /// Recognizes the URL of a repository on GitHub, GitLab or Bitbucket, like
+    /// `https://github.com/deps-rs/deps.rs.git`. Anything after the repository's name, e.g. the
+    /// path of a subdirectory, is ignored.
+    pub fn from_url(url: &str) -> Option<RepoPath> {
+        let rest = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?;
+        let rest = rest.strip_prefix("www.").unwrap_or(rest);
+
+        let mut parts = rest.split('/');
+        let site = match parts.next()? {
+            "github.com" => "github",
+            "gitlab.com" => "gitlab",
+            "bitbucket.org" => "bitbucket",
+            _ => return None,
+        };
+        let qual = parts.next()?;
+        let name = parts.next()?;
+        let name = name.strip_suffix(".git").unwrap_or(name);
+        if qual.is_empty() || name.is_empty() {
+            return None;
+        }
+
+        RepoPath::from_parts(site, qual, name).ok()
+    }
+
+    /// Returns the URL of a file's raw contents at `git_ref`, or else the default branch
+    pub fn to_usercontent_file_url(&self, path: &RelativePath, git_ref: Option<&GitRef>) -> String {
+        let (qual, name) = (self.qual.as_ref(), self.name.as_ref());
+        let git_ref = GitRef::or_head(git_ref);
+        let path = path.normalize();
+
+        match self.site {
+            RepoSite::Github => format!(
+                "https://raw.githubusercontent.com/{}/{}/{}/{}",
+                qual, name, git_ref, path
+            ),
+            RepoSite::Gitlab | RepoSite::Bitbucket => format!(
+                "{}/{}/{}/raw/{}/{}",
+                self.site.to_base_uri(),
+                qual,
+                name,
+                git_ref,
+                path
+            ),
+            RepoSite::Sourceforge => format!(
+                "{}/{}/{}/ci/{}/tree/{}?format=raw",
+                self.site.to_base_uri(),
+                qual,
+                name,
+                git_ref,
+                path
+            ),
+            RepoSite::Custom(ref raw_base) => raw_base.expand(qual, name, git_ref, path.as_str()),
+        }
+    }
+
+    /// Returns the URL of the hosting provider's view of a file at `git_ref`, or else the
+    /// default branch, optionally pointing at a line. Custom hosts link to the raw file.
+    pub fn to_source_file_url(
+        &self,
+        path: &RelativePath,
+        git_ref: Option<&GitRef>,
+        line: Option<usize>,
+    ) -> String {
+        let infix = match self.site {
+            RepoSite::Github => "blob",
+            RepoSite::Gitlab => "-/blob",
+            RepoSite::Bitbucket => "src",
+            RepoSite::Sourceforge => "ci",
+            RepoSite::Custom(_) => return self.to_usercontent_file_url(path, git_ref),
+        };
+        let anchor = match (&self.site, line) {
+            (_, None) => String::new(),
+            (RepoSite::Bitbucket, Some(line)) => format!("#lines-{}", line),
+            (RepoSite::Sourceforge, Some(line)) => format!("#l{}", line),
+            (_, Some(line)) => format!("#L{}", line),
+        };
+
+        // SourceForge puts the file path below a `tree` segment after the ref
+        let tree = match self.site {
+            RepoSite::Sourceforge => "tree/",
+            _ => "",
+        };
+
         format!(
-            "{}/{}/{}/{}/{}",
-            self.site.to_usercontent_base_uri(),
+            "{}/{}/{}/{}/{}/{}{}{}",
+            self.site.to_base_uri(),
             self.qual.as_ref(),
             self.name.as_ref(),
-            self.site.to_usercontent_repo_suffix(),
-            path.normalize()
+            infix,
+            GitRef::or_head(git_ref),
+            tree,
+            path.normalize(),
+            anchor
         )
     }
+
+    /// Returns the URL of the repository's page, or for custom hosts the host itself
+    pub fn to_repo_url(&self) -> String {
+        match self.site {
+            RepoSite::Custom(ref raw_base) => raw_base.origin().to_owned(),
+            _ => format!(
+                "{}/{}/{}",
+                self.site.to_base_uri(),
+                self.qual.as_ref(),
+                self.name.as_ref()
+            ),
+        }
+    }
 }
 
 impl fmt::Display for RepoPath {
@@ -49,34 +163,27 @@ impl fmt::Display for RepoPath {
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub enum RepoSite {
     Github,
     Gitlab,
     Bitbucket,
+    /// SourceForge, where the qualifier is the project and the name is the repository's
+    /// mount point, e.g. `code`
+    Sourceforge,
+    /// A git host without a dedicated integration, whose raw files are found through a
+    /// URL template
+    Custom(RawBase),
 }
 
 impl RepoSite {
-    pub fn to_base_uri(&self) -> &'static str {
+    pub fn to_base_uri(&self) -> &str {
         match self {
             RepoSite::Github => "https://github.com",
             RepoSite::Gitlab => "https://gitlab.com",
             RepoSite::Bitbucket => "https://bitbucket.org",
-        }
-    }
-
-    pub fn to_usercontent_base_uri(&self) -> &'static str {
-        match self {
-            RepoSite::Github => "https://raw.githubusercontent.com",
-            RepoSite::Gitlab => "https://gitlab.com",
-            RepoSite::Bitbucket => "https://bitbucket.org",
-        }
-    }
-
-    pub fn to_usercontent_repo_suffix(&self) -> &'static str {
-        match self {
-            RepoSite::Github => "HEAD",
-            RepoSite::Gitlab | RepoSite::Bitbucket => "raw/HEAD",
+            RepoSite::Sourceforge => "https://sourceforge.net/p",
+            RepoSite::Custom(raw_base) => raw_base.origin(),
         }
     }
 }
@@ -89,6 +196,8 @@ impl FromStr for RepoSite {
             "github" => Ok(RepoSite::Github),
             "gitlab" => Ok(RepoSite::Gitlab),
             "bitbucket" => Ok(RepoSite::Bitbucket),
+            "sourceforge" => Ok(RepoSite::Sourceforge),
+            "custom" => Err(anyhow!("custom repo sites need a raw_base URL template")),
             _ => Err(anyhow!("unknown repo site identifier")),
         }
     }
@@ -100,11 +209,13 @@ impl AsRef<str> for RepoSite {
             RepoSite::Github => "github",
             RepoSite::Gitlab => "gitlab",
             RepoSite::Bitbucket => "bitbucket",
+            RepoSite::Sourceforge => "sourceforge",
+            RepoSite::Custom(_) => "custom",
         }
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct RepoQualifier(String);
 
 impl FromStr for RepoQualifier {
@@ -126,7 +237,7 @@ impl AsRef<str> for RepoQualifier {
     }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct RepoName(String);
 
 impl FromStr for RepoName {
@@ -148,6 +259,158 @@ impl AsRef<str> for RepoName {
     }
 }
 
+/// URL template for the raw files of a repository on a custom git host, e.g.
+/// `https://git.example.com/{qual}/{name}/plain/`.
+///
+/// `{qual}` and `{name}` are required. `{ref}` is replaced by the git ref, `HEAD` by default,
+/// and `{path}` by the file path, which is otherwise appended.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RawBase(String);
+
+impl RawBase {
+    /// Returns the scheme and host part of the template
+    pub fn origin(&self) -> &str {
+        let host_start = self.0.find("://").map_or(0, |idx| idx + 3);
+        let host_end = self.0[host_start..]
+            .find(&['/', '?', '#'][..])
+            .map_or(self.0.len(), |idx| host_start + idx);
+        &self.0[..host_end]
+    }
+
+    /// Returns the host the template points to
+    pub fn host(&self) -> &str {
+        let origin = self.origin();
+        &origin[origin.find("://").map_or(0, |idx| idx + 3)..]
+    }
+
+    fn expand(&self, qual: &str, name: &str, git_ref: &str, path: &str) -> String {
+        let url = self
+            .0
+            .replace("{qual}", qual)
+            .replace("{name}", name)
+            .replace("{ref}", git_ref);
+
+        if url.contains("{path}") {
+            url.replace("{path}", path)
+        } else {
+            format!("{}{}", url, path)
+        }
+    }
+}
+
+impl AsRef<str> for RawBase {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl FromStr for RawBase {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<RawBase, Error> {
+        let raw_base = RawBase(input.to_string());
+        let host = raw_base.host();
+
+        ensure!(
+            input.starts_with("https://") || input.starts_with("http://"),
+            "raw_base must be an http(s) URL"
+        );
+        ensure!(
+            !host.is_empty() && !host.contains(&['@', '{', '}'][..]),
+            "invalid raw_base host"
+        );
+        ensure!(
+            input.contains("{qual}") && input.contains("{name}"),
+            "raw_base must contain {{qual}} and {{name}}"
+        );
+        Ok(raw_base)
+    }
+}
+
+/// A branch, tag or commit SHA of a repository
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct GitRef(String);
+
+impl GitRef {
+    /// Returns the ref as a string, falling back to the default branch
+    fn or_head(git_ref: Option<&GitRef>) -> &str {
+        git_ref.map_or("HEAD", AsRef::as_ref)
+    }
+}
+
+impl FromStr for GitRef {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<GitRef, Error> {
+        let is_valid = !input.is_empty()
+            && input.len() <= 255
+            && !input.starts_with(&['/', '-', '.'][..])
+            && !input.ends_with('/')
+            && !input.contains("..")
+            && !input.contains("//")
+            && input
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-/+".contains(c));
+
+        ensure!(is_valid, "invalid git ref");
+        Ok(GitRef(input.to_string()))
+    }
+}
+
+impl AsRef<str> for GitRef {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+/// Settings a repository commits in a `.deps-rs.toml` at its root
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepoConfig {
+    /// Dependencies left out of the analysis, e.g. known false positives
+    pub ignore: Vec<CrateName>,
+    /// IDs of advisories that don't apply to the repository, e.g. `RUSTSEC-2020-0071`
+    pub ignore_advisories: Vec<String>,
+    /// Style of the repository's badges when the badge URL doesn't choose one
+    pub badge_style: Option<BadgeStyle>,
+    /// Directories with the manifests to analyze, instead of the one at the root
+    pub manifests: Vec<RelativePathBuf>,
+    /// Hex-encoded SHA-256 hash of the token that allows registering webhooks
+    pub webhook_token_sha256: Option<String>,
+}
+
+impl RepoConfig {
+    /// Removes the ignored dependencies before they are analyzed
+    pub fn remove_ignored(&self, deps: &mut CrateDeps) {
+        for name in &self.ignore {
+            deps.main.shift_remove(name);
+            deps.dev.shift_remove(name);
+            deps.build.shift_remove(name);
+        }
+    }
+
+    /// Drops the ignored advisories from analyzed dependencies
+    pub fn remove_ignored_advisories(&self, deps: &mut AnalyzedDependencies) {
+        if self.ignore_advisories.is_empty() {
+            return;
+        }
+
+        let is_ignored = |advisory: &Advisory| {
+            self.ignore_advisories
+                .iter()
+                .any(|id| id == advisory.id().as_str())
+        };
+        for dep in deps
+            .main
+            .values_mut()
+            .chain(deps.dev.values_mut())
+            .chain(deps.build.values_mut())
+        {
+            dep.vulnerabilities.retain(|advisory| !is_ignored(advisory));
+            dep.informational.retain(|advisory| !is_ignored(advisory));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,7 +428,7 @@ mod tests {
 
         for (input, expected) in &paths {
             let repo = RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap();
-            let out = repo.to_usercontent_file_url(RelativePath::new(input));
+            let out = repo.to_usercontent_file_url(RelativePath::new(input), None);
 
             let exp = format!(
                 "https://raw.githubusercontent.com/deps-rs/deps.rs/HEAD/{}",
@@ -176,7 +439,7 @@ mod tests {
 
         for (input, expected) in &paths {
             let repo = RepoPath::from_parts("gitlab", "deps-rs", "deps.rs").unwrap();
-            let out = repo.to_usercontent_file_url(RelativePath::new(input));
+            let out = repo.to_usercontent_file_url(RelativePath::new(input), None);
 
             let exp = format!("https://gitlab.com/deps-rs/deps.rs/raw/HEAD/{}", expected);
             assert_eq!(out.to_string(), exp);
@@ -184,7 +447,7 @@ mod tests {
 
         for (input, expected) in &paths {
             let repo = RepoPath::from_parts("bitbucket", "deps-rs", "deps.rs").unwrap();
-            let out = repo.to_usercontent_file_url(RelativePath::new(input));
+            let out = repo.to_usercontent_file_url(RelativePath::new(input), None);
 
             let exp = format!(
                 "https://bitbucket.org/deps-rs/deps.rs/raw/HEAD/{}",
@@ -193,4 +456,135 @@ mod tests {
             assert_eq!(out.to_string(), exp);
         }
     }
+
+    #[test]
+    fn recognizes_repo_urls() {
+        let repo = RepoPath::from_url("https://github.com/deps-rs/deps.rs.git").unwrap();
+        assert_eq!(
+            repo,
+            RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap()
+        );
+
+        let repo = RepoPath::from_url("https://gitlab.com/deps-rs/deps.rs/-/tree/main").unwrap();
+        assert_eq!(
+            repo,
+            RepoPath::from_parts("gitlab", "deps-rs", "deps.rs").unwrap()
+        );
+
+        assert_eq!(RepoPath::from_url("https://github.com/deps-rs"), None);
+        assert_eq!(
+            RepoPath::from_url("https://git.example.com/deps-rs/deps.rs"),
+            None
+        );
+        assert_eq!(
+            RepoPath::from_url("git@github.com:deps-rs/deps.rs.git"),
+            None
+        );
+    }
+
+    #[test]
+    fn correct_source_url_generation() {
+        let path = RelativePath::new("/libs/badge/Cargo.toml");
+
+        let repo = RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_source_file_url(path, None, Some(12)),
+            "https://github.com/deps-rs/deps.rs/blob/HEAD/libs/badge/Cargo.toml#L12"
+        );
+        assert_eq!(
+            repo.to_source_file_url(path, None, None),
+            "https://github.com/deps-rs/deps.rs/blob/HEAD/libs/badge/Cargo.toml"
+        );
+
+        let repo = RepoPath::from_parts("gitlab", "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_source_file_url(path, None, Some(12)),
+            "https://gitlab.com/deps-rs/deps.rs/-/blob/HEAD/libs/badge/Cargo.toml#L12"
+        );
+
+        let repo = RepoPath::from_parts("bitbucket", "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_source_file_url(path, None, Some(12)),
+            "https://bitbucket.org/deps-rs/deps.rs/src/HEAD/libs/badge/Cargo.toml#lines-12"
+        );
+    }
+
+    #[test]
+    fn urls_at_git_ref() {
+        let path = RelativePath::new("Cargo.toml");
+        let git_ref: GitRef = "release/1.x".parse().unwrap();
+
+        let repo = RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_usercontent_file_url(path, Some(&git_ref)),
+            "https://raw.githubusercontent.com/deps-rs/deps.rs/release/1.x/Cargo.toml"
+        );
+        assert_eq!(
+            repo.to_source_file_url(path, Some(&git_ref), None),
+            "https://github.com/deps-rs/deps.rs/blob/release/1.x/Cargo.toml"
+        );
+
+        let repo = RepoPath::from_parts("gitlab", "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_usercontent_file_url(path, Some(&git_ref)),
+            "https://gitlab.com/deps-rs/deps.rs/raw/release/1.x/Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn git_ref_validation() {
+        for valid in &["main", "v1.2.3", "release/1.x", "4f2a9c1"] {
+            assert!(valid.parse::<GitRef>().is_ok(), "{}", valid);
+        }
+        for invalid in &["", "../main", "-main", "main/", "a b", "a?b", "a//b"] {
+            assert!(invalid.parse::<GitRef>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn custom_raw_urls() {
+        let raw_base: RawBase = "https://git.example.com/{qual}/{name}/plain/"
+            .parse()
+            .unwrap();
+        assert_eq!(raw_base.host(), "git.example.com");
+
+        let repo = RepoPath::custom(raw_base, "deps-rs", "deps.rs").unwrap();
+        assert_eq!(
+            repo.to_usercontent_file_url(RelativePath::new("/libs/badge/Cargo.toml"), None),
+            "https://git.example.com/deps-rs/deps.rs/plain/libs/badge/Cargo.toml"
+        );
+
+        let raw_base: RawBase = "https://git.example.com/{qual}/{name}.git/plain/{path}?h={ref}"
+            .parse()
+            .unwrap();
+        let repo = RepoPath::custom(raw_base, "deps-rs", "deps.rs").unwrap();
+        let git_ref: GitRef = "main".parse().unwrap();
+        assert_eq!(
+            repo.to_usercontent_file_url(RelativePath::new("Cargo.toml"), Some(&git_ref)),
+            "https://git.example.com/deps-rs/deps.rs.git/plain/Cargo.toml?h=main"
+        );
+
+        assert!("ftp://git.example.com/{qual}/{name}/"
+            .parse::<RawBase>()
+            .is_err());
+        assert!("https://git.example.com/plain/".parse::<RawBase>().is_err());
+        assert!("https://user@git.example.com/{qual}/{name}/"
+            .parse::<RawBase>()
+            .is_err());
+    }
+
+    #[test]
+    fn sourceforge_urls() {
+        let path = RelativePath::new("/libs/badge/Cargo.toml");
+        let repo = RepoPath::from_parts("sourceforge", "deps-rs", "code").unwrap();
+
+        assert_eq!(
+            repo.to_usercontent_file_url(path, None),
+            "https://sourceforge.net/p/deps-rs/code/ci/HEAD/tree/libs/badge/Cargo.toml?format=raw"
+        );
+        assert_eq!(
+            repo.to_source_file_url(path, None, Some(12)),
+            "https://sourceforge.net/p/deps-rs/code/ci/HEAD/tree/libs/badge/Cargo.toml#l12"
+        );
+    }
 }
@@ -1 +1,3 @@
-pub mod manifest;
+pub use deps_rs_core::manifest;
+
+pub mod repo_config;
@@ -0,0 +1,94 @@
+use anyhow::{ensure, Error};
+use badge::BadgeStyle;
+use relative_path::RelativePathBuf;
+use serde::Deserialize;
+
+use crate::models::crates::CrateName;
+use crate::models::repo::RepoConfig;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct DepsRsToml {
+    ignore: Vec<CrateName>,
+    ignore_advisories: Vec<String>,
+    badge_style: Option<BadgeStyle>,
+    manifests: Vec<RelativePathBuf>,
+    webhook_token_sha256: Option<String>,
+}
+
+/// Parses the `.deps-rs.toml` of a repository
+pub fn parse_repo_config(toml_str: &str) -> Result<RepoConfig, Error> {
+    let raw: DepsRsToml = toml::from_str(toml_str)?;
+
+    let mut manifests = Vec::with_capacity(raw.manifests.len());
+    for path in raw.manifests {
+        let path = path.normalize();
+        ensure!(
+            !path.starts_with(".."),
+            "manifest path {} is outside of the repository",
+            path
+        );
+        if !manifests.contains(&path) {
+            manifests.push(path);
+        }
+    }
+
+    if let Some(ref hash) = raw.webhook_token_sha256 {
+        ensure!(
+            hash.len() == 64 && hash.bytes().all(|byte| byte.is_ascii_hexdigit()),
+            "webhook-token-sha256 must be a hex-encoded SHA-256 hash"
+        );
+    }
+
+    Ok(RepoConfig {
+        ignore: raw.ignore,
+        ignore_advisories: raw.ignore_advisories,
+        badge_style: raw.badge_style,
+        manifests,
+        webhook_token_sha256: raw
+            .webhook_token_sha256
+            .map(|hash| hash.to_ascii_lowercase()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_repo_config_with_all_settings() {
+        let config = parse_repo_config(
+            r#"
+            ignore = ["openssl"]
+            ignore-advisories = ["RUSTSEC-2020-0071"]
+            badge-style = "flat-square"
+            manifests = ["crates/cli", "./crates/core/", "crates/cli"]
+            webhook-token-sha256 = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.ignore, vec!["openssl".parse().unwrap()]);
+        assert_eq!(config.ignore_advisories, vec!["RUSTSEC-2020-0071"]);
+        assert_eq!(config.badge_style, Some(BadgeStyle::FlatSquare));
+        assert_eq!(
+            config.manifests,
+            vec![
+                RelativePathBuf::from("crates/cli"),
+                RelativePathBuf::from("crates/core")
+            ]
+        );
+        assert_eq!(
+            config.webhook_token_sha256.as_deref(),
+            Some("9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08")
+        );
+    }
+
+    #[test]
+    fn reject_invalid_repo_config() {
+        assert_eq!(parse_repo_config("").unwrap(), RepoConfig::default());
+        assert!(parse_repo_config("ignored = [\"openssl\"]").is_err());
+        assert!(parse_repo_config("manifests = [\"../other\"]").is_err());
+        assert!(parse_repo_config("webhook-token-sha256 = \"secret\"").is_err());
+    }
+}
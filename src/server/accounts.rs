@@ -0,0 +1,216 @@
+//! Optional logins with GitHub or GitLab accounts, for watchlists. Logged-in users are
+//! remembered through a signed session cookie, so there is no session state on the server.
+//!
+//! The cookies are `SameSite=Lax`, which keeps other sites from submitting the watchlist and
+//! logout forms on behalf of a user.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use hyper::header::{HeaderValue, COOKIE};
+use hyper::{Body, Request};
+use sha2::Sha256;
+
+use crate::interactors::oauth::{OauthApp, OauthProvider, OauthUser};
+use crate::utils::time::unix_secs;
+
+use super::SELF_BASE_URL;
+
+/// Cookie holding the logged-in user
+pub const SESSION_COOKIE: &str = "deps_rs_session";
+/// Cookie holding the `state` of a login in progress, which the provider must send back
+pub const STATE_COOKIE: &str = "deps_rs_oauth_state";
+
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(30 * 86400);
+pub const STATE_LIFETIME: Duration = Duration::from_secs(600);
+
+/// A logged-in user. Users are told apart by their ID with the provider, as their login can be
+/// renamed and then taken by someone else; the login is only shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct User {
+    pub provider: OauthProvider,
+    pub id: u64,
+    pub login: String,
+}
+
+impl User {
+    pub fn new(provider: OauthProvider, user: OauthUser) -> User {
+        User {
+            provider,
+            id: user.id,
+            login: user.login,
+        }
+    }
+
+    /// Identifies the user across logins, like `github:583231`
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.provider.as_ref(), self.id)
+    }
+
+    /// Encodes the user for the session cookie, like `github:583231:octocat`
+    fn encode(&self) -> String {
+        format!("{}:{}", self.key(), self.login)
+    }
+
+    fn decode(data: &str) -> Option<User> {
+        let mut parts = data.splitn(3, ':');
+        Some(User {
+            provider: parts.next()?.parse().ok()?,
+            id: parts.next()?.parse().ok()?,
+            login: parts.next()?.to_owned(),
+        })
+    }
+}
+
+/// The OAuth apps users can log in with and the secret session cookies are signed with
+#[derive(Clone, Debug)]
+pub struct Accounts {
+    pub apps: Vec<OauthApp>,
+    pub session_secret: Vec<u8>,
+}
+
+impl Accounts {
+    pub fn app(&self, provider: OauthProvider) -> Option<&OauthApp> {
+        self.apps.iter().find(|app| app.provider() == provider)
+    }
+
+    pub fn providers(&self) -> Vec<OauthProvider> {
+        self.apps.iter().map(OauthApp::provider).collect()
+    }
+
+    /// Where the provider sends users back to after they authorized the app
+    pub fn redirect_uri(provider: OauthProvider) -> String {
+        format!(
+            "{}/login/{}/callback",
+            &SELF_BASE_URL as &str,
+            provider.as_ref()
+        )
+    }
+
+    fn mac(&self, data: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.session_secret).expect("HMAC takes any key");
+        mac.update(data.as_bytes());
+        mac
+    }
+
+    /// Creates an unguessable value for the `state` of a new login. Only the browser the login
+    /// started in has it in its cookie, so nobody can slip their own login into another
+    /// user's browser.
+    pub fn new_state(&self) -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let mac = self.mac(&format!("state|{}|{}", nanos, count));
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Creates the session cookie value of `user`
+    pub fn session(&self, user: &User, now: SystemTime) -> String {
+        let expires = unix_secs(now + SESSION_LIFETIME);
+        let data = format!("{}|{}", user.encode(), expires);
+        let signature = hex::encode(self.mac(&data).finalize().into_bytes());
+
+        format!("{}|{}", data, signature)
+    }
+
+    /// Returns the user of a session cookie value, unless it was tampered with or expired
+    pub fn verify_session(&self, session: &str, now: SystemTime) -> Option<User> {
+        let (data, signature) = session.rsplit_once('|')?;
+        let (user, expires) = data.rsplit_once('|')?;
+
+        let signature = hex::decode(signature).ok()?;
+        self.mac(data).verify_slice(&signature).ok()?;
+        if expires.parse::<u64>().ok()? <= unix_secs(now) {
+            return None;
+        }
+
+        User::decode(user)
+    }
+
+    /// Returns the user logged in with a request, if any
+    pub fn user(&self, req: &Request<Body>) -> Option<User> {
+        let session = cookie(req, SESSION_COOKIE)?;
+        self.verify_session(session, SystemTime::now())
+    }
+}
+
+/// Finds a cookie sent with a request
+pub fn cookie<'a>(req: &'a Request<Body>, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Creates a `Set-Cookie` header value, which removes the cookie if `max_age` is zero. Cookies
+/// are only sent over HTTPS if deps.rs is served over it.
+pub fn set_cookie(name: &str, value: &str, max_age: Duration) -> HeaderValue {
+    let secure = if SELF_BASE_URL.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    let cookie = format!(
+        "{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax{}",
+        name,
+        value,
+        max_age.as_secs(),
+        secure
+    );
+
+    HeaderValue::from_str(&cookie).expect("cookie values are visible ASCII")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_sessions() {
+        let accounts = Accounts {
+            apps: Vec::new(),
+            session_secret: b"It's a Secret to Everybody".to_vec(),
+        };
+        let now = SystemTime::now();
+        let user = User {
+            provider: OauthProvider::Github,
+            id: 583231,
+            login: "octocat".to_owned(),
+        };
+
+        let session = accounts.session(&user, now);
+        assert_eq!(accounts.verify_session(&session, now), Some(user));
+        assert_eq!(
+            accounts.verify_session(&session.replace("583231", "583232"), now),
+            None
+        );
+        assert_eq!(
+            accounts.verify_session(&session, now + SESSION_LIFETIME),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_cookies() {
+        let req = Request::builder()
+            .header(COOKIE, "theme=dark; deps_rs_session=github:octocat|1|00")
+            .body(Body::empty())
+            .unwrap();
+
+        assert_eq!(cookie(&req, SESSION_COOKIE), Some("github:octocat|1|00"));
+        assert_eq!(cookie(&req, STATE_COOKIE), None);
+    }
+}
@@ -0,0 +1,20 @@
+//! Versioned JSON API, described by an OpenAPI document served at `/api/openapi.json`.
+
+use hyper::{
+    header::{CACHE_CONTROL, CONTENT_TYPE},
+    Body, Response,
+};
+
+pub mod v1;
+
+/// The OpenAPI document. It is maintained by hand, tests make sure that it matches what the
+/// API actually returns.
+const OPENAPI_SPEC: &str = include_str!("openapi.json");
+
+pub fn openapi_response() -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .header(CACHE_CONTROL, "public, max-age=3600")
+        .body(Body::from(OPENAPI_SPEC))
+        .unwrap()
+}
@@ -0,0 +1,280 @@
+//! Version 1 of the JSON API, also served by the `status.json` routes.
+//!
+//! The response has an `aggregate` block with the counts summed over all crates and a
+//! `members` object keyed by crate name. Each member has its `manifest_path` (only set for
+//! repositories), its own `counts` and its `dependencies`, `dev_dependencies` and
+//! `build_dependencies` keyed by crate name. The `status` of a dependency is one of
+//! `up-to-date`, `outdated`, `insecure`, `unavailable` (removed from or fully yanked on
+//! crates.io), `yanked` (the requirement only allows yanked releases), `patched` (overridden
+//! in `[patch.crates-io]`, not counted as outdated) or `external-registry` (from a registry
+//! whose index isn't available, never counted as outdated). The `outdated` and `insecure`
+//! flags are set independently of it, so an insecure dependency can also be outdated.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use hyper::{header::CONTENT_TYPE, Body, Response, StatusCode};
+use indexmap::IndexMap;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{format_snapshot_id, AnalyzeDependenciesOutcome, Snapshot};
+use crate::models::crates::{AnalyzedDependencies, AnalyzedDependency, CrateName, DepScope};
+use crate::server::views;
+
+#[derive(Debug, Default, Serialize)]
+struct Counts {
+    total: usize,
+    outdated: usize,
+    insecure: usize,
+    unavailable: usize,
+    dev_outdated: usize,
+    dev_insecure: usize,
+}
+
+impl Counts {
+    fn new(deps: &AnalyzedDependencies) -> Counts {
+        Counts {
+            total: deps.count_total(DepScope::STATUS),
+            outdated: deps.count_outdated(DepScope::STATUS),
+            insecure: deps.count_insecure(DepScope::STATUS),
+            unavailable: deps
+                .main
+                .values()
+                .chain(deps.dev.values())
+                .chain(deps.build.values())
+                .filter(|dep| dep.is_unavailable())
+                .count(),
+            dev_outdated: deps.count_outdated(DepScope::DEV),
+            dev_insecure: deps.count_insecure(DepScope::DEV),
+        }
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.total += other.total;
+        self.outdated += other.outdated;
+        self.insecure += other.insecure;
+        self.unavailable += other.unavailable;
+        self.dev_outdated += other.dev_outdated;
+        self.dev_insecure += other.dev_insecure;
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Dependency<'a> {
+    required: String,
+    latest_that_matches: Option<&'a Version>,
+    /// Version in `Cargo.lock`, only set for `?locked=true` analyses
+    locked: Option<&'a Version>,
+    latest: Option<&'a Version>,
+    /// SPDX license expression, if known
+    license: Option<&'a str>,
+    status: &'static str,
+    outdated: bool,
+    insecure: bool,
+    /// Alternative registry the crate comes from
+    registry: Option<&'a str>,
+    /// Whether the project overrides the crate in `[patch.crates-io]`
+    patched: bool,
+    optional: bool,
+    target: Option<&'a str>,
+    deprecated: bool,
+    replacement: Option<&'a str>,
+    advisories: Vec<&'a str>,
+}
+
+impl<'a> Dependency<'a> {
+    fn new(dep: &'a AnalyzedDependency) -> Dependency<'a> {
+        Dependency {
+            required: dep.required.to_string(),
+            latest_that_matches: dep.latest_that_matches.as_ref(),
+            locked: dep.locked.as_ref(),
+            latest: dep.latest.as_ref(),
+            license: dep.license.as_deref(),
+            status: views::dependency_status(dep),
+            outdated: dep.is_outdated(),
+            insecure: dep.is_insecure(),
+            registry: dep.registry.as_deref(),
+            patched: dep.patched,
+            optional: dep.usage.as_ref().map_or(false, |usage| usage.optional),
+            target: dep.usage.as_ref().and_then(|usage| usage.target.as_deref()),
+            deprecated: dep.deprecation.is_some(),
+            replacement: dep
+                .deprecation
+                .as_ref()
+                .and_then(|deprecation| deprecation.replacement.as_deref()),
+            advisories: dep
+                .vulnerabilities
+                .iter()
+                .map(|advisory| advisory.id().as_str())
+                .collect(),
+        }
+    }
+}
+
+fn dependencies(deps: &IndexMap<CrateName, AnalyzedDependency>) -> IndexMap<&str, Dependency<'_>> {
+    deps.iter()
+        .map(|(name, dep)| (name.as_ref(), Dependency::new(dep)))
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+struct Member<'a> {
+    manifest_path: Option<&'a str>,
+    counts: Counts,
+    dependencies: IndexMap<&'a str, Dependency<'a>>,
+    dev_dependencies: IndexMap<&'a str, Dependency<'a>>,
+    build_dependencies: IndexMap<&'a str, Dependency<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Status<'a> {
+    aggregate: Counts,
+    members: IndexMap<&'a str, Member<'a>>,
+}
+
+fn status(outcome: &AnalyzeDependenciesOutcome) -> Status<'_> {
+    let mut aggregate = Counts::default();
+
+    let members = outcome
+        .crates
+        .iter()
+        .map(|(name, deps)| {
+            let counts = Counts::new(deps);
+            aggregate.add(&counts);
+
+            let member = Member {
+                manifest_path: outcome.manifest_paths.get(name).map(|path| path.as_str()),
+                counts,
+                dependencies: dependencies(&deps.main),
+                dev_dependencies: dependencies(&deps.dev),
+                build_dependencies: dependencies(&deps.build),
+            };
+
+            (name.as_ref(), member)
+        })
+        .collect();
+
+    Status { aggregate, members }
+}
+
+pub fn response(analysis_outcome: Option<&AnalyzeDependenciesOutcome>) -> Response<Body> {
+    let (status_code, body) = match analysis_outcome {
+        Some(outcome) => (StatusCode::OK, serde_json::to_string(&status(outcome))),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::to_string(&serde_json::json!({ "error": "analysis failed" })),
+        ),
+    };
+
+    Response::builder()
+        .status(status_code)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.expect("status serialization cannot fail")))
+        .unwrap()
+}
+
+/// A snapshot of the dependency counts of a repository
+#[derive(Debug, Serialize)]
+struct HistoryEntry {
+    /// RFC 3339 timestamp of the analysis
+    time: String,
+    total: usize,
+    outdated: usize,
+    insecure: usize,
+    /// ID of the frozen analysis, for its permalink
+    snapshot: Option<String>,
+}
+
+impl HistoryEntry {
+    fn new(snapshot: &Snapshot) -> HistoryEntry {
+        HistoryEntry {
+            time: views::feed::rfc3339(UNIX_EPOCH + Duration::from_secs(snapshot.time)),
+            total: snapshot.total,
+            outdated: snapshot.outdated,
+            insecure: snapshot.insecure,
+            snapshot: snapshot.id.map(format_snapshot_id),
+        }
+    }
+}
+
+/// Serves the daily snapshots of a repository's dependency counts, oldest first
+pub fn history_response(snapshots: &[Snapshot]) -> Response<Body> {
+    let history = serde_json::json!({
+        "snapshots": snapshots.iter().map(HistoryEntry::new).collect::<Vec<_>>(),
+    });
+
+    Response::builder()
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(history.to_string()))
+        .unwrap()
+}
+
+/// Body of the requests registering and removing webhooks
+#[derive(Debug, Deserialize)]
+pub struct WebhookRequest {
+    pub site: String,
+    pub qual: String,
+    pub name: String,
+    pub url: String,
+    /// Token whose hash the repository's `.deps-rs.toml` sets, unless the owner is logged in
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Responds to a webhook request, with an error message unless it succeeded
+pub fn webhook_response(status_code: StatusCode, error: Option<&str>) -> Response<Body> {
+    let body = match error {
+        Some(error) => Body::from(serde_json::json!({ "error": error }).to_string()),
+        None => Body::empty(),
+    };
+
+    Response::builder()
+        .status(status_code)
+        .header(CONTENT_TYPE, "application/json")
+        .body(body)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    fn spec_properties(spec: &Value, schema: &str) -> BTreeSet<String> {
+        spec["components"]["schemas"][schema]["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn fields(value: Value) -> BTreeSet<String> {
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[test]
+    fn openapi_spec_matches_responses() {
+        let spec: Value = serde_json::from_str(crate::server::api::OPENAPI_SPEC).unwrap();
+
+        let dep = AnalyzedDependency::new("1.0".parse().unwrap());
+        let dependency = serde_json::to_value(Dependency::new(&dep)).unwrap();
+        assert_eq!(fields(dependency), spec_properties(&spec, "Dependency"));
+
+        let counts = serde_json::to_value(Counts::default()).unwrap();
+        assert_eq!(fields(counts), spec_properties(&spec, "Counts"));
+
+        let snapshot = Snapshot {
+            time: 0,
+            total: 0,
+            outdated: 0,
+            insecure: 0,
+            id: None,
+        };
+        let entry = serde_json::to_value(HistoryEntry::new(&snapshot)).unwrap();
+        assert_eq!(fields(entry), spec_properties(&spec, "Snapshot"));
+    }
+}
@@ -9,4 +9,15 @@ pub const STATIC_STYLE_CSS_ETAG: &str = concat!(
     include_str!(concat!(env!("OUT_DIR"), "/style.css.sha1")),
     "\""
 );
+pub static STATIC_STATUS_JS: &[u8] = include_bytes!("../../assets/scripts/status.js");
+pub const STATIC_STATUS_JS_PATH: &str = concat!(
+    "/static/status.",
+    include_str!(concat!(env!("OUT_DIR"), "/status.js.sha1")),
+    ".js"
+);
+pub const STATIC_STATUS_JS_ETAG: &str = concat!(
+    "\"",
+    include_str!(concat!(env!("OUT_DIR"), "/status.js.sha1")),
+    "\""
+);
 pub static STATIC_FAVICON: &[u8] = include_bytes!("../../assets/logo.svg");
@@ -0,0 +1,298 @@
+//! GitHub App mode: the app receives push and pull request events of the repositories it is
+//! installed in and reports the analysis of the pushed commits as commit statuses.
+
+use anyhow::{ensure, Context as _};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use slog::{error, Logger};
+
+use crate::engine::{AnalyzeDependenciesOutcome, Engine, RepoAnalysisOptions};
+use crate::interactors::github::{CommitState, CommitStatus, GithubApp};
+use crate::models::crates::DepScope;
+use crate::models::repo::{GitRef, RepoPath};
+
+use super::SELF_BASE_URL;
+
+const STATUS_CONTEXT: &str = "deps.rs";
+
+/// The GitHub App and the secret its webhook deliveries are signed with
+#[derive(Clone, Debug)]
+pub struct GithubIntegration {
+    pub app: GithubApp,
+    pub webhook_secret: Vec<u8>,
+}
+
+impl GithubIntegration {
+    /// Checks the `X-Hub-Signature-256` header of a webhook delivery against its body
+    pub fn verify_signature(&self, signature: &str, body: &[u8]) -> bool {
+        verify_signature(&self.webhook_secret, signature, body)
+    }
+}
+
+fn verify_signature(secret: &[u8], signature: &str, body: &[u8]) -> bool {
+    let signature = match signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| hex::decode(hex).ok())
+    {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct EventOwner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct EventRepo {
+    name: String,
+    owner: EventOwner,
+}
+
+impl EventRepo {
+    fn repo_path(&self) -> anyhow::Result<RepoPath> {
+        RepoPath::from_parts("github", &self.owner.login, &self.name)
+    }
+}
+
+#[derive(Deserialize)]
+struct PushEvent {
+    after: String,
+    #[serde(default)]
+    deleted: bool,
+    repository: EventRepo,
+    installation: Option<Installation>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestEvent {
+    action: String,
+    pull_request: PullRequest,
+    repository: EventRepo,
+    installation: Option<Installation>,
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    head: PullRequestHead,
+}
+
+#[derive(Deserialize)]
+struct PullRequestHead {
+    sha: String,
+    /// Unset if the fork the pull request comes from has been deleted
+    repo: Option<EventRepo>,
+}
+
+/// A commit to analyze and report the status of
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitCheck {
+    installation_id: u64,
+    /// Repository the status is reported to
+    repo_path: RepoPath,
+    /// Repository the commit is analyzed in, the fork for pull requests from forks
+    source_path: RepoPath,
+    sha: GitRef,
+}
+
+/// Finds the commit to check in a webhook delivery, if it is about one. Only pushes of new
+/// commits and opened or updated pull requests are checked.
+pub fn parse_event(event: &str, body: &[u8]) -> anyhow::Result<Option<CommitCheck>> {
+    let (installation, repository, source, sha) = match event {
+        "push" => {
+            let push: PushEvent = serde_json::from_slice(body)?;
+            if push.deleted || push.after.bytes().all(|b| b == b'0') {
+                return Ok(None);
+            }
+            (push.installation, push.repository, None, push.after)
+        }
+        "pull_request" => {
+            let pr: PullRequestEvent = serde_json::from_slice(body)?;
+            if !matches!(pr.action.as_str(), "opened" | "synchronize" | "reopened") {
+                return Ok(None);
+            }
+            let head = pr.pull_request.head;
+            (pr.installation, pr.repository, head.repo, head.sha)
+        }
+        _ => return Ok(None),
+    };
+
+    let installation = installation.context("event is not from an app installation")?;
+    ensure!(
+        sha.len() == 40 && sha.bytes().all(|b| b.is_ascii_hexdigit()),
+        "invalid commit SHA"
+    );
+
+    let repo_path = repository.repo_path()?;
+    let source_path = match source {
+        Some(source) => source.repo_path()?,
+        None => repo_path.clone(),
+    };
+
+    Ok(Some(CommitCheck {
+        installation_id: installation.id,
+        repo_path,
+        source_path,
+        sha: sha.parse()?,
+    }))
+}
+
+/// Marks the commit as pending, analyzes it and reports the result. Insecure dependencies make
+/// the check fail, outdated ones are only mentioned in the description.
+pub async fn check_commit(
+    github: GithubIntegration,
+    engine: Engine,
+    check: CommitCheck,
+    logger: Logger,
+) {
+    let target_url = format!(
+        "{}/repo/github/{}/{}?ref={}",
+        &SELF_BASE_URL as &str,
+        check.source_path.qual.as_ref(),
+        check.source_path.name.as_ref(),
+        check.sha.as_ref()
+    );
+    let status = |state, description: String| CommitStatus {
+        state,
+        target_url: target_url.clone(),
+        description,
+        context: STATUS_CONTEXT,
+    };
+
+    let pending = status(CommitState::Pending, "Analyzing dependencies".to_owned());
+    if let Err(err) = post_status(&github, &check, &pending).await {
+        error!(logger, "failed to set commit status: {}", err);
+        return;
+    }
+
+    let options = RepoAnalysisOptions {
+        git_ref: Some(check.sha.clone()),
+        ..RepoAnalysisOptions::default()
+    };
+    let result = engine
+        .background()
+        .analyze_repo_dependencies(check.source_path.clone(), options)
+        .await;
+
+    let status = match result {
+        Ok(outcome) => {
+            let state = if outcome.any_insecure() {
+                CommitState::Failure
+            } else {
+                CommitState::Success
+            };
+            status(state, describe(&outcome))
+        }
+        Err(err) => {
+            error!(logger, "error: {}", err);
+            status(
+                CommitState::Error,
+                "Could not analyze dependencies".to_owned(),
+            )
+        }
+    };
+    if let Err(err) = post_status(&github, &check, &status).await {
+        error!(logger, "failed to set commit status: {}", err);
+    }
+}
+
+async fn post_status(
+    github: &GithubIntegration,
+    check: &CommitCheck,
+    status: &CommitStatus,
+) -> anyhow::Result<()> {
+    github
+        .app
+        .create_commit_status(
+            check.installation_id,
+            &check.repo_path,
+            check.sha.as_ref(),
+            status,
+        )
+        .await
+}
+
+fn describe(outcome: &AnalyzeDependenciesOutcome) -> String {
+    let (outdated, total) = outcome.outdated_ratio(DepScope::STATUS);
+    let insecure = outcome.count_insecure(DepScope::STATUS);
+
+    match (outdated, insecure) {
+        (0, 0) => "All dependencies are up to date".to_owned(),
+        (_, 0) => format!("{} of {} dependencies outdated", outdated, total),
+        (0, _) => format!("{} insecure dependencies", insecure),
+        (_, _) => format!(
+            "{} of {} dependencies outdated, {} insecure",
+            outdated, total, insecure
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "6dcb09b5b57875f334f61aebed695e2e4193db5e";
+
+    #[test]
+    fn parses_pull_request_from_fork() {
+        let body = format!(
+            r#"{{
+                "action": "synchronize",
+                "pull_request": {{
+                    "head": {{
+                        "sha": "{}",
+                        "repo": {{ "name": "deps.rs", "owner": {{ "login": "contributor" }} }}
+                    }}
+                }},
+                "repository": {{ "name": "deps.rs", "owner": {{ "login": "deps-rs" }} }},
+                "installation": {{ "id": 42 }}
+            }}"#,
+            SHA
+        );
+
+        let check = parse_event("pull_request", body.as_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(check.installation_id, 42);
+        assert_eq!(check.repo_path.qual.as_ref(), "deps-rs");
+        assert_eq!(check.source_path.qual.as_ref(), "contributor");
+        assert_eq!(check.sha.as_ref(), SHA);
+    }
+
+    #[test]
+    fn ignores_deleted_branches() {
+        let body = r#"{
+            "after": "0000000000000000000000000000000000000000",
+            "deleted": true,
+            "repository": { "name": "deps.rs", "owner": { "login": "deps-rs" } },
+            "installation": { "id": 42 }
+        }"#;
+
+        assert_eq!(parse_event("push", body.as_bytes()).unwrap(), None);
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let secret = b"It's a Secret to Everybody";
+
+        // example from GitHub's documentation on validating webhook deliveries
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(verify_signature(secret, signature, b"Hello, World!"));
+        assert!(!verify_signature(secret, signature, b"Hello, World?"));
+        assert!(!verify_signature(secret, "sha1=757107ea", b"Hello, World!"));
+    }
+}
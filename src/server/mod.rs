@@ -1,58 +1,230 @@
-use std::{env, sync::Arc, time::Instant};
+use std::{
+    env,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
+use badge::{BadgeStyle, BadgeVariant};
 use futures::future;
 use hyper::{
-    header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, LOCATION},
+    body::HttpBody as _,
+    header::{
+        HeaderValue, ACCEPT, CACHE_CONTROL, CONTENT_TYPE, ETAG, EXPIRES, IF_NONE_MATCH,
+        LAST_MODIFIED, LOCATION, RETRY_AFTER, SET_COOKIE, VARY,
+    },
     Body, Error as HyperError, Method, Request, Response, StatusCode,
 };
 use once_cell::sync::Lazy;
+use relative_path::RelativePathBuf;
 use route_recognizer::{Params, Router};
 use semver::VersionReq;
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
 use slog::{error, info, o, Logger};
 
+mod accounts;
+mod api;
 mod assets;
+mod github;
+mod rate_limit;
 mod views;
 
-use self::assets::{STATIC_STYLE_CSS_ETAG, STATIC_STYLE_CSS_PATH};
-use crate::engine::{AnalyzeDependenciesOutcome, Engine};
-use crate::models::crates::{CrateName, CratePath};
+pub use self::accounts::Accounts;
+pub use self::github::GithubIntegration;
+pub use self::rate_limit::{Quota, RateLimitConfig, RateLimiter};
+
+use self::accounts::User;
+use self::assets::{
+    STATIC_STATUS_JS_ETAG, STATIC_STATUS_JS_PATH, STATIC_STYLE_CSS_ETAG, STATIC_STYLE_CSS_PATH,
+};
+use self::views::badge::BadgeFormat;
+use self::views::html::i18n::Locale;
+use self::views::html::org::RepoState;
+use crate::config;
+use crate::engine::{
+    AnalyzeDependenciesOutcome, Engine, InvalidWebhook, RepoAnalysisOptions, Watched,
+};
+use crate::interactors::oauth::OauthProvider;
+use crate::models::crates::{CrateName, CratePath, DepScope, VersionLag};
 use crate::models::repo::RepoPath;
+use crate::models::repo::{GitRef, RawBase, RepoQualifier, RepoSite};
 use crate::models::SubjectPath;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum StatusFormat {
     Html,
     Svg,
+    Png,
+    /// Input for the shields.io endpoint badge
+    ShieldJson,
+    /// Badge showing the minimum supported Rust version
+    MsrvSvg,
+    /// Badge summarizing the licenses of the dependencies
+    LicenseSvg,
+    /// Page with the dependency graph of a repository
+    Graph,
+    Json,
+    Markdown,
+    Csv,
+}
+
+impl StatusFormat {
+    /// Returns what is appended to a subject's URL to request this format. Markdown and CSV
+    /// are only available through the `format` query parameter or the `Accept` header.
+    fn path_suffix(self) -> &'static str {
+        match self {
+            StatusFormat::Html | StatusFormat::Markdown | StatusFormat::Csv => "",
+            StatusFormat::Svg => "/status.svg",
+            StatusFormat::Png => "/status.png",
+            StatusFormat::ShieldJson => "/shield.json",
+            StatusFormat::MsrvSvg => "/msrv.svg",
+            StatusFormat::LicenseSvg => "/license.svg",
+            StatusFormat::Graph => "/graph",
+            StatusFormat::Json => "/status.json",
+        }
+    }
+
+    /// Returns the image format for badge formats
+    fn badge_format(self) -> Option<BadgeFormat> {
+        match self {
+            StatusFormat::Svg | StatusFormat::MsrvSvg | StatusFormat::LicenseSvg => {
+                Some(BadgeFormat::Svg)
+            }
+            StatusFormat::Png => Some(BadgeFormat::Png),
+            StatusFormat::ShieldJson => Some(BadgeFormat::ShieldJson),
+            _ => None,
+        }
+    }
+
+    /// Returns the subject of badge formats, shown on their placeholder
+    fn badge_subject(self) -> &'static str {
+        match self {
+            StatusFormat::MsrvSvg => "msrv",
+            StatusFormat::LicenseSvg => "licenses",
+            _ => "dependencies",
+        }
+    }
+
+    fn from_query_value(value: &str) -> Option<StatusFormat> {
+        match value {
+            "html" => Some(StatusFormat::Html),
+            "svg" => Some(StatusFormat::Svg),
+            "png" => Some(StatusFormat::Png),
+            "json" => Some(StatusFormat::Json),
+            "md" | "markdown" => Some(StatusFormat::Markdown),
+            "csv" => Some(StatusFormat::Csv),
+            _ => None,
+        }
+    }
+
+    fn from_media_type(media_type: &str) -> Option<StatusFormat> {
+        match media_type {
+            "text/html" => Some(StatusFormat::Html),
+            "image/svg+xml" => Some(StatusFormat::Svg),
+            "image/png" => Some(StatusFormat::Png),
+            "application/json" => Some(StatusFormat::Json),
+            "text/markdown" => Some(StatusFormat::Markdown),
+            "text/csv" => Some(StatusFormat::Csv),
+            _ => None,
+        }
+    }
+
+    /// Picks the format for a request to a subject's main route, from the `format` query
+    /// parameter or else the first media type in the `Accept` header that we can serve
+    fn negotiate(req: &Request<Body>) -> StatusFormat {
+        #[derive(Deserialize)]
+        struct FormatQuery {
+            format: Option<String>,
+        }
+
+        let from_query = req
+            .uri()
+            .query()
+            .and_then(|qs| serde_urlencoded::from_str::<FormatQuery>(qs).ok())
+            .and_then(|query| query.format)
+            .and_then(|format| StatusFormat::from_query_value(&format));
+
+        let from_accept = || {
+            req.headers()
+                .get(ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .and_then(|accept| {
+                    accept.split(',').find_map(|media_range| {
+                        let media_type = media_range.split(';').next().unwrap_or_default();
+                        StatusFormat::from_media_type(media_type.trim())
+                    })
+                })
+        };
+
+        from_query
+            .or_else(from_accept)
+            .unwrap_or(StatusFormat::Html)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 enum StaticFile {
     StyleCss,
+    StatusJs,
     FaviconPng,
 }
 
 enum Route {
     Index,
+    Sitemap,
     Static(StaticFile),
+    OpenApi,
+    Webhooks,
+    GithubEvent,
     RepoStatus(StatusFormat),
+    /// Status page of a frozen analysis of a repository
+    RepoSnapshot,
+    RepoFeed,
+    RepoCompare,
+    /// History page of a repository, or its JSON if set
+    RepoHistory {
+        json: bool,
+    },
+    OrgStatus,
+    OrgDashboard,
     CrateRedirect,
     CrateStatus(StatusFormat),
+    CrateCompare,
+    CratePrerelease(StatusFormat),
+    Login,
+    LoginCallback,
+    Logout,
+    Watchlist,
 }
 
 #[derive(Clone)]
 pub struct App {
     logger: Logger,
     engine: Engine,
+    /// Set when running as a GitHub App
+    github: Option<GithubIntegration>,
+    /// Set when users can log in to keep a watchlist
+    accounts: Option<Accounts>,
+    rate_limiter: RateLimiter,
     router: Arc<Router<Route>>,
 }
 
 impl App {
-    pub fn new(logger: Logger, engine: Engine) -> App {
+    pub fn new(
+        logger: Logger,
+        engine: Engine,
+        github: Option<GithubIntegration>,
+        accounts: Option<Accounts>,
+        rate_limiter: RateLimiter,
+    ) -> App {
         let mut router = Router::new();
 
         router.add("/", Route::Index);
+        router.add("/sitemap.xml", Route::Sitemap);
 
         router.add(STATIC_STYLE_CSS_PATH, Route::Static(StaticFile::StyleCss));
+        router.add(STATIC_STATUS_JS_PATH, Route::Static(StaticFile::StatusJs));
         router.add("/static/logo.svg", Route::Static(StaticFile::FaviconPng));
 
         router.add(
@@ -63,8 +235,87 @@ impl App {
             "/repo/:site/:qual/:name/status.svg",
             Route::RepoStatus(StatusFormat::Svg),
         );
+        router.add(
+            "/repo/:site/:qual/:name/status.png",
+            Route::RepoStatus(StatusFormat::Png),
+        );
+        router.add(
+            "/repo/:site/:qual/:name/shield.json",
+            Route::RepoStatus(StatusFormat::ShieldJson),
+        );
+        router.add(
+            "/repo/:site/:qual/:name/msrv.svg",
+            Route::RepoStatus(StatusFormat::MsrvSvg),
+        );
+        router.add(
+            "/repo/:site/:qual/:name/license.svg",
+            Route::RepoStatus(StatusFormat::LicenseSvg),
+        );
+        router.add(
+            "/repo/:site/:qual/:name/graph",
+            Route::RepoStatus(StatusFormat::Graph),
+        );
+        router.add(
+            "/repo/:site/:qual/:name/status.json",
+            Route::RepoStatus(StatusFormat::Json),
+        );
+        router.add("/repo/:site/:qual/:name/snapshot/:id", Route::RepoSnapshot);
+        router.add("/repo/:site/:qual/:name/feed.atom", Route::RepoFeed);
+        router.add("/repo/:site/:qual/:name/compare", Route::RepoCompare);
+        router.add(
+            "/repo/:site/:qual/:name/history",
+            Route::RepoHistory { json: false },
+        );
+        router.add(
+            "/repo/:site/:qual/:name/history.json",
+            Route::RepoHistory { json: true },
+        );
+
+        router.add("/org/:site/:qual/status.svg", Route::OrgStatus);
+        router.add("/org/github/:qual", Route::OrgDashboard);
+
+        router.add("/login/:provider", Route::Login);
+        router.add("/login/:provider/callback", Route::LoginCallback);
+        router.add("/logout", Route::Logout);
+        router.add("/watchlist", Route::Watchlist);
+
+        router.add("/api/openapi.json", Route::OpenApi);
+        router.add("/api/v1/webhooks", Route::Webhooks);
+        router.add("/github/events", Route::GithubEvent);
+        router.add(
+            "/api/v1/repo/:site/:qual/:name",
+            Route::RepoStatus(StatusFormat::Json),
+        );
+        router.add(
+            "/api/v1/repo/:site/:qual/:name/history",
+            Route::RepoHistory { json: true },
+        );
+        router.add(
+            "/api/v1/crate/:name/:version",
+            Route::CrateStatus(StatusFormat::Json),
+        );
 
         router.add("/crate/:name", Route::CrateRedirect);
+        router.add(
+            "/crate/:name/pre",
+            Route::CratePrerelease(StatusFormat::Html),
+        );
+        router.add(
+            "/crate/:name/pre/status.svg",
+            Route::CratePrerelease(StatusFormat::Svg),
+        );
+        router.add(
+            "/crate/:name/pre/status.png",
+            Route::CratePrerelease(StatusFormat::Png),
+        );
+        router.add(
+            "/crate/:name/pre/shield.json",
+            Route::CratePrerelease(StatusFormat::ShieldJson),
+        );
+        router.add(
+            "/crate/:name/pre/status.json",
+            Route::CratePrerelease(StatusFormat::Json),
+        );
         router.add(
             "/crate/:name/:version",
             Route::CrateStatus(StatusFormat::Html),
@@ -73,15 +324,40 @@ impl App {
             "/crate/:name/:version/status.svg",
             Route::CrateStatus(StatusFormat::Svg),
         );
+        router.add(
+            "/crate/:name/:version/status.png",
+            Route::CrateStatus(StatusFormat::Png),
+        );
+        router.add(
+            "/crate/:name/:version/shield.json",
+            Route::CrateStatus(StatusFormat::ShieldJson),
+        );
+        router.add(
+            "/crate/:name/:version/license.svg",
+            Route::CrateStatus(StatusFormat::LicenseSvg),
+        );
+        router.add(
+            "/crate/:name/:version/status.json",
+            Route::CrateStatus(StatusFormat::Json),
+        );
+        router.add("/crate/:name/:from/compare/:to", Route::CrateCompare);
 
         App {
             logger,
             engine,
+            github,
+            accounts,
+            rate_limiter,
             router: Arc::new(router),
         }
     }
 
-    pub async fn handle(&self, req: Request<Body>) -> Result<Response<Body>, HyperError> {
+    /// Handles a request that came in over a connection from `remote_ip`
+    pub async fn handle(
+        &self,
+        req: Request<Body>,
+        remote_ip: IpAddr,
+    ) -> Result<Response<Body>, HyperError> {
         let logger = self.logger.new(o!("path" => req.uri().path().to_owned()));
         let logger2 = logger.clone();
         let start = Instant::now();
@@ -89,19 +365,88 @@ impl App {
         // allows `/path/` to also match `/path`
         let normalized_path = req.uri().path().trim_end_matches('/');
 
-        let res = if let Ok(route_match) = self.router.recognize(normalized_path) {
-            match (req.method(), route_match.handler()) {
+        let route_match = self.router.recognize(normalized_path);
+        let rate_limited = match route_match {
+            Ok(ref route_match) if !matches!(route_match.handler(), Route::Static(_)) => self
+                .rate_limiter
+                .check_client(self.rate_limiter.client_ip(&req, remote_ip))
+                .err(),
+            _ => None,
+        };
+
+        let res = if let Some(retry_after) = rate_limited {
+            Ok(too_many_requests(retry_after))
+        } else if let Ok(route_match) = route_match {
+            // the main route of a subject serves whichever format the client asks for
+            let negotiated = match route_match.handler() {
+                Route::RepoStatus(StatusFormat::Html)
+                | Route::CrateStatus(StatusFormat::Html)
+                | Route::CratePrerelease(StatusFormat::Html) => Some(StatusFormat::negotiate(&req)),
+                _ => None,
+            };
+
+            let res = match (req.method(), route_match.handler()) {
                 (&Method::GET, Route::Index) => {
                     self.index(req, route_match.params().clone(), logger).await
                 }
 
+                (&Method::GET, Route::Sitemap) => self.sitemap(logger).await,
+
                 (&Method::GET, Route::RepoStatus(format)) => {
-                    self.repo_status(req, route_match.params().clone(), logger, *format)
+                    let format = negotiated.unwrap_or(*format);
+                    self.repo_status(req, route_match.params().clone(), logger, format)
+                        .await
+                }
+
+                (&Method::GET, Route::RepoSnapshot) => {
+                    self.repo_status(
+                        req,
+                        route_match.params().clone(),
+                        logger,
+                        StatusFormat::Html,
+                    )
+                    .await
+                }
+
+                (&Method::GET, Route::RepoFeed) => {
+                    self.repo_feed(req, route_match.params().clone(), logger)
+                        .await
+                }
+
+                (&Method::GET, Route::RepoCompare) => {
+                    self.repo_compare(req, route_match.params().clone(), logger)
+                        .await
+                }
+
+                (&Method::GET, Route::RepoHistory { json }) => {
+                    self.repo_history(req, route_match.params().clone(), logger, *json)
+                        .await
+                }
+
+                (&Method::GET, Route::OrgStatus) => {
+                    self.org_status(req, route_match.params().clone(), logger)
+                        .await
+                }
+
+                (&Method::GET, Route::OrgDashboard) => {
+                    self.org_dashboard(req, route_match.params().clone(), logger)
                         .await
                 }
 
                 (&Method::GET, Route::CrateStatus(format)) => {
-                    self.crate_status(req, route_match.params().clone(), logger, *format)
+                    let format = negotiated.unwrap_or(*format);
+                    self.crate_status(req, route_match.params().clone(), logger, format)
+                        .await
+                }
+
+                (&Method::GET, Route::CratePrerelease(format)) => {
+                    let format = negotiated.unwrap_or(*format);
+                    self.crate_prerelease(req, route_match.params().clone(), logger, format)
+                        .await
+                }
+
+                (&Method::GET, Route::CrateCompare) => {
+                    self.crate_compare(req, route_match.params().clone(), logger)
                         .await
                 }
 
@@ -112,7 +457,49 @@ impl App {
 
                 (&Method::GET, Route::Static(file)) => Ok(App::static_file(*file)),
 
+                (&Method::GET, Route::OpenApi) => Ok(api::openapi_response()),
+
+                (&Method::POST, Route::Webhooks) | (&Method::DELETE, Route::Webhooks) => {
+                    self.webhook(req, logger).await
+                }
+
+                (&Method::POST, Route::GithubEvent) => self.github_event(req, logger).await,
+
+                (&Method::GET, Route::Login) => Ok(self.login(route_match.params().clone())),
+
+                (&Method::GET, Route::LoginCallback) => {
+                    self.login_callback(req, route_match.params().clone(), logger)
+                        .await
+                }
+
+                (&Method::POST, Route::Logout) => Ok(self.logout()),
+
+                (&Method::GET, Route::Watchlist) => self.watchlist(req).await,
+
+                (&Method::POST, Route::Watchlist) => self.update_watchlist(req, logger).await,
+
                 _ => Ok(not_found()),
+            };
+
+            // pages are translated into the language the client prefers
+            let vary = match route_match.handler() {
+                Route::Index
+                | Route::RepoHistory { json: false }
+                | Route::RepoCompare
+                | Route::OrgDashboard
+                | Route::Watchlist
+                | Route::CrateCompare => Some("Accept-Language"),
+                _ if negotiated.is_some() => Some("Accept, Accept-Language"),
+                _ => None,
+            };
+
+            match (res, vary) {
+                (Ok(mut res), Some(vary)) => {
+                    res.headers_mut()
+                        .insert(VARY, HeaderValue::from_static(vary));
+                    Ok(res)
+                }
+                (res, _) => res,
             }
         } else {
             Ok(not_found())
@@ -137,11 +524,12 @@ impl App {
 impl App {
     async fn index(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
         _params: Params,
         logger: Logger,
     ) -> Result<Response<Body>, HyperError> {
         let engine = self.engine.clone();
+        let locale = Locale::negotiate(&req);
 
         let popular =
             future::try_join(engine.get_popular_repos(), engine.get_popular_crates()).await;
@@ -154,15 +542,46 @@ impl App {
                 *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
                 Ok(response)
             }
-            Ok((popular_repos, popular_crates)) => {
-                Ok(views::html::index::render(popular_repos, popular_crates))
-            }
+            Ok((popular_repos, popular_crates)) => Ok(views::html::index::render(
+                locale,
+                popular_repos,
+                popular_crates,
+                engine.recent_analyses(),
+            )),
         }
     }
 
+    /// Lists the front page and the status pages of the popular and recently analyzed
+    /// repositories and crates. Popular lists that can't be retrieved are left out.
+    async fn sitemap(&self, logger: Logger) -> Result<Response<Body>, HyperError> {
+        let (popular_repos, popular_crates) = future::join(
+            self.engine.get_popular_repos(),
+            self.engine.get_popular_crates(),
+        )
+        .await;
+
+        let popular_repos = popular_repos.unwrap_or_else(|err| {
+            error!(logger, "error: {}", err);
+            Vec::new()
+        });
+        let popular_crates = popular_crates.unwrap_or_else(|err| {
+            error!(logger, "error: {}", err);
+            Vec::new()
+        });
+
+        let subjects: Vec<SubjectPath> = popular_repos
+            .into_iter()
+            .map(|repo| SubjectPath::Repo(repo.path))
+            .chain(popular_crates.into_iter().map(SubjectPath::Crate))
+            .chain(self.engine.recent_analyses())
+            .collect();
+
+        Ok(views::sitemap::response(&subjects))
+    }
+
     async fn repo_status(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
         params: Params,
         logger: Logger,
         format: StatusFormat,
@@ -173,7 +592,12 @@ impl App {
         let qual = params.find("qual").expect("route param 'qual' not found");
         let name = params.find("name").expect("route param 'name' not found");
 
-        let repo_path_result = RepoPath::from_parts(site, qual, name);
+        let extra_config = ExtraConfig::from_query_string(req.uri().query());
+        let locale = Locale::negotiate(&req);
+        let repo_path_result = match (site, &extra_config.raw_base) {
+            ("custom", Some(raw_base)) => App::custom_repo_path(raw_base, qual, name),
+            _ => RepoPath::from_parts(site, qual, name),
+        };
 
         match repo_path_result {
             Err(err) => {
@@ -187,172 +611,1310 @@ impl App {
             }
 
             Ok(repo_path) => {
+                let subject_path = SubjectPath::Repo(repo_path.clone());
+
+                // frozen analyses are served as they were, without analyzing anything
+                let snapshot = params
+                    .find("id")
+                    .map(str::to_owned)
+                    .or_else(|| extra_config.snapshot.clone());
+                if let Some(snapshot) = snapshot {
+                    let frozen = server.engine.frozen_analysis(&repo_path, &snapshot).await;
+                    return Ok(match frozen {
+                        Some(analysis_outcome) => App::conditional_status_response(
+                            &req,
+                            analysis_outcome,
+                            format,
+                            locale,
+                            subject_path,
+                            extra_config,
+                            &RepoAnalysisOptions::default(),
+                        ),
+                        None => not_found(),
+                    });
+                }
+
+                let options = RepoAnalysisOptions {
+                    entry_point: extra_config.path.clone(),
+                    git_ref: extra_config.git_ref.clone(),
+                    locked: extra_config.locked,
+                    deep: extra_config.deep,
+                    include_prerelease: extra_config.include_prerelease,
+                };
+                let needs_analysis = !server.engine.is_cached_only()
+                    && !server
+                        .engine
+                        .has_cached_analysis(&subject_path, &options)
+                        .await;
+                if needs_analysis {
+                    if let Err(retry_after) = server.rate_limiter.check_repo(&repo_path) {
+                        return Ok(too_many_requests(retry_after));
+                    }
+                }
+
+                if format.badge_format().is_some() && needs_analysis {
+                    let engine = server.engine.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = engine.analyze_repo_dependencies(repo_path, options).await
+                        {
+                            error!(logger, "error: {}", err);
+                        }
+                    });
+                    return Ok(views::badge::placeholder_response(
+                        &extra_config,
+                        format.badge_subject(),
+                        format.badge_format().unwrap(),
+                    ));
+                }
+
                 let analyze_result = server
                     .engine
-                    .analyze_repo_dependencies(repo_path.clone())
+                    .analyze_repo_dependencies(repo_path.clone(), options.clone())
                     .await;
 
                 match analyze_result {
                     Err(err) => {
                         error!(logger, "error: {}", err);
-                        let response =
-                            App::status_format_analysis(None, format, SubjectPath::Repo(repo_path));
-                        Ok(response)
-                    }
-                    Ok(analysis_outcome) => {
+
+                        // point to manifests further down if there's none at the root, which
+                        // is only looked up on the default branch
+                        let manifest_dirs = match (format, &options.entry_point, &options.git_ref) {
+                            (StatusFormat::Html, None, None) => server
+                                .engine
+                                .find_manifest_dirs(repo_path)
+                                .await
+                                .unwrap_or_else(|err| {
+                                    error!(logger, "error: {}", err);
+                                    Vec::new()
+                                }),
+                            _ => Vec::new(),
+                        };
+
                         let response = App::status_format_analysis(
-                            Some(analysis_outcome),
+                            None,
                             format,
-                            SubjectPath::Repo(repo_path),
+                            locale,
+                            subject_path,
+                            extra_config,
+                            &options,
+                            &manifest_dirs,
                         );
                         Ok(response)
                     }
+                    Ok(analysis_outcome) => Ok(App::conditional_status_response(
+                        &req,
+                        analysis_outcome,
+                        format,
+                        locale,
+                        subject_path,
+                        extra_config,
+                        &options,
+                    )),
                 }
             }
         }
     }
 
-    async fn crate_redirect(
+    /// Serves the Atom feed of a repository, analyzing it first so the feed is up to date
+    async fn repo_feed(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
         params: Params,
         logger: Logger,
     ) -> Result<Response<Body>, HyperError> {
-        let engine = self.engine.clone();
-
+        let site = params.find("site").expect("route param 'site' not found");
+        let qual = params.find("qual").expect("route param 'qual' not found");
         let name = params.find("name").expect("route param 'name' not found");
-        let crate_name_result = name.parse::<CrateName>();
 
-        match crate_name_result {
+        let extra_config = ExtraConfig::from_query_string(req.uri().query());
+        let repo_path_result = match (site, &extra_config.raw_base) {
+            ("custom", Some(raw_base)) => App::custom_repo_path(raw_base, qual, name),
+            _ => RepoPath::from_parts(site, qual, name),
+        };
+
+        match repo_path_result {
             Err(err) => {
                 error!(logger, "error: {}", err);
                 let mut response = views::html::error::render(
-                    "Could not parse crate name",
-                    "Please make sure to provide a valid crate name.",
+                    "Could not parse repository path",
+                    "Please make sure to provide a valid repository path.",
                 );
                 *response.status_mut() = StatusCode::BAD_REQUEST;
                 Ok(response)
             }
 
-            Ok(crate_name) => {
-                let release_result = engine
-                    .find_latest_crate_release(crate_name, VersionReq::STAR)
-                    .await;
-
-                match release_result {
-                    Err(err) => {
-                        error!(logger, "error: {}", err);
-                        let mut response = views::html::error::render(
-                            "Could not fetch crate information",
-                            "Please make sure to provide a valid crate name.",
-                        );
-                        *response.status_mut() = StatusCode::NOT_FOUND;
-                        Ok(response)
-                    }
-                    Ok(None) => {
-                        let mut response = views::html::error::render(
-                            "Could not fetch crate information",
-                            "Please make sure to provide a valid crate name.",
-                        );
-                        *response.status_mut() = StatusCode::NOT_FOUND;
-                        Ok(response)
-                    }
-                    Ok(Some(release)) => {
-                        let redirect_url = format!(
-                            "{}/crate/{}/{}",
-                            &SELF_BASE_URL as &str,
-                            release.name.as_ref(),
-                            release.version
-                        );
-
-                        let res = Response::builder()
-                            .status(StatusCode::TEMPORARY_REDIRECT)
-                            .header(LOCATION, redirect_url)
-                            .body(Body::empty())
-                            .unwrap();
-
-                        Ok(res)
-                    }
+            Ok(repo_path) => {
+                // only analyses with the default options are recorded in the feed
+                if let Err(err) = self
+                    .engine
+                    .analyze_repo_dependencies(repo_path.clone(), RepoAnalysisOptions::default())
+                    .await
+                {
+                    error!(logger, "error: {}", err);
                 }
+
+                let entries = self.engine.repo_feed(&repo_path).await;
+                Ok(views::feed::response(&entries, &repo_path))
             }
         }
     }
 
-    async fn crate_status(
+    /// Shows how the dependencies of a repository differ between the `base` and `head` refs,
+    /// e.g. to review what a branch changes
+    async fn repo_compare(
         &self,
-        _req: Request<Body>,
+        req: Request<Body>,
         params: Params,
         logger: Logger,
-        format: StatusFormat,
     ) -> Result<Response<Body>, HyperError> {
-        let server = self.clone();
-
+        let site = params.find("site").expect("route param 'site' not found");
+        let qual = params.find("qual").expect("route param 'qual' not found");
         let name = params.find("name").expect("route param 'name' not found");
-        let version = params
-            .find("version")
-            .expect("route param 'version' not found");
 
-        let crate_path_result = CratePath::from_parts(name, version);
+        let extra_config = ExtraConfig::from_query_string(req.uri().query());
+        let repo_path_result = match (site, &extra_config.raw_base) {
+            ("custom", Some(raw_base)) => App::custom_repo_path(raw_base, qual, name),
+            _ => RepoPath::from_parts(site, qual, name),
+        };
 
-        match crate_path_result {
-            Err(err) => {
-                error!(logger, "error: {}", err);
+        let (repo_path, head) = match (repo_path_result, &extra_config.head) {
+            (Ok(repo_path), Some(head)) => (repo_path, head.clone()),
+            (result, _) => {
+                if let Err(err) = result {
+                    error!(logger, "error: {}", err);
+                }
                 let mut response = views::html::error::render(
-                    "Could not parse crate path",
-                    "Please make sure to provide a valid crate name and version.",
+                    "Could not parse repository path or refs",
+                    "Please make sure to provide a valid repository path and a head ref to compare, e.g. ?base=main&head=feature.",
                 );
                 *response.status_mut() = StatusCode::BAD_REQUEST;
-                Ok(response)
+                return Ok(response);
             }
-            Ok(crate_path) => {
-                let analyze_result = server
-                    .engine
-                    .analyze_crate_dependencies(crate_path.clone())
-                    .await;
+        };
 
-                match analyze_result {
-                    Err(err) => {
-                        error!(logger, "error: {}", err);
-                        let response = App::status_format_analysis(
-                            None,
-                            format,
-                            SubjectPath::Crate(crate_path),
-                        );
-                        Ok(response)
-                    }
-                    Ok(analysis_outcome) => {
-                        let response = App::status_format_analysis(
-                            Some(analysis_outcome),
-                            format,
-                            SubjectPath::Crate(crate_path),
-                        );
+        let options_at = |git_ref: Option<GitRef>| RepoAnalysisOptions {
+            entry_point: extra_config.path.clone(),
+            git_ref,
+            locked: extra_config.locked,
+            deep: false,
+            include_prerelease: extra_config.include_prerelease,
+        };
+        let base_options = options_at(extra_config.base.clone());
+        let head_options = options_at(Some(head.clone()));
 
-                        Ok(response)
-                    }
-                }
+        let subject_path = SubjectPath::Repo(repo_path.clone());
+        let mut needs_analysis = false;
+        for options in [&base_options, &head_options] {
+            needs_analysis |= !self.engine.is_cached_only()
+                && !self
+                    .engine
+                    .has_cached_analysis(&subject_path, options)
+                    .await;
+        }
+        if needs_analysis {
+            if let Err(retry_after) = self.rate_limiter.check_repo(&repo_path) {
+                return Ok(too_many_requests(retry_after));
             }
         }
-    }
 
-    fn status_format_analysis(
-        analysis_outcome: Option<AnalyzeDependenciesOutcome>,
-        format: StatusFormat,
-        subject_path: SubjectPath,
-    ) -> Response<Body> {
-        match format {
-            StatusFormat::Svg => views::badge::response(analysis_outcome.as_ref()),
-            StatusFormat::Html => views::html::status::render(analysis_outcome, subject_path),
+        let analyses = future::try_join(
+            self.engine
+                .analyze_repo_dependencies(repo_path.clone(), base_options),
+            self.engine
+                .analyze_repo_dependencies(repo_path.clone(), head_options),
+        )
+        .await;
+
+        match analyses {
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not analyze repository",
+                    "Please make sure that both refs exist and contain a Cargo.toml.",
+                );
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                Ok(response)
+            }
+            Ok((base, head_outcome)) => {
+                let changes = head_outcome.changes_since(&base);
+                let base_label = extra_config
+                    .base
+                    .as_ref()
+                    .map_or("HEAD", |base| base.as_ref());
+                Ok(views::html::compare::render(
+                    Locale::negotiate(&req),
+                    subject_path,
+                    base_label,
+                    head.as_ref(),
+                    &changes,
+                ))
+            }
         }
     }
 
-    fn static_file(file: StaticFile) -> Response<Body> {
-        match file {
+    /// Serves the history of a repository, analyzing it first so today's snapshot is recorded
+    async fn repo_history(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+        json: bool,
+    ) -> Result<Response<Body>, HyperError> {
+        let site = params.find("site").expect("route param 'site' not found");
+        let qual = params.find("qual").expect("route param 'qual' not found");
+        let name = params.find("name").expect("route param 'name' not found");
+
+        match RepoPath::from_parts(site, qual, name) {
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse repository path",
+                    "Please make sure to provide a valid repository path.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                Ok(response)
+            }
+
+            Ok(repo_path) => {
+                // only analyses with the default options are recorded in the history
+                if let Err(err) = self
+                    .engine
+                    .analyze_repo_dependencies(repo_path.clone(), RepoAnalysisOptions::default())
+                    .await
+                {
+                    error!(logger, "error: {}", err);
+                }
+
+                let snapshots = self.engine.repo_history(&repo_path).await;
+                if json {
+                    Ok(api::v1::history_response(&snapshots))
+                } else {
+                    let locale = Locale::negotiate(&req);
+                    Ok(views::html::history::render(locale, &snapshots, repo_path))
+                }
+            }
+        }
+    }
+
+    /// Registers a webhook for a repository on `POST` and removes it on `DELETE`
+    async fn webhook(
+        &self,
+        req: Request<Body>,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let subscribe = req.method() == Method::POST;
+        let user = self
+            .accounts
+            .as_ref()
+            .and_then(|accounts| accounts.user(&req));
+
+        let bytes = match read_body_limited(req.into_body(), MAX_WEBHOOK_REQUEST_SIZE).await? {
+            Some(bytes) => bytes,
+            None => {
+                return Ok(api::v1::webhook_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    Some("request body too large"),
+                ))
+            }
+        };
+
+        let request: api::v1::WebhookRequest = match serde_json::from_slice(&bytes) {
+            Ok(request) => request,
+            Err(err) => {
+                return Ok(api::v1::webhook_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(&err.to_string()),
+                ))
+            }
+        };
+        let repo_path = match RepoPath::from_parts(&request.site, &request.qual, &request.name) {
+            Ok(repo_path) => repo_path,
+            Err(err) => {
+                return Ok(api::v1::webhook_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(&err.to_string()),
+                ))
+            }
+        };
+
+        // users logged in with GitHub or GitLab own the repositories under their name there,
+        // which is looked up by ID as the user may have been renamed since logging in
+        let owner_app = match (&self.accounts, &user) {
+            (Some(accounts), Some(user)) if repo_path.site.as_ref() == user.provider.as_ref() => {
+                accounts.app(user.provider)
+            }
+            _ => None,
+        };
+        let is_owner = match (owner_app, &user) {
+            (Some(app), Some(user)) => match app.user_id(repo_path.qual.as_ref()).await {
+                Ok(owner_id) => owner_id == Some(user.id),
+                Err(err) => {
+                    error!(
+                        logger,
+                        "failed looking up the owner of a repository: {}", err
+                    );
+                    false
+                }
+            },
+            _ => false,
+        };
+        let authorized = match request.token {
+            _ if is_owner => Ok(true),
+            Some(ref token) => self.engine.verify_webhook_token(&repo_path, token).await,
+            None => Ok(false),
+        };
+        match authorized {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(api::v1::webhook_response(
+                    StatusCode::FORBIDDEN,
+                    Some("log in as the owner of the repository, or send the token whose SHA-256 hash its .deps-rs.toml sets as webhook-token-sha256"),
+                ))
+            }
+            Err(err) => {
+                return Ok(api::v1::webhook_response(
+                    StatusCode::BAD_REQUEST,
+                    Some(&format!("{:#}", err)),
+                ))
+            }
+        }
+
+        let result = if subscribe {
+            self.engine
+                .subscribe_webhook(&repo_path, &request.url)
+                .await
+                .map(|created| {
+                    if created {
+                        StatusCode::CREATED
+                    } else {
+                        StatusCode::OK
+                    }
+                })
+        } else {
+            self.engine
+                .unsubscribe_webhook(&repo_path, &request.url)
+                .await
+                .map(|removed| {
+                    if removed {
+                        StatusCode::NO_CONTENT
+                    } else {
+                        StatusCode::NOT_FOUND
+                    }
+                })
+        };
+
+        match result {
+            Ok(status_code) => Ok(api::v1::webhook_response(status_code, None)),
+            Err(err) if err.is::<InvalidWebhook>() => Ok(api::v1::webhook_response(
+                StatusCode::BAD_REQUEST,
+                Some(&err.to_string()),
+            )),
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                Ok(api::v1::webhook_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Some("could not save webhooks"),
+                ))
+            }
+        }
+    }
+
+    /// Receives a webhook delivery of the GitHub App and checks the pushed commit in the
+    /// background
+    async fn github_event(
+        &self,
+        req: Request<Body>,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let github = match self.github {
+            Some(ref github) => github.clone(),
+            None => return Ok(not_found()),
+        };
+
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let event = header("x-github-event").unwrap_or_default();
+        let signature = header("x-hub-signature-256").unwrap_or_default();
+
+        let bytes = match read_body_limited(req.into_body(), MAX_GITHUB_EVENT_SIZE).await? {
+            Some(bytes) => bytes,
+            None => return Ok(body_too_large()),
+        };
+
+        if !github.verify_signature(&signature, &bytes) {
+            return Ok(plain_response(
+                StatusCode::UNAUTHORIZED,
+                "invalid signature",
+            ));
+        }
+
+        match github::parse_event(&event, &bytes) {
+            Ok(Some(check)) => {
+                tokio::spawn(github::check_commit(
+                    github,
+                    self.engine.clone(),
+                    check,
+                    logger,
+                ));
+                Ok(plain_response(StatusCode::ACCEPTED, "checking commit"))
+            }
+            Ok(None) => Ok(plain_response(StatusCode::OK, "ignored")),
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                Ok(plain_response(StatusCode::BAD_REQUEST, &err.to_string()))
+            }
+        }
+    }
+
+    /// Sends the user to the login page of a provider, remembering the `state` the provider
+    /// has to send them back with in a cookie
+    fn login(&self, params: Params) -> Response<Body> {
+        let provider = params
+            .find("provider")
+            .expect("route param 'provider' not found");
+
+        let (accounts, app) = match (&self.accounts, provider.parse::<OauthProvider>()) {
+            (Some(accounts), Ok(provider)) => match accounts.app(provider) {
+                Some(app) => (accounts, app),
+                None => return not_found(),
+            },
+            _ => return not_found(),
+        };
+
+        let state = accounts.new_state();
+        let authorize_url = app.authorize_url(&Accounts::redirect_uri(app.provider()), &state);
+
+        Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header(LOCATION, authorize_url)
+            .header(
+                SET_COOKIE,
+                accounts::set_cookie(accounts::STATE_COOKIE, &state, accounts::STATE_LIFETIME),
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// Logs in the user a provider sent back after they authorized deps.rs, and continues to
+    /// their watchlist
+    async fn login_callback(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        #[derive(Deserialize)]
+        struct CallbackQuery {
+            code: String,
+            state: String,
+        }
+
+        let provider = params
+            .find("provider")
+            .expect("route param 'provider' not found");
+
+        let (accounts, app) = match (&self.accounts, provider.parse::<OauthProvider>()) {
+            (Some(accounts), Ok(provider)) => match accounts.app(provider) {
+                Some(app) => (accounts, app),
+                None => return Ok(not_found()),
+            },
+            _ => return Ok(not_found()),
+        };
+
+        let query = req
+            .uri()
+            .query()
+            .and_then(|qs| serde_urlencoded::from_str::<CallbackQuery>(qs).ok());
+        let query = match query {
+            Some(query)
+                if accounts::cookie(&req, accounts::STATE_COOKIE) == Some(query.state.as_str()) =>
+            {
+                query
+            }
+            _ => {
+                let mut response = views::html::error::render(
+                    "Could not log in",
+                    "The login was cancelled or has expired. Please try again.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                return Ok(response);
+            }
+        };
+
+        let login = app
+            .login(&query.code, &Accounts::redirect_uri(app.provider()))
+            .await;
+        let user = match login {
+            Ok(login) => User::new(app.provider(), login),
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not log in",
+                    "Please make sure to authorize deps.rs and try again.",
+                );
+                *response.status_mut() = StatusCode::BAD_GATEWAY;
+                return Ok(response);
+            }
+        };
+
+        let session = accounts.session(&user, SystemTime::now());
+        Ok(Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header(LOCATION, format!("{}/watchlist", &SELF_BASE_URL as &str))
+            .header(
+                SET_COOKIE,
+                accounts::set_cookie(
+                    accounts::SESSION_COOKIE,
+                    &session,
+                    accounts::SESSION_LIFETIME,
+                ),
+            )
+            .header(
+                SET_COOKIE,
+                accounts::set_cookie(accounts::STATE_COOKIE, "", Duration::ZERO),
+            )
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Removes the session cookie and goes back to the front page
+    fn logout(&self) -> Response<Body> {
+        if self.accounts.is_none() {
+            return not_found();
+        }
+
+        Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header(LOCATION, SELF_BASE_URL.as_str())
+            .header(
+                SET_COOKIE,
+                accounts::set_cookie(accounts::SESSION_COOKIE, "", Duration::ZERO),
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// Shows the watchlist of the logged-in user, or how to log in
+    async fn watchlist(&self, req: Request<Body>) -> Result<Response<Body>, HyperError> {
+        let accounts = match self.accounts {
+            Some(ref accounts) => accounts,
+            None => return Ok(not_found()),
+        };
+        let locale = Locale::negotiate(&req);
+        let user = accounts.user(&req);
+
+        let mut watched = Vec::new();
+        if let Some(ref user) = user {
+            for subject in self.engine.watchlist(&user.key()).await {
+                let latest = match subject {
+                    Watched::Crate(ref name) => self
+                        .engine
+                        .find_latest_crate_release(name.clone(), VersionReq::STAR)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(|release| release.version),
+                    Watched::Repo(_) => None,
+                };
+                watched.push((subject, latest));
+            }
+        }
+
+        let mut response = views::html::watchlist::render(
+            locale,
+            &accounts.providers(),
+            user.as_ref().map(|user| user.login.as_str()),
+            &watched,
+        );
+        response
+            .headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+        Ok(response)
+    }
+
+    /// Adds a repository or crate to the logged-in user's watchlist, or removes it, as
+    /// submitted from the watchlist page
+    async fn update_watchlist(
+        &self,
+        req: Request<Body>,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Action {
+            Watch,
+            Unwatch,
+        }
+
+        #[derive(Deserialize)]
+        struct WatchlistForm {
+            action: Action,
+            subject: String,
+        }
+
+        let user = match self.accounts {
+            Some(ref accounts) => accounts.user(&req),
+            None => return Ok(not_found()),
+        };
+        let user = match user {
+            Some(user) => user,
+            None => {
+                let mut response = views::html::error::render(
+                    "Not logged in",
+                    "Please log in to change your watchlist.",
+                );
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                return Ok(response);
+            }
+        };
+
+        let bytes = match read_body_limited(req.into_body(), MAX_WATCHLIST_REQUEST_SIZE).await? {
+            Some(bytes) => bytes,
+            None => return Ok(body_too_large()),
+        };
+
+        let result = match serde_urlencoded::from_bytes::<WatchlistForm>(&bytes) {
+            Ok(form) => match form.subject.parse::<Watched>() {
+                Ok(subject) => match form.action {
+                    Action::Watch => self.engine.watch(&user.key(), &subject).await,
+                    Action::Unwatch => self.engine.unwatch(&user.key(), &subject).await,
+                },
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err.into()),
+        };
+
+        match result {
+            Ok(_) => Ok(Response::builder()
+                .status(StatusCode::SEE_OTHER)
+                .header(LOCATION, format!("{}/watchlist", &SELF_BASE_URL as &str))
+                .body(Body::empty())
+                .unwrap()),
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not update watchlist",
+                    &format!("{}. Please go back and try again.", err),
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Creates the path of a repository on a custom git host, which must be allowed through
+    /// `CUSTOM_GIT_HOSTS`
+    fn custom_repo_path(raw_base: &RawBase, qual: &str, name: &str) -> anyhow::Result<RepoPath> {
+        let host = raw_base.host();
+        anyhow::ensure!(
+            CUSTOM_GIT_HOSTS
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host)),
+            "custom git host {} is not allowed",
+            host
+        );
+
+        RepoPath::custom(raw_base.clone(), qual, name)
+    }
+
+    /// Serves a badge summarizing all recently analyzed repositories of an owner
+    async fn org_status(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let site = params.find("site").expect("route param 'site' not found");
+        let qual = params.find("qual").expect("route param 'qual' not found");
+
+        let extra_config = ExtraConfig::from_query_string(req.uri().query());
+
+        match (site.parse::<RepoSite>(), qual.parse::<RepoQualifier>()) {
+            (Ok(site), Ok(qual)) => {
+                let summary = self.engine.summarize_org(site, &qual).await;
+                Ok(views::badge::org_response(summary, &extra_config))
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse organization path",
+                    "Please make sure to provide a valid site and owner.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Shows the Rust repositories of a GitHub organization with their badges and how many of
+    /// them have issues. Analyses that aren't cached yet are started in the background as far
+    /// as the rate limit allows, and counted as pending until they finish.
+    async fn org_dashboard(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let qual = params.find("qual").expect("route param 'qual' not found");
+        let locale = Locale::negotiate(&req);
+
+        let qual = match qual.parse::<RepoQualifier>() {
+            Ok(qual) => qual,
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse organization path",
+                    "Please make sure to provide a valid owner.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                return Ok(response);
+            }
+        };
+
+        let repos = match self.engine.get_org_repos(qual.clone()).await {
+            Ok(repos) => repos,
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not retrieve organization",
+                    "Please make sure the organization or user exists on GitHub.",
+                );
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                return Ok(response);
+            }
+        };
+
+        let options = RepoAnalysisOptions::default();
+        let mut states = Vec::with_capacity(repos.len());
+        for repo in repos {
+            let subject_path = SubjectPath::Repo(repo.path.clone());
+            let state = if self
+                .engine
+                .has_cached_analysis(&subject_path, &options)
+                .await
+            {
+                match self
+                    .engine
+                    .analyze_repo_dependencies(repo.path.clone(), options.clone())
+                    .await
+                {
+                    Ok(outcome) => RepoState::Analyzed {
+                        any_issues: outcome.any_issues(),
+                        any_insecure: outcome.any_insecure(),
+                    },
+                    Err(_) => RepoState::Failed,
+                }
+            } else {
+                if !self.engine.is_cached_only() && self.rate_limiter.check_repo(&repo.path).is_ok()
+                {
+                    let engine = self.engine.clone();
+                    let (repo_path, options, logger) =
+                        (repo.path.clone(), options.clone(), logger.clone());
+                    tokio::spawn(async move {
+                        if let Err(err) = engine.analyze_repo_dependencies(repo_path, options).await
+                        {
+                            error!(logger, "error: {}", err);
+                        }
+                    });
+                }
+                RepoState::Pending
+            };
+            states.push((repo, state));
+        }
+
+        Ok(views::html::org::render(locale, &qual, &states))
+    }
+
+    async fn crate_redirect(
+        &self,
+        _req: Request<Body>,
+        params: Params,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let engine = self.engine.clone();
+
+        let name = params.find("name").expect("route param 'name' not found");
+        let crate_name_result = name.parse::<CrateName>();
+
+        match crate_name_result {
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse crate name",
+                    "Please make sure to provide a valid crate name.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                Ok(response)
+            }
+
+            Ok(crate_name) => {
+                let release_result = engine
+                    .find_latest_crate_release(crate_name, VersionReq::STAR)
+                    .await;
+
+                match release_result {
+                    Err(err) => {
+                        error!(logger, "error: {}", err);
+                        let mut response = views::html::error::render(
+                            "Could not fetch crate information",
+                            "Please make sure to provide a valid crate name.",
+                        );
+                        *response.status_mut() = StatusCode::NOT_FOUND;
+                        Ok(response)
+                    }
+                    Ok(None) => {
+                        let mut response = views::html::error::render(
+                            "Could not fetch crate information",
+                            "Please make sure to provide a valid crate name.",
+                        );
+                        *response.status_mut() = StatusCode::NOT_FOUND;
+                        Ok(response)
+                    }
+                    Ok(Some(release)) => {
+                        let redirect_url = format!(
+                            "{}/crate/{}/{}",
+                            &SELF_BASE_URL as &str,
+                            release.name.as_ref(),
+                            release.version
+                        );
+
+                        let res = Response::builder()
+                            .status(StatusCode::TEMPORARY_REDIRECT)
+                            .header(LOCATION, redirect_url)
+                            .body(Body::empty())
+                            .unwrap();
+
+                        Ok(res)
+                    }
+                }
+            }
+        }
+    }
+
+    async fn crate_status(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+        format: StatusFormat,
+    ) -> Result<Response<Body>, HyperError> {
+        let name = params.find("name").expect("route param 'name' not found");
+        let version = params
+            .find("version")
+            .expect("route param 'version' not found");
+
+        let crate_path_result = CratePath::from_parts(name, version);
+
+        match crate_path_result {
+            Err(err) => {
+                if let (Ok(crate_name), Some(req_version)) =
+                    (name.parse::<CrateName>(), partial_version_req(version))
+                {
+                    return self
+                        .redirect_to_matching_release(req, crate_name, req_version, logger, format)
+                        .await;
+                }
+
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse crate path",
+                    "Please make sure to provide a valid crate name and version.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                Ok(response)
+            }
+            Ok(crate_path)
+                if crate_path.name.as_ref() != name
+                    || crate_path.version.to_string() != version =>
+            {
+                // redirect sloppy URLs like `/crate/foo/v1.2.3` to the canonical one
+                let mut redirect_url = format!(
+                    "{}/crate/{}/{}{}",
+                    &SELF_BASE_URL as &str,
+                    crate_path.name.as_ref(),
+                    crate_path.version,
+                    format.path_suffix()
+                );
+                if let Some(query) = req.uri().query() {
+                    redirect_url.push('?');
+                    redirect_url.push_str(query);
+                }
+
+                Ok(Response::builder()
+                    .status(StatusCode::MOVED_PERMANENTLY)
+                    .header(LOCATION, redirect_url)
+                    .body(Body::empty())
+                    .unwrap())
+            }
+            Ok(crate_path) => {
+                self.serve_crate_status(req, crate_path, logger, format)
+                    .await
+            }
+        }
+    }
+
+    /// Shows how the dependencies of a crate changed between two releases
+    async fn crate_compare(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+    ) -> Result<Response<Body>, HyperError> {
+        let name = params.find("name").expect("route param 'name' not found");
+        let from = params.find("from").expect("route param 'from' not found");
+        let to = params.find("to").expect("route param 'to' not found");
+
+        let crate_paths = CratePath::from_parts(name, from)
+            .and_then(|from| Ok((from, CratePath::from_parts(name, to)?)));
+        let (from_path, to_path) = match crate_paths {
+            Ok(crate_paths) => crate_paths,
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse crate path",
+                    "Please make sure to provide a valid crate name and versions.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                return Ok(response);
+            }
+        };
+
+        let analyses = future::try_join(
+            self.engine.analyze_crate_dependencies(from_path.clone()),
+            self.engine.analyze_crate_dependencies(to_path.clone()),
+        )
+        .await;
+
+        match analyses {
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not analyze crate",
+                    "Please make sure that both versions of the crate exist.",
+                );
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                Ok(response)
+            }
+            Ok((before, after)) => {
+                let changes = after.changes_since(&before);
+                Ok(views::html::compare::render(
+                    Locale::negotiate(&req),
+                    SubjectPath::Crate(to_path.clone()),
+                    &from_path.version.to_string(),
+                    &to_path.version.to_string(),
+                    &changes,
+                ))
+            }
+        }
+    }
+
+    /// Redirects a partial version like `/crate/serde/1.0` to the newest matching release
+    async fn redirect_to_matching_release(
+        &self,
+        req: Request<Body>,
+        crate_name: CrateName,
+        req_version: VersionReq,
+        logger: Logger,
+        format: StatusFormat,
+    ) -> Result<Response<Body>, HyperError> {
+        let release_result = self
+            .engine
+            .find_latest_crate_release(crate_name, req_version)
+            .await;
+
+        match release_result {
+            Ok(Some(release)) => {
+                let mut redirect_url = format!(
+                    "{}/crate/{}/{}{}",
+                    &SELF_BASE_URL as &str,
+                    release.name.as_ref(),
+                    release.version,
+                    format.path_suffix()
+                );
+                if let Some(query) = req.uri().query() {
+                    redirect_url.push('?');
+                    redirect_url.push_str(query);
+                }
+
+                Ok(Response::builder()
+                    .status(StatusCode::TEMPORARY_REDIRECT)
+                    .header(LOCATION, redirect_url)
+                    .body(Body::empty())
+                    .unwrap())
+            }
+            result => {
+                if let Err(err) = result {
+                    error!(logger, "error: {}", err);
+                }
+                let mut response = views::html::error::render(
+                    "Could not find a matching crate release",
+                    "Please make sure to provide a valid crate name and version.",
+                );
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                Ok(response)
+            }
+        }
+    }
+
+    /// Redirects to, or serves the badge of, the newest release including pre-releases
+    async fn crate_prerelease(
+        &self,
+        req: Request<Body>,
+        params: Params,
+        logger: Logger,
+        format: StatusFormat,
+    ) -> Result<Response<Body>, HyperError> {
+        let name = params.find("name").expect("route param 'name' not found");
+
+        let crate_name = match name.parse::<CrateName>() {
+            Ok(crate_name) => crate_name,
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not parse crate name",
+                    "Please make sure to provide a valid crate name.",
+                );
+                *response.status_mut() = StatusCode::BAD_REQUEST;
+                return Ok(response);
+            }
+        };
+
+        let release = match self.engine.find_newest_crate_release(crate_name).await {
+            Ok(Some(release)) => release,
+            Ok(None) => {
+                let mut response = views::html::error::render(
+                    "Could not fetch crate information",
+                    "Please make sure to provide a valid crate name.",
+                );
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                return Ok(response);
+            }
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let mut response = views::html::error::render(
+                    "Could not fetch crate information",
+                    "Please make sure to provide a valid crate name.",
+                );
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                return Ok(response);
+            }
+        };
+
+        match format {
+            StatusFormat::Html => {
+                let redirect_url = format!(
+                    "{}/crate/{}/{}",
+                    &SELF_BASE_URL as &str,
+                    release.name.as_ref(),
+                    release.version
+                );
+
+                Ok(Response::builder()
+                    .status(StatusCode::TEMPORARY_REDIRECT)
+                    .header(LOCATION, redirect_url)
+                    .body(Body::empty())
+                    .unwrap())
+            }
+            _ => {
+                let crate_path = CratePath {
+                    name: release.name,
+                    version: release.version,
+                };
+                self.serve_crate_status(req, crate_path, logger, format)
+                    .await
+            }
+        }
+    }
+
+    async fn serve_crate_status(
+        &self,
+        req: Request<Body>,
+        crate_path: CratePath,
+        logger: Logger,
+        format: StatusFormat,
+    ) -> Result<Response<Body>, HyperError> {
+        let server = self.clone();
+        let extra_config = ExtraConfig::from_query_string(req.uri().query());
+        let locale = Locale::negotiate(&req);
+
+        let subject_path = SubjectPath::Crate(crate_path.clone());
+        if format.badge_format().is_some()
+            && !server.engine.is_cached_only()
+            && !server
+                .engine
+                .has_cached_analysis(&subject_path, &RepoAnalysisOptions::default())
+                .await
+        {
+            let engine = server.engine.clone();
+            tokio::spawn(async move {
+                if let Err(err) = engine.analyze_crate_dependencies(crate_path).await {
+                    error!(logger, "error: {}", err);
+                }
+            });
+            return Ok(views::badge::placeholder_response(
+                &extra_config,
+                format.badge_subject(),
+                format.badge_format().unwrap(),
+            ));
+        }
+
+        let analyze_result = server.engine.analyze_crate_dependencies(crate_path).await;
+
+        match analyze_result {
+            Err(err) => {
+                error!(logger, "error: {}", err);
+                let response = App::status_format_analysis(
+                    None,
+                    format,
+                    locale,
+                    subject_path,
+                    extra_config,
+                    &RepoAnalysisOptions::default(),
+                    &[],
+                );
+                Ok(response)
+            }
+            Ok(analysis_outcome) => Ok(App::conditional_status_response(
+                &req,
+                analysis_outcome,
+                format,
+                locale,
+                subject_path,
+                extra_config,
+                &RepoAnalysisOptions::default(),
+            )),
+        }
+    }
+
+    fn status_format_analysis(
+        analysis_outcome: Option<AnalyzeDependenciesOutcome>,
+        format: StatusFormat,
+        locale: Locale,
+        subject_path: SubjectPath,
+        badge_knobs: ExtraConfig,
+        options: &RepoAnalysisOptions,
+        manifest_dirs: &[RelativePathBuf],
+    ) -> Response<Body> {
+        let analysis_outcome = analysis_outcome.map(|mut outcome| {
+            outcome.ignore_dependencies(&badge_knobs.ignore);
+            if let Some(ref features) = badge_knobs.features {
+                outcome.remove_disabled_optional(features);
+            }
+            outcome
+        });
+
+        match format {
+            StatusFormat::Svg => {
+                views::badge::response(analysis_outcome.as_ref(), &badge_knobs, BadgeFormat::Svg)
+            }
+            StatusFormat::Png => {
+                views::badge::response(analysis_outcome.as_ref(), &badge_knobs, BadgeFormat::Png)
+            }
+            StatusFormat::ShieldJson => views::badge::response(
+                analysis_outcome.as_ref(),
+                &badge_knobs,
+                BadgeFormat::ShieldJson,
+            ),
+            StatusFormat::MsrvSvg => {
+                views::badge::msrv_response(analysis_outcome.as_ref(), &badge_knobs)
+            }
+            StatusFormat::LicenseSvg => {
+                views::badge::license_response(analysis_outcome.as_ref(), &badge_knobs)
+            }
+            StatusFormat::Html => views::html::status::render(
+                locale,
+                analysis_outcome,
+                subject_path,
+                options,
+                badge_knobs.informational,
+                manifest_dirs,
+            ),
+            StatusFormat::Graph => match (analysis_outcome, subject_path) {
+                (Some(outcome), SubjectPath::Repo(repo_path)) => {
+                    views::html::graph::render(outcome, repo_path, options)
+                }
+                (outcome, subject_path) => views::html::status::render(
+                    locale,
+                    outcome,
+                    subject_path,
+                    options,
+                    false,
+                    manifest_dirs,
+                ),
+            },
+            StatusFormat::Json => api::v1::response(analysis_outcome.as_ref()),
+            StatusFormat::Markdown => {
+                views::markdown::response(analysis_outcome.as_ref(), &subject_path)
+            }
+            StatusFormat::Csv => views::csv::response(analysis_outcome.as_ref()),
+        }
+    }
+
+    /// Renders a successful analysis with `ETag` and `Last-Modified` headers, or answers
+    /// `304 Not Modified` without rendering if the client's copy is still current
+    fn conditional_status_response(
+        req: &Request<Body>,
+        analysis_outcome: AnalyzeDependenciesOutcome,
+        format: StatusFormat,
+        locale: Locale,
+        subject_path: SubjectPath,
+        badge_knobs: ExtraConfig,
+        options: &RepoAnalysisOptions,
+    ) -> Response<Body> {
+        let etag = analysis_etag(&analysis_outcome, format, locale, req.uri().query());
+        let last_modified = views::feed::http_date(analysis_outcome.analyzed_at);
+
+        let mut response = App::status_format_analysis(
+            Some(analysis_outcome),
+            format,
+            locale,
+            subject_path,
+            badge_knobs,
+            options,
+            &[],
+        );
+        if etag_matches(req.headers().get(IF_NONE_MATCH), &etag) {
+            // caches refresh the stored response with these, so they must match the full one
+            let mut not_modified = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+                .unwrap();
+            for name in &[CACHE_CONTROL, EXPIRES, VARY] {
+                for value in response.headers().get_all(name) {
+                    not_modified.headers_mut().append(name, value.clone());
+                }
+            }
+            response = not_modified;
+        }
+
+        let headers = response.headers_mut();
+        if let Ok(etag) = HeaderValue::from_str(&etag) {
+            headers.insert(ETAG, etag);
+        }
+        if let Ok(last_modified) = HeaderValue::from_str(&last_modified) {
+            headers.insert(LAST_MODIFIED, last_modified);
+        }
+        response
+    }
+
+    fn static_file(file: StaticFile) -> Response<Body> {
+        match file {
             StaticFile::StyleCss => Response::builder()
                 .header(CONTENT_TYPE, "text/css; charset=utf-8")
                 .header(ETAG, STATIC_STYLE_CSS_ETAG)
                 .header(CACHE_CONTROL, "public, max-age=365000000, immutable")
                 .body(Body::from(assets::STATIC_STYLE_CSS))
                 .unwrap(),
+            StaticFile::StatusJs => Response::builder()
+                .header(CONTENT_TYPE, "text/javascript; charset=utf-8")
+                .header(ETAG, STATIC_STATUS_JS_ETAG)
+                .header(CACHE_CONTROL, "public, max-age=365000000, immutable")
+                .body(Body::from(assets::STATIC_STATUS_JS))
+                .unwrap(),
             StaticFile::FaviconPng => Response::builder()
                 .header(CONTENT_TYPE, "image/svg+xml")
                 .body(Body::from(assets::STATIC_FAVICON))
@@ -361,9 +1923,415 @@ impl App {
     }
 }
 
+/// Interprets an incomplete version like `1` or `1.0` as a caret requirement
+fn partial_version_req(version: &str) -> Option<VersionReq> {
+    let components: Vec<&str> = version.split('.').collect();
+    let is_partial = components.len() < 3
+        && components
+            .iter()
+            .all(|c| !c.is_empty() && c.chars().all(|c| c.is_ascii_digit()));
+
+    if is_partial {
+        VersionReq::parse(&format!("^{}", version)).ok()
+    } else {
+        None
+    }
+}
+
+/// Computes a weak entity tag for a response rendered from an analysis. Besides the analysis,
+/// the format, the language and the query with the badge options go into it.
+fn analysis_etag(
+    outcome: &AnalyzeDependenciesOutcome,
+    format: StatusFormat,
+    locale: Locale,
+    query: Option<&str>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(outcome).unwrap_or_default());
+    hasher.update(format!("{:?}", format));
+    hasher.update(locale.as_str());
+    hasher.update(query.unwrap_or_default());
+
+    format!("W/\"{}\"", hex::encode(&hasher.finalize()[..16]))
+}
+
+/// Checks if an `If-None-Match` header lists `etag`, comparing weakly as RFC 7232 requires
+fn etag_matches(if_none_match: Option<&HeaderValue>, etag: &str) -> bool {
+    let if_none_match = match if_none_match.and_then(|value| value.to_str().ok()) {
+        Some(if_none_match) => if_none_match,
+        None => return false,
+    };
+    let opaque_tag = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque_tag(tag) == opaque_tag(etag))
+}
+
 fn not_found() -> Response<Body> {
     views::html::error::render_404()
 }
 
+fn too_many_requests(retry_after: Duration) -> Response<Body> {
+    let mut response = views::html::error::render(
+        "Too many requests",
+        "Please wait a moment before trying again.",
+    );
+    *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+    // rounded up, so clients don't retry before a token is available
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(secs));
+    response
+}
+
+fn plain_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from(message.to_owned()))
+        .unwrap()
+}
+
+fn body_too_large() -> Response<Body> {
+    plain_response(StatusCode::PAYLOAD_TOO_LARGE, "request body too large")
+}
+
+/// Reads a request body, or returns `None` as soon as it turns out to be longer than `limit`
+async fn read_body_limited(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, HyperError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+/// What the status of a badge shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeDisplay {
+    /// A summary, e.g. "3 of 12 outdated"
+    #[default]
+    Ratio,
+    /// The number of outdated and insecure dependencies, e.g. "3 outdated, 1 insecure"
+    Count,
+}
+
+/// Least severe issue that changes the color of a badge. Outdated dependencies that lag less
+/// behind leave it green; unavailable, yanked and insecure dependencies always turn it red.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeThreshold {
+    Patch,
+    Minor,
+    /// Only semver-incompatible upgrades
+    #[default]
+    Major,
+    /// Only vulnerabilities, outdated dependencies never change the color
+    Insecure,
+}
+
+impl BadgeThreshold {
+    /// Returns the smallest lag of an outdated dependency that changes the color, if any does
+    pub fn min_lag(self) -> Option<VersionLag> {
+        match self {
+            BadgeThreshold::Patch => Some(VersionLag::Patch),
+            BadgeThreshold::Minor => Some(VersionLag::Minor),
+            BadgeThreshold::Major => Some(VersionLag::Major),
+            BadgeThreshold::Insecure => None,
+        }
+    }
+}
+
+/// Configuration options passed as query parameters
+#[derive(Debug, Clone, Default)]
+pub struct ExtraConfig {
+    /// Badge variant, e.g. `mono` for a black-and-white badge
+    pub variant: BadgeVariant,
+    /// Badge shape, named like the shields.io styles, e.g. `flat-square` or `social`. Unset
+    /// if the URL doesn't choose one, leaving it to the repository's `.deps-rs.toml`.
+    pub style: Option<BadgeStyle>,
+    /// What the badge status shows, e.g. `count` for the number of outdated dependencies
+    pub display: BadgeDisplay,
+    /// Requested `max-age` of badge responses, at least `BADGE_MIN_CACHE_SECONDS`
+    pub cache_seconds: Option<u64>,
+    /// ID of a frozen analysis of a repository to show instead of analyzing it
+    pub snapshot: Option<String>,
+    /// Directory of a repository to start the analysis at instead of the root
+    pub path: Option<RelativePathBuf>,
+    /// URL template for the raw files of a repository on a custom git host
+    pub raw_base: Option<RawBase>,
+    /// Branch, tag or commit of a repository to analyze instead of the default branch
+    pub git_ref: Option<GitRef>,
+    /// Ref that `head` is compared against on the compare page, the default branch if unset
+    pub base: Option<GitRef>,
+    /// Ref whose dependency changes the compare page shows
+    pub head: Option<GitRef>,
+    /// Whether to analyze the versions locked in a repository's `Cargo.lock`
+    pub locked: bool,
+    /// Whether the status page lists informational advisories, e.g. about unmaintained crates
+    pub informational: bool,
+    /// Whether to also analyze the dependencies of a repository's dependencies
+    pub deep: bool,
+    /// Whether dependencies that require a pre-release are compared against newer ones
+    pub include_prerelease: bool,
+    /// Dependencies left out of the status, e.g. intentionally pinned ones
+    pub ignore: Vec<CrateName>,
+    /// Whether badges also count outdated and insecure dev-dependencies
+    pub include_dev: bool,
+    /// Whether badges leave out build-dependencies, e.g. when they are pinned on purpose
+    pub exclude_build: bool,
+    /// Whether badges count dependencies overridden in `[patch.crates-io]` as outdated
+    pub include_patched: bool,
+    /// Enabled features, if optional dependencies they don't enable are left out of the status
+    pub features: Option<Vec<String>>,
+    /// Least severe issue that changes the color of the badge, e.g. `insecure` to keep it
+    /// green as long as there are no vulnerabilities
+    pub fail_on: BadgeThreshold,
+}
+
+/// Deserializes a query parameter, or `None` if it isn't valid
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(T::deserialize(deserializer).ok())
+}
+
+impl ExtraConfig {
+    fn from_query_string(qs: Option<&str>) -> Self {
+        // an invalid parameter is ignored by itself, rather than along with all others
+        #[derive(Debug, Default, Deserialize)]
+        struct ExtraConfigPartial {
+            #[serde(default, deserialize_with = "lenient")]
+            variant: Option<BadgeVariant>,
+            #[serde(default, deserialize_with = "lenient")]
+            style: Option<BadgeStyle>,
+            #[serde(default, deserialize_with = "lenient")]
+            display: Option<BadgeDisplay>,
+            #[serde(rename = "cacheSeconds", default, deserialize_with = "lenient")]
+            cache_seconds: Option<u64>,
+            snapshot: Option<String>,
+            path: Option<String>,
+            raw_base: Option<String>,
+            #[serde(rename = "ref")]
+            git_ref: Option<String>,
+            base: Option<String>,
+            head: Option<String>,
+            #[serde(default, deserialize_with = "lenient")]
+            locked: Option<bool>,
+            #[serde(default, deserialize_with = "lenient")]
+            informational: Option<bool>,
+            #[serde(default, deserialize_with = "lenient")]
+            deep: Option<bool>,
+            #[serde(default, deserialize_with = "lenient")]
+            include_prerelease: Option<bool>,
+            ignore: Option<String>,
+            #[serde(default, deserialize_with = "lenient")]
+            include_dev: Option<bool>,
+            #[serde(default, deserialize_with = "lenient")]
+            exclude_build: Option<bool>,
+            #[serde(default, deserialize_with = "lenient")]
+            include_patched: Option<bool>,
+            features: Option<String>,
+            #[serde(default, deserialize_with = "lenient")]
+            fail_on: Option<BadgeThreshold>,
+        }
+
+        let extra_config = qs
+            .and_then(|qs| serde_urlencoded::from_str::<ExtraConfigPartial>(qs).ok())
+            .unwrap_or_default();
+
+        Self {
+            variant: extra_config.variant.unwrap_or_default(),
+            style: extra_config.style,
+            display: extra_config.display.unwrap_or_default(),
+            cache_seconds: extra_config
+                .cache_seconds
+                .map(|secs| secs.max(*BADGE_MIN_CACHE_SECONDS)),
+            snapshot: extra_config.snapshot,
+            path: extra_config
+                .path
+                .map(|path| RelativePathBuf::from(path).normalize())
+                .filter(|path| !path.as_str().is_empty() && !path.starts_with("..")),
+            raw_base: extra_config
+                .raw_base
+                .and_then(|raw_base| raw_base.parse().ok()),
+            git_ref: extra_config
+                .git_ref
+                .and_then(|git_ref| git_ref.parse().ok()),
+            base: extra_config.base.and_then(|base| base.parse().ok()),
+            head: extra_config.head.and_then(|head| head.parse().ok()),
+            locked: extra_config.locked.unwrap_or(false),
+            informational: extra_config.informational.unwrap_or(false),
+            deep: extra_config.deep.unwrap_or(false),
+            include_prerelease: extra_config.include_prerelease.unwrap_or(false),
+            ignore: extra_config
+                .ignore
+                .map(|names| {
+                    names
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .filter_map(|name| name.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            include_dev: extra_config.include_dev.unwrap_or(false),
+            exclude_build: extra_config.exclude_build.unwrap_or(false),
+            include_patched: extra_config.include_patched.unwrap_or(false),
+            features: extra_config.features.map(|features| {
+                features
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|feature| !feature.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            }),
+            fail_on: extra_config.fail_on.unwrap_or_default(),
+        }
+    }
+
+    /// Kinds of dependencies the badges count
+    pub fn dep_scope(&self) -> DepScope {
+        DepScope {
+            main: true,
+            dev: self.include_dev,
+            build: !self.exclude_build,
+            patched: self.include_patched,
+        }
+    }
+}
+
+/// Largest body accepted when registering or removing a webhook
+const MAX_WEBHOOK_REQUEST_SIZE: usize = 4096;
+
+/// Largest body accepted when changing a watchlist
+const MAX_WATCHLIST_REQUEST_SIZE: usize = 1024;
+
+/// Largest GitHub webhook delivery accepted, push events list all pushed commits
+const MAX_GITHUB_EVENT_SIZE: usize = 5 * 1024 * 1024;
+
 static SELF_BASE_URL: Lazy<String> =
-    Lazy::new(|| env::var("BASE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string()));
+    Lazy::new(|| env::var("BASE_URL").unwrap_or_else(|_| config::get().server.base_url.clone()));
+
+/// Hosts that repositories may be retrieved from through a `raw_base` URL template, including
+/// the Gitea instances whose popular repositories are listed on the front page
+static CUSTOM_GIT_HOSTS: Lazy<Vec<String>> = Lazy::new(|| {
+    let server_config = &config::get().server;
+    let mut hosts = config::hosts_from_env("CUSTOM_GIT_HOSTS", &server_config.custom_git_hosts);
+    hosts.extend(config::hosts_from_env(
+        "POPULAR_GITEA_HOSTS",
+        &server_config.popular_gitea_hosts,
+    ));
+    hosts
+});
+
+/// Lower bound for the `cacheSeconds` query parameter on badges
+static BADGE_MIN_CACHE_SECONDS: Lazy<u64> = Lazy::new(|| {
+    env::var("BADGE_MIN_CACHE_SECONDS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(config::get().server.badge_min_cache_seconds)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(uri: &str, accept: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(accept) = accept {
+            builder = builder.header(ACCEPT, accept);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn negotiates_status_format() {
+        let negotiate = |uri, accept| StatusFormat::negotiate(&request(uri, accept));
+
+        assert_eq!(negotiate("/crate/serde/1.0.0", None), StatusFormat::Html);
+        assert_eq!(
+            negotiate("/crate/serde/1.0.0?format=csv", Some("application/json")),
+            StatusFormat::Csv
+        );
+        assert_eq!(
+            negotiate("/crate/serde/1.0.0?format=md", None),
+            StatusFormat::Markdown
+        );
+        assert_eq!(
+            negotiate(
+                "/crate/serde/1.0.0",
+                Some("text/plain, application/json;q=0.9")
+            ),
+            StatusFormat::Json
+        );
+        assert_eq!(
+            negotiate("/crate/serde/1.0.0?format=pdf", Some("*/*")),
+            StatusFormat::Html
+        );
+    }
+
+    #[test]
+    fn parses_ignored_dependencies() {
+        let extra_config =
+            ExtraConfig::from_query_string(Some("ignore=rand,%20serde_json,,not%20valid"));
+        assert_eq!(
+            extra_config.ignore,
+            vec!["rand".parse().unwrap(), "serde_json".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn parses_enabled_features() {
+        let features = |qs| ExtraConfig::from_query_string(Some(qs)).features;
+
+        assert_eq!(features(""), None);
+        assert_eq!(features("features="), Some(vec![]));
+        assert_eq!(
+            features("features=default,%20tls,"),
+            Some(vec!["default".to_owned(), "tls".to_owned()])
+        );
+    }
+
+    #[test]
+    fn parses_badge_threshold() {
+        let fail_on = |qs| ExtraConfig::from_query_string(Some(qs)).fail_on;
+
+        assert_eq!(fail_on(""), BadgeThreshold::Major);
+        assert_eq!(fail_on("fail_on=insecure"), BadgeThreshold::Insecure);
+        assert_eq!(BadgeThreshold::Insecure.min_lag(), None);
+        assert_eq!(fail_on("fail_on=minor").min_lag(), Some(VersionLag::Minor));
+    }
+
+    #[test]
+    fn ignores_invalid_parameters_by_themselves() {
+        let extra_config =
+            ExtraConfig::from_query_string(Some("locked=yes&fail_on=never&deep=true&path=sub"));
+
+        assert!(!extra_config.locked);
+        assert_eq!(extra_config.fail_on, BadgeThreshold::Major);
+        assert!(extra_config.deep);
+        assert_eq!(extra_config.path, Some(RelativePathBuf::from("sub")));
+    }
+
+    #[test]
+    fn matches_etags_weakly() {
+        let matches = |if_none_match| {
+            etag_matches(Some(&HeaderValue::from_static(if_none_match)), "W/\"abc\"")
+        };
+
+        assert!(matches("W/\"abc\""));
+        assert!(matches("\"xyz\", \"abc\""));
+        assert!(matches("*"));
+        assert!(!matches("W/\"xyz\""));
+        assert!(!etag_matches(None, "W/\"abc\""));
+    }
+}
@@ -0,0 +1,172 @@
+//! Token bucket rate limiting of clients and of the analyses started for a repository.
+
+use std::{
+    fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::{Body, Request};
+use lru_time_cache::LruCache;
+
+use crate::models::repo::RepoPath;
+
+/// Clients and repositories whose buckets are tracked at the same time, beyond which the least
+/// recently seen ones start over with a full bucket
+const MAX_TRACKED_KEYS: usize = 100_000;
+
+/// How many requests a key may make: `burst` at once, refilled at `per_minute`
+#[derive(Clone, Copy, Debug)]
+pub struct Quota {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Quota {
+    /// Time it takes to refill an empty bucket, after which it is as good as untracked
+    fn refill_duration(&self) -> Duration {
+        Duration::from_secs_f64(f64::from(self.burst) * 60.0 / f64::from(self.per_minute))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct TokenBuckets<K> {
+    quota: Quota,
+    buckets: Mutex<LruCache<K, Bucket>>,
+}
+
+impl<K: Clone + Ord> TokenBuckets<K> {
+    fn new(quota: Quota) -> TokenBuckets<K> {
+        let buckets =
+            LruCache::with_expiry_duration_and_capacity(quota.refill_duration(), MAX_TRACKED_KEYS);
+
+        TokenBuckets {
+            quota,
+            buckets: Mutex::new(buckets),
+        }
+    }
+
+    /// Takes a token from the bucket of `key`, or returns how long until there is one
+    fn acquire(&self, key: &K, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(self.quota.burst);
+        let per_sec = f64::from(self.quota.per_minute) / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let mut bucket = buckets.get(key).copied().unwrap_or(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(burst);
+        bucket.updated = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        };
+
+        buckets.insert(key.clone(), bucket);
+        result
+    }
+}
+
+/// Limits on how often a client may make requests and how often analyses of a repository may
+/// be started, both disabled if unset
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RateLimitConfig {
+    pub per_client: Option<Quota>,
+    pub per_repo: Option<Quota>,
+    /// Whether clients are identified by the last address in `X-Forwarded-For`, which is
+    /// only trustworthy behind a proxy that sets it
+    pub trust_forwarded_for: bool,
+}
+
+/// Rate limiter shared by all connections
+#[derive(Clone)]
+pub struct RateLimiter {
+    per_client: Option<Arc<TokenBuckets<IpAddr>>>,
+    per_repo: Option<Arc<TokenBuckets<RepoPath>>>,
+    trust_forwarded_for: bool,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("RateLimiter").finish()
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> RateLimiter {
+        RateLimiter {
+            per_client: config.per_client.map(|q| Arc::new(TokenBuckets::new(q))),
+            per_repo: config.per_repo.map(|q| Arc::new(TokenBuckets::new(q))),
+            trust_forwarded_for: config.trust_forwarded_for,
+        }
+    }
+
+    /// Returns the address of the client that made a request over a connection from `remote_ip`
+    pub fn client_ip(&self, req: &Request<Body>, remote_ip: IpAddr) -> IpAddr {
+        if !self.trust_forwarded_for {
+            return remote_ip;
+        }
+
+        req.headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .last()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or(remote_ip)
+    }
+
+    /// Counts a request of a client, returning how long it has to wait if it made too many
+    pub fn check_client(&self, ip: IpAddr) -> Result<(), Duration> {
+        match self.per_client {
+            Some(ref buckets) => buckets.acquire(&ip, Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a fresh analysis of a repository, returning how long until another one may be
+    /// started if there have been too many
+    pub fn check_repo(&self, repo_path: &RepoPath) -> Result<(), Duration> {
+        match self.per_repo {
+            Some(ref buckets) => buckets.acquire(repo_path, Instant::now()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_refills_over_time() {
+        let buckets = TokenBuckets::new(Quota {
+            burst: 2,
+            per_minute: 60,
+        });
+        let start = Instant::now();
+
+        assert_eq!(buckets.acquire(&"a", start), Ok(()));
+        assert_eq!(buckets.acquire(&"a", start), Ok(()));
+        assert_eq!(buckets.acquire(&"a", start), Err(Duration::from_secs(1)));
+        assert_eq!(buckets.acquire(&"b", start), Ok(()));
+
+        assert_eq!(
+            buckets.acquire(&"a", start + Duration::from_secs(1)),
+            Ok(())
+        );
+    }
+}
@@ -1,57 +1,331 @@
-use badge::{Badge, BadgeOptions};
-use hyper::header::CONTENT_TYPE;
-use hyper::{Body, Response};
+use badge::{Badge, BadgeCache, BadgeOptions, BadgeStyle};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::{Body, Response, StatusCode};
+use once_cell::sync::Lazy;
+use serde::Serialize;
 
-use crate::engine::AnalyzeDependenciesOutcome;
+use crate::engine::{AnalyzeDependenciesOutcome, OrgSummary};
+use crate::server::{BadgeDisplay, ExtraConfig};
 
-pub fn badge(analysis_outcome: Option<&AnalyzeDependenciesOutcome>) -> Badge {
-    let opts = match analysis_outcome {
+static BADGE_CACHE: Lazy<BadgeCache> = Lazy::new(|| BadgeCache::new(1024));
+
+/// Image format a badge is served in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BadgeFormat {
+    Svg,
+    /// Rasterized, for places that don't allow embedding SVG
+    Png,
+    /// Input for the shields.io endpoint badge, which renders it with its own styling
+    ShieldJson,
+}
+
+/// The response schema of the shields.io endpoint badge
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ShieldEndpoint<'a> {
+    schema_version: u8,
+    label: &'a str,
+    message: &'a str,
+    color: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_seconds: Option<u64>,
+}
+
+fn shield_json(options: &BadgeOptions, cache_seconds: Option<u64>) -> String {
+    let endpoint = ShieldEndpoint {
+        schema_version: 1,
+        label: &options.subject,
+        message: &options.status,
+        // shields.io takes hex colors without the leading `#`
+        color: options.color.trim_start_matches('#'),
+        cache_seconds,
+    };
+
+    serde_json::to_string(&endpoint).unwrap()
+}
+
+fn badge_response(
+    options: BadgeOptions,
+    format: BadgeFormat,
+    cache_seconds: Option<u64>,
+) -> Response<Body> {
+    let (content_type, body) = match format {
+        BadgeFormat::Svg => (
+            "image/svg+xml; charset=utf-8",
+            Body::from(BADGE_CACHE.to_svg(options)),
+        ),
+        BadgeFormat::ShieldJson => (
+            "application/json; charset=utf-8",
+            Body::from(shield_json(&options, cache_seconds)),
+        ),
+        BadgeFormat::Png => match badge::svg_to_png(&BADGE_CACHE.to_svg(options)) {
+            Ok(png) => ("image/png", Body::from(png)),
+            Err(_) => {
+                return Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+                    .body(Body::from("failed to render badge\n"))
+                    .unwrap()
+            }
+        },
+    };
+
+    let mut builder = Response::builder().header(CONTENT_TYPE, content_type);
+    if let Some(secs) = cache_seconds {
+        builder = builder.header(CACHE_CONTROL, format!("public, max-age={}", secs));
+    }
+
+    builder.body(body).unwrap()
+}
+
+/// Status of a badge in count mode, e.g. "3 outdated, 1 insecure", colored by severity
+fn count_status(
+    outcome: &AnalyzeDependenciesOutcome,
+    badge_knobs: &ExtraConfig,
+) -> (String, &'static str) {
+    let scope = badge_knobs.dep_scope();
+    let (outdated, _) = outcome.outdated_ratio(scope);
+    let insecure = outcome.count_insecure(scope);
+    let unavailable = outcome.count_unavailable();
+    let yanked = outcome.count_yanked();
+
+    let mut parts = Vec::new();
+    if outdated > 0 || (insecure == 0 && unavailable == 0 && yanked == 0) {
+        parts.push(format!("{} outdated", outdated));
+    }
+    if insecure > 0 {
+        parts.push(format!("{} insecure", insecure));
+    }
+    if unavailable > 0 {
+        parts.push(format!("{} unavailable", unavailable));
+    }
+    if yanked > 0 {
+        parts.push(format!("{} yanked", yanked));
+    }
+
+    let color = match outdated {
+        _ if insecure > 0 || unavailable > 0 || yanked > 0 => "#e05d44",
+        0 => "#4c1",
+        _ if !lags_enough(outcome, badge_knobs) => "#97ca00",
+        1..=2 => "#dfb317",
+        3..=9 => "#fe7d37",
+        _ => "#e05d44",
+    };
+
+    (parts.join(", "), color)
+}
+
+/// Checks if any outdated dependency lags enough behind to warrant a warning color
+fn lags_enough(outcome: &AnalyzeDependenciesOutcome, badge_knobs: &ExtraConfig) -> bool {
+    match (
+        badge_knobs.fail_on.min_lag(),
+        outcome.max_lag(badge_knobs.dep_scope()),
+    ) {
+        (Some(min_lag), Some(lag)) => lag >= min_lag,
+        _ => false,
+    }
+}
+
+/// Style chosen in the badge URL, or else the one the repository's `.deps-rs.toml` asks for
+fn badge_style(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> BadgeStyle {
+    badge_knobs
+        .style
+        .or_else(|| analysis_outcome.and_then(|outcome| outcome.badge_style))
+        .unwrap_or_default()
+}
+
+fn badge_options(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> BadgeOptions {
+    let (status, color) = match analysis_outcome {
+        Some(outcome) if badge_knobs.display == BadgeDisplay::Count => {
+            count_status(outcome, badge_knobs)
+        }
         Some(outcome) => {
-            if outcome.any_insecure() {
-                BadgeOptions {
-                    subject: "dependencies".into(),
-                    status: "insecure".into(),
-                    color: "#e05d44".into(),
-                }
+            if outcome.count_insecure(badge_knobs.dep_scope()) > 0 {
+                ("insecure".into(), "#e05d44")
+            } else if outcome.any_unavailable() {
+                ("unavailable".into(), "#e05d44")
+            } else if outcome.any_yanked() {
+                ("yanked".into(), "#e05d44")
             } else {
-                let (outdated, total) = outcome.outdated_ratio();
-
-                if outdated > 0 {
-                    BadgeOptions {
-                        subject: "dependencies".into(),
-                        status: format!("{} of {} outdated", outdated, total),
-                        color: "#dfb317".into(),
-                    }
+                let (outdated, total) = outcome.outdated_ratio(badge_knobs.dep_scope());
+
+                if outdated > 0 && lags_enough(outcome, badge_knobs) {
+                    (format!("{} of {} outdated", outdated, total), "#dfb317")
+                } else if outdated > 0 {
+                    (format!("{} of {} outdated", outdated, total), "#97ca00")
                 } else if total > 0 {
-                    BadgeOptions {
-                        subject: "dependencies".into(),
-                        status: "up to date".into(),
-                        color: "#4c1".into(),
-                    }
+                    ("up to date".into(), "#4c1")
                 } else {
-                    BadgeOptions {
-                        subject: "dependencies".into(),
-                        status: "none".into(),
-                        color: "#4c1".into(),
-                    }
+                    ("none".into(), "#4c1")
                 }
             }
         }
-        None => BadgeOptions {
-            subject: "dependencies".into(),
-            status: "unknown".into(),
-            color: "#9f9f9f".into(),
+        None => ("unknown".into(), "#9f9f9f"),
+    };
+
+    BadgeOptions {
+        subject: "dependencies".into(),
+        status,
+        color: color.into(),
+        variant: badge_knobs.variant,
+        style: badge_style(analysis_outcome, badge_knobs),
+    }
+}
+
+pub fn badge(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> Badge {
+    Badge::new(badge_options(analysis_outcome, badge_knobs))
+}
+
+pub fn response(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+    format: BadgeFormat,
+) -> Response<Body> {
+    badge_response(
+        badge_options(analysis_outcome, badge_knobs),
+        format,
+        badge_knobs.cache_seconds,
+    )
+}
+
+fn org_badge_options(summary: OrgSummary, badge_knobs: &ExtraConfig) -> BadgeOptions {
+    let repos = match summary.repos {
+        1 => "1 repo".to_string(),
+        n => format!("{} repos", n),
+    };
+
+    let (status, color) = match summary {
+        OrgSummary { repos: 0, .. } => ("unknown".into(), "#9f9f9f"),
+        OrgSummary { with_issues: 0, .. } => (format!("{}, all up to date", repos), "#4c1"),
+        OrgSummary { with_issues, .. } => {
+            (format!("{}, {} with issues", repos, with_issues), "#dfb317")
+        }
+    };
+
+    BadgeOptions {
+        subject: "dependencies".into(),
+        status,
+        color: color.into(),
+        variant: badge_knobs.variant,
+        style: badge_knobs.style.unwrap_or_default(),
+    }
+}
+
+fn msrv_badge_options(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> BadgeOptions {
+    let (status, color) = match analysis_outcome {
+        Some(outcome) => match (outcome.msrv(), outcome.count_exceeding_msrv()) {
+            (None, _) => ("not declared".into(), "#9f9f9f"),
+            (Some(msrv), 0) => (msrv.to_string(), "#007ec6"),
+            (Some(msrv), 1) => (format!("{}, 1 dependency needs newer", msrv), "#dfb317"),
+            (Some(msrv), n) => (
+                format!("{}, {} dependencies need newer", msrv, n),
+                "#dfb317",
+            ),
         },
+        None => ("unknown".into(), "#9f9f9f"),
     };
 
-    Badge::new(opts)
+    BadgeOptions {
+        subject: "msrv".into(),
+        status,
+        color: color.into(),
+        variant: badge_knobs.variant,
+        style: badge_style(analysis_outcome, badge_knobs),
+    }
 }
 
-pub fn response(analysis_outcome: Option<&AnalyzeDependenciesOutcome>) -> Response<Body> {
-    let badge = badge(analysis_outcome).to_svg();
+/// Responds with a badge showing the minimum supported Rust version a repository declares.
+pub fn msrv_response(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> Response<Body> {
+    badge_response(
+        msrv_badge_options(analysis_outcome, badge_knobs),
+        BadgeFormat::Svg,
+        badge_knobs.cache_seconds,
+    )
+}
+
+fn license_badge_options(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> BadgeOptions {
+    let (status, color) = match analysis_outcome.map(|outcome| outcome.license_counts()) {
+        Some(counts) if counts.is_empty() => ("none".into(), "#4c1"),
+        Some(counts) => {
+            // only the most common licenses fit on a badge
+            let mut parts: Vec<String> = counts
+                .iter()
+                .take(2)
+                .map(|(license, count)| format!("{} {}", count, license.unwrap_or("unknown")))
+                .collect();
+            let other: usize = counts.iter().skip(2).map(|(_, count)| count).sum();
+            if other > 0 {
+                parts.push(format!("{} other", other));
+            }
+
+            (parts.join(", "), "#007ec6")
+        }
+        None => ("unknown".into(), "#9f9f9f"),
+    };
+
+    BadgeOptions {
+        subject: "licenses".into(),
+        status,
+        color: color.into(),
+        variant: badge_knobs.variant,
+        style: badge_style(analysis_outcome, badge_knobs),
+    }
+}
+
+/// Responds with a badge summarizing the licenses of the dependencies, most common first.
+pub fn license_response(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    badge_knobs: &ExtraConfig,
+) -> Response<Body> {
+    badge_response(
+        license_badge_options(analysis_outcome, badge_knobs),
+        BadgeFormat::Svg,
+        badge_knobs.cache_seconds,
+    )
+}
+
+/// Responds with a badge summarizing the repositories of an organization.
+pub fn org_response(summary: OrgSummary, badge_knobs: &ExtraConfig) -> Response<Body> {
+    badge_response(
+        org_badge_options(summary, badge_knobs),
+        BadgeFormat::Svg,
+        badge_knobs.cache_seconds,
+    )
+}
+
+/// Responds with a badge indicating that the analysis is still running.
+///
+/// The short max-age makes clients come back for the actual status soon.
+pub fn placeholder_response(
+    badge_knobs: &ExtraConfig,
+    subject: &str,
+    format: BadgeFormat,
+) -> Response<Body> {
+    let options = BadgeOptions {
+        subject: subject.into(),
+        status: "checking…".into(),
+        color: "#9f9f9f".into(),
+        variant: badge_knobs.variant,
+        style: badge_knobs.style.unwrap_or_default(),
+    };
 
-    Response::builder()
-        .header(CONTENT_TYPE, "image/svg+xml; charset=utf-8")
-        .body(Body::from(badge))
-        .unwrap()
+    badge_response(options, format, Some(10))
 }
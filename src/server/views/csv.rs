@@ -0,0 +1,79 @@
+//! Analysis results as CSV, with one row per dependency of each crate.
+
+use hyper::{header::CONTENT_TYPE, Body, Response, StatusCode};
+use semver::Version;
+
+use crate::engine::AnalyzeDependenciesOutcome;
+
+const HEADER: &str = "crate,kind,dependency,required,latest_that_matches,latest,status";
+
+/// Quotes a field if it contains characters with a special meaning in CSV
+fn field(value: &str) -> String {
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+fn render(outcome: &AnalyzeDependenciesOutcome) -> String {
+    let mut output = String::from(HEADER);
+    output.push('\n');
+
+    for (crate_name, deps) in &outcome.crates {
+        for (kind, deps) in &[
+            ("normal", &deps.main),
+            ("dev", &deps.dev),
+            ("build", &deps.build),
+        ] {
+            for (name, dep) in deps.iter() {
+                let version = |version: &Option<Version>| {
+                    version
+                        .as_ref()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                };
+
+                let row = [
+                    field(crate_name.as_ref()),
+                    kind.to_string(),
+                    field(name.as_ref()),
+                    field(&dep.required.to_string()),
+                    version(&dep.latest_that_matches),
+                    version(&dep.latest),
+                    super::dependency_status(dep).to_owned(),
+                ];
+                output.push_str(&row.join(","));
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+pub fn response(analysis_outcome: Option<&AnalyzeDependenciesOutcome>) -> Response<Body> {
+    match analysis_outcome {
+        Some(outcome) => Response::builder()
+            .header(CONTENT_TYPE, "text/csv; charset=utf-8")
+            .body(Body::from(render(outcome)))
+            .unwrap(),
+        None => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Body::from("analysis failed\n"))
+            .unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_special_fields() {
+        assert_eq!(field("serde"), "serde");
+        assert_eq!(field(">=1.0, <2.0"), "\">=1.0, <2.0\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,178 @@
+//! Atom feed of the issues found in a repository over time.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::{header::CONTENT_TYPE, Body, Response};
+use maud::html;
+
+use crate::engine::{FeedEntry, FeedEvent, RepoAnalysisOptions};
+use crate::models::repo::RepoPath;
+use crate::server::views::html::status::options_query;
+use crate::server::SELF_BASE_URL;
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+
+/// Splits a time into the days since the Unix epoch and the UTC date and seconds of that day
+fn civil_time(time: SystemTime) -> (u64, (i64, i64, i64), u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (days, (year, month, day), secs_of_day)
+}
+
+/// Formats a time as an RFC 3339 timestamp in UTC, as Atom requires
+pub fn rfc3339(time: SystemTime) -> String {
+    let (_, (year, month, day), secs_of_day) = civil_time(time);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Formats a time as an HTTP date, e.g. for the `Last-Modified` header
+pub fn http_date(time: SystemTime) -> String {
+    // the epoch was a Thursday
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (days, (year, month, day), secs_of_day) = civil_time(time);
+
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+fn entry_title(event: &FeedEvent) -> String {
+    match event {
+        FeedEvent::Outdated { name, latest } => {
+            format!("{} {} is available", name.as_ref(), latest)
+        }
+        FeedEvent::Advisory { name, id, title } => {
+            format!("{}: {} ({})", name.as_ref(), title, id)
+        }
+    }
+}
+
+fn entry_link(event: &FeedEvent, status_url: &str) -> String {
+    match event {
+        FeedEvent::Outdated { .. } => status_url.to_owned(),
+        FeedEvent::Advisory { id, .. } => format!("https://rustsec.org/advisories/{}.html", id),
+    }
+}
+
+fn entry_summary(event: &FeedEvent) -> String {
+    match event {
+        FeedEvent::Outdated { name, latest } => format!(
+            "The dependency {} is outdated, its latest release is {}.",
+            name.as_ref(),
+            latest
+        ),
+        FeedEvent::Advisory { name, id, .. } => format!(
+            "The security advisory {} affects the dependency {}.",
+            id,
+            name.as_ref()
+        ),
+    }
+}
+
+fn render(entries: &[FeedEntry], repo_path: &RepoPath) -> String {
+    let repo_url = format!(
+        "{}/repo/{}/{}/{}",
+        &SELF_BASE_URL as &str,
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref()
+    );
+    let query = options_query(Some(&repo_path.site), &RepoAnalysisOptions::default());
+    let status_url = format!("{}{}", repo_url, query);
+    let feed_url = format!("{}/feed.atom{}", repo_url, query);
+    let updated = entries
+        .first()
+        .map(|entry| entry.time)
+        .unwrap_or_else(SystemTime::now);
+
+    let feed = html! {
+        feed xmlns="http://www.w3.org/2005/Atom" {
+            id { (feed_url) }
+            title { (repo_path.qual.as_ref()) " / " (repo_path.name.as_ref()) " dependencies - Deps.rs" }
+            updated { (rfc3339(updated)) }
+            link rel="self" href=(feed_url) {}
+            link rel="alternate" type="text/html" href=(status_url) {}
+            author { name { "Deps.rs" } }
+
+            @for entry in entries {
+                entry {
+                    id { (feed_url) "#" (entry.key) }
+                    title { (entry_title(&entry.event)) }
+                    updated { (rfc3339(entry.time)) }
+                    link rel="alternate" href=(entry_link(&entry.event, &status_url)) {}
+                    summary { (entry_summary(&entry.event)) }
+                }
+            }
+        }
+    };
+
+    format!("{}{}", XML_DECLARATION, feed.into_string())
+}
+
+/// Serves the feed of a repository, newest entries first
+pub fn response(entries: &[FeedEntry], repo_path: &RepoPath) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/atom+xml; charset=utf-8")
+        .body(Body::from(render(entries, repo_path)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn formats_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "2000-02-29T12:34:56Z"
+        );
+    }
+
+    #[test]
+    fn formats_http_date() {
+        // example from RFC 7231
+        assert_eq!(
+            http_date(UNIX_EPOCH + Duration::from_secs(784_111_777)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use hyper::{Body, Response};
+use maud::{html, Markup};
+
+use crate::models::crates::{AnalyzedDependency, CrateName, DependencyChange};
+use crate::models::SubjectPath;
+
+use super::i18n::Locale;
+use super::status::{render_title, status_tag};
+
+fn render_side(locale: Locale, dep: Option<&AnalyzedDependency>) -> Markup {
+    html! {
+        @if let Some(dep) = dep {
+            code { (dep.required.to_string()) }
+            " "
+            (status_tag(locale, dep))
+        } @else {
+            span class="has-text-grey" { "—" }
+        }
+    }
+}
+
+fn render_change_tag(locale: Locale, change: &DependencyChange) -> Markup {
+    html! {
+        @if change.before.is_none() {
+            span class="tag is-success is-light" { (locale.tr("added")) }
+        } @else if change.after.is_none() {
+            span class="tag is-danger is-light" { (locale.tr("removed")) }
+        } @else {
+            span class="tag is-info is-light" { (locale.tr("changed")) }
+        }
+    }
+}
+
+fn change_table(locale: Locale, crate_name: &CrateName, changes: &[DependencyChange]) -> Markup {
+    html! {
+        h2 class="title is-3" {
+            (locale.tr("Crate "))
+            code { (crate_name.as_ref()) }
+        }
+        table class="table is-fullwidth is-striped is-hoverable" {
+            thead {
+                tr {
+                    th { (locale.tr("Crate")) }
+                    th { (locale.tr("Kind")) }
+                    th class="has-text-right" { (locale.tr("Before")) }
+                    th class="has-text-right" { (locale.tr("After")) }
+                    th class="has-text-right" { (locale.tr("Change")) }
+                }
+            }
+            tbody {
+                @for change in changes {
+                    tr {
+                        td { (change.name.as_ref()) }
+                        td { (locale.tr(change.kind)) }
+                        td class="has-text-right" { (render_side(locale, change.before.as_ref())) }
+                        td class="has-text-right" { (render_side(locale, change.after.as_ref())) }
+                        td class="has-text-right" { (render_change_tag(locale, change)) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the dependencies that changed between two analyses of a subject, `from` and `to`
+/// naming the compared versions
+pub fn render(
+    locale: Locale,
+    subject_path: SubjectPath,
+    from: &str,
+    to: &str,
+    changes: &[(CrateName, Vec<DependencyChange>)],
+) -> Response<Body> {
+    let name = match subject_path {
+        SubjectPath::Repo(ref repo_path) => {
+            format!("{} / {}", repo_path.qual.as_ref(), repo_path.name.as_ref())
+        }
+        SubjectPath::Crate(ref crate_path) => crate_path.name.as_ref().to_owned(),
+    };
+    let title = format!("{} {} → {}", name, from, to);
+
+    let body = html! {
+        section class="hero is-light" {
+            div class="hero-head" { (super::render_navbar()) }
+            div class="hero-body" {
+                div class="container" {
+                    h1 class="title is-1" { (render_title(&subject_path)) }
+                    p class="subtitle" {
+                        (locale.tr("Dependency changes from "))
+                        code { (from) }
+                        (locale.tr(" to "))
+                        code { (to) }
+                    }
+                }
+            }
+        }
+        section class="section" {
+            div class="container" {
+                @if changes.is_empty() {
+                    p class="notification has-text-centered" { (locale.tr("No dependencies have changed.")) }
+                }
+                @for (crate_name, crate_changes) in changes {
+                    (change_table(locale, crate_name, crate_changes))
+                }
+            }
+        }
+        (super::render_footer(locale, None))
+    };
+
+    super::render_html(locale, &title, body)
+}
@@ -6,8 +6,11 @@ use maud::html;
 
 use crate::server::assets::STATIC_STYLE_CSS_PATH;
 
+use super::i18n::Locale;
+
 pub fn render(title: &str, descr: &str) -> Response<Body> {
     super::render_html(
+        Locale::En,
         title,
         html! {
             section class="hero is-light" {
@@ -21,14 +24,14 @@ pub fn render(title: &str, descr: &str) -> Response<Body> {
                     }
                 }
             }
-            (super::render_footer(None))
+            (super::render_footer(Locale::En, None))
         },
     )
 }
 
 pub fn render_404() -> Response<Body> {
     let rendered = html! {
-        html {
+        html lang=(Locale::En.as_str()) {
             head {
                 meta charset="utf-8";
                 meta name="viewport" content="width=device-width, initial-scale=1";
@@ -50,7 +53,7 @@ pub fn render_404() -> Response<Body> {
                         }
                     }
                 }
-                (super::render_footer(None))
+                (super::render_footer(Locale::En, None))
             }
         }
     };
@@ -0,0 +1,192 @@
+use hyper::{Body, Response};
+use indexmap::IndexMap;
+use maud::{html, Markup};
+
+use crate::engine::{AnalyzeDependenciesOutcome, RepoAnalysisOptions};
+use crate::models::crates::{AnalyzedDependency, CrateName};
+use crate::models::repo::RepoPath;
+use crate::models::SubjectPath;
+use crate::server::views::dependency_status;
+
+use super::i18n::Locale;
+use super::status::{options_query, render_title};
+
+const ROW_HEIGHT: usize = 28;
+const NODE_HEIGHT: usize = 20;
+const NODE_WIDTH: usize = 240;
+/// Room on the left for the arcs between workspace crates
+const MEMBER_X: usize = 80;
+const EXTERNAL_X: usize = 640;
+
+/// Highlights the edges of a workspace crate while hovering it
+const GRAPH_STYLE: &str = "
+.graph-member path { fill: none; stroke: #b5b5b5; stroke-opacity: 0.6; }
+.graph-member:hover path { stroke: #3273dc; stroke-opacity: 1; stroke-width: 2; }
+.graph-member .dev { stroke-dasharray: 4 3; }
+.graph text { font-size: 12px; dominant-baseline: middle; }
+";
+
+/// Returns the node color of a dependency status, matching the badge colors
+fn status_color(status: &str) -> &'static str {
+    match status {
+        "up-to-date" => "#4c1",
+        "outdated" => "#dfb317",
+        "external-registry" | "patched" => "#9f9f9f",
+        _ => "#e05d44",
+    }
+}
+
+/// Orders statuses by severity, so a crate used by several members is shown in the worst one
+fn status_rank(status: &str) -> u8 {
+    match status {
+        "up-to-date" => 0,
+        "external-registry" | "patched" => 1,
+        "outdated" => 2,
+        _ => 3,
+    }
+}
+
+fn node_y(idx: usize) -> usize {
+    idx * ROW_HEIGHT + (ROW_HEIGHT - NODE_HEIGHT) / 2
+}
+
+fn edge_y(idx: usize) -> usize {
+    node_y(idx) + NODE_HEIGHT / 2
+}
+
+/// Returns how far left the arc between two workspace crates bulges, more for distant rows
+fn arc_control_x(from: usize, to: usize) -> usize {
+    let span = if from > to { from - to } else { to - from };
+    MEMBER_X - (MEMBER_X - 10).min(20 + 10 * span)
+}
+
+fn render_graph(outcome: &AnalyzeDependenciesOutcome) -> Markup {
+    let members: Vec<&CrateName> = outcome.crates.iter().map(|(name, _)| name).collect();
+
+    let mut externals: IndexMap<&CrateName, (&AnalyzedDependency, &'static str)> = IndexMap::new();
+    for (name, dep) in outcome
+        .crates
+        .iter()
+        .flat_map(|(_, deps)| deps.main.iter().chain(&deps.dev).chain(&deps.build))
+    {
+        let status = dependency_status(dep);
+        let entry = externals.entry(name).or_insert((dep, status));
+        if status_rank(status) > status_rank(entry.1) {
+            *entry = (dep, status);
+        }
+    }
+    externals.sort_keys();
+
+    let member_idx = |name: &CrateName| members.iter().position(|member| *member == name);
+    let rows = members.len().max(externals.len());
+    let height = rows * ROW_HEIGHT;
+    let width = EXTERNAL_X + NODE_WIDTH;
+
+    html! {
+        svg class="graph" xmlns="http://www.w3.org/2000/svg" viewBox=(format!("0 0 {} {}", width, height)) width=(width) height=(height) {
+            style { (GRAPH_STYLE) }
+
+            @for (idx, (name, deps)) in outcome.crates.iter().enumerate() {
+                g class="graph-member" {
+                    @for (dep_name, is_dev) in deps.main.keys().map(|n| (n, false)).chain(deps.build.keys().map(|n| (n, false))).chain(deps.dev.keys().map(|n| (n, true))) {
+                        @if let Some(target) = externals.get_index_of(dep_name) {
+                            path.dev[is_dev] d=(format!(
+                                "M {x1} {y1} C {mx} {y1}, {mx} {y2}, {x2} {y2}",
+                                x1 = MEMBER_X + NODE_WIDTH,
+                                x2 = EXTERNAL_X,
+                                mx = (MEMBER_X + NODE_WIDTH + EXTERNAL_X) / 2,
+                                y1 = edge_y(idx),
+                                y2 = edge_y(target),
+                            )) {}
+                        }
+                    }
+                    @for (_, dependency) in outcome.internal_deps.iter().filter(|(dependent, _)| dependent == name) {
+                        @if let Some(target) = member_idx(dependency) {
+                            path d=(format!(
+                                "M {x} {y1} C {cx} {y1}, {cx} {y2}, {x} {y2}",
+                                x = MEMBER_X,
+                                cx = arc_control_x(idx, target),
+                                y1 = edge_y(idx),
+                                y2 = edge_y(target),
+                            )) {}
+                        }
+                    }
+                    rect x=(MEMBER_X) y=(node_y(idx)) width=(NODE_WIDTH) height=(NODE_HEIGHT) rx="4" fill="#363636" {
+                        title { (name.as_ref()) }
+                    }
+                    text x=(MEMBER_X + 8) y=(edge_y(idx)) fill="#fff" { (name.as_ref()) }
+                }
+            }
+
+            @for (idx, (name, (dep, status))) in externals.iter().enumerate() {
+                a href=(dep.deps_rs_path(name.as_ref())) {
+                    rect x=(EXTERNAL_X) y=(node_y(idx)) width=(NODE_WIDTH) height=(NODE_HEIGHT) rx="4" fill=(status_color(status)) {
+                        title { (name.as_ref()) " " (dep.required) ": " (status) }
+                    }
+                    text x=(EXTERNAL_X + 8) y=(edge_y(idx)) fill="#fff" { (name.as_ref()) }
+                }
+            }
+        }
+    }
+}
+
+fn render_legend() -> Markup {
+    html! {
+        p class="is-size-7" {
+            span class="tag is-dark" { "workspace crate" } " "
+            @for status in &["up-to-date", "outdated", "insecure", "external-registry", "patched"] {
+                span class="tag" style=(format!("background-color: {}; color: #fff;", status_color(status))) { (status) } " "
+            }
+            "Dashed edges are dev-dependencies. Hover a workspace crate to highlight its dependencies."
+        }
+    }
+}
+
+/// Renders the dependency graph of a repository: its workspace crates, the path dependencies
+/// between them and their external dependencies colored by status
+pub fn render(
+    analysis_outcome: AnalyzeDependenciesOutcome,
+    repo_path: RepoPath,
+    options: &RepoAnalysisOptions,
+) -> Response<Body> {
+    let title = format!(
+        "{} / {} dependency graph",
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref()
+    );
+    let status_url = format!(
+        "{}/repo/{}/{}/{}{}",
+        &super::SELF_BASE_URL as &str,
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref(),
+        options_query(Some(&repo_path.site), options)
+    );
+    let subject_path = SubjectPath::Repo(repo_path);
+
+    let body = html! {
+        section class="hero is-light" {
+            div class="hero-head" { (super::render_navbar()) }
+            div class="hero-body" {
+                div class="container" {
+                    h1 class="title is-1" { (render_title(&subject_path)) }
+                    p class="subtitle" {
+                        "Dependency graph — back to the "
+                        a href=(status_url) { "analysis" }
+                    }
+                }
+            }
+        }
+        section class="section" {
+            div class="container" {
+                (render_legend())
+                div style="overflow-x: auto;" {
+                    (render_graph(&analysis_outcome))
+                }
+            }
+        }
+        (super::render_footer(Locale::En, Some(analysis_outcome.duration)))
+    };
+
+    super::render_html(Locale::En, &title, body)
+}
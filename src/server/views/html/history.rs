@@ -0,0 +1,179 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use hyper::{Body, Response};
+use maud::{html, Markup};
+
+use crate::engine::{format_snapshot_id, RepoAnalysisOptions, Snapshot};
+use crate::models::repo::RepoPath;
+use crate::models::SubjectPath;
+use crate::server::views::feed::rfc3339;
+
+use super::i18n::Locale;
+use super::status::{options_query, render_title};
+
+const CHART_WIDTH: usize = 800;
+const CHART_HEIGHT: usize = 240;
+/// Room around the plot for the axis labels
+const CHART_MARGIN: usize = 40;
+
+/// Lines of the chart with their colors, matching the badge colors
+const SERIES: [(&str, &str); 3] = [
+    ("total", "#9f9f9f"),
+    ("outdated", "#dfb317"),
+    ("insecure", "#e05d44"),
+];
+
+fn series_value(snapshot: &Snapshot, series: &str) -> usize {
+    match series {
+        "total" => snapshot.total,
+        "outdated" => snapshot.outdated,
+        _ => snapshot.insecure,
+    }
+}
+
+/// Formats the day of a snapshot, e.g. `2021-03-14`
+fn snapshot_date(snapshot: &Snapshot) -> String {
+    let mut date = rfc3339(UNIX_EPOCH + Duration::from_secs(snapshot.time));
+    date.truncate(10);
+    date
+}
+
+/// Plots the counts of each snapshot, spread evenly over the time between the first and the
+/// last one
+fn render_chart(locale: Locale, snapshots: &[Snapshot]) -> Markup {
+    let first = snapshots.first().map_or(0, |snapshot| snapshot.time);
+    let last = snapshots.last().map_or(0, |snapshot| snapshot.time);
+    let max = snapshots
+        .iter()
+        .map(|snapshot| snapshot.total)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let plot_width = CHART_WIDTH - 2 * CHART_MARGIN;
+    let plot_height = CHART_HEIGHT - 2 * CHART_MARGIN;
+    let x = |snapshot: &Snapshot| match last - first {
+        0 => CHART_MARGIN + plot_width / 2,
+        span => CHART_MARGIN + ((snapshot.time - first) * plot_width as u64 / span) as usize,
+    };
+    let y = |value: usize| CHART_MARGIN + plot_height - value * plot_height / max;
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" viewBox=(format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT)) width="100%" {
+            line x1=(CHART_MARGIN) y1=(y(0)) x2=(CHART_WIDTH - CHART_MARGIN) y2=(y(0)) stroke="#dbdbdb" {}
+            text x=(CHART_MARGIN - 8) y=(y(0)) text-anchor="end" font-size="12" { "0" }
+            text x=(CHART_MARGIN - 8) y=(y(max)) text-anchor="end" font-size="12" { (max) }
+            @if let (Some(first), Some(last)) = (snapshots.first(), snapshots.last()) {
+                text x=(CHART_MARGIN) y=(CHART_HEIGHT - 12) font-size="12" { (snapshot_date(first)) }
+                text x=(CHART_WIDTH - CHART_MARGIN) y=(CHART_HEIGHT - 12) text-anchor="end" font-size="12" { (snapshot_date(last)) }
+            }
+
+            @for (series, color) in &SERIES {
+                polyline fill="none" stroke=(color) stroke-width="2" points=(snapshots.iter().map(|snapshot| format!("{},{}", x(snapshot), y(series_value(snapshot, series)))).collect::<Vec<_>>().join(" ")) {}
+                @for snapshot in snapshots {
+                    circle cx=(x(snapshot)) cy=(y(series_value(snapshot, series))) r="3" fill=(color) {
+                        title { (snapshot_date(snapshot)) ": " (series_value(snapshot, series)) " " (locale.tr(*series)) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_legend(locale: Locale) -> Markup {
+    html! {
+        p class="is-size-7" {
+            @for (series, color) in &SERIES {
+                span class="tag" style=(format!("background-color: {}; color: #fff;", color)) { (locale.tr(*series)) } " "
+            }
+        }
+    }
+}
+
+/// Lists the snapshots whose analysis is frozen, newest first, linking to their permalinks
+fn render_permalinks(locale: Locale, snapshots: &[Snapshot], repo_url: &str) -> Markup {
+    html! {
+        @if snapshots.iter().any(|snapshot| snapshot.id.is_some()) {
+            h2 class="title is-4" { (locale.tr("Snapshots")) }
+            table class="table is-fullwidth is-striped is-hoverable" {
+                thead {
+                    tr {
+                        th { (locale.tr("Date")) }
+                        @for (series, _) in &SERIES {
+                            th class="has-text-right" { (locale.tr(*series)) }
+                        }
+                    }
+                }
+                tbody {
+                    @for snapshot in snapshots.iter().rev() {
+                        @if let Some(id) = snapshot.id {
+                            tr {
+                                td {
+                                    a href=(format!("{}/snapshot/{}", repo_url, format_snapshot_id(id))) { (snapshot_date(snapshot)) }
+                                }
+                                @for (series, _) in &SERIES {
+                                    td class="has-text-right" { (series_value(snapshot, series)) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders how the counts of a repository's main and build dependencies evolved, from one
+/// snapshot per day
+pub fn render(locale: Locale, snapshots: &[Snapshot], repo_path: RepoPath) -> Response<Body> {
+    let title = format!(
+        "{} / {} {}",
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref(),
+        locale.tr("history")
+    );
+    let repo_url = format!(
+        "{}/repo/{}/{}/{}",
+        &super::SELF_BASE_URL as &str,
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref()
+    );
+    let status_url = format!(
+        "{}{}",
+        repo_url,
+        options_query(Some(&repo_path.site), &RepoAnalysisOptions::default())
+    );
+    let subject_path = SubjectPath::Repo(repo_path);
+
+    let body = html! {
+        section class="hero is-light" {
+            div class="hero-head" { (super::render_navbar()) }
+            div class="hero-body" {
+                div class="container" {
+                    h1 class="title is-1" { (render_title(&subject_path)) }
+                    p class="subtitle" {
+                        (locale.tr("History — back to the "))
+                        a href=(status_url) { (locale.tr("analysis")) }
+                    }
+                }
+            }
+        }
+        section class="section" {
+            div class="container" {
+                @if snapshots.is_empty() {
+                    p class="notification has-text-centered" {
+                        (locale.tr("No history has been recorded yet. From now on, the dependencies are counted once a day."))
+                    }
+                } @else {
+                    (render_legend(locale))
+                    (render_chart(locale, snapshots))
+                    (render_permalinks(locale, snapshots, &repo_url))
+                }
+            }
+        }
+        (super::render_footer(locale, None))
+    };
+
+    super::render_html(locale, &title, body)
+}
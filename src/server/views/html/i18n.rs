@@ -0,0 +1,433 @@
+//! Translations of the HTML pages. Messages are looked up gettext-style by their English text,
+//! which is also what a locale shows for messages it has no translation of.
+
+use std::{cmp::Ordering, collections::HashMap, fmt};
+
+use hyper::header::ACCEPT_LANGUAGE;
+use hyper::{Body, Request};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+/// A language the HTML pages are available in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    /// Language tag of the locale, e.g. for the `lang` attribute of a page
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    /// Matches a language tag like `de` or `de-AT` by its primary language
+    fn from_tag(tag: &str) -> Option<Locale> {
+        let primary = tag.split('-').next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("en") {
+            Some(Locale::En)
+        } else if primary.eq_ignore_ascii_case("de") {
+            Some(Locale::De)
+        } else {
+            None
+        }
+    }
+
+    /// Picks the locale for a request from the `lang` query parameter, or else the most
+    /// preferred language in the `Accept-Language` header that there are translations for
+    pub fn negotiate(req: &Request<Body>) -> Locale {
+        #[derive(Deserialize)]
+        struct LangQuery {
+            lang: Option<String>,
+        }
+
+        let from_query = req
+            .uri()
+            .query()
+            .and_then(|qs| serde_urlencoded::from_str::<LangQuery>(qs).ok())
+            .and_then(|query| query.lang)
+            .and_then(|lang| Locale::from_tag(lang.trim()));
+
+        let from_accept = || {
+            let accept = req.headers().get(ACCEPT_LANGUAGE)?.to_str().ok()?;
+
+            let mut ranges = accept
+                .split(',')
+                .filter_map(|range| {
+                    let mut parts = range.split(';');
+                    let locale = Locale::from_tag(parts.next()?.trim())?;
+                    let quality = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                        Some(quality) => quality.trim().parse::<f32>().ok()?,
+                        None => 1.0,
+                    };
+                    Some((quality, locale))
+                })
+                .filter(|&(quality, _)| quality > 0.0)
+                .collect::<Vec<_>>();
+
+            // the sort is stable, equally preferred languages keep their order
+            ranges.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+            ranges.first().map(|&(_, locale)| locale)
+        };
+
+        from_query.or_else(from_accept).unwrap_or_default()
+    }
+
+    /// Translates a message, given by its English text
+    pub fn tr(self, msgid: &'static str) -> &'static str {
+        let catalog = match self {
+            Locale::En => return msgid,
+            Locale::De => &*DE,
+        };
+
+        catalog.get(msgid).copied().unwrap_or(msgid)
+    }
+
+    /// Translates a message with `{}` placeholders and fills them in with `args`, in order
+    pub fn format(self, msgid: &'static str, args: &[&dyn fmt::Display]) -> String {
+        let mut pieces = self.tr(msgid).split("{}");
+        let mut message = pieces.next().unwrap_or_default().to_owned();
+
+        for (piece, arg) in pieces.zip(args) {
+            message.push_str(&arg.to_string());
+            message.push_str(piece);
+        }
+
+        message
+    }
+}
+
+static DE: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
+    IntoIterator::into_iter([
+        // footer
+        (
+            " is a service for the Rust community. It is open source on ",
+            " ist ein Dienst für die Rust-Community. Der Quellcode ist offen auf ",
+        ),
+        ("Please report any issues on the ", "Probleme meldest du bitte im "),
+        ("issue tracker", "Issue-Tracker"),
+        ("(rendered in {} ms)", "(in {} ms gerendert)"),
+        // index page
+        (
+            "Keep your dependencies up-to-date",
+            "Halte deine Abhängigkeiten aktuell",
+        ),
+        (
+            "Deps.rs uses semantic versioning to detect outdated or insecure dependencies in your project's ",
+            "Deps.rs erkennt anhand semantischer Versionierung veraltete oder unsichere Abhängigkeiten im Manifest deines Projekts, der ",
+        ),
+        ("Popular Repositories", "Beliebte Repositories"),
+        ("Popular Crates", "Beliebte Crates"),
+        ("Recently checked", "Kürzlich geprüft"),
+        ("Repository or crate", "Repository oder Crate"),
+        // status page
+        ("at ", "Stand "),
+        ("Dependency graph", "Abhängigkeitsgraph"),
+        ("Atom feed", "Atom-Feed"),
+        ("History", "Verlauf"),
+        (
+            "deps.rs is currently in maintenance mode. This is ",
+            "deps.rs befindet sich gerade im Wartungsmodus. Dies ist ",
+        ),
+        ("an earlier analysis", "eine frühere Analyse"),
+        (" which might be out of date.", ", die veraltet sein kann."),
+        ("Permalink to this analysis", "Permalink zu dieser Analyse"),
+        ("This is a ", "Dies ist eine "),
+        ("frozen analysis", "eingefrorene Analyse"),
+        (
+            " from {}, it doesn't change along with the repository. ",
+            " vom {}, sie ändert sich nicht mit dem Repository. ",
+        ),
+        ("See the current status", "Zum aktuellen Status"),
+        (
+            "This repository has more manifests than deps.rs analyzes. ",
+            "Dieses Repository hat mehr Manifeste, als deps.rs analysiert. ",
+        ),
+        (
+            "{} manifests were left out",
+            "{} Manifeste wurden ausgelassen",
+        ),
+        (
+            ", so the dependencies listed here are incomplete.",
+            ", daher sind die hier aufgeführten Abhängigkeiten unvollständig.",
+        ),
+        ("This project contains ", "Dieses Projekt enthält "),
+        ("known security vulnerabilities", "bekannte Sicherheitslücken"),
+        (
+            ". Find detailed information at the ",
+            ". Ausführliche Informationen stehen ",
+        ),
+        ("bottom", "weiter unten"),
+        (
+            "{} insecure development dependencies",
+            "{} unsichere Entwicklungsabhängigkeiten",
+        ),
+        (
+            "{} outdated development dependencies",
+            "{} veraltete Entwicklungsabhängigkeiten",
+        ),
+        (
+            "This project depends on crates which ",
+            "Dieses Projekt hängt von Crates ab, die ",
+        ),
+        (
+            "no longer have any available releases",
+            "keine verfügbaren Releases mehr haben",
+        ),
+        (
+            ", either because they were removed from crates.io or all of their versions were yanked.",
+            ", weil sie entweder von crates.io entfernt oder alle ihre Versionen zurückgezogen wurden.",
+        ),
+        (
+            "This project depends on crates whose requirements ",
+            "Dieses Projekt hängt von Crates ab, deren Anforderungen ",
+        ),
+        (
+            "only allow yanked releases",
+            "nur zurückgezogene Releases erlauben",
+        ),
+        (
+            ". These can't be resolved without an existing Cargo.lock entry.",
+            ". Diese lassen sich ohne bestehenden Eintrag in der Cargo.lock nicht auflösen.",
+        ),
+        ("Filter dependencies by name", "Abhängigkeiten nach Namen filtern"),
+        (
+            "Minimum supported Rust version: ",
+            "Minimal unterstützte Rust-Version: ",
+        ),
+        (
+            "No external dependencies! 🙌",
+            "Keine externen Abhängigkeiten! 🙌",
+        ),
+        ("Dependencies", "Abhängigkeiten"),
+        ("Dev dependencies", "Entwicklungsabhängigkeiten"),
+        ("Build dependencies", "Build-Abhängigkeiten"),
+        ("Git dependencies", "Git-Abhängigkeiten"),
+        (
+            "(not checked for updates or advisories)",
+            "(nicht auf Updates oder Sicherheitshinweise geprüft)",
+        ),
+        ("Repository", "Repository"),
+        ("Branch / revision", "Branch / Revision"),
+        ("default branch", "Standard-Branch"),
+        (
+            "Commits and days the pin is behind the default branch",
+            "Commits und Tage, die der Pin hinter dem Standard-Branch liegt",
+        ),
+        ("{} commits, {} days", "{} Commits, {} Tage"),
+        ("stale", "veraltet"),
+        ("patched", "gepatcht"),
+        (
+            "Features that enable the dependency",
+            "Features, die die Abhängigkeit aktivieren",
+        ),
+        (
+            "Overridden in [patch.crates-io]",
+            "In [patch.crates-io] überschrieben",
+        ),
+        (
+            "This project has git dependencies pinned to ",
+            "Dieses Projekt hat Git-Abhängigkeiten, die auf ",
+        ),
+        (
+            "commits far behind their repository",
+            "Commits weit hinter ihrem Repository",
+        ),
+        (
+            ". They are marked as stale in the git dependency tables.",
+            " festgelegt sind. Sie sind in den Tabellen der Git-Abhängigkeiten als veraltet markiert.",
+        ),
+        (
+            "This project depends on crates that ",
+            "Dieses Projekt hängt von Crates ab, die ",
+        ),
+        (
+            "need a newer Rust than its rust-version ({} dependencies)",
+            "ein neueres Rust als seine rust-version benötigen ({} Abhängigkeiten)",
+        ),
+        (
+            ". Upgrading them may break builds with the declared minimum supported Rust version.",
+            ". Ein Upgrade kann Builds mit der angegebenen minimal unterstützten Rust-Version brechen.",
+        ),
+        ("needs Rust ", "benötigt Rust "),
+        ("edition ", "Edition "),
+        ("Some manifests ", "Einige Manifeste "),
+        ("could not be analyzed", "konnten nicht analysiert werden"),
+        (" and were left out:", " und wurden ausgelassen:"),
+        (
+            "The path dependencies of some manifests ",
+            "Die Pfad-Abhängigkeiten einiger Manifeste ",
+        ),
+        ("lead back to themselves", "führen zu ihnen selbst zurück"),
+        (
+            ", which Cargo rejects. The cycles were not followed:",
+            ", was Cargo ablehnt. Die Zyklen wurden nicht verfolgt:",
+        ),
+        ("{} downloads", "{} Downloads"),
+        ("Analyze repository", "Repository analysieren"),
+        (
+            "Analyze the release on crates.io",
+            "Veröffentlichte Version auf crates.io analysieren",
+        ),
+        (
+            "rust-version of the newest release the requirement allows",
+            "rust-version der neuesten Version, die die Anforderung erlaubt",
+        ),
+        (
+            "rust-version of the newest release",
+            "rust-version der neuesten Version",
+        ),
+        ("({} total, all up-to-date)", "({} insgesamt, alle aktuell)"),
+        ("({} total, {} insecure)", "({} insgesamt, {} unsicher)"),
+        ("({} total, {} outdated)", "({} insgesamt, {} veraltet)"),
+        (
+            "({} total, {} outdated, {} insecure)",
+            "({} insgesamt, {} veraltet, {} unsicher)",
+        ),
+        ("Sort by name", "Nach Namen sortieren"),
+        ("License", "Lizenz"),
+        ("Sort by requirement", "Nach Anforderung sortieren"),
+        ("Required", "Benötigt"),
+        (
+            "Newest release the requirement allows",
+            "Neuestes Release, das die Anforderung erlaubt",
+        ),
+        ("Latest matching", "Neueste passende"),
+        ("Sort by latest version", "Nach neuester Version sortieren"),
+        ("Latest", "Neueste"),
+        ("Behind", "Rückstand"),
+        ("Sort by status", "Nach Status sortieren"),
+        ("Platform-specific dependency", "Plattformspezifische Abhängigkeit"),
+        ("unknown", "unbekannt"),
+        ("Show in Cargo.toml", "In der Cargo.toml anzeigen"),
+        ("Version in Cargo.lock", "Version in der Cargo.lock"),
+        ("locked ", "gesperrt "),
+        (
+            "Changes between the resolved and the latest version",
+            "Änderungen zwischen der aufgelösten und der neuesten Version",
+        ),
+        ("Changelog and releases", "Changelog und Releases"),
+        ("external registry", "externe Registry"),
+        ("unavailable", "nicht verfügbar"),
+        ("insecure", "unsicher"),
+        (
+            "All releases the requirement allows are yanked",
+            "Alle Releases, die die Anforderung erlaubt, wurden zurückgezogen",
+        ),
+        ("yanked", "zurückgezogen"),
+        ("out of date", "veraltet"),
+        ("up to date", "aktuell"),
+        ("Transitive dependencies", "Transitive Abhängigkeiten"),
+        (
+            "No transitive dependencies.",
+            "Keine transitiven Abhängigkeiten.",
+        ),
+        (
+            "Dependencies of dependencies",
+            "Abhängigkeiten von Abhängigkeiten",
+        ),
+        (
+            "Failed to analyze repository",
+            "Das Repository konnte nicht analysiert werden",
+        ),
+        (
+            "The repository you requested might be structured in an uncommon way that is not yet supported.",
+            "Das angefragte Repository ist womöglich auf eine ungewöhnliche Art aufgebaut, die noch nicht unterstützt wird.",
+        ),
+        ("There might be no ", "Womöglich gibt es keine "),
+        (
+            " at the root of the repository. These directories contain one:",
+            " im Hauptverzeichnis des Repositorys. Diese Verzeichnisse enthalten eine:",
+        ),
+        // compare page
+        ("Kind", "Art"),
+        ("Before", "Vorher"),
+        ("After", "Nachher"),
+        ("Change", "Änderung"),
+        ("main", "normal"),
+        ("dev", "Entwicklung"),
+        ("build", "Build"),
+        ("added", "hinzugefügt"),
+        ("removed", "entfernt"),
+        ("changed", "geändert"),
+        ("Dependency changes from ", "Geänderte Abhängigkeiten von "),
+        (" to ", " bis "),
+        ("No dependencies have changed.", "Keine Abhängigkeiten haben sich geändert."),
+        // history page
+        ("history", "Verlauf"),
+        ("History — back to the ", "Verlauf — zurück zur "),
+        ("analysis", "Analyse"),
+        ("total", "insgesamt"),
+        ("outdated", "veraltet"),
+        (
+            "No history has been recorded yet. From now on, the dependencies are counted once a day.",
+            "Bisher wurde kein Verlauf aufgezeichnet. Ab jetzt werden die Abhängigkeiten einmal am Tag gezählt.",
+        ),
+        ("Snapshots", "Momentaufnahmen"),
+        ("Date", "Datum"),
+        // organization dashboard
+        ("Repositories", "Repositories"),
+        ("With issues", "Mit Problemen"),
+        ("Pending", "Ausstehend"),
+        (
+            "Rust repositories, most recently pushed first",
+            "Rust-Repositories, zuletzt aktualisierte zuerst",
+        ),
+        ("No Rust repositories found.", "Keine Rust-Repositories gefunden."),
+        // watchlist page
+        ("Watchlist", "Beobachtungsliste"),
+        ("Logged in as {}", "Angemeldet als {}"),
+        ("Log out", "Abmelden"),
+        (
+            "Log in to follow the status of repositories and crates.",
+            "Melde dich an, um den Status von Repositories und Crates zu verfolgen.",
+        ),
+        ("Log in with GitHub", "Mit GitHub anmelden"),
+        ("Log in with GitLab", "Mit GitLab anmelden"),
+        ("Watch", "Beobachten"),
+        ("Remove", "Entfernen"),
+        (
+            "You aren't watching any repositories or crates yet.",
+            "Du beobachtest noch keine Repositories oder Crates.",
+        ),
+    ])
+    .collect()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_locale() {
+        let negotiate = |uri: &str, accept_language: Option<&str>| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some(accept_language) = accept_language {
+                builder = builder.header(ACCEPT_LANGUAGE, accept_language);
+            }
+            Locale::negotiate(&builder.body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(negotiate("/", None), Locale::En);
+        assert_eq!(negotiate("/", Some("de-AT,de;q=0.9")), Locale::De);
+        assert_eq!(negotiate("/", Some("fr, en;q=0.5, de;q=0.8")), Locale::De);
+        assert_eq!(negotiate("/", Some("de;q=0, fr")), Locale::En);
+        assert_eq!(negotiate("/?lang=en", Some("de")), Locale::En);
+        assert_eq!(negotiate("/?lang=de", None), Locale::De);
+        assert_eq!(negotiate("/?lang=xx", Some("de")), Locale::De);
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        assert_eq!(Locale::De.tr("Latest"), "Neueste");
+        assert_eq!(Locale::De.tr("Crate"), "Crate");
+        assert_eq!(
+            Locale::De.format("({} total, {} outdated)", &[&3, &1]),
+            "(3 insgesamt, 1 veraltet)"
+        );
+    }
+}
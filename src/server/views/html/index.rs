@@ -1,32 +1,100 @@
 use hyper::{Body, Response};
 use maud::{html, Markup};
 
+use crate::engine::RepoAnalysisOptions;
 use crate::models::crates::CratePath;
-use crate::models::repo::Repository;
+use crate::models::repo::{RepoPath, Repository};
+use crate::models::SubjectPath;
 
-fn popular_table(popular_repos: Vec<Repository>, popular_crates: Vec<CratePath>) -> Markup {
+use super::i18n::Locale;
+use super::status::options_query;
+
+/// Links a repository's status page, or with `suffix` one of its other pages
+fn repo_url(repo_path: &RepoPath, suffix: &str) -> String {
+    format!(
+        "{}/repo/{}/{}/{}{}{}",
+        &super::SELF_BASE_URL as &str,
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref(),
+        suffix,
+        options_query(Some(&repo_path.site), &RepoAnalysisOptions::default())
+    )
+}
+
+/// Links a crate's status page, or with `suffix` one of its other pages
+fn crate_url(crate_path: &CratePath, suffix: &str) -> String {
+    format!(
+        "{}/crate/{}/{}{}",
+        &super::SELF_BASE_URL as &str,
+        crate_path.name.as_ref(),
+        crate_path.version,
+        suffix
+    )
+}
+
+fn recent_table(locale: Locale, recent: Vec<SubjectPath>) -> Markup {
+    html! {
+        h2 class="title is-3" { (locale.tr("Recently checked")) }
+
+        table class="table is-fullwidth is-striped is-hoverable" {
+            thead {
+                tr {
+                    th { (locale.tr("Repository or crate")) }
+                    th class="has-text-right" { (locale.tr("Status")) }
+                }
+            }
+            tbody {
+                @for subject_path in recent {
+                    @let (url, badge_url, name) = match subject_path {
+                        SubjectPath::Repo(ref repo_path) => (
+                            repo_url(repo_path, ""),
+                            repo_url(repo_path, "/status.svg"),
+                            format!("{} / {}", repo_path.qual.as_ref(), repo_path.name.as_ref()),
+                        ),
+                        SubjectPath::Crate(ref crate_path) => (
+                            crate_url(crate_path, ""),
+                            crate_url(crate_path, "/status.svg"),
+                            format!("{} {}", crate_path.name.as_ref(), crate_path.version),
+                        ),
+                    };
+                    tr {
+                        td { a href=(url) { (name) } }
+                        td class="has-text-right" { img src=(badge_url); }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn popular_table(
+    locale: Locale,
+    popular_repos: Vec<Repository>,
+    popular_crates: Vec<CratePath>,
+) -> Markup {
     html! {
         div class="columns" {
             div class="column" {
-                h2 class="title is-3" { "Popular Repositories" }
+                h2 class="title is-3" { (locale.tr("Popular Repositories")) }
 
                 table class="table is-fullwidth is-striped is-hoverable" {
                     thead {
                         tr {
-                            th { "Repository" }
-                            th class="has-text-right" { "Status" }
+                            th { (locale.tr("Repository")) }
+                            th class="has-text-right" { (locale.tr("Status")) }
                         }
                     }
                     tbody {
                         @for repo in popular_repos.into_iter().take(10) {
                             tr {
                                 td {
-                                    a href=(format!("{}/repo/{}/{}/{}", &super::SELF_BASE_URL as &str, repo.path.site.as_ref(), repo.path.qual.as_ref(), repo.path.name.as_ref())) {
+                                    a href=(repo_url(&repo.path, "")) {
                                         (format!("{} / {}", repo.path.qual.as_ref(), repo.path.name.as_ref()))
                                     }
                                 }
                                 td class="has-text-right" {
-                                    img src=(format!("{}/repo/{}/{}/{}/status.svg", &super::SELF_BASE_URL as &str, repo.path.site.as_ref(), repo.path.qual.as_ref(), repo.path.name.as_ref()));
+                                    img src=(repo_url(&repo.path, "/status.svg"));
                                 }
                             }
                         }
@@ -34,25 +102,25 @@ fn popular_table(popular_repos: Vec<Repository>, popular_crates: Vec<CratePath>)
                 }
             }
             div class="column" {
-                h2 class="title is-3" { "Popular Crates" }
+                h2 class="title is-3" { (locale.tr("Popular Crates")) }
 
                 table class="table is-fullwidth is-striped is-hoverable" {
                     thead {
                         tr {
-                            th { "Crate" }
-                            th class="has-text-right" { "Status" }
+                            th { (locale.tr("Crate")) }
+                            th class="has-text-right" { (locale.tr("Status")) }
                         }
                     }
                     tbody {
                         @for crate_path in popular_crates {
                             tr {
                                 td {
-                                    a href=(format!("{}/crate/{}/{}", &super::SELF_BASE_URL as &str, crate_path.name.as_ref(), crate_path.version)) {
+                                    a href=(crate_url(&crate_path, "")) {
                                         (crate_path.name.as_ref().to_string())
                                     }
                                 }
                                 td class="has-text-right" {
-                                    img src=(format!("{}/crate/{}/{}/status.svg", &super::SELF_BASE_URL as &str, crate_path.name.as_ref(), crate_path.version));
+                                    img src=(crate_url(&crate_path, "/status.svg"));
                                 }
                             }
                         }
@@ -63,17 +131,23 @@ fn popular_table(popular_repos: Vec<Repository>, popular_crates: Vec<CratePath>)
     }
 }
 
-pub fn render(popular_repos: Vec<Repository>, popular_crates: Vec<CratePath>) -> Response<Body> {
+pub fn render(
+    locale: Locale,
+    popular_repos: Vec<Repository>,
+    popular_crates: Vec<CratePath>,
+    recent: Vec<SubjectPath>,
+) -> Response<Body> {
     super::render_html(
-        "Keep your dependencies up-to-date",
+        locale,
+        locale.tr("Keep your dependencies up-to-date"),
         html! {
             section class="hero is-light" {
                 div class="hero-head" { (super::render_navbar()) }
                 div class="hero-body" {
                     div class="container" {
-                        p class="title is-1" { "Keep your dependencies up-to-date" }
+                        p class="title is-1" { (locale.tr("Keep your dependencies up-to-date")) }
                         p {
-                            "Deps.rs uses semantic versioning to detect outdated or insecure dependencies in your project's"
+                            (locale.tr("Deps.rs uses semantic versioning to detect outdated or insecure dependencies in your project's "))
                             code { "Cargo.toml" }
                             "."
                         }
@@ -81,9 +155,14 @@ pub fn render(popular_repos: Vec<Repository>, popular_crates: Vec<CratePath>) ->
                 }
             }
             section class="section" {
-                div class="container" { (popular_table(popular_repos, popular_crates)) }
+                div class="container" { (popular_table(locale, popular_repos, popular_crates)) }
+            }
+            @if !recent.is_empty() {
+                section class="section" {
+                    div class="container" { (recent_table(locale, recent)) }
+                }
             }
-            (super::render_footer(None))
+            (super::render_footer(locale, None))
         },
     )
 }
@@ -4,16 +4,24 @@ use hyper::header::CONTENT_TYPE;
 use hyper::{Body, Response};
 use maud::{html, Markup, Render};
 
+pub mod compare;
 pub mod error;
+pub mod graph;
+pub mod history;
+pub mod i18n;
 pub mod index;
+pub mod org;
 pub mod status;
+pub mod watchlist;
 
 use crate::server::assets::STATIC_STYLE_CSS_PATH;
 use crate::server::SELF_BASE_URL;
 
-fn render_html<B: Render>(title: &str, body: B) -> Response<Body> {
+use self::i18n::Locale;
+
+fn render_html<B: Render>(locale: Locale, title: &str, body: B) -> Response<Body> {
     let rendered = html! {
-        html {
+        html lang=(locale.as_str()) {
             head {
                 meta charset="utf-8";
                 meta name="viewport" content="width=device-width, initial-scale=1";
@@ -47,7 +55,7 @@ fn render_navbar() -> Markup {
     }
 }
 
-fn render_footer(duration: Option<Duration>) -> Markup {
+fn render_footer(locale: Locale, duration: Option<Duration>) -> Markup {
     let duration_millis = duration.map(|d| d.as_secs() * 1000 + (d.subsec_millis()) as u64);
 
     html! {
@@ -56,17 +64,17 @@ fn render_footer(duration: Option<Duration>) -> Markup {
                 div class="content has-text-centered" {
                     p {
                         strong { "Deps.rs" }
-                        " is a service for the Rust community. It is open source on "
+                        (locale.tr(" is a service for the Rust community. It is open source on "))
                         a href="https://github.com/deps-rs/deps.rs" { "GitHub" }
                         "."
                     }
                     p {
-                        "Please report any issues on the "
-                        a href="https://github.com/deps-rs/deps.rs/issues" { "issue tracker" }
+                        (locale.tr("Please report any issues on the "))
+                        a href="https://github.com/deps-rs/deps.rs/issues" { (locale.tr("issue tracker")) }
                         "."
                     }
                     @if let Some(millis) = duration_millis {
-                        p class="has-text-grey is-size-7" { (format!("(rendered in {} ms)", millis)) }
+                        p class="has-text-grey is-size-7" { (locale.format("(rendered in {} ms)", &[&millis])) }
                     }
                 }
             }
@@ -0,0 +1,143 @@
+use font_awesome_as_a_crate::{svg as fa, Type as FaType};
+use hyper::{Body, Response};
+use maud::{html, Markup, PreEscaped};
+
+use crate::models::repo::{RepoQualifier, Repository};
+
+use super::i18n::Locale;
+
+/// What is known about the dependencies of a repository on an organization's dashboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepoState {
+    /// Not analyzed yet, or the analysis is still running
+    Pending,
+    Failed,
+    Analyzed {
+        any_issues: bool,
+        any_insecure: bool,
+    },
+}
+
+fn render_summary(locale: Locale, repos: &[(Repository, RepoState)]) -> Markup {
+    let (mut up_to_date, mut with_issues, mut insecure, mut pending) = (0, 0, 0, 0);
+    for (_, state) in repos {
+        match *state {
+            RepoState::Analyzed {
+                any_issues: false, ..
+            } => up_to_date += 1,
+            RepoState::Analyzed { any_insecure, .. } => {
+                with_issues += 1;
+                if any_insecure {
+                    insecure += 1;
+                }
+            }
+            RepoState::Pending => pending += 1,
+            RepoState::Failed => {}
+        }
+    }
+
+    html! {
+        nav class="level" {
+            div class="level-item has-text-centered" {
+                div {
+                    p class="heading" { (locale.tr("Repositories")) }
+                    p class="title" { (repos.len()) }
+                }
+            }
+            div class="level-item has-text-centered" {
+                div {
+                    p class="heading" { (locale.tr("up to date")) }
+                    p class="title has-text-success" { (up_to_date) }
+                }
+            }
+            div class="level-item has-text-centered" {
+                div {
+                    p class="heading" { (locale.tr("With issues")) }
+                    p class="title has-text-warning" { (with_issues) }
+                }
+            }
+            div class="level-item has-text-centered" {
+                div {
+                    p class="heading" { (locale.tr("insecure")) }
+                    p class="title has-text-danger" { (insecure) }
+                }
+            }
+            div class="level-item has-text-centered" {
+                div {
+                    p class="heading" { (locale.tr("Pending")) }
+                    p class="title has-text-grey" { (pending) }
+                }
+            }
+        }
+    }
+}
+
+fn render_repo(locale: Locale, repo: &Repository, state: RepoState) -> Markup {
+    let status_url = format!(
+        "{}/repo/{}/{}/{}",
+        &super::SELF_BASE_URL as &str,
+        repo.path.site.as_ref(),
+        repo.path.qual.as_ref(),
+        repo.path.name.as_ref()
+    );
+
+    html! {
+        div class="column is-one-third" {
+            div class="box" {
+                p class="title is-5" { a href=(status_url) { (repo.path.name.as_ref()) } }
+                @if !repo.description.is_empty() {
+                    p class="is-size-7 has-text-grey" { (repo.description) }
+                }
+                p class="mt-3" {
+                    @if state == RepoState::Failed {
+                        span class="tag is-light" { (locale.tr("Failed to analyze repository")) }
+                    } @else {
+                        img src=(format!("{}/status.svg", status_url));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the dashboard of a GitHub organization, with one box per repository
+pub fn render(
+    locale: Locale,
+    qual: &RepoQualifier,
+    repos: &[(Repository, RepoState)],
+) -> Response<Body> {
+    let fa_github = PreEscaped(fa(FaType::Brands, "github").unwrap());
+
+    let body = html! {
+        section class="hero is-light" {
+            div class="hero-head" { (super::render_navbar()) }
+            div class="hero-body" {
+                div class="container" {
+                    h1 class="title is-1" {
+                        a href=(format!("https://github.com/{}", qual.as_ref())) {
+                            (fa_github) " " (qual.as_ref())
+                        }
+                    }
+                    p class="subtitle" { (locale.tr("Rust repositories, most recently pushed first")) }
+                }
+            }
+        }
+        section class="section" {
+            div class="container" {
+                @if repos.is_empty() {
+                    p class="notification has-text-centered" { (locale.tr("No Rust repositories found.")) }
+                } @else {
+                    (render_summary(locale, repos))
+                    div class="columns is-multiline" {
+                        @for (repo, state) in repos {
+                            (render_repo(locale, repo, *state))
+                        }
+                    }
+                }
+            }
+        }
+        (super::render_footer(locale, None))
+    };
+
+    super::render_html(locale, qual.as_ref(), body)
+}
@@ -1,16 +1,27 @@
+use std::cmp::Ordering;
+
 use font_awesome_as_a_crate::{svg as fa, Type as FaType};
 use hyper::{Body, Response};
 use indexmap::IndexMap;
 use maud::{html, Markup, PreEscaped};
 use pulldown_cmark::{html, Parser};
-use rustsec::advisory::Advisory;
-use semver::Version;
+use relative_path::RelativePathBuf;
+use rustsec::advisory::{Advisory, Severity};
+use semver::{Version, VersionReq};
 
-use crate::engine::AnalyzeDependenciesOutcome;
-use crate::models::crates::{AnalyzedDependencies, AnalyzedDependency, CrateName};
-use crate::models::repo::RepoSite;
+use crate::engine::{format_snapshot_id, AnalyzeDependenciesOutcome, RepoAnalysisOptions};
+use crate::models::crates::{
+    AnalyzedDependencies, AnalyzedDependency, AnalyzedGitDependency, CrateName, CratesIoInfo,
+    DepScope, DuplicateDependency, PackageMetadata, RequirementConflict, RustVersion,
+};
+use crate::models::repo::{GitRef, RepoPath, RepoSite};
 use crate::models::SubjectPath;
+use crate::server::assets::STATIC_STATUS_JS_PATH;
 use crate::server::views::badge;
+use crate::server::views::feed::rfc3339;
+use crate::server::ExtraConfig;
+
+use super::i18n::Locale;
 
 fn get_crates_url(name: impl AsRef<str>) -> String {
     format!("https://crates.io/crates/{}", name.as_ref())
@@ -20,35 +31,299 @@ fn get_crates_version_url(name: impl AsRef<str>, version: &Version) -> String {
     format!("https://crates.io/crates/{}/{}", name.as_ref(), version)
 }
 
-fn dependency_tables(crate_name: &CrateName, deps: &AnalyzedDependencies) -> Markup {
+/// The repository and path of a crate's manifest, for linking to its source
+type ManifestSource<'a> = (&'a RepoPath, Option<&'a GitRef>, &'a RelativePathBuf);
+
+fn source_url(source: Option<ManifestSource<'_>>, line: Option<usize>) -> Option<String> {
+    source.map(|(repo_path, git_ref, manifest_path)| {
+        repo_path.to_source_file_url(manifest_path, git_ref, line)
+    })
+}
+
+/// Renders the edition, description and repository a crate declares in its manifest
+fn package_metadata(locale: Locale, metadata: &PackageMetadata) -> Markup {
+    html! {
+        @if let Some(ref description) = metadata.description {
+            p class="subtitle is-6" { (description) }
+        }
+        @if metadata.edition.is_some() || metadata.repository.is_some() {
+            p class="subtitle is-6" {
+                @if let Some(ref edition) = metadata.edition {
+                    span class="tag is-light" { (locale.tr("edition ")) (edition) }
+                    " "
+                }
+                // only link web URLs, the field can contain anything
+                @if let Some(repository) = metadata.repository.as_ref().filter(|url| url.starts_with("https://") || url.starts_with("http://")) {
+                    a class="has-text-grey" href=(repository) { (repository) }
+                }
+            }
+        }
+    }
+}
+
+fn dependency_tables(
+    locale: Locale,
+    crate_name: &CrateName,
+    source: Option<ManifestSource<'_>>,
+    msrv: Option<&RustVersion>,
+    metadata: Option<&PackageMetadata>,
+    published: bool,
+    deps: &AnalyzedDependencies,
+) -> Markup {
     html! {
         h2 class="title is-3" {
-            "Crate "
+            (locale.tr("Crate "))
             code { (crate_name.as_ref()) }
         }
+        @if let (Some((_, _, manifest_path)), Some(url)) = (source, source_url(source, None)) {
+            p class="subtitle is-6" {
+                a class="has-text-grey" href=(url) { (manifest_path) }
+            }
+        }
+        @if let Some(metadata) = metadata {
+            (package_metadata(locale, metadata))
+        }
+        @if published {
+            p class="subtitle is-6" {
+                a href=(format!("{}/crate/{}", &super::SELF_BASE_URL as &str, crate_name.as_ref())) { (locale.tr("Analyze the release on crates.io")) }
+            }
+        }
+        @if let Some(msrv) = msrv {
+            p class="subtitle is-6" {
+                (locale.tr("Minimum supported Rust version: ")) code { (msrv) }
+            }
+        }
 
-        @if deps.main.is_empty() && deps.dev.is_empty() && deps.build.is_empty() {
-            p class="notification has-text-centered" { "No external dependencies! 🙌" }
+        @if deps.main.is_empty() && deps.dev.is_empty() && deps.build.is_empty() && deps.git.is_empty() {
+            p class="notification has-text-centered" { (locale.tr("No external dependencies! 🙌")) }
         }
 
         @if !deps.main.is_empty() {
-            (dependency_table("Dependencies", &deps.main))
+            (dependency_table(locale, locale.tr("Dependencies"), &row_id_prefix(crate_name, "main"), &deps.main, source, msrv))
         }
 
         @if !deps.dev.is_empty() {
-            (dependency_table("Dev dependencies", &deps.dev))
+            (dependency_table(locale, locale.tr("Dev dependencies"), &row_id_prefix(crate_name, "dev"), &deps.dev, source, msrv))
         }
 
         @if !deps.build.is_empty() {
-            (dependency_table("Build dependencies", &deps.build))
+            (dependency_table(locale, locale.tr("Build dependencies"), &row_id_prefix(crate_name, "build"), &deps.build, source, msrv))
+        }
+
+        @if !deps.git.is_empty() {
+            (git_dependency_table(locale, &deps.git))
+        }
+    }
+}
+
+/// Lists the git dependencies, which can't be checked against any releases. Pins to a tag or
+/// commit show how far they are behind their repository's default branch, if known.
+fn git_dependency_table(
+    locale: Locale,
+    deps: &IndexMap<CrateName, AnalyzedGitDependency>,
+) -> Markup {
+    html! {
+        h3 class="title is-4" { (locale.tr("Git dependencies")) }
+        p class="subtitle is-5" { (locale.tr("(not checked for updates or advisories)")) }
+
+        table class="table is-fullwidth is-striped is-hoverable" {
+            thead {
+                tr {
+                    th { (locale.tr("Crate")) }
+                    th { (locale.tr("Repository")) }
+                    th class="has-text-right" { (locale.tr("Branch / revision")) }
+                    th class="has-text-right" title=(locale.tr("Commits and days the pin is behind the default branch")) { (locale.tr("Behind")) }
+                }
+            }
+            tbody {
+                @for (name, dep) in deps {
+                    tr {
+                        td { (name.as_ref()) }
+                        td {
+                            @if dep.source.url.starts_with("https://") || dep.source.url.starts_with("http://") {
+                                a href=(dep.source.url) { code { (dep.source.url) } }
+                            } @else {
+                                code { (dep.source.url) }
+                            }
+                        }
+                        td class="has-text-right" {
+                            @if let Some(ref reference) = dep.source.reference {
+                                code { (reference) }
+                            } @else {
+                                span class="has-text-grey" { (locale.tr("default branch")) }
+                            }
+                        }
+                        td class="has-text-right" {
+                            @if let Some(lag) = dep.lag {
+                                (locale.format("{} commits, {} days", &[&lag.commits, &lag.days]))
+                                @if lag.is_stale() {
+                                    " "
+                                    span class="tag is-warning" { (locale.tr("stale")) }
+                                }
+                            } @else {
+                                span class="has-text-grey" { "—" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the index of the first semver component (0 = major, 1 = minor, 2 = patch) in
+/// which the latest version differs from what the requirement currently resolves to
+fn outdated_component(dep: &AnalyzedDependency) -> Option<usize> {
+    let latest = dep.latest.as_ref()?;
+    if !dep.is_outdated() {
+        return None;
+    }
+
+    let current = match dep.locked.as_ref().or(dep.latest_that_matches.as_ref()) {
+        Some(ref version) => (version.major, version.minor, version.patch),
+        None => {
+            let comparator = dep.required.comparators.first()?;
+            (
+                comparator.major,
+                comparator.minor.unwrap_or(0),
+                comparator.patch.unwrap_or(0),
+            )
+        }
+    };
+
+    if latest.major != current.0 {
+        Some(0)
+    } else if latest.minor != current.1 {
+        Some(1)
+    } else {
+        Some(2)
+    }
+}
+
+/// Renders a version or requirement with every semver component starting at
+/// `component` highlighted according to the severity of the difference
+fn render_version_diff(version: &str, component: Option<usize>) -> Markup {
+    let prefix_len = version
+        .find(|c: char| c.is_ascii_digit() || c == '*')
+        .unwrap_or(version.len());
+    let (prefix, rest) = version.split_at(prefix_len);
+    let suffix_start = rest.find(&['-', '+'][..]).unwrap_or(rest.len());
+    let (core, suffix) = rest.split_at(suffix_start);
+    let segments: Vec<&str> = core.split('.').collect();
+
+    match component {
+        Some(idx) if idx < segments.len() => {
+            let class = match idx {
+                0 => "semver-major",
+                1 => "semver-minor",
+                _ => "semver-patch",
+            };
+            let unchanged: String = segments[..idx].iter().map(|s| format!("{}.", s)).collect();
+
+            html! {
+                code {
+                    (prefix) (unchanged)
+                    span class=(class) { (segments[idx..].join(".")) (suffix) }
+                }
+            }
+        }
+        _ => html! { code { (version) } },
+    }
+}
+
+/// Cuts a text down to at most `max_chars` characters, marking where it was cut
+fn shorten(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text.to_owned(),
+    }
+}
+
+/// Part of a requirement that dependency tables are sorted by, e.g. `1.2` for `^1.2`
+fn requirement_sort_value(req: &VersionReq) -> String {
+    match req.comparators.first() {
+        Some(comparator) => format!(
+            "{}.{}.{}",
+            comparator.major,
+            comparator.minor.unwrap_or(0),
+            comparator.patch.unwrap_or(0)
+        ),
+        None => String::new(),
+    }
+}
+
+/// Rank of a dependency's status that dependency tables are sorted by, more severe is higher
+fn status_sort_value(dep: &AnalyzedDependency) -> u8 {
+    if dep.is_unanalyzed() {
+        0
+    } else if dep.is_unavailable() {
+        5
+    } else if dep.is_insecure() {
+        4
+    } else if dep.is_yanked() {
+        3
+    } else if dep.is_outdated() && !dep.patched {
+        2
+    } else {
+        1
+    }
+}
+
+/// Prefix of the ids of a crate's dependency rows of one kind, which are followed by the
+/// dependency's name, e.g. `dep-my-crate-dev-serde`
+fn row_id_prefix(crate_name: &CrateName, kind: &str) -> String {
+    format!("dep-{}-{}", crate_name.as_ref(), kind)
+}
+
+/// Renders a search field that `scripts/status.js` uses to hide the dependency rows whose name
+/// doesn't match across all tables
+fn dependency_filter(locale: Locale) -> Markup {
+    html! {
+        div class="field" {
+            div class="control" {
+                input id="dependency-filter" class="input" type="search"
+                    placeholder=(locale.tr("Filter dependencies by name")) aria-label=(locale.tr("Filter dependencies by name"));
+            }
+        }
+    }
+}
+
+/// Renders the status of a dependency as a colored tag
+pub(super) fn status_tag(locale: Locale, dep: &AnalyzedDependency) -> Markup {
+    html! {
+        @if dep.is_unanalyzed() {
+            span class="tag is-info" title=(dep.registry.as_deref().unwrap_or_default()) { (locale.tr("external registry")) }
+        } @else if dep.is_unavailable() {
+            span class="tag is-danger" { (locale.tr("unavailable")) }
+        } @else if dep.is_insecure() {
+            span class="tag is-danger" { (locale.tr("insecure")) }
+        } @else if dep.is_yanked() {
+            span class="tag is-danger" title=(locale.tr("All releases the requirement allows are yanked")) { (locale.tr("yanked")) }
+        } @else if dep.patched {
+            span class="tag is-info" title=(locale.tr("Overridden in [patch.crates-io]")) { (locale.tr("patched")) }
+        } @else if dep.is_outdated() {
+            span class="tag is-warning" { (locale.tr("out of date")) }
+        } @else {
+            span class="tag is-success" { (locale.tr("up to date")) }
         }
     }
 }
 
-fn dependency_table(title: &str, deps: &IndexMap<CrateName, AnalyzedDependency>) -> Markup {
+fn dependency_table(
+    locale: Locale,
+    title: &str,
+    row_id_prefix: &str,
+    deps: &IndexMap<CrateName, AnalyzedDependency>,
+    source: Option<ManifestSource<'_>>,
+    msrv: Option<&RustVersion>,
+) -> Markup {
     let count_total = deps.len();
     let count_insecure = deps.iter().filter(|&(_, dep)| dep.is_insecure()).count();
-    let count_outdated = deps.iter().filter(|&(_, dep)| dep.is_outdated()).count();
+    let count_outdated = deps
+        .iter()
+        .filter(|&(_, dep)| dep.is_outdated() && !dep.patched)
+        .count();
 
     let fa_cube = PreEscaped(fa(FaType::Solid, "cube").unwrap());
 
@@ -56,49 +331,141 @@ fn dependency_table(title: &str, deps: &IndexMap<CrateName, AnalyzedDependency>)
         h3 class="title is-4" { (title) }
         p class="subtitle is-5" {
             (match (count_outdated, count_insecure) {
-                (0, 0) => format!("({} total, all up-to-date)", count_total),
-                (0, _) => format!("({} total, {} insecure)", count_total, count_insecure),
-                (_, 0) => format!("({} total, {} outdated)", count_total, count_outdated),
-                (_, _) => format!("({} total, {} outdated, {} insecure)", count_total, count_outdated, count_insecure),
+                (0, 0) => locale.format("({} total, all up-to-date)", &[&count_total]),
+                (0, _) => locale.format("({} total, {} insecure)", &[&count_total, &count_insecure]),
+                (_, 0) => locale.format("({} total, {} outdated)", &[&count_total, &count_outdated]),
+                (_, _) => locale.format("({} total, {} outdated, {} insecure)", &[&count_total, &count_outdated, &count_insecure]),
             })
         }
 
-        table class="table is-fullwidth is-striped is-hoverable" {
+        table class="table is-fullwidth is-striped is-hoverable is-sortable" {
             thead {
                 tr {
-                    th { "Crate" }
-                    th class="has-text-right" { "Required" }
-                    th class="has-text-right" { "Latest" }
-                    th class="has-text-right" { "Status" }
+                    th data-sortable title=(locale.tr("Sort by name")) { (locale.tr("Crate")) }
+                    th { (locale.tr("License")) }
+                    th class="has-text-right" data-sortable title=(locale.tr("Sort by requirement")) { (locale.tr("Required")) }
+                    th class="has-text-right" data-sortable title=(locale.tr("Newest release the requirement allows")) { (locale.tr("Latest matching")) }
+                    th class="has-text-right" data-sortable title=(locale.tr("Sort by latest version")) { (locale.tr("Latest")) }
+                    th class="has-text-right" { (locale.tr("Behind")) }
+                    th class="has-text-right" data-sortable title=(locale.tr("Sort by status")) { (locale.tr("Status")) }
                 }
             }
             tbody {
                 @for (name, dep) in deps {
-                    tr {
-                        td {
-                            a class="has-text-grey" href=(get_crates_url(&name)) {
-                                { (fa_cube) }
+                    @let component = outdated_component(dep);
+                    tr id=(format!("{}-{}", row_id_prefix, name.as_ref())) class="dependency" data-name=(name.as_ref()) {
+                        td data-sort-value=(name.as_ref()) {
+                            @if dep.is_from_external_registry() {
+                                span class="has-text-grey" { (fa_cube) }
+                                { "\u{00A0}" } // non-breaking space
+                                (name.as_ref())
+                            } @else {
+                                a class="has-text-grey" href=(get_crates_url(&name)) {
+                                    { (fa_cube) }
+                                }
+                                { "\u{00A0}" } // non-breaking space
+                                a href=(dep.deps_rs_path(name.as_ref())) { (name.as_ref()) }
+                            }
+                            @if let Some(ref usage) = dep.usage {
+                                @if usage.optional {
+                                    " "
+                                    span class="tag is-light" { (locale.tr("optional")) }
+                                    @if !usage.features.is_empty() {
+                                        " "
+                                        span class="is-size-7 has-text-grey" title=(locale.tr("Features that enable the dependency")) {
+                                            (usage.features.join(", "))
+                                        }
+                                    }
+                                }
+                                @if let Some(ref target) = usage.target {
+                                    " "
+                                    span class="tag is-light" title=(locale.tr("Platform-specific dependency")) { (target) }
+                                }
+                            }
+                            @if let (Some(msrv), Some(ref rust_version)) = (msrv, &dep.rust_version) {
+                                @if dep.exceeds_msrv(msrv) {
+                                    " "
+                                    span class="tag is-warning is-light" title=(locale.tr("rust-version of the newest release the requirement allows")) { (locale.tr("needs Rust ")) (rust_version) }
+                                }
+                            }
+                            @if let Some(ref description) = dep.description {
+                                br;
+                                span class="is-size-7 has-text-grey" title=(description) { (shorten(description, 80)) }
+                            }
+                            @if let Some(ref deprecation) = dep.deprecation {
+                                br;
+                                span class="is-size-7 has-text-grey" {
+                                    "deprecated"
+                                    @if let Some(ref replacement) = deprecation.replacement {
+                                        " — consider "
+                                        a href=(get_crates_url(replacement)) { (replacement) }
+                                    }
+                                }
                             }
-                            { "\u{00A0}" } // non-breaking space
-                            a href=(dep.deps_rs_path(name.as_ref())) { (name.as_ref()) }
                         }
-                        td class="has-text-right" { code { (dep.required.to_string()) } }
-                        td class="has-text-right" {
+                        td class="is-size-7" {
+                            @if let Some(ref license) = dep.license {
+                                (license)
+                            } @else {
+                                span class="has-text-grey" { (locale.tr("unknown")) }
+                            }
+                        }
+                        td class="has-text-right" data-sort-value=(requirement_sort_value(&dep.required)) {
+                            @let required = html! {
+                                @if dep.required.comparators.len() == 1 {
+                                    (render_version_diff(&dep.required.to_string(), component))
+                                } @else {
+                                    code { (dep.required.to_string()) }
+                                }
+                            };
+                            @if let Some(url) = source_url(source, dep.manifest_line) {
+                                a href=(url) title=(locale.tr("Show in Cargo.toml")) { (required) }
+                            } @else {
+                                (required)
+                            }
+                            @if let Some(ref locked) = dep.locked {
+                                br;
+                                span class="is-size-7 has-text-grey" title=(locale.tr("Version in Cargo.lock")) { (locale.tr("locked ")) (locked) }
+                            }
+                        }
+                        td class="has-text-right" data-sort-value=(dep.latest_that_matches.as_ref().map(Version::to_string).unwrap_or_default()) {
+                            @if let Some(ref latest_that_matches) = dep.latest_that_matches {
+                                code { (latest_that_matches) }
+                            } @else {
+                                "N/A"
+                            }
+                        }
+                        td class="has-text-right" data-sort-value=(dep.latest.as_ref().map(Version::to_string).unwrap_or_default()) {
                             @if let Some(ref latest) = dep.latest {
-                                code { (latest.to_string()) }
+                                (render_version_diff(&latest.to_string(), component))
                             } @else {
                                 "N/A"
                             }
+                            @if let (Some(msrv), Some(ref rust_version)) = (msrv, &dep.latest_rust_version) {
+                                @if dep.latest_exceeds_msrv(msrv) && !dep.exceeds_msrv(msrv) {
+                                    " "
+                                    span class="tag is-light" title=(locale.tr("rust-version of the newest release")) { (locale.tr("needs Rust ")) (rust_version) }
+                                }
+                            }
+                            @if let Some(diff_url) = dep.diff_url(name.as_ref()) {
+                                br;
+                                span class="is-size-7" {
+                                    a href=(diff_url) title=(locale.tr("Changes between the resolved and the latest version")) { "diff" }
+                                    @if let Some(ref repository) = dep.repository {
+                                        " · "
+                                        a href=(repository) title=(locale.tr("Changelog and releases")) { "repository" }
+                                    }
+                                }
+                            }
                         }
                         td class="has-text-right" {
-                            @if dep.is_insecure() {
-                                span class="tag is-danger" { "insecure" }
-                            } @else if dep.is_outdated() {
-                                span class="tag is-warning" { "out of date" }
-                            } @else {
-                                span class="tag is-success" { "up to date" }
+                            @if let Some(lag) = dep.lag() {
+                                span class=(format!("semver-{}", lag.as_str())) { (lag.as_str()) }
                             }
                         }
+                        td class="has-text-right" data-sort-value=(status_sort_value(dep)) {
+                            (status_tag(locale, dep))
+                        }
                     }
                 }
             }
@@ -106,22 +473,42 @@ fn dependency_table(title: &str, deps: &IndexMap<CrateName, AnalyzedDependency>)
     }
 }
 
+/// Renders the dependencies of the dependencies in a section that is collapsed unless
+/// something needs attention
+fn transitive_dependencies(locale: Locale, deps: &AnalyzedDependencies) -> Markup {
+    let needs_attention =
+        deps.count_outdated(DepScope::STATUS) > 0 || deps.count_insecure(DepScope::STATUS) > 0;
+
+    html! {
+        details id="transitive" open[needs_attention] {
+            summary class="title is-3" { (locale.tr("Transitive dependencies")) }
+            @if deps.main.is_empty() {
+                p class="notification has-text-centered" { (locale.tr("No transitive dependencies.")) }
+            } @else {
+                (dependency_table(locale, locale.tr("Dependencies of dependencies"), "dep-transitive", &deps.main, None, None))
+            }
+        }
+    }
+}
+
 fn get_site_icon(site: &RepoSite) -> &'static str {
     match *site {
         RepoSite::Github => "github",
         RepoSite::Gitlab => "gitlab",
         RepoSite::Bitbucket => "bitbucket",
+        // Font Awesome has no SourceForge icon
+        RepoSite::Sourceforge | RepoSite::Custom(_) => "git",
     }
 }
 
-fn render_title(subject_path: &SubjectPath) -> Markup {
+pub(super) fn render_title(subject_path: &SubjectPath) -> Markup {
     match *subject_path {
         SubjectPath::Repo(ref repo_path) => {
             let site_icon = get_site_icon(&repo_path.site);
             let fa_site_icon = PreEscaped(fa(FaType::Brands, site_icon).unwrap());
 
             html! {
-                a href=(format!("{}/{}/{}", repo_path.site.to_base_uri(), repo_path.qual.as_ref(), repo_path.name.as_ref())) {
+                a href=(repo_path.to_repo_url()) {
                     { (fa_site_icon) }
                     (format!(" {} / {}", repo_path.qual.as_ref(), repo_path.name.as_ref()))
                 }
@@ -140,22 +527,126 @@ fn render_title(subject_path: &SubjectPath) -> Markup {
     }
 }
 
-fn render_dev_dependency_box(outcome: &AnalyzeDependenciesOutcome) -> Markup {
-    let insecure = outcome.count_dev_insecure();
-    let outdated = outcome.count_dev_outdated();
+fn render_dev_dependency_box(locale: Locale, outcome: &AnalyzeDependenciesOutcome) -> Markup {
+    let insecure = outcome.count_insecure(DepScope::DEV);
+    let (outdated, _) = outcome.outdated_ratio(DepScope::DEV);
     let text = if insecure > 0 {
-        format!("{} insecure development dependencies", insecure)
+        locale.format("{} insecure development dependencies", &[&insecure])
     } else {
-        format!("{} outdated development dependencies", outdated)
+        locale.format("{} outdated development dependencies", &[&outdated])
     };
 
     html! {
         div class="notification is-warning" {
-            p { "This project contains " b { (text) } "." }
+            p { (locale.tr("This project contains ")) b { (text) } "." }
+        }
+    }
+}
+
+fn render_version_mismatches(outcome: &AnalyzeDependenciesOutcome) -> Markup {
+    html! {
+        div class="notification is-warning" {
+            p { "Some workspace members depend on a sibling version they don't match:" }
+            ul {
+                @for mismatch in &outcome.version_mismatches {
+                    li {
+                        code { (mismatch.dependent.as_ref()) }
+                        " requires "
+                        code { (mismatch.dependency.as_ref()) " " (mismatch.required.to_string()) }
+                        ", but the workspace contains version "
+                        code { (mismatch.actual.to_string()) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the external crates that workspace members require in incompatible versions
+fn duplicate_list(duplicates: &[DuplicateDependency]) -> Markup {
+    html! {
+        h3 class="title is-3" id="duplicates" { "Duplicate Dependencies" }
+        p class="subtitle is-5" {
+            "These crates are locked in several incompatible versions, which are all compiled"
+        }
+
+        table class="table is-fullwidth is-striped is-hoverable" {
+            thead {
+                tr {
+                    th { "Crate" }
+                    th class="has-text-right" { "Versions" }
+                }
+            }
+            tbody {
+                @for duplicate in duplicates {
+                    tr {
+                        td {
+                            a href=(get_crates_url(&duplicate.name)) { (duplicate.name.as_ref()) }
+                        }
+                        td class="has-text-right" {
+                            @for (idx, version) in duplicate.versions.iter().enumerate() {
+                                @if idx > 0 { ", " }
+                                code { (version) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn requirement_conflict_list(conflicts: &[RequirementConflict]) -> Markup {
+    html! {
+        h3 class="title is-3" id="conflicts" { "Conflicting Requirements" }
+        p class="subtitle is-5" {
+            "These crates are required in semver-incompatible versions and will be built more than once."
+        }
+
+        table class="table is-fullwidth is-striped is-hoverable" {
+            thead {
+                tr {
+                    th { "Crate" }
+                    th { "Member" }
+                    th class="has-text-right" { "Required" }
+                }
+            }
+            tbody {
+                @for conflict in conflicts {
+                    @for (idx, (member, req)) in conflict.requirements.iter().enumerate() {
+                        tr {
+                            td {
+                                @if idx == 0 {
+                                    a href=(get_crates_url(&conflict.name)) { (conflict.name.as_ref()) }
+                                }
+                            }
+                            td { code { (member.as_ref()) } }
+                            td class="has-text-right" { code { (req.to_string()) } }
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
+/// Returns the CVSS base score of an advisory, if it has one
+fn cvss_score(advisory: &Advisory) -> Option<f64> {
+    advisory
+        .metadata
+        .cvss
+        .as_ref()
+        .map(|cvss| cvss.score().value())
+}
+
+fn severity_class(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "is-danger",
+        Severity::Medium => "is-warning",
+        Severity::Low | Severity::None => "is-info",
+    }
+}
+
 fn build_rustsec_link(advisory: &Advisory) -> String {
     format!(
         "https://rustsec.org/advisories/{}.html",
@@ -170,9 +661,66 @@ fn render_markdown(description: &str) -> Markup {
 }
 
 /// Renders a list of all security vulnerabilities affecting the repository
+/// Lists the informational advisories, e.g. about unmaintained or unsound crates, of all
+/// dependencies
+fn informational_list(analysis_outcome: &AnalyzeDependenciesOutcome) -> Markup {
+    let mut advisories: Vec<(&CrateName, &Advisory)> = analysis_outcome
+        .crates
+        .iter()
+        .flat_map(|(_, deps)| deps.main.iter().chain(&deps.dev).chain(&deps.build))
+        .flat_map(|(name, dep)| {
+            dep.informational
+                .iter()
+                .map(move |advisory| (name, advisory))
+        })
+        .collect();
+    advisories.sort_unstable_by_key(|&(name, advisory)| (name, advisory.id()));
+    advisories.dedup_by_key(|&mut (_, advisory)| advisory.id());
+
+    html! {
+        h3 class="title is-3" id="informational" { "Unmaintained dependencies" }
+        p class="subtitle is-5" { "Informational advisories from the RustSec database, e.g. about crates that are unmaintained or unsound" }
+
+        @if advisories.is_empty() {
+            p class="notification has-text-centered" { "No informational advisories." }
+        } @else {
+            table class="table is-fullwidth is-striped is-hoverable" {
+                thead {
+                    tr {
+                        th { "Crate" }
+                        th { "Advisory" }
+                        th { "Kind" }
+                    }
+                }
+                tbody {
+                    @for (name, advisory) in advisories {
+                        tr {
+                            td { code { (name.as_ref()) } }
+                            td {
+                                a href=(build_rustsec_link(advisory)) { (advisory.id()) }
+                                ": " (advisory.title())
+                            }
+                            td {
+                                @if let Some(ref informational) = advisory.metadata.informational {
+                                    span class="tag is-warning" { (informational.as_str()) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn vulnerability_list(analysis_outcome: &AnalyzeDependenciesOutcome) -> Markup {
     let mut vulnerabilities = Vec::new();
-    for (_, analyzed_crate) in &analysis_outcome.crates {
+    let analyzed_crates = analysis_outcome
+        .crates
+        .iter()
+        .map(|(_, deps)| deps)
+        .chain(&analysis_outcome.transitive);
+    for analyzed_crate in analyzed_crates {
         vulnerabilities.extend(
             &mut analyzed_crate
                 .main
@@ -200,6 +748,11 @@ fn vulnerability_list(analysis_outcome: &AnalyzeDependenciesOutcome) -> Markup {
     let mut vulnerabilities: Vec<&Advisory> = vulnerabilities.into_iter().flatten().collect();
     vulnerabilities.sort_unstable_by_key(|&v| v.id());
     vulnerabilities.dedup();
+    // most severe first, advisories without a CVSS score last
+    vulnerabilities.sort_by(|a, b| {
+        let (a, b) = (cvss_score(a), cvss_score(b));
+        b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+    });
 
     html! {
         h3 class="title is-3" id="vulnerabilities" { "Security Vulnerabilities" }
@@ -207,7 +760,15 @@ fn vulnerability_list(analysis_outcome: &AnalyzeDependenciesOutcome) -> Markup {
         @for vuln in vulnerabilities {
             div class="box" {
                 h3 class="title is-4" { code { (vuln.metadata.package.as_str()) } ": " (vuln.title()) }
-                p class="subtitle is-5" style="margin-top: -0.5rem;" { a href=(build_rustsec_link(vuln)) { (vuln.id()) } }
+                p class="subtitle is-5" style="margin-top: -0.5rem;" {
+                    a href=(build_rustsec_link(vuln)) { (vuln.id()) }
+                    @if let Some(ref cvss) = vuln.metadata.cvss {
+                        " "
+                        span class=(format!("tag {}", severity_class(cvss.severity()))) title=(cvss) {
+                            (cvss.severity().as_str()) " " (format!("{:.1}", cvss.score().value()))
+                        }
+                    }
+                }
 
                 article { (render_markdown(vuln.description())) }
 
@@ -242,7 +803,41 @@ fn vulnerability_list(analysis_outcome: &AnalyzeDependenciesOutcome) -> Markup {
     }
 }
 
-fn render_failure(subject_path: SubjectPath) -> Markup {
+/// Returns the query string selecting how a repository is analyzed, along with the URL
+/// template of a custom site
+pub(crate) fn options_query(site: Option<&RepoSite>, options: &RepoAnalysisOptions) -> String {
+    let mut params = Vec::new();
+    if let Some(RepoSite::Custom(raw_base)) = site {
+        params.push(("raw_base", raw_base.as_ref()));
+    }
+    if let Some(ref path) = options.entry_point {
+        params.push(("path", path.as_str()));
+    }
+    if let Some(ref git_ref) = options.git_ref {
+        params.push(("ref", git_ref.as_ref()));
+    }
+    if options.locked {
+        params.push(("locked", "true"));
+    }
+    if options.deep {
+        params.push(("deep", "true"));
+    }
+    if options.include_prerelease {
+        params.push(("include_prerelease", "true"));
+    }
+
+    serde_urlencoded::to_string(&params)
+        .ok()
+        .filter(|query| !query.is_empty())
+        .map(|query| format!("?{}", query))
+        .unwrap_or_default()
+}
+
+fn render_failure(
+    locale: Locale,
+    subject_path: SubjectPath,
+    manifest_dirs: &[RelativePathBuf],
+) -> Markup {
     html! {
         section class="hero is-light" {
             div class="hero-head" { (super::render_navbar()) }
@@ -257,18 +852,67 @@ fn render_failure(subject_path: SubjectPath) -> Markup {
         section class="section" {
             div class="container" {
                 div class="notification is-danger" {
-                    h2 class="title is-3" { "Failed to analyze repository" }
-                    p { "The repository you requested might be structured in an uncommon way that is not yet supported." }
+                    h2 class="title is-3" { (locale.tr("Failed to analyze repository")) }
+                    @if manifest_dirs.is_empty() {
+                        p { (locale.tr("The repository you requested might be structured in an uncommon way that is not yet supported.")) }
+                    } @else {
+                        p { (locale.tr("There might be no ")) code { "Cargo.toml" } (locale.tr(" at the root of the repository. These directories contain one:")) }
+                        ul {
+                            @for dir in manifest_dirs {
+                                li { a href=(options_query(None, &RepoAnalysisOptions { entry_point: Some(dir.clone()), ..Default::default() })) { code { (dir) } } }
+                            }
+                        }
+                    }
                 }
             }
         }
-        (super::render_footer(None))
+        (super::render_footer(locale, None))
     }
 }
 
+/// Renders what crates.io lists about an analyzed crate release
+fn render_crates_io_info(locale: Locale, info: &CratesIoInfo) -> Markup {
+    html! {
+        @if let Some(ref description) = info.description {
+            p class="subtitle" { (description) }
+        }
+        p {
+            @if let Some(ref license) = info.license {
+                span class="tag is-light" title=(locale.tr("License")) { (license) }
+                " "
+            }
+            @if let Some(downloads) = info.downloads {
+                (locale.format("{} downloads", &[&downloads]))
+            }
+            @if let Some(ref repository) = info.repository {
+                @if info.downloads.is_some() {
+                    " · "
+                }
+                a href=(repository) { (locale.tr("Repository")) }
+                @if let Some(repo_path) = RepoPath::from_url(repository) {
+                    " · "
+                    a href=(format!("{}/repo/{}/{}/{}", &super::SELF_BASE_URL as &str, repo_path.site.as_ref(), repo_path.qual.as_ref(), repo_path.name.as_ref())) {
+                        (locale.tr("Analyze repository"))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Formats the day an analysis ran, e.g. `2021-03-14`
+fn analysis_date(analysis_outcome: &AnalyzeDependenciesOutcome) -> String {
+    let mut date = rfc3339(analysis_outcome.analyzed_at);
+    date.truncate(10);
+    date
+}
+
 fn render_success(
+    locale: Locale,
     analysis_outcome: AnalyzeDependenciesOutcome,
     subject_path: SubjectPath,
+    options: &RepoAnalysisOptions,
+    show_informational: bool,
 ) -> Markup {
     let self_path = match subject_path {
         SubjectPath::Repo(ref repo_path) => format!(
@@ -282,10 +926,21 @@ fn render_success(
         }
     };
     let status_base_url = format!("{}/{}", &super::SELF_BASE_URL as &str, self_path);
+    let site = match subject_path {
+        SubjectPath::Repo(ref repo_path) => Some(&repo_path.site),
+        SubjectPath::Crate(_) => None,
+    };
+    let query = options_query(site, options);
+
+    let status_data_uri =
+        badge::badge(Some(&analysis_outcome), &ExtraConfig::default()).to_svg_data_uri();
 
-    let status_data_uri = badge::badge(Some(&analysis_outcome)).to_svg_data_uri();
+    let requirement_conflicts = analysis_outcome.requirement_conflicts();
 
-    let hero_class = if analysis_outcome.any_insecure() {
+    let hero_class = if analysis_outcome.any_insecure()
+        || analysis_outcome.any_unavailable()
+        || analysis_outcome.any_yanked()
+    {
         "is-danger"
     } else if analysis_outcome.any_outdated() {
         "is-warning"
@@ -301,33 +956,173 @@ fn render_success(
                     h1 class="title is-1" {
                         (render_title(&subject_path))
                     }
+                    @if let Some(ref git_ref) = options.git_ref {
+                        p class="subtitle" { (locale.tr("at ")) code { (git_ref.as_ref()) } }
+                    }
+                    @if let Some(ref info) = analysis_outcome.crates_io {
+                        (render_crates_io_info(locale, info))
+                    }
+                    @if site.is_some() {
+                        p {
+                            a href=(format!("{}/graph{}", status_base_url, query)) { (locale.tr("Dependency graph")) }
+                            " · "
+                            a href=(format!("{}/feed.atom{}", status_base_url, options_query(site, &RepoAnalysisOptions::default()))) { (locale.tr("Atom feed")) }
+                            " · "
+                            a href=(format!("{}/history{}", status_base_url, options_query(site, &RepoAnalysisOptions::default()))) { (locale.tr("History")) }
+                        }
+                    }
 
                     img src=(status_data_uri);
+                    @if let (Some(id), false) = (analysis_outcome.snapshot, analysis_outcome.frozen) {
+                        p class="is-size-7" {
+                            a href=(format!("{}/snapshot/{}", status_base_url, format_snapshot_id(id))) { (locale.tr("Permalink to this analysis")) }
+                        }
+                    }
                 }
             }
             div class="hero-footer" {
                 div class="container" {
                     pre class="is-size-7" {
-                        (format!("[![dependency status]({}/status.svg)]({})", status_base_url, status_base_url))
+                        (format!("[![dependency status]({}/status.svg{})]({}{})", status_base_url, query, status_base_url, query))
+                    }
+                    @if site.is_some() && analysis_outcome.msrv().is_some() {
+                        pre class="is-size-7" {
+                            (format!("[![msrv]({}/msrv.svg{})]({}{})", status_base_url, query, status_base_url, query))
+                        }
                     }
                 }
             }
         }
         section class="section" {
             div class="container" {
+                @if analysis_outcome.stale {
+                    div class="notification is-info" {
+                        p { (locale.tr("deps.rs is currently in maintenance mode. This is "))
+                            b { (locale.tr("an earlier analysis")) }
+                            (locale.tr(" which might be out of date."))
+                        }
+                    }
+                }
+                @if analysis_outcome.frozen {
+                    div class="notification is-info" {
+                        p { (locale.tr("This is a "))
+                            b { (locale.tr("frozen analysis")) }
+                            (locale.format(" from {}, it doesn't change along with the repository. ", &[&analysis_date(analysis_outcome)]))
+                            a href=(status_base_url) { (locale.tr("See the current status")) } "."
+                        }
+                    }
+                }
+                @if analysis_outcome.skipped_manifests > 0 {
+                    div class="notification is-warning" {
+                        p {
+                            (locale.tr("This repository has more manifests than deps.rs analyzes. "))
+                            b { (locale.format("{} manifests were left out", &[&analysis_outcome.skipped_manifests])) }
+                            (locale.tr(", so the dependencies listed here are incomplete."))
+                        }
+                    }
+                }
+                @if !analysis_outcome.manifest_errors.is_empty() {
+                    div class="notification is-warning" {
+                        p {
+                            (locale.tr("Some manifests "))
+                            b { (locale.tr("could not be analyzed")) }
+                            (locale.tr(" and were left out:"))
+                        }
+                        ul {
+                            @for manifest_error in &analysis_outcome.manifest_errors {
+                                li { code { (manifest_error.path) } ": " (manifest_error.message) }
+                            }
+                        }
+                    }
+                }
+                @if !analysis_outcome.dependency_cycles.is_empty() {
+                    div class="notification is-warning" {
+                        p {
+                            (locale.tr("The path dependencies of some manifests "))
+                            b { (locale.tr("lead back to themselves")) }
+                            (locale.tr(", which Cargo rejects. The cycles were not followed:"))
+                        }
+                        ul {
+                            @for cycle in &analysis_outcome.dependency_cycles {
+                                li { code { (cycle.iter().map(RelativePathBuf::as_str).collect::<Vec<_>>().join(" → ")) } }
+                            }
+                        }
+                    }
+                }
                 @if analysis_outcome.any_insecure() {
                     div class="notification is-warning" {
-                        p { "This project contains "
-                            b { "known security vulnerabilities" }
-                            ". Find detailed information at the "
-                            a href="#vulnerabilities" { "bottom"} "."
+                        p { (locale.tr("This project contains "))
+                            b { (locale.tr("known security vulnerabilities")) }
+                            (locale.tr(". Find detailed information at the "))
+                            a href="#vulnerabilities" { (locale.tr("bottom")) } "."
                         }
                     }
                 } @else if analysis_outcome.any_dev_issues() {
-                    (render_dev_dependency_box(&analysis_outcome))
+                    (render_dev_dependency_box(locale, &analysis_outcome))
+                }
+                @if analysis_outcome.any_unavailable() {
+                    div class="notification is-danger" {
+                        p { (locale.tr("This project depends on crates which "))
+                            b { (locale.tr("no longer have any available releases")) }
+                            (locale.tr(", either because they were removed from crates.io or all of their versions were yanked."))
+                        }
+                    }
+                }
+                @if analysis_outcome.any_yanked() {
+                    div class="notification is-danger" {
+                        p { (locale.tr("This project depends on crates whose requirements "))
+                            b { (locale.tr("only allow yanked releases")) }
+                            (locale.tr(". These can't be resolved without an existing Cargo.lock entry."))
+                        }
+                    }
+                }
+                @if analysis_outcome.count_stale_git() > 0 {
+                    div class="notification is-warning" {
+                        p { (locale.tr("This project has git dependencies pinned to "))
+                            b { (locale.tr("commits far behind their repository")) }
+                            (locale.tr(". They are marked as stale in the git dependency tables."))
+                        }
+                    }
+                }
+                @if analysis_outcome.count_exceeding_msrv() > 0 {
+                    div class="notification is-warning" {
+                        p { (locale.tr("This project depends on crates that "))
+                            b { (locale.format("need a newer Rust than its rust-version ({} dependencies)", &[&analysis_outcome.count_exceeding_msrv()])) }
+                            (locale.tr(". Upgrading them may break builds with the declared minimum supported Rust version."))
+                        }
+                    }
+                }
+                @if !analysis_outcome.version_mismatches.is_empty() {
+                    (render_version_mismatches(&analysis_outcome))
+                }
+                @if analysis_outcome.crates.iter().any(|(_, deps)| !deps.main.is_empty() || !deps.dev.is_empty() || !deps.build.is_empty()) {
+                    (dependency_filter(locale))
                 }
                 @for (crate_name, deps) in &analysis_outcome.crates {
-                    (dependency_tables(crate_name, deps))
+                    @let source = match (&subject_path, analysis_outcome.manifest_paths.get(crate_name)) {
+                        (SubjectPath::Repo(repo_path), Some(manifest_path)) => Some((repo_path, options.git_ref.as_ref(), manifest_path)),
+                        _ => None,
+                    };
+                    @let metadata = analysis_outcome.package_metadata.get(crate_name);
+                    // crates of a repository are assumed to be on crates.io unless they opt out
+                    @let published = site.is_some() && !metadata.map_or(false, |metadata| metadata.unpublished);
+                    (dependency_tables(locale, crate_name, source, analysis_outcome.rust_versions.get(crate_name), metadata, published, deps))
+                }
+
+                @if let Some(ref transitive) = analysis_outcome.transitive {
+                    (transitive_dependencies(locale, transitive))
+                }
+
+                @if !analysis_outcome.duplicates.is_empty() {
+                    (duplicate_list(&analysis_outcome.duplicates))
+                }
+
+                @if !requirement_conflicts.is_empty() {
+                    (requirement_conflict_list(&requirement_conflicts))
+                }
+
+                @if show_informational {
+                    (informational_list(&analysis_outcome))
                 }
 
                 @if analysis_outcome.any_insecure() {
@@ -335,13 +1130,21 @@ fn render_success(
                 }
             }
         }
-        (super::render_footer(Some(analysis_outcome.duration)))
+        (super::render_footer(locale, Some(analysis_outcome.duration)))
+        script src=(STATIC_STATUS_JS_PATH) defer {}
     }
 }
 
+/// Renders a status page in `locale`. `options` are those a repository analysis ran with,
+/// `show_informational` lists informational advisories and `manifest_dirs` are suggested
+/// instead if the analysis failed.
 pub fn render(
+    locale: Locale,
     analysis_outcome: Option<AnalyzeDependenciesOutcome>,
     subject_path: SubjectPath,
+    options: &RepoAnalysisOptions,
+    show_informational: bool,
+    manifest_dirs: &[RelativePathBuf],
 ) -> Response<Body> {
     let title = match subject_path {
         SubjectPath::Repo(ref repo_path) => {
@@ -353,8 +1156,16 @@ pub fn render(
     };
 
     if let Some(outcome) = analysis_outcome {
-        super::render_html(&title, render_success(outcome, subject_path))
+        super::render_html(
+            locale,
+            &title,
+            render_success(locale, outcome, subject_path, options, show_informational),
+        )
     } else {
-        super::render_html(&title, render_failure(subject_path))
+        super::render_html(
+            locale,
+            &title,
+            render_failure(locale, subject_path, manifest_dirs),
+        )
     }
 }
@@ -0,0 +1,147 @@
+use font_awesome_as_a_crate::{svg as fa, Type as FaType};
+use hyper::{Body, Response};
+use maud::{html, Markup, PreEscaped};
+use semver::Version;
+
+use crate::engine::Watched;
+use crate::interactors::oauth::OauthProvider;
+
+use super::i18n::Locale;
+
+fn render_login(locale: Locale, providers: &[OauthProvider]) -> Markup {
+    html! {
+        p class="block" { (locale.tr("Log in to follow the status of repositories and crates.")) }
+        div class="buttons" {
+            @for provider in providers {
+                a class="button is-dark" href=(format!("{}/login/{}", &super::SELF_BASE_URL as &str, provider.as_ref())) {
+                    span class="icon" { (PreEscaped(fa(FaType::Brands, provider.as_ref()).unwrap())) }
+                    span {
+                        @match provider {
+                            OauthProvider::Github => { (locale.tr("Log in with GitHub")) }
+                            OauthProvider::Gitlab => { (locale.tr("Log in with GitLab")) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Links the status page of a watched subject and its badge. Repositories on custom git hosts
+/// can't be watched, so they need no `raw_base`. Crates are linked in their latest release,
+/// without a badge if none could be found.
+fn render_watched(locale: Locale, watched: &Watched, latest: Option<&Version>) -> Markup {
+    let base_url = &super::SELF_BASE_URL as &str;
+    let (url, badge_url) = match (watched, latest) {
+        (Watched::Repo(repo_path), _) => {
+            let url = format!(
+                "{}/repo/{}/{}/{}",
+                base_url,
+                repo_path.site.as_ref(),
+                repo_path.qual.as_ref(),
+                repo_path.name.as_ref()
+            );
+            (url.clone(), Some(format!("{}/status.svg", url)))
+        }
+        (Watched::Crate(name), Some(version)) => {
+            let url = format!("{}/crate/{}/{}", base_url, name.as_ref(), version);
+            (url.clone(), Some(format!("{}/status.svg", url)))
+        }
+        (Watched::Crate(name), None) => (format!("{}/crate/{}", base_url, name.as_ref()), None),
+    };
+
+    html! {
+        tr {
+            td { a href=(url) { (watched.to_string()) } }
+            td class="has-text-right" {
+                @if let Some(badge_url) = badge_url {
+                    img src=(badge_url);
+                } @else {
+                    span class="tag is-light" { (locale.tr("unavailable")) }
+                }
+            }
+            td class="has-text-right" {
+                form method="post" action="/watchlist" {
+                    input type="hidden" name="action" value="unwatch";
+                    input type="hidden" name="subject" value=(watched.to_string());
+                    button class="button is-small" type="submit" { (locale.tr("Remove")) }
+                }
+            }
+        }
+    }
+}
+
+fn render_list(locale: Locale, watched: &[(Watched, Option<Version>)]) -> Markup {
+    html! {
+        form class="block" method="post" action="/watchlist" {
+            input type="hidden" name="action" value="watch";
+            div class="field has-addons" {
+                div class="control is-expanded" {
+                    input class="input" type="text" name="subject" placeholder="github/deps-rs/deps.rs, serde" required;
+                }
+                div class="control" {
+                    button class="button is-info" type="submit" { (locale.tr("Watch")) }
+                }
+            }
+        }
+        @if watched.is_empty() {
+            p class="notification has-text-centered" {
+                (locale.tr("You aren't watching any repositories or crates yet."))
+            }
+        } @else {
+            table class="table is-fullwidth is-striped is-hoverable" {
+                thead {
+                    tr {
+                        th { (locale.tr("Repository or crate")) }
+                        th class="has-text-right" { (locale.tr("Status")) }
+                        th {}
+                    }
+                }
+                tbody {
+                    @for (watched, latest) in watched {
+                        (render_watched(locale, watched, latest.as_ref()))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Renders the watchlist of the logged-in `user`, or the login buttons of `providers` if
+/// nobody is logged in
+pub fn render(
+    locale: Locale,
+    providers: &[OauthProvider],
+    user: Option<&str>,
+    watched: &[(Watched, Option<Version>)],
+) -> Response<Body> {
+    let body = html! {
+        section class="hero is-light" {
+            div class="hero-head" { (super::render_navbar()) }
+            div class="hero-body" {
+                div class="container" {
+                    h1 class="title is-1" { (locale.tr("Watchlist")) }
+                    @if let Some(user) = user {
+                        form class="subtitle" method="post" action="/logout" {
+                            (locale.format("Logged in as {}", &[&user]))
+                            " "
+                            button class="button is-small is-light" type="submit" { (locale.tr("Log out")) }
+                        }
+                    }
+                }
+            }
+        }
+        section class="section" {
+            div class="container" {
+                @if user.is_some() {
+                    (render_list(locale, watched))
+                } @else {
+                    (render_login(locale, providers))
+                }
+            }
+        }
+        (super::render_footer(locale, None))
+    };
+
+    super::render_html(locale, locale.tr("Watchlist"), body)
+}
@@ -0,0 +1,86 @@
+//! Analysis results as Markdown tables, e.g. for pasting into issues or pull requests.
+
+use std::fmt::Write as _;
+
+use hyper::{header::CONTENT_TYPE, Body, Response, StatusCode};
+use indexmap::IndexMap;
+
+use crate::engine::AnalyzeDependenciesOutcome;
+use crate::models::crates::{AnalyzedDependency, CrateName};
+use crate::models::SubjectPath;
+
+fn title(subject_path: &SubjectPath) -> String {
+    match subject_path {
+        SubjectPath::Repo(repo_path) => {
+            format!("{}/{}", repo_path.qual.as_ref(), repo_path.name.as_ref())
+        }
+        SubjectPath::Crate(crate_path) => {
+            format!("{} {}", crate_path.name.as_ref(), crate_path.version)
+        }
+    }
+}
+
+fn table(output: &mut String, title: &str, deps: &IndexMap<CrateName, AnalyzedDependency>) {
+    if deps.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(output, "### {}\n", title);
+    output.push_str("| Crate | Required | Latest | Status |\n");
+    output.push_str("| --- | --- | --- | --- |\n");
+
+    for (name, dep) in deps {
+        let latest = dep
+            .latest
+            .as_ref()
+            .map_or_else(|| "N/A".to_owned(), ToString::to_string);
+
+        let _ = writeln!(
+            output,
+            "| {} | `{}` | `{}` | {} |",
+            name.as_ref(),
+            dep.required,
+            latest,
+            super::dependency_status(dep)
+        );
+    }
+
+    output.push('\n');
+}
+
+fn render(outcome: &AnalyzeDependenciesOutcome, subject_path: &SubjectPath) -> String {
+    let mut output = format!("# Dependency status of {}\n\n", title(subject_path));
+
+    for (crate_name, deps) in &outcome.crates {
+        let _ = writeln!(output, "## {}\n", crate_name.as_ref());
+
+        if deps.main.is_empty() && deps.dev.is_empty() && deps.build.is_empty() {
+            output.push_str("No external dependencies.\n\n");
+        }
+
+        table(&mut output, "Dependencies", &deps.main);
+        table(&mut output, "Dev dependencies", &deps.dev);
+        table(&mut output, "Build dependencies", &deps.build);
+    }
+
+    output
+}
+
+pub fn response(
+    analysis_outcome: Option<&AnalyzeDependenciesOutcome>,
+    subject_path: &SubjectPath,
+) -> Response<Body> {
+    let (status_code, body) = match analysis_outcome {
+        Some(outcome) => (StatusCode::OK, render(outcome, subject_path)),
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to analyze {}.\n", title(subject_path)),
+        ),
+    };
+
+    Response::builder()
+        .status(status_code)
+        .header(CONTENT_TYPE, "text/markdown; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
@@ -1,2 +1,28 @@
+use crate::models::crates::AnalyzedDependency;
+
 pub mod badge;
+pub mod csv;
+pub mod feed;
 pub mod html;
+pub mod markdown;
+pub mod sitemap;
+
+/// Returns the machine-readable status of a dependency, as used by the text-based views
+/// and the API
+pub fn dependency_status(dep: &AnalyzedDependency) -> &'static str {
+    if dep.is_unanalyzed() {
+        "external-registry"
+    } else if dep.is_unavailable() {
+        "unavailable"
+    } else if dep.is_insecure() {
+        "insecure"
+    } else if dep.is_yanked() {
+        "yanked"
+    } else if dep.patched {
+        "patched"
+    } else if dep.is_outdated() {
+        "outdated"
+    } else {
+        "up-to-date"
+    }
+}
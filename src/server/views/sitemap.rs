@@ -0,0 +1,83 @@
+//! Sitemap of the front page and the status pages of popular and recently analyzed subjects,
+//! for search engines.
+
+use hyper::{header::CONTENT_TYPE, Body, Response};
+use maud::html;
+
+use crate::engine::RepoAnalysisOptions;
+use crate::models::crates::CratePath;
+use crate::models::repo::RepoPath;
+use crate::models::SubjectPath;
+use crate::server::views::html::status::options_query;
+use crate::server::SELF_BASE_URL;
+
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+
+fn repo_url(repo_path: &RepoPath) -> String {
+    format!(
+        "{}/repo/{}/{}/{}{}",
+        &SELF_BASE_URL as &str,
+        repo_path.site.as_ref(),
+        repo_path.qual.as_ref(),
+        repo_path.name.as_ref(),
+        options_query(Some(&repo_path.site), &RepoAnalysisOptions::default())
+    )
+}
+
+fn crate_url(crate_path: &CratePath) -> String {
+    format!(
+        "{}/crate/{}/{}",
+        &SELF_BASE_URL as &str,
+        crate_path.name.as_ref(),
+        crate_path.version
+    )
+}
+
+/// Lists the front page and then the status page of each subject, leaving out duplicates
+fn render(subjects: &[SubjectPath]) -> String {
+    let mut urls = vec![format!("{}/", &SELF_BASE_URL as &str)];
+    for subject_path in subjects {
+        let url = match subject_path {
+            SubjectPath::Repo(repo_path) => repo_url(repo_path),
+            SubjectPath::Crate(crate_path) => crate_url(crate_path),
+        };
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    let sitemap = html! {
+        urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" {
+            @for page_url in &urls {
+                url { loc { (page_url) } }
+            }
+        }
+    };
+
+    format!("{}{}", XML_DECLARATION, sitemap.into_string())
+}
+
+/// Serves the sitemap listing the status pages of `subjects`
+pub fn response(subjects: &[SubjectPath]) -> Response<Body> {
+    Response::builder()
+        .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(Body::from(render(subjects)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_each_page_once() {
+        let repo = SubjectPath::Repo(RepoPath::from_parts("github", "deps-rs", "deps.rs").unwrap());
+        let krate = SubjectPath::Crate(CratePath::from_parts("serde", "1.0.0").unwrap());
+
+        let sitemap = render(&[repo.clone(), krate, repo]);
+        assert!(sitemap.starts_with(XML_DECLARATION));
+        assert_eq!(sitemap.matches("<url>").count(), 3);
+        assert!(sitemap.contains("/repo/github/deps-rs/deps.rs</loc>"));
+        assert!(sitemap.contains("/crate/serde/1.0.0</loc>"));
+    }
+}
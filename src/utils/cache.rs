@@ -1,10 +1,100 @@
-use std::{fmt, sync::Arc, time::Duration};
+use std::{
+    fmt,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use derive_more::{Display, Error, From};
 use hyper::service::Service;
 use lru_time_cache::LruCache;
-use slog::{debug, Logger};
+use redis::{aio::ConnectionManager, AsyncCommands as _};
+use serde::{de::DeserializeOwned, Serialize};
+use slog::{debug, error, Logger};
 use tokio::sync::Mutex;
+use tower::ServiceExt as _;
+
+use crate::utils::time::unix_secs;
+use crate::BoxFuture;
+
+/// Time a Redis command may take, after which the store counts as a miss so a slow Redis
+/// doesn't hold up requests
+const REDIS_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Failed Redis commands are logged at most once in this interval, as every request would
+/// log them while Redis is down
+const REDIS_ERROR_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Storage for cached values, either in-process or shared between replicas.
+pub trait CacheStore<K, V>: fmt::Debug + Send + Sync {
+    /// Returns the value stored under `key`, if it hasn't expired yet
+    fn get(&self, key: &K) -> BoxFuture<Option<V>>;
+
+    /// Like `get`, but without counting as a use of the entry
+    fn peek(&self, key: &K) -> BoxFuture<Option<V>> {
+        self.get(key)
+    }
+
+    fn insert(&self, key: K, value: V) -> BoxFuture<()>;
+}
+
+/// A shared handle to a cache store
+pub type Store<K, V> = Arc<dyn CacheStore<K, V>>;
+
+/// Where the caches that can be shared between replicas keep their values
+#[derive(Clone)]
+pub enum CacheBackend {
+    /// Every replica keeps its own values in memory
+    Memory,
+    Redis(ConnectionManager),
+}
+
+impl fmt::Debug for CacheBackend {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheBackend::Memory => fmt.write_str("Memory"),
+            CacheBackend::Redis(_) => fmt.write_str("Redis"),
+        }
+    }
+}
+
+impl CacheBackend {
+    /// Connects to the Redis server at `url`, e.g. `redis://127.0.0.1/`
+    pub async fn redis(url: &str) -> anyhow::Result<CacheBackend> {
+        let client = redis::Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+        Ok(CacheBackend::Redis(conn))
+    }
+
+    /// Creates a store on this backend. Only in-memory stores are limited to `capacity`
+    /// entries, entries in Redis are just evicted after `ttl`. `namespace` keeps the keys of
+    /// different stores apart.
+    pub fn store<K, V>(
+        &self,
+        namespace: &str,
+        ttl: Duration,
+        capacity: usize,
+        logger: Logger,
+    ) -> Store<K, V>
+    where
+        K: Serialize + Clone + Ord + Send + Sync + 'static,
+        V: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+    {
+        match self {
+            CacheBackend::Memory => Arc::new(TtlCache::new(ttl, capacity)),
+            CacheBackend::Redis(conn) => Arc::new(RedisStore {
+                conn: conn.clone(),
+                namespace: format!("deps.rs:{}", namespace),
+                ttl,
+                logger,
+                marker: PhantomData,
+            }),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Display, From, Error)]
 pub struct CacheError<E> {
@@ -17,7 +107,7 @@ where
     S: Service<Req>,
 {
     inner: S,
-    cache: Arc<Mutex<LruCache<Req, S::Response>>>,
+    cache: Store<Req, S::Response>,
     logger: Logger,
 }
 
@@ -35,31 +125,35 @@ where
 impl<S, Req> Cache<S, Req>
 where
     S: Service<Req> + fmt::Debug + Clone,
-    S::Response: Clone,
-    Req: Clone + Eq + Ord + fmt::Debug,
+    S::Response: Clone + Send + Sync + 'static,
+    Req: Clone + Eq + Ord + fmt::Debug + Send + Sync + 'static,
 {
+    /// Creates a cache that keeps the responses in memory
     pub fn new(service: S, ttl: Duration, capacity: usize, logger: Logger) -> Cache<S, Req> {
-        let cache = LruCache::with_expiry_duration_and_capacity(ttl, capacity);
+        Cache::with_store(service, Arc::new(TtlCache::new(ttl, capacity)), logger)
+    }
 
+    pub fn with_store(service: S, store: Store<Req, S::Response>, logger: Logger) -> Cache<S, Req> {
         Cache {
             inner: service,
-            cache: Arc::new(Mutex::new(cache)),
+            cache: store,
             logger,
         }
     }
 
+    /// Returns the cached response to `req` without calling the service on a miss
+    pub async fn cached(&self, req: &Req) -> Option<S::Response> {
+        self.cache.peek(req).await
+    }
+
     pub async fn cached_query(&self, req: Req) -> Result<S::Response, S::Error> {
-        {
-            let mut cache = self.cache.lock().await;
-
-            if let Some(cached_response) = cache.get(&req) {
-                debug!(
-                    self.logger, "cache hit";
-                    "svc" => format!("{:?}", self.inner),
-                    "req" => format!("{:?}", &req)
-                );
-                return Ok(cached_response.clone());
-            }
+        if let Some(cached_response) = self.cache.get(&req).await {
+            debug!(
+                self.logger, "cache hit";
+                "svc" => format!("{:?}", self.inner),
+                "req" => format!("{:?}", &req)
+            );
+            return Ok(cached_response);
         }
 
         debug!(
@@ -69,13 +163,205 @@ where
         );
 
         let mut service = self.inner.clone();
-        let fresh = service.call(req.clone()).await?;
+        let fresh = service.ready().await?.call(req.clone()).await?;
 
-        {
-            let mut cache = self.cache.lock().await;
-            cache.insert(req, fresh.clone());
-        }
+        self.cache.insert(req, fresh.clone()).await;
 
         Ok(fresh)
     }
 }
+
+/// A key-value store with expiry for values that aren't produced by a single `Service`.
+#[derive(Clone)]
+pub struct TtlCache<K, V> {
+    cache: Arc<Mutex<LruCache<K, V>>>,
+}
+
+impl<K, V> fmt::Debug for TtlCache<K, V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("TtlCache").finish()
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Clone + Ord,
+    V: Clone,
+{
+    pub fn new(ttl: Duration, capacity: usize) -> TtlCache<K, V> {
+        let cache = LruCache::with_expiry_duration_and_capacity(ttl, capacity);
+
+        TtlCache {
+            cache: Arc::new(Mutex::new(cache)),
+        }
+    }
+
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.cache.lock().await.get(key).cloned()
+    }
+
+    pub async fn peek(&self, key: &K) -> Option<V> {
+        self.cache.lock().await.peek(key).cloned()
+    }
+
+    pub async fn insert(&self, key: K, value: V) {
+        self.cache.lock().await.insert(key, value);
+    }
+
+    /// Inserts `value` unless `key` has an unexpired value already. Returns whether it did.
+    pub async fn insert_new(&self, key: K, value: V) -> bool {
+        let mut cache = self.cache.lock().await;
+        if cache.peek(&key).is_some() {
+            return false;
+        }
+        cache.insert(key, value);
+        true
+    }
+
+    /// Replaces the value of `key` with what `update` makes of the current one, without
+    /// letting concurrent updates in between
+    pub async fn update<F>(&self, key: K, update: F)
+    where
+        F: FnOnce(Option<V>) -> V,
+    {
+        let mut cache = self.cache.lock().await;
+        let value = update(cache.remove(&key));
+        cache.insert(key, value);
+    }
+
+    /// Returns all entries that haven't expired yet
+    pub async fn entries(&self) -> Vec<(K, V)> {
+        self.cache
+            .lock()
+            .await
+            .peek_iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl<K, V> CacheStore<K, V> for TtlCache<K, V>
+where
+    K: Clone + Ord + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn get(&self, key: &K) -> BoxFuture<Option<V>> {
+        let cache = self.clone();
+        let key = key.clone();
+        Box::pin(async move { TtlCache::get(&cache, &key).await })
+    }
+
+    fn peek(&self, key: &K) -> BoxFuture<Option<V>> {
+        let cache = self.clone();
+        let key = key.clone();
+        Box::pin(async move { TtlCache::peek(&cache, &key).await })
+    }
+
+    fn insert(&self, key: K, value: V) -> BoxFuture<()> {
+        let cache = self.clone();
+        Box::pin(async move { TtlCache::insert(&cache, key, value).await })
+    }
+}
+
+/// Keeps values as JSON in Redis, so that all replicas share them. Failing to reach Redis is
+/// logged and treated like a cache miss.
+struct RedisStore<K, V> {
+    conn: ConnectionManager,
+    namespace: String,
+    ttl: Duration,
+    logger: Logger,
+    marker: PhantomData<fn(K) -> V>,
+}
+
+impl<K, V> fmt::Debug for RedisStore<K, V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("RedisStore")
+            .field("namespace", &self.namespace)
+            .finish()
+    }
+}
+
+/// Logs a failed Redis command, unless one was logged within the last interval. The next one
+/// logged tells how many were left out.
+fn log_redis_error(logger: &Logger, what: &str, err: &dyn fmt::Display) {
+    static LAST_LOGGED: AtomicU64 = AtomicU64::new(0);
+    static LEFT_OUT: AtomicU64 = AtomicU64::new(0);
+
+    let now = unix_secs(SystemTime::now());
+    let last = LAST_LOGGED.load(Ordering::Relaxed);
+    let due = now >= last + REDIS_ERROR_LOG_INTERVAL.as_secs()
+        && LAST_LOGGED
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok();
+    if !due {
+        LEFT_OUT.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+
+    let left_out = LEFT_OUT.swap(0, Ordering::Relaxed);
+    error!(logger, "failed {} redis: {}", what, err; "left_out" => left_out);
+}
+
+impl<K, V> RedisStore<K, V>
+where
+    K: Serialize,
+{
+    fn key(&self, key: &K) -> Option<String> {
+        let key = serde_json::to_string(key).ok()?;
+        Some(format!("{}:{}", self.namespace, key))
+    }
+}
+
+impl<K, V> CacheStore<K, V> for RedisStore<K, V>
+where
+    K: Serialize,
+    V: Serialize + DeserializeOwned + Send + 'static,
+{
+    fn get(&self, key: &K) -> BoxFuture<Option<V>> {
+        let key = self.key(key);
+        let mut conn = self.conn.clone();
+        let logger = self.logger.clone();
+
+        Box::pin(async move {
+            let key = key?;
+            let data: Option<Vec<u8>> =
+                match tokio::time::timeout(REDIS_TIMEOUT, conn.get(key)).await {
+                    Ok(Ok(data)) => data,
+                    Ok(Err(err)) => {
+                        log_redis_error(&logger, "reading from", &err);
+                        return None;
+                    }
+                    Err(elapsed) => {
+                        log_redis_error(&logger, "reading from", &elapsed);
+                        return None;
+                    }
+                };
+
+            serde_json::from_slice(&data?).ok()
+        })
+    }
+
+    fn insert(&self, key: K, value: V) -> BoxFuture<()> {
+        let key = self.key(&key);
+        let data = serde_json::to_vec(&value);
+        // Redis doesn't accept a zero expiry
+        let ttl = self.ttl.as_secs().max(1) as usize;
+        let mut conn = self.conn.clone();
+        let logger = self.logger.clone();
+
+        Box::pin(async move {
+            let (key, data) = match (key, data) {
+                (Some(key), Ok(data)) => (key, data),
+                _ => return,
+            };
+
+            let result: Result<redis::RedisResult<()>, _> =
+                tokio::time::timeout(REDIS_TIMEOUT, conn.set_ex(key, data, ttl)).await;
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => log_redis_error(&logger, "writing to", &err),
+                Err(elapsed) => log_redis_error(&logger, "writing to", &elapsed),
+            }
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use hickory_resolver::{system_conf::read_system_conf, TokioAsyncResolver};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// DNS resolver shared by the outbound HTTP clients.
+///
+/// Resolved addresses are kept in an in-process cache so that repeated lookups of
+/// the same hosts (raw.githubusercontent.com, crates.io, ...) during an analysis
+/// don't each hit the network.
+#[derive(Clone)]
+pub struct CachingResolver {
+    inner: Arc<TokioAsyncResolver>,
+}
+
+impl CachingResolver {
+    /// Creates a resolver from the system configuration (`/etc/resolv.conf`).
+    ///
+    /// `min_ttl` is the minimum time a successful lookup is cached for, regardless of
+    /// the record's own TTL, while `negative_ttl` caps how long failed lookups are kept.
+    pub fn from_system_conf(
+        cache_size: usize,
+        min_ttl: Duration,
+        negative_ttl: Duration,
+    ) -> Result<Self> {
+        let (config, mut opts) = read_system_conf()?;
+        opts.cache_size = cache_size;
+        opts.positive_min_ttl = Some(min_ttl);
+        opts.negative_max_ttl = Some(negative_ttl);
+
+        Ok(Self {
+            inner: Arc::new(TokioAsyncResolver::tokio(config, opts)),
+        })
+    }
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.inner.clone();
+
+        Box::pin(async move {
+            let lookup = resolver.lookup_ip(name.as_str()).await?;
+            let addrs: Addrs = Box::new(lookup.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+impl fmt::Debug for CachingResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CachingResolver")
+    }
+}
+
+/// Resolves names to public addresses only, so that URLs given by users can't reach the
+/// server itself or its private network
+#[derive(Clone, Debug)]
+pub struct PublicResolver {
+    /// Falls back to the system resolver if unset
+    inner: Option<Arc<CachingResolver>>,
+}
+
+impl PublicResolver {
+    pub fn new(inner: Option<Arc<CachingResolver>>) -> PublicResolver {
+        PublicResolver { inner }
+    }
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let inner = self.inner.clone();
+
+        Box::pin(async move {
+            let ips: Vec<IpAddr> = match inner {
+                Some(resolver) => resolver
+                    .inner
+                    .lookup_ip(name.as_str())
+                    .await?
+                    .iter()
+                    .collect(),
+                None => tokio::net::lookup_host((name.as_str(), 0))
+                    .await?
+                    .map(|addr| addr.ip())
+                    .collect(),
+            };
+
+            let public: Vec<SocketAddr> = ips
+                .into_iter()
+                .filter(|ip| is_public(*ip))
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect();
+            if public.is_empty() {
+                return Err(anyhow!("{} has no public address", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(public.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether an address is reachable on the internet, rather than e.g. a loopback, private or
+/// link-local one
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || a == 0
+                // shared address space used for carrier-grade NAT
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            if segments[..5] == [0; 5] && segments[5] == 0xffff {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public(IpAddr::from([a, b, c, d]));
+            }
+
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local addresses
+                || segments[0] & 0xfe00 == 0xfc00
+                // link-local addresses
+                || segments[0] & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_addresses() {
+        let public = |ip: &str| is_public(ip.parse().unwrap());
+
+        assert!(public("140.82.112.3"));
+        assert!(public("2606:50c0:8000::153"));
+
+        assert!(!public("127.0.0.1"));
+        assert!(!public("10.1.2.3"));
+        assert!(!public("172.16.0.1"));
+        assert!(!public("192.168.1.1"));
+        assert!(!public("169.254.169.254"));
+        assert!(!public("100.64.0.1"));
+        assert!(!public("0.0.0.0"));
+        assert!(!public("::1"));
+        assert!(!public("fd00::1"));
+        assert!(!public("fe80::1"));
+        assert!(!public("::ffff:127.0.0.1"));
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, fmt, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::{Error, Result};
 use crates_index::Index;
@@ -6,36 +6,198 @@ use slog::{error, info, Logger};
 use tokio::task::spawn_blocking;
 use tokio::time::{self, Interval};
 
+/// URL of the crates.io index served with Cargo's sparse protocol
+const CRATES_IO_SPARSE_INDEX_URL: &str = "https://index.crates.io/";
+
+/// An index served over HTTP with Cargo's sparse protocol, where only the files of the crates
+/// that are looked up are fetched
+#[derive(Clone, Debug)]
+pub struct SparseIndex {
+    url: String,
+}
+
+impl SparseIndex {
+    pub fn new(url: &str) -> SparseIndex {
+        let url = url.strip_prefix("sparse+").unwrap_or(url);
+        SparseIndex {
+            url: format!("{}/", url.trim_end_matches('/')),
+        }
+    }
+
+    pub fn crates_io() -> SparseIndex {
+        SparseIndex::new(CRATES_IO_SPARSE_INDEX_URL)
+    }
+
+    /// Returns the URL of the index file of a crate
+    pub fn crate_url(&self, name: &str) -> String {
+        let name = name.to_lowercase();
+        let prefix = match name.len() {
+            1 => "1".to_owned(),
+            2 => "2".to_owned(),
+            3 => format!("3/{}", &name[..1]),
+            _ => format!("{}/{}", &name[..2], &name[2..4]),
+        };
+
+        format!("{}{}/{}", self.url, prefix, name)
+    }
+}
+
+/// An index to look up the releases of crates in
+#[derive(Clone)]
+pub enum CrateIndex {
+    /// A local clone of a git index, which is kept up to date by `ManagedIndex`
+    Git(Index),
+    Sparse(SparseIndex),
+}
+
+impl CrateIndex {
+    /// Sets up the index at `url`, which is a sparse index if prefixed with `sparse+`
+    fn from_url(url: &str, path: PathBuf) -> CrateIndex {
+        if url.starts_with("sparse+") {
+            CrateIndex::Sparse(SparseIndex::new(url))
+        } else {
+            CrateIndex::Git(Index::with_path(path, url))
+        }
+    }
+}
+
+/// An alternative registry as configured by the operator
+#[derive(Clone, Debug)]
+pub struct RegistryConfig {
+    /// Name dependencies refer to the registry by, as in `registry = "name"`
+    pub name: String,
+    /// URL of the registry's index, prefixed with `sparse+` for sparse indexes
+    pub index_url: String,
+}
+
+/// An alternative registry's index
+#[derive(Clone)]
+pub struct Registry {
+    pub name: String,
+    pub index_url: String,
+    pub index: CrateIndex,
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("name", &self.name)
+            .field("index_url", &self.index_url)
+            .finish()
+    }
+}
+
+/// Alternative registries whose indexes are kept next to the crates.io index.
+#[derive(Clone, Debug, Default)]
+pub struct Registries {
+    registries: Arc<BTreeMap<String, Registry>>,
+}
+
+impl Registries {
+    /// Sets up the registries, with each git index stored in a subdirectory of `dir`
+    pub fn new(configs: Vec<RegistryConfig>, dir: PathBuf) -> Registries {
+        let registries = configs
+            .into_iter()
+            .map(|config| {
+                let index = CrateIndex::from_url(&config.index_url, dir.join(&config.name));
+                let registry = Registry {
+                    name: config.name.clone(),
+                    index_url: config.index_url,
+                    index,
+                };
+                (config.name, registry)
+            })
+            .collect();
+
+        Registries {
+            registries: Arc::new(registries),
+        }
+    }
+
+    /// Finds a registry by the name or index URL a dependency declaration refers to it by
+    pub fn find(&self, registry: &str) -> Option<&Registry> {
+        self.registries.get(registry).or_else(|| {
+            self.registries
+                .values()
+                .find(|candidate| same_url(&candidate.index_url, registry))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Registry> {
+        self.registries.values()
+    }
+}
+
+fn same_url(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
 pub struct ManagedIndex {
-    index: Index,
+    index: CrateIndex,
+    registries: Registries,
     update_interval: Interval,
     logger: Logger,
 }
 
 impl ManagedIndex {
-    pub fn new(update_interval: Duration, logger: Logger) -> Self {
-        // the index path is configurable through the `CARGO_HOME` env variable
-        let index = Index::new_cargo_default();
+    /// Uses the sparse crates.io index unless `sparse` is false, in which case the git index
+    /// is cloned
+    pub fn new(
+        update_interval: Duration,
+        sparse: bool,
+        registries: Registries,
+        logger: Logger,
+    ) -> Self {
+        let index = if sparse {
+            CrateIndex::Sparse(SparseIndex::crates_io())
+        } else {
+            // the index path is configurable through the `CARGO_HOME` env variable
+            CrateIndex::Git(Index::new_cargo_default())
+        };
         let update_interval = time::interval(update_interval);
         Self {
             index,
+            registries,
             update_interval,
             logger,
         }
     }
 
-    pub fn index(&self) -> Index {
+    pub fn index(&self) -> CrateIndex {
         self.index.clone()
     }
 
+    pub fn registries(&self) -> Registries {
+        self.registries.clone()
+    }
+
+    /// Returns the git indexes of crates.io and all alternative registries, by name. Sparse
+    /// indexes don't need to be kept locally.
+    fn indexes(&self) -> Vec<(String, Index)> {
+        let registries = self
+            .registries
+            .iter()
+            .map(|registry| (registry.name.clone(), registry.index.clone()));
+
+        std::iter::once(("crates.io".to_owned(), self.index()))
+            .chain(registries)
+            .filter_map(|(name, index)| match index {
+                CrateIndex::Git(index) => Some((name, index)),
+                CrateIndex::Sparse(_) => None,
+            })
+            .collect()
+    }
+
     pub async fn initial_clone(&mut self) -> Result<()> {
-        let index = self.index();
+        let indexes = self.indexes();
         let logger = self.logger.clone();
 
         spawn_blocking(move || {
-            if !index.exists() {
-                info!(logger, "Cloning crates.io-index");
-                index.retrieve()?;
+            for (name, index) in indexes {
+                if !index.exists() {
+                    info!(logger, "Cloning {} index", name);
+                    index.retrieve()?;
+                }
             }
             Ok::<_, Error>(())
         })
@@ -48,17 +210,47 @@ impl ManagedIndex {
             if let Err(e) = self.refresh().await {
                 error!(
                     self.logger,
-                    "failed refreshing the crates.io-index, the operation will be retried: {}", e
+                    "failed refreshing the indexes, the operation will be retried: {}", e
                 );
             }
             self.update_interval.tick().await;
         }
     }
 
-    async fn refresh(&self) -> Result<()> {
-        let index = self.index();
+    /// Clones the indexes, or updates them if they already exist.
+    pub async fn refresh(&self) -> Result<()> {
+        let indexes = self.indexes();
 
-        spawn_blocking(move || index.retrieve_or_update()).await??;
+        spawn_blocking(move || {
+            for (_, index) in indexes {
+                index.retrieve_or_update()?;
+            }
+            Ok::<_, Error>(())
+        })
+        .await??;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparse_index_crate_urls() {
+        let index = SparseIndex::crates_io();
+        assert_eq!(index.crate_url("a"), "https://index.crates.io/1/a");
+        assert_eq!(index.crate_url("xz"), "https://index.crates.io/2/xz");
+        assert_eq!(index.crate_url("syn"), "https://index.crates.io/3/s/syn");
+        assert_eq!(
+            index.crate_url("Serde"),
+            "https://index.crates.io/se/rd/serde"
+        );
+
+        let index = SparseIndex::new("sparse+https://example.com/index");
+        assert_eq!(
+            index.crate_url("serde"),
+            "https://example.com/index/se/rd/serde"
+        );
+    }
+}
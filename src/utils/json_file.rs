@@ -0,0 +1,64 @@
+//! State persisted to a JSON file.
+
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Error};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::fs;
+
+/// A JSON file holding a value, which is replaced as a whole on saves
+#[derive(Clone, Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+}
+
+impl JsonFileStore {
+    pub fn new(path: PathBuf) -> JsonFileStore {
+        JsonFileStore { path }
+    }
+
+    /// Reads the value, or returns the default one if the file doesn't exist yet
+    pub async fn load<T: DeserializeOwned + Default>(&self) -> Result<T, Error> {
+        Ok(self.load_if_exists().await?.unwrap_or_default())
+    }
+
+    /// Reads the value, or returns `None` if the file doesn't exist
+    pub async fn load_if_exists<T: DeserializeOwned>(&self) -> Result<Option<T>, Error> {
+        match fs::read(&self.path).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .with_context(|| format!("could not parse {}", self.path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the value to a temporary file first, so a crash can't leave a truncated one
+    pub async fn save<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(value)?).await?;
+        fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn saves_and_loads() {
+        let path =
+            std::env::temp_dir().join(format!("json-file-store-{}.json", std::process::id()));
+        let store = JsonFileStore::new(path.clone());
+
+        let missing: Vec<String> = store.load().await.unwrap();
+        assert!(missing.is_empty());
+
+        store.save(&vec!["a".to_owned()]).await.unwrap();
+        let saved: Vec<String> = store.load().await.unwrap();
+        assert_eq!(saved, vec!["a"]);
+
+        fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    error::Error as StdError,
+    fmt,
+    future::{ready, Ready},
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Error};
+use cadence::{prelude::*, StatsdClient};
+use futures::FutureExt as _;
+use relative_path::RelativePathBuf;
+use reqwest::StatusCode;
+use tower::{
+    limit::ConcurrencyLimit,
+    retry::{Policy, Retry},
+    timeout::{error::Elapsed, Timeout},
+    BoxError, Layer, Service, ServiceBuilder,
+};
+
+use crate::models::crates::CrateName;
+use crate::models::repo::{GitRef, RepoPath, RepoQualifier, RepoSite};
+use crate::BoxFuture;
+
+/// An interactor wrapped in the middleware shared by all interactors, from the outside in:
+/// metrics, retries, a concurrency limit, a circuit breaker and a timeout. The breaker sits
+/// outside of the timeout so that it sees calls to hanging hosts time out.
+pub type Stack<S> = Metered<Retry<Attempts, ConcurrencyLimit<Breaker<Timeout<S>>>>>;
+
+/// Consecutive failures after which calls to a host are short-circuited
+const BREAKER_FAILURES: u32 = 5;
+/// How long calls to a failing host are short-circuited before it gets another chance
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Settings for the middleware wrapped around an interactor.
+#[derive(Clone, Copy, Debug)]
+pub struct StackConfig {
+    /// Name under which metrics are reported
+    pub name: &'static str,
+    /// Timeout of a single attempt
+    pub timeout: Duration,
+    /// How often failed calls are retried
+    pub retries: usize,
+    /// Calls that may be in flight at once, across all clones of the service
+    pub concurrency: usize,
+}
+
+/// Wraps `service` in the shared middleware.
+pub fn stack<S>(service: S, config: StackConfig, metrics: StatsdClient) -> Stack<S> {
+    let service = Breaker::new(Timeout::new(service, config.timeout), config.name);
+
+    ServiceBuilder::new()
+        .layer(MetricsLayer::new(config.name, metrics))
+        .retry(Attempts(config.retries))
+        .concurrency_limit(config.concurrency)
+        .service(service)
+}
+
+fn into_anyhow(err: BoxError) -> Error {
+    anyhow!(err)
+}
+
+/// Retry policy allowing a fixed number of retries for calls that timed out, couldn't connect
+/// or got a server error. Other failures, e.g. a missing file, would only fail again, and so
+/// would calls the circuit breaker turned away.
+#[derive(Clone, Copy, Debug)]
+pub struct Attempts(pub usize);
+
+impl<Req: Clone, Res> Policy<Req, Res, Error> for Attempts {
+    type Future = Ready<Self>;
+
+    fn retry(&self, _req: &Req, result: Result<&Res, &Error>) -> Option<Self::Future> {
+        match result {
+            Err(err) if self.0 > 0 && !err.is::<CircuitOpen>() && is_retryable(&**err) => {
+                Some(ready(Attempts(self.0 - 1)))
+            }
+            _ => None,
+        }
+    }
+
+    fn clone_request(&self, req: &Req) -> Option<Req> {
+        Some(req.clone())
+    }
+}
+
+/// Returns how long to wait before retrying after `attempt` failed attempts: `base`, doubled
+/// for each further attempt, of which a random part is left out so that retries of
+/// concurrent calls spread out
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    let full = base * 2u32.saturating_pow(attempt);
+
+    // randomly keyed, so the hash of nothing is a random number
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = full.as_millis() as u64 / 2;
+    full - Duration::from_millis(random % (jitter + 1))
+}
+
+/// Layer reporting the duration and failures of calls as metrics.
+#[derive(Clone, Debug)]
+pub struct MetricsLayer {
+    name: &'static str,
+    metrics: StatsdClient,
+}
+
+impl MetricsLayer {
+    pub fn new(name: &'static str, metrics: StatsdClient) -> MetricsLayer {
+        MetricsLayer { name, metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = Metered<S>;
+
+    fn layer(&self, inner: S) -> Metered<S> {
+        Metered {
+            inner,
+            name: self.name,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Metered<S> {
+    inner: S,
+    name: &'static str,
+    metrics: StatsdClient,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Metered<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metered")
+            .field("inner", &self.inner)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<S, Req> Service<Req> for Metered<S>
+where
+    S: Service<Req>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let name = self.name;
+        let metrics = self.metrics.clone();
+        let start = Instant::now();
+        let fut = self.inner.call(req);
+
+        async move {
+            let result = fut.await;
+
+            // failing to report metrics is no reason to fail the call
+            let _ = metrics.time(&format!("{}.duration", name), start.elapsed());
+            if result.is_err() {
+                let _ = metrics.incr(&format!("{}.errors", name));
+            }
+
+            result
+        }
+        .boxed()
+    }
+}
+
+/// Identifies the host a request goes to, so that the circuit breaker can tell failing hosts
+/// apart. Requests of interactors that only talk to one host all share the default.
+pub trait Upstream {
+    fn upstream(&self) -> String {
+        String::new()
+    }
+}
+
+impl Upstream for () {}
+
+impl Upstream for CrateName {}
+
+impl Upstream for RepoQualifier {}
+
+impl Upstream for RepoPath {
+    fn upstream(&self) -> String {
+        match self.site {
+            RepoSite::Custom(ref raw_base) => raw_base.host().to_owned(),
+            ref site => site.as_ref().to_owned(),
+        }
+    }
+}
+
+impl Upstream for (RepoPath, String) {
+    fn upstream(&self) -> String {
+        self.0.upstream()
+    }
+}
+
+impl Upstream for (RepoPath, Option<GitRef>, RelativePathBuf) {
+    fn upstream(&self) -> String {
+        self.0.upstream()
+    }
+}
+
+/// Returns the HTTP errors among the causes of an error
+fn http_causes<'a>(
+    err: &'a (dyn StdError + 'static),
+) -> impl Iterator<Item = &'a reqwest::Error> + 'a {
+    std::iter::successors(Some(err), |&err| err.source())
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+}
+
+/// Whether an error means that the host is in trouble and might go away by itself, unlike
+/// e.g. a file that doesn't exist
+pub fn is_transient(err: &Error) -> bool {
+    let err: &(dyn StdError + 'static) = &**err;
+    err.is::<Elapsed>()
+        || http_causes(err).any(|err| {
+            err.is_timeout()
+                || err.is_connect()
+                || err.status().map_or(false, |status| {
+                    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+                })
+        })
+}
+
+/// Whether a failed call might succeed right away when tried again. Rate limited calls
+/// wouldn't, so unlike `is_transient` this leaves out `429 Too Many Requests`.
+fn is_retryable(err: &(dyn StdError + 'static)) -> bool {
+    err.is::<Elapsed>()
+        || http_causes(err).any(|err| {
+            err.is_timeout()
+                || err.is_connect()
+                || err
+                    .status()
+                    .map_or(false, |status| status.is_server_error())
+        })
+}
+
+/// Error of calls to a host that keeps failing, which the circuit breaker doesn't let through
+#[derive(Debug)]
+pub struct CircuitOpen {
+    host: String,
+    retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} keeps failing, not trying again for {} seconds",
+            self.host,
+            self.retry_in.as_secs() + 1
+        )
+    }
+}
+
+impl StdError for CircuitOpen {}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Host failures in a row
+    failures: u32,
+    /// Set once there were too many failures in a row
+    open_until: Option<Instant>,
+}
+
+/// Fails calls to a host right away while it keeps failing, instead of letting each of them
+/// run into the timeout. Once the cooldown is over, the next call decides whether the host is
+/// back or gets another cooldown.
+#[derive(Clone)]
+pub struct Breaker<S> {
+    inner: S,
+    name: &'static str,
+    circuits: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+impl<S> Breaker<S> {
+    pub fn new(inner: S, name: &'static str) -> Breaker<S> {
+        Breaker {
+            inner,
+            name,
+            circuits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Breaker<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Breaker")
+            .field("inner", &self.inner)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<S, Req> Service<Req> for Breaker<S>
+where
+    S: Service<Req>,
+    S::Error: Into<BoxError>,
+    S::Response: Send + 'static,
+    S::Future: Send + 'static,
+    Req: Upstream,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = BoxFuture<Result<S::Response, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner
+            .poll_ready(cx)
+            .map_err(|err| into_anyhow(err.into()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let upstream = req.upstream();
+
+        let open_until = self
+            .circuits
+            .lock()
+            .unwrap()
+            .get(&upstream)
+            .and_then(|circuit| circuit.open_until);
+        if let Some(retry_in) =
+            open_until.and_then(|until| until.checked_duration_since(Instant::now()))
+        {
+            let host = if upstream.is_empty() {
+                self.name.to_owned()
+            } else {
+                upstream
+            };
+            let err = Error::new(CircuitOpen { host, retry_in });
+            return ready(Err(err)).boxed();
+        }
+
+        let circuits = self.circuits.clone();
+        let fut = self.inner.call(req);
+
+        async move {
+            let result = fut.await.map_err(|err| into_anyhow(err.into()));
+
+            let mut circuits = circuits.lock().unwrap();
+            match result {
+                Err(ref err) if is_transient(err) => {
+                    let circuit = circuits.entry(upstream).or_default();
+                    circuit.failures += 1;
+                    if circuit.failures >= BREAKER_FAILURES {
+                        circuit.open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+                    }
+                }
+                // the host answered, even if it was with an error
+                _ => {
+                    circuits.remove(&upstream);
+                }
+            }
+            drop(circuits);
+
+            result
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        let base = Duration::from_millis(200);
+
+        for attempt in 0..4 {
+            let full = base * 2u32.pow(attempt);
+            let delay = backoff(base, attempt);
+            assert!(delay <= full && delay >= full / 2, "{:?}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_only_timeouts() {
+        let mut hanging = ServiceBuilder::new()
+            .timeout(Duration::from_millis(1))
+            .service(tower::service_fn(|()| {
+                futures::future::pending::<Result<(), BoxError>>()
+            }));
+        let elapsed = hanging.call(()).await.unwrap_err();
+        assert!(is_retryable(&*elapsed));
+
+        let missing: BoxError = anyhow!("404 Not Found").into();
+        assert!(!is_retryable(&*missing));
+    }
+
+    #[tokio::test]
+    async fn hanging_host_opens_the_circuit() {
+        use tower::ServiceExt as _;
+
+        let hanging = tower::service_fn(|()| futures::future::pending::<Result<(), Error>>());
+        let config = StackConfig {
+            name: "hanging",
+            timeout: Duration::from_millis(1),
+            retries: 0,
+            concurrency: 1,
+        };
+        let metrics = StatsdClient::from_sink("test", cadence::NopMetricSink);
+        let mut service = stack(hanging, config, metrics);
+
+        for _ in 0..BREAKER_FAILURES {
+            let err = service.ready().await.unwrap().call(()).await.unwrap_err();
+            assert!((*err).is::<Elapsed>(), "{:#}", err);
+            assert!(is_transient(&err));
+        }
+
+        let err = service.ready().await.unwrap().call(()).await.unwrap_err();
+        assert!(err.is::<CircuitOpen>(), "{:#}", err);
+    }
+}
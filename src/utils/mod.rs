@@ -1,2 +1,8 @@
 pub mod cache;
+pub mod dns;
 pub mod index;
+pub mod json_file;
+pub mod middleware;
+pub mod pacer;
+pub mod single_flight;
+pub mod time;
@@ -0,0 +1,57 @@
+//! Spacing out calls to an API that only allows a limited request rate.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::{sleep_until, Instant};
+
+/// Hands out slots for calls at least `interval` apart, shared between all clones.
+#[derive(Clone, Debug)]
+pub struct Pacer {
+    interval: Duration,
+    /// Earliest time the next slot can be at
+    next: Arc<Mutex<Instant>>,
+}
+
+impl Pacer {
+    pub fn new(interval: Duration) -> Pacer {
+        Pacer {
+            interval,
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Waits for the next free slot. Returns `false` right away, without taking a slot, if
+    /// that is more than `max_wait` away.
+    pub async fn wait(&self, max_wait: Duration) -> bool {
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = (*next).max(now);
+            if slot - now > max_wait {
+                return false;
+            }
+            *next = slot + self.interval;
+            slot
+        };
+
+        sleep_until(slot).await;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spaces_out_slots() {
+        let pacer = Pacer::new(Duration::from_secs(60));
+
+        assert!(pacer.wait(Duration::from_secs(0)).await);
+        assert!(!pacer.wait(Duration::from_secs(30)).await);
+        assert!(!pacer.clone().wait(Duration::from_secs(30)).await);
+    }
+}
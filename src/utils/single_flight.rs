@@ -0,0 +1,153 @@
+use std::{collections::HashMap, fmt, hash::Hash, sync::Arc, sync::Mutex};
+
+use futures::future::{FutureExt as _, Shared};
+
+use crate::BoxFuture;
+
+/// Deduplicates concurrent work: callers asking for a key that is already being worked on
+/// await the running future instead of starting their own. Work that all of its callers gave
+/// up waiting for is dropped, which cancels whatever it was still waiting for.
+#[derive(Clone)]
+pub struct SingleFlight<K, V> {
+    in_flight: Arc<Mutex<InFlight<K, V>>>,
+}
+
+struct InFlight<K, V> {
+    flights: HashMap<K, Flight<V>>,
+    /// Identifies the next flight, so waiters can't leave a newer one for the same key
+    next_id: u64,
+}
+
+struct Flight<V> {
+    id: u64,
+    work: Shared<BoxFuture<V>>,
+    waiters: usize,
+}
+
+impl<K, V> fmt::Debug for SingleFlight<K, V> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SingleFlight").finish()
+    }
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> SingleFlight<K, V> {
+        SingleFlight {
+            in_flight: Arc::new(Mutex::new(InFlight {
+                flights: HashMap::new(),
+                next_id: 0,
+            })),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Awaits the work running for `key`, or starts it with `work` if there is none
+    pub async fn run<F>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> BoxFuture<V>,
+    {
+        let (shared, id) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let InFlight { flights, next_id } = &mut *in_flight;
+            let flight = flights.entry(key.clone()).or_insert_with(|| {
+                *next_id += 1;
+                Flight {
+                    id: *next_id,
+                    work: work().shared(),
+                    waiters: 0,
+                }
+            });
+            flight.waiters += 1;
+            (flight.work.clone(), flight.id)
+        };
+
+        // leaves the flight even if this future is dropped before the work finishes, e.g.
+        // because the client disconnected
+        let _waiter = Waiter {
+            in_flight: &self.in_flight,
+            key,
+            id,
+        };
+
+        shared.await
+    }
+}
+
+/// Leaves a flight when dropped, whether its caller got the result or gave up waiting
+struct Waiter<'a, K: Eq + Hash, V> {
+    in_flight: &'a Mutex<InFlight<K, V>>,
+    key: K,
+    id: u64,
+}
+
+impl<K: Eq + Hash, V> Drop for Waiter<'_, K, V> {
+    fn drop(&mut self) {
+        let mut in_flight = match self.in_flight.lock() {
+            Ok(in_flight) => in_flight,
+            Err(_) => return,
+        };
+
+        let remove = match in_flight.flights.get_mut(&self.key) {
+            Some(flight) if flight.id == self.id => {
+                flight.waiters -= 1;
+                // finished work isn't reused, and unfinished work nobody waits for is cancelled
+                flight.waiters == 0 || flight.work.peek().is_some()
+            }
+            _ => false,
+        };
+        if remove {
+            in_flight.flights.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::{join_all, pending};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_runs_share_work() {
+        let single_flight = SingleFlight::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let calls = (0..5).map(|_| {
+            let runs = runs.clone();
+            single_flight.run("key", move || {
+                Box::pin(async move {
+                    tokio::task::yield_now().await;
+                    runs.fetch_add(1, Ordering::SeqCst)
+                })
+            })
+        });
+
+        assert_eq!(join_all(calls).await, vec![0; 5]);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // finished work isn't reused
+        let again = single_flight.run("key", || Box::pin(async { 42 })).await;
+        assert_eq!(again, 42);
+    }
+
+    #[tokio::test]
+    async fn abandoned_work_is_cancelled() {
+        let single_flight = SingleFlight::default();
+
+        let abandoned = single_flight
+            .run("key", || Box::pin(pending()))
+            .now_or_never();
+        assert_eq!(abandoned, None);
+        assert!(single_flight.in_flight.lock().unwrap().flights.is_empty());
+
+        let again = single_flight.run("key", || Box::pin(async { 42 })).await;
+        assert_eq!(again, 42);
+    }
+}